@@ -4,6 +4,7 @@ using System.Collections.Generic;
 using UnityEngine;
 using NativeWebSocket;
 using Newtonsoft.Json;
+using Newtonsoft.Json.Linq;
 
 /// Websocket client to AR Bridge...connecting to Jetson Backend
 
@@ -136,10 +137,12 @@ public class ARBridge : MonoBehaviour
 
     private void OnWebSocketMessage(byte[] data)
     {
-        string json = System.Text.Encoding.UTF8.GetString(data);
         try
         {
-            var message = JsonConvert.DeserializeObject<ServerMessage>(json);
+            // Binary messages once MessagePack is selected, JSON text before that
+            var message = MessagePackReader.IsBinaryMessage(data)
+                ? FromMessagePack(MessagePackReader.Decode(data))
+                : JsonConvert.DeserializeObject<ServerMessage>(System.Text.Encoding.UTF8.GetString(data));
             if (message?.Frame != null)
             {
                 framesReceived++; //sum received frames
@@ -162,6 +165,10 @@ public class ARBridge : MonoBehaviour
             {
                 Debug.Log($"Welcome: {message.Connected.server_version} | Session: {message.Connected.session_id}");
             }
+            else if (message?.EncodingSelected != null)
+            {
+                Debug.Log($"Wire format: {message.EncodingSelected.format} | Compressed: {message.EncodingSelected.compressed}");
+            }
         }
         catch (Exception e)
         {
@@ -204,7 +211,9 @@ public class ARBridge : MonoBehaviour
                     device_name = "Meta Quest 3",
                     supports_hand_tracking = true,
                     supports_spatial_audio = true,
-                    max_fps = (uint)config.targetFPS
+                    max_fps = (uint)config.targetFPS,
+                    wire_formats = new List<string> { "MessagePack", "Json" },
+                    supports_compression = true
                 }
             }
         };
@@ -214,6 +223,59 @@ public class ARBridge : MonoBehaviour
         Debug.Log("Sent Client Capabilities");
     }
 
+    /// <summary>
+    /// MessagePack structs are arrays in field order, map the ones this client reads
+    /// Enums arrive as a one-entry map from variant name to its fields
+    /// </summary>
+    private static ServerMessage FromMessagePack(JToken token)
+    {
+        var message = new ServerMessage();
+        if (!(token is JObject variants)) return message;
+
+        if (variants["Frame"] is JArray frame)
+        {
+            message.Frame = new ARFrame
+            {
+                timestamp = frame[0].Value<ulong>(),
+                frame_id = frame[1].Value<uint>(),
+                protocol_version = frame[2].Value<ushort>()
+            };
+            foreach (JArray obj in frame[3])
+            {
+                var bbox = (JArray)obj[2];
+                message.Frame.objects.Add(new DetectedObject
+                {
+                    @class = obj[0].Value<string>(),
+                    confidence = obj[1].Value<float>(),
+                    bbox = new BoundingBox
+                    {
+                        x = bbox[0].Value<float>(),
+                        y = bbox[1].Value<float>(),
+                        width = bbox[2].Value<float>(),
+                        height = bbox[3].Value<float>()
+                    }
+                });
+            }
+        }
+        else if (variants["Connected"] is JArray connected)
+        {
+            message.Connected = new ConnectedMessage
+            {
+                server_version = connected[0].Value<string>(),
+                session_id = connected[1].Value<string>()
+            };
+        }
+        else if (variants["EncodingSelected"] is JArray selected)
+        {
+            message.EncodingSelected = new EncodingSelectedMessage
+            {
+                format = selected[0].Value<string>(),
+                compressed = selected[1].Value<bool>()
+            };
+        }
+        return message;
+    }
+
     async void OnApplicationQuit()
     {
         isQuitting = true;
@@ -229,6 +291,14 @@ public class ARBridge : MonoBehaviour
     {
         public ConnectedMessage Connected;
         public ARFrame Frame;
+        public EncodingSelectedMessage EncodingSelected;
+    }
+
+    [Serializable]
+    public class EncodingSelectedMessage
+    {
+        public string format;
+        public bool compressed;
     }
 
     [Serializable]
@@ -282,5 +352,8 @@ public class ARBridge : MonoBehaviour
         public bool supports_hand_tracking;
         public bool supports_spatial_audio;
         public uint max_fps;
+        // Preferred first, the bridge confirms its pick with EncodingSelected
+        public List<string> wire_formats;
+        public bool supports_compression;
     }
 }
\ No newline at end of file
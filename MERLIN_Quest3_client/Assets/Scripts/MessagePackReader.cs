@@ -0,0 +1,142 @@
+using System;
+using System.IO;
+using System.IO.Compression;
+using System.Text;
+using Newtonsoft.Json.Linq;
+
+/// Minimal MessagePack decoder for AR Bridge binary messages
+/// Header byte 0x01 MessagePack, 0x02 MessagePack deflated (raw deflate)
+/// Maps become JObject, arrays JArray (structs arrive as arrays without field names), nil JValue null
+public static class MessagePackReader
+{
+    public const byte HeaderMsgPack = 0x01;
+    public const byte HeaderMsgPackDeflate = 0x02;
+
+    public static bool IsBinaryMessage(byte[] data)
+    {
+        return data.Length > 0 && (data[0] == HeaderMsgPack || data[0] == HeaderMsgPackDeflate);
+    }
+
+    /// <summary>
+    /// Strip the header byte, inflate if needed and decode the body
+    /// </summary>
+    public static JToken Decode(byte[] data)
+    {
+        byte[] body;
+        if (data[0] == HeaderMsgPackDeflate)
+        {
+            using (var inflater = new DeflateStream(new MemoryStream(data, 1, data.Length - 1), CompressionMode.Decompress))
+            using (var packed = new MemoryStream())
+            {
+                inflater.CopyTo(packed);
+                body = packed.ToArray();
+            }
+        }
+        else if (data[0] == HeaderMsgPack)
+        {
+            body = new byte[data.Length - 1];
+            Buffer.BlockCopy(data, 1, body, 0, body.Length);
+        }
+        else
+        {
+            throw new FormatException($"Unknown binary message header 0x{data[0]:x2}");
+        }
+
+        int offset = 0;
+        return ReadValue(body, ref offset);
+    }
+
+    private static JToken ReadValue(byte[] b, ref int i)
+    {
+        byte tag = Next(b, ref i);
+        if (tag <= 0x7f) return new JValue(tag);
+        if (tag >= 0xe0) return new JValue((sbyte)tag);
+        if ((tag & 0xf0) == 0x80) return ReadMap(b, ref i, tag & 0x0f);
+        if ((tag & 0xf0) == 0x90) return ReadArray(b, ref i, tag & 0x0f);
+        if ((tag & 0xe0) == 0xa0) return ReadString(b, ref i, tag & 0x1f);
+
+        switch (tag)
+        {
+            case 0xc0: return JValue.CreateNull();
+            case 0xc2: return new JValue(false);
+            case 0xc3: return new JValue(true);
+            case 0xc4: return ReadBytes(b, ref i, (int)ReadUInt(b, ref i, 1));
+            case 0xc5: return ReadBytes(b, ref i, (int)ReadUInt(b, ref i, 2));
+            case 0xc6: return ReadBytes(b, ref i, (int)ReadUInt(b, ref i, 4));
+            case 0xca: return new JValue(BitConverter.Int32BitsToSingle((int)ReadUInt(b, ref i, 4)));
+            case 0xcb: return new JValue(BitConverter.Int64BitsToDouble((long)ReadUInt(b, ref i, 8)));
+            case 0xcc: return new JValue(ReadUInt(b, ref i, 1));
+            case 0xcd: return new JValue(ReadUInt(b, ref i, 2));
+            case 0xce: return new JValue(ReadUInt(b, ref i, 4));
+            case 0xcf: return new JValue(ReadUInt(b, ref i, 8));
+            case 0xd0: return new JValue((sbyte)ReadUInt(b, ref i, 1));
+            case 0xd1: return new JValue((short)ReadUInt(b, ref i, 2));
+            case 0xd2: return new JValue((int)ReadUInt(b, ref i, 4));
+            case 0xd3: return new JValue((long)ReadUInt(b, ref i, 8));
+            case 0xd9: return ReadString(b, ref i, (int)ReadUInt(b, ref i, 1));
+            case 0xda: return ReadString(b, ref i, (int)ReadUInt(b, ref i, 2));
+            case 0xdb: return ReadString(b, ref i, (int)ReadUInt(b, ref i, 4));
+            case 0xdc: return ReadArray(b, ref i, (int)ReadUInt(b, ref i, 2));
+            case 0xdd: return ReadArray(b, ref i, (int)ReadUInt(b, ref i, 4));
+            case 0xde: return ReadMap(b, ref i, (int)ReadUInt(b, ref i, 2));
+            case 0xdf: return ReadMap(b, ref i, (int)ReadUInt(b, ref i, 4));
+            default: throw new FormatException($"Unsupported MessagePack type 0x{tag:x2}");
+        }
+    }
+
+    private static JArray ReadArray(byte[] b, ref int i, int count)
+    {
+        var array = new JArray();
+        for (int n = 0; n < count; n++) array.Add(ReadValue(b, ref i));
+        return array;
+    }
+
+    private static JObject ReadMap(byte[] b, ref int i, int count)
+    {
+        var map = new JObject();
+        for (int n = 0; n < count; n++)
+        {
+            string key = ReadValue(b, ref i).ToString();
+            map[key] = ReadValue(b, ref i);
+        }
+        return map;
+    }
+
+    private static JValue ReadString(byte[] b, ref int i, int length)
+    {
+        Check(b, i, length);
+        string text = Encoding.UTF8.GetString(b, i, length);
+        i += length;
+        return new JValue(text);
+    }
+
+    private static JValue ReadBytes(byte[] b, ref int i, int length)
+    {
+        Check(b, i, length);
+        var bytes = new byte[length];
+        Buffer.BlockCopy(b, i, bytes, 0, length);
+        i += length;
+        return new JValue(bytes);
+    }
+
+    // Big-endian unsigned integer of `size` bytes
+    private static ulong ReadUInt(byte[] b, ref int i, int size)
+    {
+        Check(b, i, size);
+        ulong value = 0;
+        for (int n = 0; n < size; n++) value = (value << 8) | b[i + n];
+        i += size;
+        return value;
+    }
+
+    private static byte Next(byte[] b, ref int i)
+    {
+        Check(b, i, 1);
+        return b[i++];
+    }
+
+    private static void Check(byte[] b, int i, int length)
+    {
+        if (length < 0 || i + length > b.Length) throw new FormatException("Truncated MessagePack message");
+    }
+}
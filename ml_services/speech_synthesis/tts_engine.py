@@ -2,10 +2,20 @@
 import subprocess
 from pathlib import Path
 import os
+import socket
+import struct
+import numpy as np
 
 # suppress ONNX runtime warnings
 os.environ['ORT_LOGGING_LEVEL'] = '3'
 
+# rust_comms playback socket, frames match rust_comms/src/audio/publisher.rs
+PLAYBACK_SOCKET_PATH = os.environ.get("MERLIN_PLAYBACK_SOCKET", "/tmp/merlin_playback.sock")
+FRAME_HEADER = struct.Struct("<4sQQII")  # magic, sequence, capture_us, sample_rate, num_samples
+PIPER_SAMPLE_RATE = 22050
+# Extra wait past the audio's length for rust_comms to finish playing it (queued audio ahead of ours)
+PLAYBACK_DRAIN_SLACK_SECONDS = 10.0
+
 class TTSEngine:
     def __init__(self, voice_model = "en_US-lessac-medium"):
         """Init Piper TTS, ARGS: voice model name(in piper_voice env file)"""
@@ -19,11 +29,20 @@ class TTSEngine:
         print(f"TTS Engine: Ready {voice_model}")
 
     def speak(self, text: str, blocking = True):
-        """Speak text, ARGS: text to speak, blocking or non-blocking"""
+        """Speak text, ARGS: text to speak, blocking or non-blocking
+        blocking returns once the audio has finished playing, not just once it was handed off"""
         if not text or not text.strip():
             return
         print(f"MERLIN: {text}")
 
+        # Play through rust_comms when it's running so the mic is ducked while we talk
+        if os.path.exists(PLAYBACK_SOCKET_PATH):
+            try:
+                self._speak_via_rust(text, wait = blocking)
+                return
+            except OSError as e:
+                print(f"Rust playback unavailable ({e}), falling back to paplay")
+
         try:
             # TTS Pipeline: text -> piper -> raw audio -> aplay (output audio for ALSA)
             piper_cmd = [
@@ -67,6 +86,55 @@ class TTSEngine:
         except Exception as e:
             print(f"TTS Error: {e}")
 
+    def _speak_via_rust(self, text: str, wait = True):
+        """Send piper output to the rust_comms playback queue
+        Always blocks until sent, with wait also until rust_comms has played it (it closes the socket then)"""
+        with socket.socket(socket.AF_UNIX, socket.SOCK_STREAM) as sock:
+            # Connect before starting piper, so a dead socket doesn't leave piper behind
+            sock.connect(PLAYBACK_SOCKET_PATH)
+            piper_process = subprocess.Popen(
+                ["piper", "--model", str(self.model_path), "--output-raw"],
+                stdin = subprocess.PIPE,
+                stdout = subprocess.PIPE,
+                stderr = subprocess.DEVNULL
+            )
+            try:
+                piper_process.stdin.write(text.encode())
+                piper_process.stdin.close()
+
+                sequence = 0
+                sent_samples = 0
+                leftover = b""
+                while True:
+                    # 4410 bytes = 100ms of s16le mono at 22.05kHz
+                    chunk = piper_process.stdout.read(4410)
+                    if not chunk:
+                        break
+                    raw = leftover + chunk
+                    whole = len(raw) // 2 * 2  # pipe reads can split a sample
+                    raw, leftover = raw[:whole], raw[whole:]
+                    samples = (np.frombuffer(raw, dtype = "<i2").astype(np.float32) / 32768.0).astype("<f4")
+                    header = FRAME_HEADER.pack(b"MRLN", sequence, 0, PIPER_SAMPLE_RATE, len(samples))
+                    sock.sendall(header + samples.tobytes())
+                    sequence += 1
+                    sent_samples += len(samples)
+                piper_process.wait()
+            finally:
+                # Send failed halfway: don't leave piper running or as a zombie
+                if piper_process.poll() is None:
+                    piper_process.terminate()
+                    piper_process.wait()
+
+            if wait:
+                # Half-close, rust_comms closes its side once the queued audio has played
+                sock.shutdown(socket.SHUT_WR)
+                sock.settimeout(sent_samples / PIPER_SAMPLE_RATE + PLAYBACK_DRAIN_SLACK_SECONDS)
+                try:
+                    while sock.recv(1):
+                        pass
+                except socket.timeout:
+                    print("Playback didn't finish in time, continuing")
+
 
 #Test
 if __name__ == "__main__":
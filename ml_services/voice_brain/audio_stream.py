@@ -0,0 +1,126 @@
+"""Client for the processed audio stream published by rust_comms"""
+import os
+import socket
+import struct
+
+import numpy as np
+
+# Must match rust_comms/src/audio/publisher.rs
+DEFAULT_SOCKET_PATH = "/tmp/merlin_audio.sock"
+FRAME_MAGIC = b"MRLN"
+FRAME_HEADER = struct.Struct("<4sQQII")  # magic, sequence, capture_us, sample_rate, num_samples
+
+
+class AudioFrame:
+    """One published chunk: 16kHz mono float32 samples plus sequence and capture time"""
+    __slots__ = ("sequence", "capture_timestamp_us", "sample_rate", "samples")
+
+    def __init__(self, sequence, capture_timestamp_us, sample_rate, samples):
+        self.sequence = sequence
+        self.capture_timestamp_us = capture_timestamp_us
+        self.sample_rate = sample_rate
+        self.samples = samples
+
+
+class RustAudioStream:
+    """Subscribe to rust_comms audio instead of opening the mic ourselves
+    Usage:
+        with RustAudioStream() as stream:
+            for frame in stream:
+                ...
+    """
+    def __init__(self, socket_path = None, timeout = 5.0):
+        self.socket_path = socket_path or os.environ.get("MERLIN_AUDIO_SOCKET", DEFAULT_SOCKET_PATH)
+        self.timeout = timeout
+        self.sock = None
+        self.last_sequence = None
+        self.dropped_frames = 0 # frames missing from the sequence since connect
+
+    @staticmethod
+    def available(socket_path = None, timeout = 0.5):
+        """True if a publisher accepts connections (a stale socket file left by a crash doesn't count)"""
+        path = socket_path or os.environ.get("MERLIN_AUDIO_SOCKET", DEFAULT_SOCKET_PATH)
+        probe = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+        probe.settimeout(timeout)
+        try:
+            probe.connect(path)
+            return True
+        except OSError:
+            return False
+        finally:
+            probe.close()
+
+    def connect(self):
+        self.sock = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+        self.sock.settimeout(self.timeout)
+        self.sock.connect(self.socket_path)
+        self.last_sequence = None
+        self.dropped_frames = 0
+        return self
+
+    def close(self):
+        if self.sock is not None:
+            self.sock.close()
+            self.sock = None
+
+    def __enter__(self):
+        return self.connect()
+
+    def __exit__(self, *exc):
+        self.close()
+
+    def __iter__(self):
+        return self
+
+    def __next__(self):
+        try:
+            return self.read_frame()
+        except ConnectionError:
+            raise StopIteration
+
+    def _read_exact(self, size):
+        buf = bytearray(size)
+        view = memoryview(buf)
+        got = 0
+        while got < size:
+            n = self.sock.recv_into(view[got:], size - got)
+            if n == 0:
+                raise ConnectionError("Audio publisher closed the stream")
+            got += n
+        return buf
+
+    def read_frame(self):
+        """Block until the next frame arrives"""
+        magic, sequence, capture_us, sample_rate, num_samples = FRAME_HEADER.unpack(
+            self._read_exact(FRAME_HEADER.size)
+        )
+        if magic != FRAME_MAGIC:
+            raise ValueError(f"Bad frame magic: {magic!r}")
+        payload = self._read_exact(num_samples * 4)
+        samples = np.frombuffer(payload, dtype = "<f4")
+
+        # Sequence gaps mean the publisher dropped buffers for us
+        if self.last_sequence is not None and sequence > self.last_sequence + 1:
+            self.dropped_frames += sequence - self.last_sequence - 1
+        self.last_sequence = sequence
+
+        return AudioFrame(sequence, capture_us, sample_rate, samples)
+
+    def chunks(self, chunk_samples):
+        """Re-chunk the stream into fixed-size blocks (e.g. 320 samples = 20ms for VAD)"""
+        pending = np.zeros(0, dtype = np.float32)
+        for frame in self:
+            pending = np.concatenate((pending, frame.samples))
+            while len(pending) >= chunk_samples:
+                yield pending[:chunk_samples]
+                pending = pending[chunk_samples:]
+
+
+#Test
+if __name__ == "__main__":
+    print("Reading from rust_comms audio stream...")
+    with RustAudioStream() as stream:
+        for frame in stream:
+            rms = float(np.sqrt(np.mean(frame.samples ** 2))) if len(frame.samples) else 0.0
+            print(f"seq={frame.sequence} t={frame.capture_timestamp_us} "
+                  f"n={len(frame.samples)} rms={rms:.4f} dropped={stream.dropped_frames}")
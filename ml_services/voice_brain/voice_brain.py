@@ -4,6 +4,7 @@ import os
 os.environ["PYTORCH_CUDA_ALLOC_CONF"] = 'backend:native, expandable_segments:True'
 
 import sys
+import signal
 from pathlib import Path
 sys.path.insert(0, str(Path(__file__).parent.parent))
 import torch
@@ -16,16 +17,20 @@ from faster_whisper import WhisperModel
 import webrtcvad
 from reasoning_engine.llm_client import LLMClient
 from speech_synthesis.tts_engine import TTSEngine
+from audio_stream import RustAudioStream
 
 # import rust audio filters
 try:
-    from merlin_audio import PyNoiseGate, PyNormalizer
+    from merlin_audio import PyAudioPipeline, PyRecordingCatalog, PyWavFileWriter
     RUST_FILTERS = True
     print(f"Rust Filters Available")
 except ImportError as e:
     RUST_FILTERS = False
     print(f"Rust filters Unavailable: {e}")
 
+# Filter state is saved this often while listening, so a crash or kill -9 loses at most this much leveling
+FILTER_STATE_SAVE_SECONDS = 60.0
+
 class VoiceBrain:
     def __init__(self):
         print("Initializing MERLIN's Voice Brain...")
@@ -44,21 +49,19 @@ class VoiceBrain:
         #Load Rust audio filters
         if RUST_FILTERS:
             print("Loading Rust Audio filters...")
-            self.noise_gate = PyNoiseGate (
-                threshold_db = -45.0,
-                attack_ms = 5.0,
-                release_ms = 100.0,
-                sample_rate = 16000.0
-            )
-            self.normalizer = PyNormalizer (
-                target_level_db = -20.0,
-                window_ms = 200.0,
-                sample_rate = 16000.0
-            )
+            # Gate -> normalizer in one call, GIL released while it runs
+            self.filters = PyAudioPipeline({
+                "sample_rate": 16000,
+                "stages": [
+                    {"type": "gate", "threshold_db": -45.0, "attack_ms": 5.0, "release_ms": 100.0},
+                    {"type": "normalizer", "target_db": -20.0, "window_ms": 200.0},
+                ],
+            })
+            self.load_filter_state()
             print("Audio Filters Ready")
         else:
-            self.noise_gate = None
-            self.normalizer = None
+            self.filters = None
+        self.filter_state_saved_at = time.time()
 
         print("Loading LLM Client...")
         self.llm = LLMClient()
@@ -79,23 +82,64 @@ class VoiceBrain:
         self.frame_samples = int(self.sample_rate * self.chunk_duration)
         self.silence_duration = 2.0 #2s silence to indicate end of speech
 
+        # Optional: keep every utterance, same file layout and sidecars as rust_comms recordings
+        self.record_dir = os.environ.get("MERLIN_VOICE_RECORD_DIR") if RUST_FILTERS else None
+        self.recordings = PyRecordingCatalog(self.record_dir) if self.record_dir else None
+
         print(f"Wake words : {self.wake_words}")
 
     def apply_filters(self, audio_f32):
         """Apply rust audio to 160khz audio chunk"""
-        if not RUST_FILTERS or self.noise_gate is None:
+        if not RUST_FILTERS or self.filters is None:
             return audio_f32
-        
-        # Apply noise gate
-        filtered_bytes = self.noise_gate.process(audio_f32.tobytes())
 
-        # Normalize
-        normalized_bytes = self.normalizer.process(filtered_bytes)
+        result, _ = self.filters.process(np.asarray(audio_f32, dtype = np.float32))
+        return result
+
+    def filter_state_path(self):
+        return Path(os.environ.get("MERLIN_FILTER_STATE", Path.home() / ".merlin" / "voice_filters.state"))
 
-        # Convert back to f32 array
-        result = np.frombuffer(normalized_bytes, dtype = np.float32)
+    def load_filter_state(self):
+        """Pick up gate envelope / normalizer gain from the last run so restarts start leveled"""
+        path = self.filter_state_path()
+        if not path.exists():
+            return
+        try:
+            self.filters.__setstate__(path.read_bytes())
+            print(f"Filter state restored from {path}")
+        except (OSError, ValueError) as e:
+            print(f"Ignoring filter state {path}: {e}")
+
+    def save_filter_state(self):
+        if self.filters is None:
+            return
+        path = self.filter_state_path()
+        try:
+            path.parent.mkdir(parents = True, exist_ok = True)
+            # Write then rename, a kill mid-save leaves the previous state intact
+            tmp = path.with_name(path.name + ".tmp")
+            tmp.write_bytes(self.filters.__getstate__())
+            tmp.replace(path)
+            self.filter_state_saved_at = time.time()
+        except OSError as e:
+            print(f"Could not save filter state: {e}")
+
+    def save_filter_state_if_due(self):
+        if time.time() - self.filter_state_saved_at >= FILTER_STATE_SAVE_SECONDS:
+            self.save_filter_state()
+
+    def record_utterance(self, audio, text):
+        """Save one utterance, transcript goes in its sidecar
+        A full disk or bad permissions costs the recording, not the listen loop"""
+        try:
+            with PyWavFileWriter(self.record_dir, sample_rate = self.sample_rate, device = "voice_brain", tags = ["utterance"]) as writer:
+                writer.write(audio)
+            for recording in writer.recordings:
+                self.recordings.add(recording.file_path)
+                self.recordings.annotate(recording.file_path, transcript = text)
+        except OSError as e:
+            print(f"Could not record utterance: {e}")
 
-        return result
     def is_speech(self, audio_chunk):
         "WebRTC VAD for speech detection"
         if len(audio_chunk) != self.frame_samples:
@@ -107,6 +151,13 @@ class VoiceBrain:
             return False
     
     def listen_with_vad(self):
+        # rust_comms owns the mic when it's running, subscribe instead
+        if RustAudioStream.available():
+            return self.listen_with_vad_stream()
+        return self.listen_with_vad_device()
+
+    def listen_with_vad_device(self):
+        """VAD loop on the local mic through sounddevice"""
         print("\nWaiting for speech", end="", flush=True)
         device_info = sd.query_devices(0, 'input')
         mic_rate = int(device_info['default_samplerate'])
@@ -173,6 +224,49 @@ class VoiceBrain:
         audio = np.concatenate(chunks).flatten()
         return audio.astype(np.float32)
     
+    def listen_with_vad_stream(self):
+        """Same VAD loop fed by rust_comms, audio arrives already 16kHz and filtered"""
+        print("\nWaiting for speech (rust_comms stream)", end="", flush=True)
+        chunks = []
+        silence_chunks = 0
+        speech_frames = 0
+        max_silence_chunks = int(self.silence_duration / self.chunk_duration)
+        speech_started = False
+
+        try:
+            with RustAudioStream() as stream:
+                for chunk in stream.chunks(self.frame_samples):
+                    if self.is_speech(chunk):
+                        if not speech_started:
+                            speech_frames += 1
+                            if speech_frames >= 3:
+                                print("\n Speech deteced!", end="", flush=True)
+                                speech_started = True
+                                speech_frames = 0
+                        else:
+                            chunks.append(chunk)
+                            silence_chunks = 0
+                            print("Now Speaking", end="", flush=True)
+                    else:
+                        speech_frames = 0
+                        if speech_started:
+                            silence_chunks += 1
+                            print(".", end="", flush=True)
+
+                    if speech_started and silence_chunks >= max_silence_chunks:
+                        break
+                    if len(chunks) > 300:
+                        break
+        except OSError as e:
+            # Publisher went away (or never accepted us): the mic is free again
+            print(f"\nAudio stream error: {e}, falling back to the microphone")
+            return self.listen_with_vad_device()
+        print()
+
+        if len(chunks) == 0:
+            return None
+        return np.concatenate(chunks).astype(np.float32)
+
     def transcribe(self, audio):
         print("Transcribing...")
         start = time.time()
@@ -207,11 +301,14 @@ class VoiceBrain:
         while True:
             # VAD detects speech
             audio = self.listen_with_vad()
+            self.save_filter_state_if_due()
             if audio is None:
                 continue
 
             # Transcribe
             text = self.transcribe(audio)
+            if self.recordings is not None:
+                self.record_utterance(audio, text)
 
             # Check for wake word
             if self.contains_wakeword(text):
@@ -256,13 +353,19 @@ class VoiceBrain:
                 print(f"No wake word: \"{text}\" (ignored)")
 
     
+def _interrupt_on_sigterm(signum, frame):
+    """systemd / docker stop send SIGTERM: shut down the same way as Ctrl C"""
+    raise KeyboardInterrupt
+
 def main():
+    signal.signal(signal.SIGTERM, _interrupt_on_sigterm)
     brain = VoiceBrain()
     print("\n MERLIN voice brain test, phase1, Ctrl C to escape")
     try:
         brain.listen_loop()
     except KeyboardInterrupt:
         print("\n\n Shutting Down")
+        brain.save_filter_state()
         del brain._whisper
         torch.cuda.empty_cache()
         gc.collect()
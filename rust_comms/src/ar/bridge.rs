@@ -7,7 +7,10 @@ use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::time::{interval, Duration};
 
+use super::codec::{self, Encoding};
+use super::feed::FrameFeed;
 use super::protocol::*;
+use super::stats::BridgeStats;
 
 // AR Bridge Server build
 // - Accpet Quest connection on port
@@ -21,9 +24,14 @@ pub struct ARBridgeServer {
     clients: Arc<Mutex<Vec<ConnectedClient>>>,
     /// Stream configs
     config: StreamConfig,
+    /// Frame / client counters for monitoring
+    stats: Arc<BridgeStats>,
+    /// Frames from the CV pipeline, streamed instead of dummies once live
+    feed: FrameFeed,
 }
 
 ///Individual client connection state
+#[allow(dead_code)]
 struct ConnectedClient {
     // WebSocket stream
     ws: WebSocketStream<TcpStream>,
@@ -40,9 +48,20 @@ pub struct StreamConfig {
     // Target fps
     pub target_fps: u32,
     pub quality: QualityPreset,
+    /// Deflate binary frames for clients that offer supports_compression in Connect
     pub enable_compression: bool,
 }
 
+impl StreamConfig {
+    /// Defaults, MERLIN_AR_COMPRESSION=1 turns on enable_compression
+    pub fn from_env() -> Self {
+        Self {
+            enable_compression: std::env::var("MERLIN_AR_COMPRESSION").is_ok_and(|value| value == "1"),
+            ..Self::default()
+        }
+    }
+}
+
 impl Default for StreamConfig {
     fn default() -> Self {
         Self {
@@ -62,9 +81,26 @@ impl ARBridgeServer {
             bind_addr: bind_addr.into(),
             clients: Arc::new(Mutex::new(Vec::new())),
             config: StreamConfig::default(),
+            stats: Arc::new(BridgeStats::new()),
+            feed: FrameFeed::new(),
         }
     }
 
+    pub fn with_config(mut self, config: StreamConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Shared counters, grab before `run` to report on them
+    pub fn stats(&self) -> Arc<BridgeStats> {
+        Arc::clone(&self.stats)
+    }
+
+    /// Publish frames here (or `feed().listen(socket)`) to stream them to every client
+    pub fn feed(&self) -> FrameFeed {
+        self.feed.clone()
+    }
+
     pub async fn run(&self) -> Result<(), Box<dyn std::error::Error>> {
         println!("Starting AR Bridge...");
         println!("Listening on: {}, TargetFPS: {}", self.bind_addr, self.config.target_fps);
@@ -80,10 +116,12 @@ impl ARBridgeServer {
                     // Clone Arc for spawned task
                     let clients = Arc::clone(&self.clients);
                     let config = self.config.clone();
+                    let stats = Arc::clone(&self.stats);
+                    let feed = self.feed.clone();
 
                     //Handle client in separate task
                     tokio::spawn(async move {
-                        if let Err(e) = Self::handle_client(stream, clients, config).await {
+                        if let Err(e) = Self::handle_client(stream, clients, config, stats, feed).await {
                             eprintln!("Client Error: {}", e);
                         }
                     });
@@ -100,38 +138,44 @@ impl ARBridgeServer {
         stream: TcpStream,
         _clients: Arc<Mutex<Vec<ConnectedClient>>>,
         config: StreamConfig,
+        stats: Arc<BridgeStats>,
+        feed: FrameFeed,
     ) -> Result<(), Box<dyn std::error::Error>> {
         // TCP to websocket
         let ws = accept_async(stream).await?;
         println!("Websocket handshake completed");
-        let (mut write, mut read) = ws.split();
+        let (write, mut read) = ws.split();
+        // JSON until the client's Connect picks something else
+        let writer = Arc::new(Mutex::new(ClientWriter { sink: write, encoding: Encoding::JSON }));
+        let session_id = uuid::Uuid::new_v4().to_string();
+        // Unregisters on every exit path, including `?`
+        let _registration = ClientRegistration::new(Arc::clone(&stats), &session_id);
         //Send acknowledgement
         let welcome = ServerMessage::Connected {
             server_version: "1.0.0".to_string(),
-            session_id: uuid::Uuid::new_v4().to_string(),
+            session_id: session_id.clone(),
         };
-        let welcome_json = serde_json::to_string(&welcome)?;
-        write.send(Message::Text(welcome_json.into())).await?;
+        writer.lock().await.send(&welcome).await.map_err(|e| e.to_string())?;
 
         // start streaming task
+        let allow_compression = config.enable_compression;
+        let stream_writer = Arc::clone(&writer);
+        let stream_stats = Arc::clone(&stats);
+        let stream_session = session_id.clone();
         let write_handle: tokio::task::JoinHandle<Result<(), Box<dyn std::error::Error + Send + Sync>>> =
             tokio::spawn(async move {
-                ARBridgeServer::stream_frames(write, config).await
+                ARBridgeServer::stream_frames(stream_writer, config, stream_stats, stream_session, feed).await
             });
 
         while let Some(msg) = read.next().await {
-            match msg? {
-                Message::Text(text) => {
-                    //parse message
-                    if let Ok(client_msg) = serde_json::from_str::<ClientMessage>(&text) {
-                        Self::handle_client_message(client_msg).await;
-                    }
-                }
-                Message::Close(_) => {
-                    println!("Client disconnected");
-                    break;
-                }
-                _ => {}
+            let msg = msg?;
+            if let Message::Close(_) = msg {
+                println!("Client disconnected");
+                break;
+            }
+            // JSON text or binary, whatever the client negotiated
+            if let Ok(Some(client_msg)) = codec::decode::<ClientMessage>(&msg) {
+                Self::handle_client_message(client_msg, &stats, &session_id, &writer, allow_compression).await;
             }
         }
         write_handle.abort();
@@ -139,33 +183,45 @@ impl ARBridgeServer {
     }
 
     /// Stream ARFrames at Target FPS
+    /// Published frames are sent once each (at most target FPS), dummies until the feed goes live
     async fn stream_frames(
-        mut write: futures_util::stream::SplitSink<WebSocketStream<TcpStream>, Message>,
+        writer: Arc<Mutex<ClientWriter>>,
         config: StreamConfig,
+        stats: Arc<BridgeStats>,
+        session_id: String,
+        feed: FrameFeed,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         // Calculate frame interval
         let frame_interval = Duration::from_secs_f32(1.0 / config.target_fps as f32);
         let mut frame_timer = interval(frame_interval);
         let mut frame_id = 0u32;
+        let mut sent_version = 0u64;
         println!("Starting stream at {} FPS", config.target_fps);
         loop {
             frame_timer.tick().await;
-            // Generate dummy ARFrame
-            let frame = ARFrame::new_dummy(Self::get_timestamp_us());
-            let mut frame = frame;
-            frame.frame_id = frame_id;
-            //Serialize to JSON
-            let frame_json = serde_json::to_string(&ServerMessage::Frame(frame))?;
-            //Send frame
-            if write.send(Message::Text(frame_json.into())).await.is_err() {
+            let frame = if feed.is_live() {
+                // Nothing new since the last tick, don't resend
+                let Some(published) = feed.newer_than(sent_version) else { continue };
+                sent_version = published.version;
+                published.frame
+            } else {
+                // Generate dummy ARFrame
+                let mut frame = ARFrame::new_dummy(Self::get_timestamp_us());
+                frame.frame_id = frame_id;
+                frame
+            };
+            //Send frame in the client's encoding
+            if writer.lock().await.send(&ServerMessage::Frame(frame)).await.is_err() {
+                stats.send_error(&session_id);
                 println!("Client disconnected during streaming");
                 break;
             }
+            stats.frame_sent(&session_id);
             // Wrapping to avoid integra overflow without panic
             frame_id = frame_id.wrapping_add(1);
 
             // log progress every second
-            if frame_id % config.target_fps == 0 {
+            if frame_id.is_multiple_of(config.target_fps) {
                 println!("Streaming: {} frames sent", frame_id);
             }
         }
@@ -173,12 +229,24 @@ impl ARBridgeServer {
     }
 
     /// Handle client messages
-    async fn handle_client_message(msg: ClientMessage) {
+    async fn handle_client_message(
+        msg: ClientMessage,
+        stats: &BridgeStats,
+        session_id: &str,
+        writer: &Mutex<ClientWriter>,
+        allow_compression: bool,
+    ) {
         match msg {
             ClientMessage::Connect { client_id, protocol_version, capabilities } => {
                 println!("Client connected: {}", client_id);
+                stats.set_client_id(session_id, &client_id);
                 println!("Protocol Version: {}", protocol_version);
                 println!("Device: {}", capabilities.device_name);
+                let encoding = Encoding::negotiate(&capabilities, allow_compression);
+                match writer.lock().await.select(encoding).await {
+                    Ok(()) => println!("Wire format: {}", encoding.name()),
+                    Err(e) => eprintln!("Failed to confirm wire format: {}", e),
+                }
             }
             ClientMessage::ConfigureStream { target_fps, quality } => {
                 println!(" Stream Config, {}FPS, {:?}", target_fps, quality);
@@ -200,4 +268,140 @@ impl ARBridgeServer {
     }
 }
 
+/// Write half of a client connection and the encoding negotiated for it
+/// Shared by the stream task and the Connect handler so the switch lands between two messages
+struct ClientWriter {
+    sink: futures_util::stream::SplitSink<WebSocketStream<TcpStream>, Message>,
+    encoding: Encoding,
+}
+
+impl ClientWriter {
+    async fn send(&mut self, message: &ServerMessage) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let encoded = self.encoding.encode(message)?;
+        self.sink.send(encoded).await?;
+        Ok(())
+    }
+
+    /// Confirm as JSON whatever the current encoding (a client may Connect again to renegotiate), then switch
+    async fn select(&mut self, encoding: Encoding) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let selected = ServerMessage::EncodingSelected {
+            format: encoding.format,
+            compressed: encoding.compressed,
+        };
+        self.sink.send(Encoding::JSON.encode(&selected)?).await?;
+        self.encoding = encoding;
+        Ok(())
+    }
+}
+
+/// Keeps a client listed in BridgeStats for the life of its connection
+struct ClientRegistration {
+    stats: Arc<BridgeStats>,
+    session_id: String,
+}
+
+impl ClientRegistration {
+    fn new(stats: Arc<BridgeStats>, session_id: &str) -> Self {
+        stats.client_connected(session_id);
+        Self {
+            stats,
+            session_id: session_id.to_string(),
+        }
+    }
+}
+
+impl Drop for ClientRegistration {
+    fn drop(&mut self) {
+        self.stats.client_disconnected(&self.session_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_tungstenite::connect_async;
+
+    #[tokio::test]
+    async fn test_connect_negotiates_binary_frames() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let addr = format!("127.0.0.1:{}", port);
+        let server = ARBridgeServer::new(addr.clone()).with_config(StreamConfig { enable_compression: true, ..StreamConfig::default() });
+        let feed = server.feed();
+        tokio::spawn(async move { server.run().await.map_err(|e| e.to_string()) });
+
+        let mut ws = None;
+        for _ in 0..50 {
+            if let Ok((connected, _)) = connect_async(format!("ws://{}", addr)).await {
+                ws = Some(connected);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let mut ws = ws.expect("bridge did not start");
+        let welcome = ws.next().await.unwrap().unwrap();
+        assert!(matches!(codec::decode(&welcome).unwrap(), Some(ServerMessage::Connected { .. })));
+
+        let connect = ClientMessage::Connect {
+            client_id: "test".into(),
+            protocol_version: 0x0100,
+            capabilities: ClientCapabilities {
+                device_name: "test".into(),
+                supports_hand_tracking: true,
+                supports_spatial_audio: false,
+                max_fps: 30,
+                wire_formats: vec![WireFormat::MessagePack],
+                supports_compression: true,
+            },
+        };
+        ws.send(Encoding::JSON.encode(&connect).unwrap()).await.unwrap();
+        feed.publish(codec::sample_frame(42));
+
+        // Dummy frames may still arrive as JSON until the switch, then everything is binary
+        let mut selected = false;
+        loop {
+            let msg = ws.next().await.unwrap().unwrap();
+            match codec::decode::<ServerMessage>(&msg).unwrap() {
+                Some(ServerMessage::EncodingSelected { format, compressed }) => {
+                    assert!(msg.is_text());
+                    assert_eq!((format, compressed), (WireFormat::MessagePack, true));
+                    selected = true;
+                }
+                Some(ServerMessage::Frame(frame)) if selected => {
+                    assert!(msg.is_binary());
+                    if frame.timestamp == 42 {
+                        assert_eq!(frame.hands.unwrap().right_hand.unwrap().landmarks.len(), 21);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
 
+        // Renegotiate back to JSON from a binary connection, the confirmation is still JSON
+        let reconnect = ClientMessage::Connect {
+            client_id: "test".into(),
+            protocol_version: 0x0100,
+            capabilities: ClientCapabilities {
+                device_name: "test".into(),
+                supports_hand_tracking: true,
+                supports_spatial_audio: false,
+                max_fps: 30,
+                wire_formats: vec![WireFormat::Json],
+                supports_compression: false,
+            },
+        };
+        ws.send(Encoding::MSGPACK.encode(&reconnect).unwrap()).await.unwrap();
+        loop {
+            let msg = ws.next().await.unwrap().unwrap();
+            if let Some(ServerMessage::EncodingSelected { format, compressed }) = codec::decode(&msg).unwrap() {
+                assert!(msg.is_text());
+                assert_eq!((format, compressed), (WireFormat::Json, false));
+                break;
+            }
+        }
+        feed.publish(codec::sample_frame(43));
+        let msg = ws.next().await.unwrap().unwrap();
+        assert!(msg.is_text());
+        assert!(matches!(codec::decode(&msg).unwrap(), Some(ServerMessage::Frame(frame)) if frame.timestamp == 43));
+    }
+}
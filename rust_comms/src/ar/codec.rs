@@ -0,0 +1,270 @@
+//! AR Message Encodings
+//!
+//! -JSON: text messages, the default and the fallback for clients that don't negotiate
+//! -MessagePack: binary messages, structs as arrays without field names (rmp_serde compact layout)
+//! -Binary messages start with a header byte so each one decodes without connection state:
+//!  0x01 MessagePack, 0x02 MessagePack deflated (raw deflate, RFC 1951)
+//! -Picked per client from the ClientCapabilities in Connect, see ARBridgeServer
+
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio_tungstenite::tungstenite::Message;
+
+use super::protocol::*;
+
+pub const HEADER_MSGPACK: u8 = 0x01;
+pub const HEADER_MSGPACK_DEFLATE: u8 = 0x02;
+/// Smaller messages go uncompressed, deflate only adds overhead to them
+pub const COMPRESS_MIN_BYTES: usize = 128;
+/// Inflated size limit for one client message
+const MAX_INFLATED_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Encoding of one client connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Encoding {
+    pub format: WireFormat,
+    /// Deflate binary messages of COMPRESS_MIN_BYTES or more
+    pub compressed: bool,
+}
+
+impl Encoding {
+    pub const JSON: Self = Self { format: WireFormat::Json, compressed: false };
+    pub const MSGPACK: Self = Self { format: WireFormat::MessagePack, compressed: false };
+    pub const MSGPACK_DEFLATE: Self = Self { format: WireFormat::MessagePack, compressed: true };
+
+    /// Client's preferred format (JSON if it lists none)
+    /// Compression needs a binary format, client support and `allow_compression` on the server
+    pub fn negotiate(capabilities: &ClientCapabilities, allow_compression: bool) -> Self {
+        let format = capabilities.wire_formats.first().copied().unwrap_or_default();
+        Self {
+            format,
+            compressed: format == WireFormat::MessagePack && capabilities.supports_compression && allow_compression,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match (self.format, self.compressed) {
+            (WireFormat::Json, _) => "json",
+            (WireFormat::MessagePack, false) => "msgpack",
+            (WireFormat::MessagePack, true) => "msgpack+deflate",
+        }
+    }
+
+    pub fn encode<T: Serialize>(&self, message: &T) -> Result<Message, String> {
+        match self.format {
+            WireFormat::Json => serde_json::to_string(message)
+                .map(|json| Message::Text(json.into()))
+                .map_err(|e| e.to_string()),
+            WireFormat::MessagePack => {
+                let packed = rmp_serde::to_vec(message).map_err(|e| e.to_string())?;
+                let mut bytes = Vec::with_capacity(packed.len() + 1);
+                if self.compressed && packed.len() >= COMPRESS_MIN_BYTES {
+                    bytes.push(HEADER_MSGPACK_DEFLATE);
+                    let mut encoder = DeflateEncoder::new(bytes, Compression::fast());
+                    encoder.write_all(&packed).map_err(|e| e.to_string())?;
+                    bytes = encoder.finish().map_err(|e| e.to_string())?;
+                } else {
+                    bytes.push(HEADER_MSGPACK);
+                    bytes.extend_from_slice(&packed);
+                }
+                Ok(Message::Binary(bytes.into()))
+            }
+        }
+    }
+}
+
+/// Decode a text (JSON) or binary message of either encoding
+/// Ok(None) for control frames (ping, pong, close)
+pub fn decode<T: DeserializeOwned>(message: &Message) -> Result<Option<T>, String> {
+    match message {
+        Message::Text(text) => serde_json::from_str(text).map(Some).map_err(|e| e.to_string()),
+        Message::Binary(bytes) => {
+            let (&header, body) = bytes.split_first().ok_or("Empty binary message")?;
+            match header {
+                HEADER_MSGPACK => rmp_serde::from_slice(body).map(Some).map_err(|e| e.to_string()),
+                HEADER_MSGPACK_DEFLATE => {
+                    let mut packed = Vec::new();
+                    DeflateDecoder::new(body)
+                        .take(MAX_INFLATED_BYTES + 1)
+                        .read_to_end(&mut packed)
+                        .map_err(|e| e.to_string())?;
+                    if packed.len() as u64 > MAX_INFLATED_BYTES {
+                        return Err(format!("Message inflates past {} bytes", MAX_INFLATED_BYTES));
+                    }
+                    rmp_serde::from_slice(&packed).map(Some).map_err(|e| e.to_string())
+                }
+                other => Err(format!("Unknown binary message header 0x{:02x}", other)),
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Bytes on the wire for one encoded message
+pub fn wire_size(message: &Message) -> usize {
+    match message {
+        Message::Text(text) => text.len(),
+        Message::Binary(bytes) => bytes.len(),
+        _ => 0,
+    }
+}
+
+/// Size and speed of one encoding for a given frame
+#[derive(Debug, Clone)]
+pub struct EncodingReport {
+    pub encoding: Encoding,
+    pub bytes: usize,
+    pub encode_per_sec: f64,
+    pub decode_per_sec: f64,
+}
+
+impl EncodingReport {
+    /// Link load at `fps`, in kbit/s
+    pub fn kbps_at(&self, fps: u32) -> f64 {
+        (self.bytes * fps as usize * 8) as f64 / 1000.0
+    }
+}
+
+/// Encode and decode `ServerMessage::Frame(frame)` `iterations` times in every encoding
+/// Backs `ar_server bench` and the size tests
+pub fn compare(frame: &ARFrame, iterations: u32) -> Result<Vec<EncodingReport>, String> {
+    let message = ServerMessage::Frame(frame.clone());
+    let iterations = iterations.max(1);
+    let per_sec = |elapsed: Duration| iterations as f64 / elapsed.as_secs_f64().max(1e-9);
+    [Encoding::JSON, Encoding::MSGPACK, Encoding::MSGPACK_DEFLATE]
+        .into_iter()
+        .map(|encoding| {
+            let encoded = encoding.encode(&message)?;
+            let start = Instant::now();
+            for _ in 0..iterations {
+                encoding.encode(&message)?;
+            }
+            let encode_per_sec = per_sec(start.elapsed());
+            let start = Instant::now();
+            for _ in 0..iterations {
+                decode::<ServerMessage>(&encoded)?;
+            }
+            Ok(EncodingReport {
+                encoding,
+                bytes: wire_size(&encoded),
+                encode_per_sec,
+                decode_per_sec: per_sec(start.elapsed()),
+            })
+        })
+        .collect()
+}
+
+/// Busy frame for size / speed comparisons: three tracked objects and both hands (21 joints each)
+pub fn sample_frame(timestamp: u64) -> ARFrame {
+    let hand = |side: f32| HandPose {
+        landmarks: std::array::from_fn(|joint| {
+            let t = joint as f32;
+            Vector3::new(0.5 + side * 0.1 + 0.013 * (t * 0.7).sin(), 0.42 + 0.011 * t, -0.31 - 0.002 * t)
+        }),
+        confidences: std::array::from_fn(|joint| 0.9 - joint as f32 * 0.003),
+        gesture: Some(GestureType::OpenPalm),
+    };
+    let object = |class: &str, id: u32| DetectedObject {
+        class: class.to_string(),
+        confidence: 0.87,
+        bbox: BoundingBox { x: 0.1 * id as f32, y: 0.25, width: 0.18, height: 0.3 },
+        position_3d: Some(Vector3::new(0.2 * id as f32, 0.05, 1.4)),
+        tracking_id: Some(id),
+    };
+    ARFrame {
+        objects: vec![object("cup", 1), object("laptop", 2), object("person", 3)],
+        hands: Some(HandTrackingData {
+            left_hand: Some(hand(-1.0)),
+            right_hand: Some(hand(1.0)),
+            confidence: 0.95,
+            source: TrackingSource::JetsonMediaPipe,
+        }),
+        ..ARFrame::new_dummy(timestamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_encoding_round_trips_a_hand_frame() {
+        let message = ServerMessage::Frame(sample_frame(1_700_000_000_000_000));
+        let expected = serde_json::to_value(&message).unwrap();
+        for encoding in [Encoding::JSON, Encoding::MSGPACK, Encoding::MSGPACK_DEFLATE] {
+            let encoded = encoding.encode(&message).unwrap();
+            assert_eq!(matches!(encoded, Message::Binary(_)), encoding.format == WireFormat::MessagePack);
+            let decoded: ServerMessage = decode(&encoded).unwrap().unwrap();
+            assert_eq!(serde_json::to_value(&decoded).unwrap(), expected, "{}", encoding.name());
+        }
+
+        // Small messages skip deflate but stay readable
+        let pong = ServerMessage::Pong { client_timestamp: 1, server_timestamp: 2 };
+        let encoded = Encoding::MSGPACK_DEFLATE.encode(&pong).unwrap();
+        assert!(matches!(&encoded, Message::Binary(bytes) if bytes[0] == HEADER_MSGPACK));
+        assert!(decode::<ServerMessage>(&encoded).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_binary_frames_are_much_smaller_than_json() {
+        let frame = sample_frame(1_700_000_000_000_000);
+        let reports = compare(&frame, 20).unwrap();
+        let (json, msgpack, deflated) = (reports[0].bytes, reports[1].bytes, reports[2].bytes);
+        assert!(msgpack * 2 < json, "msgpack {} vs json {}", msgpack, json);
+        assert!(deflated <= msgpack, "deflate {} vs msgpack {}", deflated, msgpack);
+        // estimate_size is the MessagePack frame body, the wire adds the enum tag and header byte
+        let estimate = frame.estimate_size();
+        assert!(estimate < msgpack && estimate + 16 > msgpack);
+    }
+
+    #[test]
+    fn test_negotiation_falls_back_to_json() {
+        // Connect from a client that predates wire_formats
+        let old: ClientCapabilities = serde_json::from_str(
+            r#"{"device_name": "Meta Quest 3", "supports_hand_tracking": true, "supports_spatial_audio": true, "max_fps": 30}"#,
+        )
+        .unwrap();
+        assert_eq!(Encoding::negotiate(&old, true), Encoding::JSON);
+
+        let binary = ClientCapabilities {
+            wire_formats: vec![WireFormat::MessagePack, WireFormat::Json],
+            supports_compression: true,
+            ..old.clone()
+        };
+        assert_eq!(Encoding::negotiate(&binary, true), Encoding::MSGPACK_DEFLATE);
+        assert_eq!(Encoding::negotiate(&binary, false), Encoding::MSGPACK);
+        let json_first = ClientCapabilities { wire_formats: vec![WireFormat::Json], ..binary };
+        assert_eq!(Encoding::negotiate(&json_first, true), Encoding::JSON);
+
+        // A newer client offering formats this server doesn't know still connects, with the first known one
+        let newer = |formats: &str| -> ClientCapabilities {
+            serde_json::from_str(&format!(
+                r#"{{"device_name": "Quest", "supports_hand_tracking": true, "supports_spatial_audio": true, "max_fps": 30, "wire_formats": {}}}"#,
+                formats
+            ))
+            .unwrap()
+        };
+        let offered = newer(r#"["Protobuf", {"Cbor": 2}, "MessagePack", "Json"]"#);
+        assert_eq!(offered.wire_formats, vec![WireFormat::MessagePack, WireFormat::Json]);
+        assert_eq!(Encoding::negotiate(&offered, false), Encoding::MSGPACK);
+        assert_eq!(Encoding::negotiate(&newer(r#"["Protobuf"]"#), true), Encoding::JSON);
+        // Same over MessagePack, the binary Connect path
+        let packed = rmp_serde::to_vec(&("Quest", true, true, 30, ("Protobuf", "MessagePack"), true)).unwrap();
+        let offered: ClientCapabilities = rmp_serde::from_slice(&packed).unwrap();
+        assert_eq!(offered.wire_formats, vec![WireFormat::MessagePack]);
+    }
+
+    #[test]
+    fn test_decode_rejects_bad_binary() {
+        for bytes in [vec![], vec![0x7f, 1, 2], vec![HEADER_MSGPACK, 0xc1], vec![HEADER_MSGPACK_DEFLATE, 0xff, 0xff]] {
+            assert!(decode::<ClientMessage>(&Message::Binary(bytes.into())).is_err());
+        }
+        assert!(decode::<ClientMessage>(&Message::Ping(Vec::new().into())).unwrap().is_none());
+    }
+}
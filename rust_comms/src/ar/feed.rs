@@ -0,0 +1,146 @@
+use std::io::{self, BufRead, BufReader};
+use std::os::unix::net::UnixListener;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use super::protocol::ARFrame;
+
+pub const DEFAULT_FRAME_SOCKET_PATH: &str = "/tmp/merlin_ar_frames.sock";
+
+/// Frame Feed
+///
+/// Latest ARFrame from the CV pipeline, picked up by every client's stream task
+/// -Publishers overwrite, each client only sends frames newer than the last one it sent
+/// -Until something is published the bridge keeps streaming dummy frames as a heartbeat
+/// -Fed in-process (merlin_audio.PyARPublisher.serve) or over a Unix socket as JSON lines
+#[derive(Debug, Clone, Default)]
+pub struct FrameFeed {
+    latest: Arc<Mutex<Option<PublishedFrame>>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PublishedFrame {
+    /// Increments on every publish, starts at 1
+    pub version: u64,
+    pub frame: ARFrame,
+}
+
+impl FrameFeed {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the latest frame, returns its version
+    pub fn publish(&self, frame: ARFrame) -> u64 {
+        let mut latest = self.latest.lock().unwrap();
+        let version = latest.as_ref().map_or(0, |published| published.version) + 1;
+        *latest = Some(PublishedFrame { version, frame });
+        version
+    }
+
+    /// Latest frame if it's newer than `version` (0 = anything)
+    pub fn newer_than(&self, version: u64) -> Option<PublishedFrame> {
+        self.latest
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|published| published.version > version)
+            .cloned()
+    }
+
+    /// Anything published yet, decides between real and dummy frames
+    pub fn is_live(&self) -> bool {
+        self.latest.lock().unwrap().is_some()
+    }
+
+    /// Accept ARFrames as newline-delimited JSON on a Unix socket
+    /// Invalid lines are logged and skipped, the connection stays open
+    pub fn listen(&self, socket_path: impl AsRef<Path>) -> io::Result<()> {
+        let socket_path = socket_path.as_ref();
+        if socket_path.exists() {
+            std::fs::remove_file(socket_path)?; // left over from a previous run
+        }
+        let listener = UnixListener::bind(socket_path)?;
+        let feed = self.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { continue };
+                let feed = feed.clone();
+                thread::spawn(move || {
+                    for line in BufReader::new(stream).lines() {
+                        let Ok(line) = line else { break };
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+                        match serde_json::from_str::<ARFrame>(&line)
+                            .map_err(|e| e.to_string())
+                            .and_then(|frame| frame.validate().map(|_| frame))
+                        {
+                            Ok(frame) => {
+                                feed.publish(frame);
+                            }
+                            Err(e) => eprintln!("Rejected AR frame: {}", e),
+                        }
+                    }
+                });
+            }
+        });
+        println!("AR frame socket listening on {:?}", socket_path);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ar::protocol::{BoundingBox, DetectedObject};
+    use std::io::Write;
+    use std::os::unix::net::UnixStream;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_feed_only_hands_out_newer_frames() {
+        let feed = FrameFeed::new();
+        assert!(!feed.is_live());
+        assert!(feed.newer_than(0).is_none());
+
+        let version = feed.publish(ARFrame::new_dummy(1));
+        assert_eq!(version, 1);
+        assert_eq!(feed.newer_than(0).unwrap().frame.timestamp, 1);
+        assert!(feed.newer_than(version).is_none());
+
+        feed.publish(ARFrame::new_dummy(2));
+        let latest = feed.newer_than(version).unwrap();
+        assert_eq!((latest.version, latest.frame.timestamp), (2, 2));
+        assert!(feed.is_live());
+    }
+
+    #[test]
+    fn test_socket_publishes_valid_frames_only() {
+        let path = std::env::temp_dir().join(format!("merlin_ar_feed_test_{}.sock", std::process::id()));
+        let feed = FrameFeed::new();
+        feed.listen(&path).unwrap();
+
+        let mut bad = ARFrame::new_dummy(5);
+        bad.objects.push(DetectedObject {
+            class: "cup".into(),
+            confidence: 1.5,
+            bbox: BoundingBox { x: 0.1, y: 0.1, width: 0.2, height: 0.2 },
+            position_3d: None,
+            tracking_id: None,
+        });
+        let mut client = UnixStream::connect(&path).unwrap();
+        writeln!(client, "not json").unwrap();
+        writeln!(client, "{}", serde_json::to_string(&bad).unwrap()).unwrap();
+        writeln!(client, "{}", serde_json::to_string(&ARFrame::new_dummy(7)).unwrap()).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while !feed.is_live() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        let published = feed.newer_than(0).expect("valid frame was not published");
+        assert_eq!((published.version, published.frame.timestamp), (1, 7));
+        std::fs::remove_file(&path).ok();
+    }
+}
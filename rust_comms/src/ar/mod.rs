@@ -1,5 +1,11 @@
 pub mod protocol;
 pub mod bridge;
+pub mod stats;
+pub mod feed;
+pub mod codec;
 
-pub use protocol::{ARFrame, ClientMessage, ServerMessage};
-pub use bridge::{ARBridgeServer, StreamConfig};
\ No newline at end of file
+pub use protocol::{ARFrame, ClientMessage, ServerMessage, WireFormat};
+pub use bridge::{ARBridgeServer, StreamConfig};
+pub use stats::{BridgeStats, ClientStats};
+pub use feed::{FrameFeed, PublishedFrame, DEFAULT_FRAME_SOCKET_PATH};
+pub use codec::{Encoding, EncodingReport};
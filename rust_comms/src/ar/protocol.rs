@@ -1,4 +1,4 @@
-/// Big List of AR Protocol Structs
+//! Big List of AR Protocol Structs
 
 use serde::{Deserialize, Serialize};
 ///Protocol for AR data packets from Jetson -> Quest 3
@@ -20,6 +20,20 @@ pub struct ARFrame {
     pub device_states: Option <Vec<DeviceState>>,
 }
 
+/// Write sink that only counts bytes
+struct ByteCounter(usize);
+
+impl std::io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 impl ARFrame {
     /// Min AR Frame for now
     pub fn new_dummy(timestamp: u64) -> Self {
@@ -34,12 +48,42 @@ impl ARFrame {
         }
     }
 
-    // Calculate frame size in bytes
+    /// Frame size in bytes on the binary (MessagePack) wire, before compression
+    /// -Counted while encoding into a byte counter, nothing is allocated
+    /// -JSON of the same frame is several times larger, see ar::codec
     pub fn estimate_size(&self) -> usize {
-        // BaseL 16 bytes for: timestamp, frame_id, version, padding
-        // - Object: approx 40 bytes each (class, bbox, confidence)
-        // - Hnads: approx 200 bytes (21 joints, 3 coords, 4 bytes each)
-        16 + (self.objects.len() * 40) + self.hands.as_ref().map_or(0, |_| 200) + self.audio_context.as_ref().map_or(0, |_| 64)
+        let mut counter = ByteCounter(0);
+        // Plain structs, strings and numbers into a sink that never fails: encoding can't error
+        rmp_serde::encode::write(&mut counter, self).expect("ARFrame encodes to MessagePack");
+        counter.0
+    }
+
+    /// Check detector output before it reaches the headset
+    /// -Confidences in [0.0, 1.0], boxes inside the normalized frame, no NaN / inf coordinates
+    pub fn validate(&self) -> Result<(), String> {
+        for (index, object) in self.objects.iter().enumerate() {
+            object.validate().map_err(|e| format!("objects[{}]: {}", index, e))?;
+        }
+        if let Some(hands) = &self.hands {
+            hands.validate().map_err(|e| format!("hands: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+fn check_unit(field: &str, value: f32) -> Result<(), String> {
+    if (0.0..=1.0).contains(&value) {
+        Ok(())
+    } else {
+        Err(format!("{} {} outside [0.0, 1.0]", field, value))
+    }
+}
+
+fn check_finite(field: &str, vector: &Vector3) -> Result<(), String> {
+    if vector.x.is_finite() && vector.y.is_finite() && vector.z.is_finite() {
+        Ok(())
+    } else {
+        Err(format!("{} ({}, {}, {}) is not finite", field, vector.x, vector.y, vector.z))
     }
 }
 
@@ -53,6 +97,20 @@ pub struct DetectedObject {
     pub tracking_id: Option<u32>,
 }
 
+impl DetectedObject {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.class.is_empty() {
+            return Err("class is empty".into());
+        }
+        check_unit("confidence", self.confidence)?;
+        self.bbox.validate()?;
+        if let Some(position) = &self.position_3d {
+            check_finite("position_3d", position)?;
+        }
+        Ok(())
+    }
+}
+
 // 2D Bounding Box (Normalized coords)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BoundingBox {
@@ -62,6 +120,25 @@ pub struct BoundingBox {
     pub height: f32,// Box Height [0.0, 1.0]
 }
 
+/// Slack for detector rounding at the frame edge
+const BBOX_TOLERANCE: f32 = 1e-3;
+
+impl BoundingBox {
+    pub fn validate(&self) -> Result<(), String> {
+        check_unit("bbox x", self.x)?;
+        check_unit("bbox y", self.y)?;
+        check_unit("bbox width", self.width)?;
+        check_unit("bbox height", self.height)?;
+        if self.x + self.width > 1.0 + BBOX_TOLERANCE || self.y + self.height > 1.0 + BBOX_TOLERANCE {
+            return Err(format!(
+                "bbox ({}, {}, {}, {}) extends past the frame",
+                self.x, self.y, self.width, self.height
+            ));
+        }
+        Ok(())
+    }
+}
+
 // 3D Vector
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Vector3 {
@@ -97,6 +174,31 @@ pub struct HandPose {
     pub gesture: Option<GestureType>,
 }
 
+impl HandTrackingData {
+    pub fn validate(&self) -> Result<(), String> {
+        check_unit("confidence", self.confidence)?;
+        if let Some(hand) = &self.left_hand {
+            hand.validate().map_err(|e| format!("left_hand: {}", e))?;
+        }
+        if let Some(hand) = &self.right_hand {
+            hand.validate().map_err(|e| format!("right_hand: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+impl HandPose {
+    pub fn validate(&self) -> Result<(), String> {
+        for (joint, landmark) in self.landmarks.iter().enumerate() {
+            check_finite(&format!("landmark {}", joint), landmark)?;
+        }
+        for (joint, &confidence) in self.confidences.iter().enumerate() {
+            check_unit(&format!("landmark {} confidence", joint), confidence)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum GestureType {
     OpenPalm,
@@ -154,6 +256,7 @@ pub struct DeviceState {
 
 // Client to Server
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(clippy::large_enum_variant)]
 pub enum ClientMessage {
     // Init handshake with quest
     Connect {
@@ -191,6 +294,42 @@ pub struct ClientCapabilities {
     pub supports_hand_tracking: bool,
     pub supports_spatial_audio: bool,
     pub max_fps: u32,
+    /// Encodings the client reads, preferred first; empty (older clients) means JSON
+    /// Formats this server doesn't know (newer clients) are skipped
+    #[serde(default, deserialize_with = "known_wire_formats")]
+    pub wire_formats: Vec<WireFormat>,
+    /// Client inflates deflated binary messages
+    #[serde(default)]
+    pub supports_compression: bool,
+}
+
+/// Message encodings offered in Connect, see ar::codec
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum WireFormat {
+    /// Text messages, every client understands these
+    #[default]
+    Json,
+    /// Binary messages, structs as arrays without field names
+    MessagePack,
+}
+
+/// One wire_formats entry, anything unrecognized parses as Unknown instead of failing the Connect
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OfferedFormat {
+    Known(WireFormat),
+    Unknown(serde::de::IgnoredAny),
+}
+
+fn known_wire_formats<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<WireFormat>, D::Error> {
+    let offered: Vec<OfferedFormat> = Deserialize::deserialize(deserializer)?;
+    Ok(offered
+        .into_iter()
+        .filter_map(|format| match format {
+            OfferedFormat::Known(format) => Some(format),
+            OfferedFormat::Unknown(_) => None,
+        })
+        .collect())
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -219,6 +358,7 @@ pub enum InteractionType {
 
 /// Jetson to quest response
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(clippy::large_enum_variant)]
 pub enum ServerMessage {
     // Connection Acknowledgement
     Connected {
@@ -226,6 +366,11 @@ pub enum ServerMessage {
         session_id: String,
     },
     Frame(ARFrame),
+    // Reply to Connect, always sent as JSON; every message after it uses the selected encoding
+    EncodingSelected {
+        format: WireFormat,
+        compressed: bool,
+    },
     Error {
         code: u16,
         message: String,
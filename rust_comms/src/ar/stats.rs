@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Bridge Statistics
+///
+/// Counters shared between client tasks and whoever reports on them (metrics endpoint)
+/// -Totals survive disconnects, per-client entries live as long as the connection
+/// -Plain std Mutex, never held across an await
+#[derive(Debug, Default)]
+pub struct BridgeStats {
+    frames_sent: AtomicU64,
+    send_errors: AtomicU64,
+    connections: AtomicU64,
+    clients: Mutex<HashMap<String, ClientStats>>,
+}
+
+/// One connected client, keyed by session id
+#[derive(Debug, Clone)]
+pub struct ClientStats {
+    pub session_id: String,
+    /// Id from the Connect message, None until the client sends one
+    pub client_id: Option<String>,
+    pub connected_at: Instant,
+    pub frames_sent: u64,
+    pub send_errors: u64,
+    /// Frames actually delivered over the last ~1s
+    pub fps: f32,
+    window_start: Instant,
+    window_frames: u32,
+}
+
+impl ClientStats {
+    fn new(session_id: &str) -> Self {
+        let now = Instant::now();
+        Self {
+            session_id: session_id.to_string(),
+            client_id: None,
+            connected_at: now,
+            frames_sent: 0,
+            send_errors: 0,
+            fps: 0.0,
+            window_start: now,
+            window_frames: 0,
+        }
+    }
+
+    /// Label for reports: client id when known, else session id
+    pub fn name(&self) -> &str {
+        self.client_id.as_deref().unwrap_or(&self.session_id)
+    }
+}
+
+impl BridgeStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn client_connected(&self, session_id: &str) {
+        self.connections.fetch_add(1, Ordering::Relaxed);
+        self.clients
+            .lock()
+            .unwrap()
+            .insert(session_id.to_string(), ClientStats::new(session_id));
+    }
+
+    pub fn client_disconnected(&self, session_id: &str) {
+        self.clients.lock().unwrap().remove(session_id);
+    }
+
+    pub fn set_client_id(&self, session_id: &str, client_id: &str) {
+        if let Some(client) = self.clients.lock().unwrap().get_mut(session_id) {
+            client.client_id = Some(client_id.to_string());
+        }
+    }
+
+    pub fn frame_sent(&self, session_id: &str) {
+        self.frames_sent.fetch_add(1, Ordering::Relaxed);
+        if let Some(client) = self.clients.lock().unwrap().get_mut(session_id) {
+            client.frames_sent += 1;
+            client.window_frames += 1;
+            let elapsed = client.window_start.elapsed().as_secs_f32();
+            if elapsed >= 1.0 {
+                client.fps = client.window_frames as f32 / elapsed;
+                client.window_start = Instant::now();
+                client.window_frames = 0;
+            }
+        }
+    }
+
+    pub fn send_error(&self, session_id: &str) {
+        self.send_errors.fetch_add(1, Ordering::Relaxed);
+        if let Some(client) = self.clients.lock().unwrap().get_mut(session_id) {
+            client.send_errors += 1;
+        }
+    }
+
+    pub fn frames_sent(&self) -> u64 {
+        self.frames_sent.load(Ordering::Relaxed)
+    }
+
+    pub fn send_errors(&self) -> u64 {
+        self.send_errors.load(Ordering::Relaxed)
+    }
+
+    /// Connections accepted since start
+    pub fn connections(&self) -> u64 {
+        self.connections.load(Ordering::Relaxed)
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    /// Copy of every connected client, sorted by connect time
+    pub fn clients(&self) -> Vec<ClientStats> {
+        let mut clients: Vec<ClientStats> = self.clients.lock().unwrap().values().cloned().collect();
+        clients.sort_by_key(|c| c.connected_at);
+        clients
+    }
+}
@@ -0,0 +1,275 @@
+//! Offline Filter Chain
+//!
+//! Runs recorded WAVs through the same NoiseGate -> Normalizer chain as the live pipeline
+//! -Lets filter parameters be tuned against fixed input instead of a live mic
+//! -Output keeps the input's sample format, so files can be compared sample for sample
+//! -Reports levels before and after, directory runs give one report per file
+
+use super::filters::FilterConfig;
+use super::loudness::LoudnessMeter;
+use super::metrics::CLIP_THRESHOLD;
+use super::wav_writer::{quantize, PCM16_MAX, PCM24_MAX};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// SNR frames are this long
+const FRAME_SECONDS: f32 = 0.02;
+/// Percentiles of frame RMS taken as the speech and noise levels
+const SIGNAL_PERCENTILE: f32 = 0.95;
+const NOISE_PERCENTILE: f32 = 0.10;
+/// Floor for every dB figure, keeps digital silence finite in reports
+const SILENCE_DB: f32 = -120.0;
+
+/// Level measurements for one side (before or after) of a processed file
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SignalStats {
+    pub rms_db: f32,
+    pub peak_db: f32,
+    /// Integrated loudness, None when the file is too short or gated out entirely
+    pub integrated_lufs: Option<f32>,
+    /// Quiet frames (10th percentile of 20ms RMS), taken as the noise
+    pub noise_floor_db: f32,
+    /// Loud frames (95th percentile) minus the noise floor
+    pub snr_db: f32,
+    pub clipped_samples: u64,
+}
+
+impl SignalStats {
+    /// Measure interleaved samples
+    /// Args:
+    /// - samples: interleaved f32 samples
+    /// - sample_rate: audio sample rate (Hz)
+    /// - channels: interleaved channel count
+    pub fn measure(samples: &[f32], sample_rate: u32, channels: u16) -> Self {
+        let sum_squares: f64 = samples.iter().map(|&x| x as f64 * x as f64).sum();
+        let rms = (sum_squares / samples.len().max(1) as f64).sqrt() as f32;
+        let peak = samples.iter().map(|&x| x.abs()).fold(0.0f32, f32::max);
+
+        let mut loudness = LoudnessMeter::new(sample_rate, channels);
+        let integrated = loudness.process(samples).integrated_lufs;
+
+        let frame_len = ((sample_rate as f32 * FRAME_SECONDS) as usize * channels.max(1) as usize).max(1);
+        let mut frame_db: Vec<f32> = samples
+            .chunks(frame_len)
+            .map(|frame| {
+                let mean_square = frame.iter().map(|&x| x * x).sum::<f32>() / frame.len() as f32;
+                to_db(mean_square.sqrt())
+            })
+            .collect();
+        frame_db.sort_by(f32::total_cmp);
+        let noise_floor_db = percentile(&frame_db, NOISE_PERCENTILE);
+
+        Self {
+            rms_db: to_db(rms),
+            peak_db: to_db(peak),
+            integrated_lufs: integrated.is_finite().then_some(integrated),
+            noise_floor_db,
+            snr_db: percentile(&frame_db, SIGNAL_PERCENTILE) - noise_floor_db,
+            clipped_samples: samples.iter().filter(|x| x.abs() >= CLIP_THRESHOLD).count() as u64,
+        }
+    }
+}
+
+/// Before/after report for one file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProcessReport {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub duration_seconds: f64,
+    pub before: SignalStats,
+    pub after: SignalStats,
+    /// Share of samples processed with the gate open, 0-100
+    pub gate_open_percent: f32,
+}
+
+/// Run `samples` through the filter chain in place, returns the gate open percentage
+/// Same order as the live pipeline: gate, then normalizer (which hard limits to [-1, 1])
+pub fn apply_filters(samples: &mut [f32], config: &FilterConfig, sample_rate: u32) -> f32 {
+    let mut gate = config.noise_gate(sample_rate as f32);
+    let mut normalizer = config.normalizer(sample_rate as f32);
+    let mut open_samples = 0usize;
+    for sample in samples.chunks_mut(1) {
+        gate.process(sample);
+        if gate.is_open() {
+            open_samples += 1;
+        }
+    }
+    normalizer.process(samples);
+    100.0 * open_samples as f32 / samples.len().max(1) as f32
+}
+
+/// Interleaved samples of a WAV scaled to [-1.0, 1.0], whatever its sample format
+pub fn read_wav(path: &Path) -> Result<(hound::WavSpec, Vec<f32>), Box<dyn std::error::Error>> {
+    let mut reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = 1.0 / (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|s| s as f32 * scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+    Ok((spec, samples))
+}
+
+/// Process one WAV into `output` (a file path, parent dirs are created)
+pub fn process_wav(input: &Path, output: &Path, config: &FilterConfig) -> Result<ProcessReport, Box<dyn std::error::Error>> {
+    let (spec, mut samples) = read_wav(input)?;
+
+    let before = SignalStats::measure(&samples, spec.sample_rate, spec.channels);
+    let gate_open_percent = apply_filters(&mut samples, config, spec.sample_rate);
+    let after = SignalStats::measure(&samples, spec.sample_rate, spec.channels);
+
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut writer = hound::WavWriter::create(output, spec)?;
+    match (spec.sample_format, spec.bits_per_sample) {
+        (hound::SampleFormat::Float, _) => {
+            for &sample in &samples {
+                writer.write_sample(sample)?;
+            }
+        }
+        (hound::SampleFormat::Int, 16) => {
+            for &sample in &samples {
+                writer.write_sample(quantize(sample as f64 * PCM16_MAX, PCM16_MAX) as i16)?;
+            }
+        }
+        (hound::SampleFormat::Int, 24) => {
+            for &sample in &samples {
+                writer.write_sample(quantize(sample as f64 * PCM24_MAX, PCM24_MAX) as i32)?;
+            }
+        }
+        (hound::SampleFormat::Int, bits) => {
+            let max = ((1i64 << (bits - 1)) - 1) as f64;
+            for &sample in &samples {
+                writer.write_sample(quantize(sample as f64 * max, max) as i32)?;
+            }
+        }
+    }
+    writer.finalize()?;
+
+    let frames = samples.len() / spec.channels.max(1) as usize;
+    Ok(ProcessReport {
+        input: input.to_path_buf(),
+        output: output.to_path_buf(),
+        sample_rate: spec.sample_rate,
+        channels: spec.channels,
+        duration_seconds: frames as f64 / spec.sample_rate as f64,
+        before,
+        after,
+        gate_open_percent,
+    })
+}
+
+/// Process every .wav in `dir` (not recursive, sorted by name) into `out_dir` under the same names
+/// A file that fails is reported with its error and the rest still run
+pub fn process_directory(
+    dir: &Path,
+    out_dir: &Path,
+    config: &FilterConfig,
+) -> std::io::Result<Vec<(PathBuf, Result<ProcessReport, String>)>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("wav")))
+        .collect();
+    paths.sort();
+
+    Ok(paths
+        .into_iter()
+        .map(|path| {
+            let output = out_dir.join(path.file_name().expect("read_dir entries have names"));
+            let report = process_wav(&path, &output, config).map_err(|e| e.to_string());
+            (path, report)
+        })
+        .collect())
+}
+
+fn to_db(amplitude: f32) -> f32 {
+    (20.0 * amplitude.max(1e-10).log10()).max(SILENCE_DB)
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[f32], fraction: f32) -> f32 {
+    if sorted.is_empty() {
+        return SILENCE_DB;
+    }
+    let index = ((sorted.len() - 1) as f32 * fraction).round() as usize;
+    sorted[index]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Noise bed with a loud tone burst in the middle
+    fn speech_like(sample_rate: u32) -> Vec<f32> {
+        (0..sample_rate * 2)
+            .map(|i| {
+                let noise = if i % 2 == 0 { 0.002 } else { -0.002 };
+                let in_burst = (sample_rate / 2..sample_rate * 3 / 2).contains(&i);
+                let tone = if in_burst { 0.3 * (i as f32 * 0.0628).sin() } else { 0.0 };
+                noise + tone
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_process_directory_reports_before_and_after() {
+        let dir = std::env::temp_dir().join(format!("merlin_batch_{}", std::process::id()));
+        let input_dir = dir.join("in");
+        let out_dir = dir.join("out");
+        fs::create_dir_all(&input_dir).unwrap();
+
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(input_dir.join("take1.wav"), spec).unwrap();
+        for sample in speech_like(16000) {
+            writer.write_sample((sample * 32767.0) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+        fs::write(input_dir.join("broken.wav"), b"not a wav").unwrap();
+
+        let results = process_directory(&input_dir, &out_dir, &FilterConfig::default()).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].0.ends_with("broken.wav") && results[0].1.is_err());
+
+        let report = results[1].1.as_ref().unwrap();
+        assert_eq!(report.output, out_dir.join("take1.wav"));
+        assert!((report.duration_seconds - 2.0).abs() < 1e-9);
+        // Gate only opens for the burst (half the file)
+        assert!((40.0..60.0).contains(&report.gate_open_percent), "{}", report.gate_open_percent);
+        // Gate removes the -54dB noise bed, so the estimate improves a lot
+        assert!(report.before.snr_db > 30.0 && report.after.snr_db > report.before.snr_db + 20.0);
+        assert!(report.after.noise_floor_db < report.before.noise_floor_db);
+        assert_eq!(report.before.clipped_samples, 0);
+
+        let output = hound::WavReader::open(&report.output).unwrap();
+        assert_eq!(output.spec(), spec);
+        assert_eq!(output.duration(), 32000);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_signal_stats_silence_and_clipping() {
+        let silence = SignalStats::measure(&[0.0; 1600], 16000, 1);
+        assert_eq!(silence.rms_db, SILENCE_DB);
+        assert_eq!(silence.snr_db, 0.0);
+        assert_eq!(silence.integrated_lufs, None);
+
+        let clipped = SignalStats::measure(&[1.0, -1.0, 0.5, 0.0], 16000, 1);
+        assert_eq!(clipped.clipped_samples, 2);
+        assert_eq!(clipped.peak_db, 0.0);
+    }
+}
@@ -0,0 +1,377 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::encrypted_writer::{read_encrypted_info, ENCRYPTED_EXTENSION};
+use super::flac::{read_flac, read_streaminfo};
+use super::recorder::is_recording_file;
+use super::sidecar::{sidecar_path, RecordingMetadata};
+use super::traits::RecordingInfo;
+
+/// Recording Catalog
+///
+/// Index of a recordings dir built from the JSON sidecars
+/// -WAV/FLAC files without a sidecar (crash, older recordings) are indexed from the audio itself
+/// -Query by time range, duration, loudness or tag
+/// -Export copies audio + sidecars and writes a manifest
+pub struct RecordingCatalog {
+    dir: PathBuf,
+    entries: Vec<CatalogEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogEntry {
+    pub audio_path: PathBuf,
+    pub metadata: RecordingMetadata,
+    /// False when metadata was reconstructed from the audio file
+    pub has_sidecar: bool,
+}
+
+/// Filters for `RecordingCatalog::query`, all optional and combined with AND
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordingQuery {
+    /// Recordings overlapping [from, to]
+    pub from: Option<DateTime<Local>>,
+    pub to: Option<DateTime<Local>>,
+    pub min_duration_seconds: Option<f64>,
+    pub max_duration_seconds: Option<f64>,
+    /// Integrated loudness bounds, silent recordings never match these
+    pub min_lufs: Option<f32>,
+    pub max_lufs: Option<f32>,
+    pub tag: Option<String>,
+}
+
+/// Manifest written next to exported recordings
+pub const EXPORT_MANIFEST: &str = "catalog.json";
+
+impl RecordingQuery {
+    pub fn matches(&self, metadata: &RecordingMetadata) -> bool {
+        let loudness = metadata.levels.and_then(|levels| levels.integrated_lufs);
+        self.from.is_none_or(|from| metadata.ended_at >= from)
+            && self.to.is_none_or(|to| metadata.started_at <= to)
+            && self.min_duration_seconds.is_none_or(|min| metadata.duration_seconds >= min)
+            && self.max_duration_seconds.is_none_or(|max| metadata.duration_seconds <= max)
+            && self.min_lufs.is_none_or(|min| loudness.is_some_and(|lufs| lufs >= min))
+            && self.max_lufs.is_none_or(|max| loudness.is_some_and(|lufs| lufs <= max))
+            && self.tag.as_ref().is_none_or(|tag| metadata.has_tag(tag))
+    }
+}
+
+/// Parse a local time: RFC 3339, "YYYY-MM-DD HH:MM:SS", "YYYY-MM-DDTHH:MM:SS" or "YYYY-MM-DD"
+pub fn parse_time(text: &str) -> Result<DateTime<Local>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.with_timezone(&Local));
+    }
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .ok_or_else(|| format!("Unrecognized time: {}", text))?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| format!("Nonexistent local time: {}", text))
+}
+
+impl RecordingCatalog {
+    /// Index `dir`, an empty catalog if it doesn't exist yet
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let mut catalog = Self {
+            dir: dir.into(),
+            entries: Vec::new(),
+        };
+        catalog.refresh()?;
+        Ok(catalog)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Re-scan the directory
+    pub fn refresh(&mut self) -> io::Result<()> {
+        self.entries.clear();
+        if !self.dir.exists() {
+            return Ok(());
+        }
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if !is_recording_file(&path) {
+                continue;
+            }
+            match Self::load_entry(&path) {
+                Ok(entry) => self.entries.push(entry),
+                Err(e) => eprintln!("Catalog: skipping {:?}: {}", path, e),
+            }
+        }
+        self.entries
+            .sort_by(|a, b| a.metadata.started_at.cmp(&b.metadata.started_at).then_with(|| a.audio_path.cmp(&b.audio_path)));
+        Ok(())
+    }
+
+    /// Index one new or updated recording without re-scanning the directory
+    pub fn add(&mut self, audio_path: impl AsRef<Path>) -> io::Result<&CatalogEntry> {
+        let entry = Self::load_entry(audio_path.as_ref())?;
+        self.entries.retain(|existing| existing.audio_path != entry.audio_path);
+        let index = self.entries.partition_point(|existing| {
+            (&existing.metadata.started_at, &existing.audio_path) <= (&entry.metadata.started_at, &entry.audio_path)
+        });
+        self.entries.insert(index, entry);
+        Ok(&self.entries[index])
+    }
+
+    fn load_entry(audio_path: &Path) -> io::Result<CatalogEntry> {
+        let sidecar = sidecar_path(audio_path);
+        if sidecar.exists() {
+            return Ok(CatalogEntry {
+                audio_path: audio_path.to_path_buf(),
+                metadata: RecordingMetadata::load(&sidecar)?,
+                has_sidecar: true,
+            });
+        }
+        Ok(CatalogEntry {
+            audio_path: audio_path.to_path_buf(),
+            metadata: metadata_from_header(audio_path)?,
+            has_sidecar: false,
+        })
+    }
+
+    /// Every recording, oldest first
+    pub fn entries(&self) -> &[CatalogEntry] {
+        &self.entries
+    }
+
+    pub fn query(&self, query: &RecordingQuery) -> Vec<&CatalogEntry> {
+        self.entries.iter().filter(|entry| query.matches(&entry.metadata)).collect()
+    }
+
+    /// Look up by file name ("audio_X.wav") or full path
+    pub fn find(&self, file: &str) -> Option<&CatalogEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.metadata.file == file || entry.audio_path == Path::new(file))
+    }
+
+    /// Set transcript and/or add tags, creating the sidecar if the recording had none
+    pub fn annotate(&mut self, file: &str, transcript: Option<String>, tags: &[String]) -> io::Result<RecordingMetadata> {
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.metadata.file == file || entry.audio_path == Path::new(file))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("No recording {}", file)))?;
+        if transcript.is_some() {
+            entry.metadata.transcript = transcript;
+        }
+        for tag in tags {
+            if !entry.metadata.has_tag(tag) {
+                entry.metadata.tags.push(tag.clone());
+            }
+        }
+        entry.metadata.save(sidecar_path(&entry.audio_path))?;
+        entry.has_sidecar = true;
+        Ok(entry.metadata.clone())
+    }
+
+    /// Copy recordings and their sidecars into `dest`, plus a manifest of all their metadata
+    /// Returns the copied audio paths
+    pub fn export(entries: &[&CatalogEntry], dest: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+        let dest = dest.as_ref();
+        fs::create_dir_all(dest)?;
+        let mut copied = Vec::with_capacity(entries.len());
+        let mut manifest = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let target = dest.join(&entry.metadata.file);
+            fs::copy(&entry.audio_path, &target)?;
+            entry.metadata.save(sidecar_path(&target))?;
+            manifest.push(&entry.metadata);
+            copied.push(target);
+        }
+        let json = serde_json::to_string_pretty(&manifest).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(dest.join(EXPORT_MANIFEST), json)?;
+        Ok(copied)
+    }
+}
+
+/// Rate, channels and length in frames from the audio itself
+fn read_format(audio_path: &Path) -> io::Result<(u32, u16, u64)> {
+    if audio_path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case(ENCRYPTED_EXTENSION)) {
+        // Plaintext header and chunk lengths are enough, no key needed
+        let (header, frames) = read_encrypted_info(audio_path)?;
+        return Ok((header.sample_rate, header.channels, frames));
+    }
+    if audio_path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("flac")) {
+        // STREAMINFO is patched every commit, only a recording killed before its first one still says 0
+        let info = read_streaminfo(audio_path)?;
+        let frames = if info.total_frames > 0 { info.total_frames } else { read_flac(audio_path)?.frames() };
+        return Ok((info.sample_rate, info.channels, frames));
+    }
+    let reader = hound::WavReader::open(audio_path).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let spec = reader.spec();
+    Ok((spec.sample_rate, spec.channels, reader.duration() as u64))
+}
+
+/// Best effort metadata for a recording with no sidecar
+/// Start time from the audio_YYYYMMDD_HHMMSS name, else the file's mtime minus its duration
+fn metadata_from_header(audio_path: &Path) -> io::Result<RecordingMetadata> {
+    let (sample_rate, channels, frames) = read_format(audio_path)?;
+    let duration_seconds = frames as f64 / sample_rate as f64;
+    let file_metadata = fs::metadata(audio_path)?;
+    let duration = chrono::Duration::milliseconds((duration_seconds * 1000.0) as i64);
+
+    let started_at = audio_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.strip_prefix("audio_"))
+        .and_then(|stamp| stamp.get(..15))
+        .and_then(|stamp| NaiveDateTime::parse_from_str(stamp, "%Y%m%d_%H%M%S").ok())
+        .and_then(|naive| Local.from_local_datetime(&naive).earliest())
+        .unwrap_or_else(|| {
+            let modified: DateTime<Local> = file_metadata.modified().map(Into::into).unwrap_or_else(|_| Local::now());
+            modified - duration
+        });
+
+    let info = RecordingInfo {
+        file_path: audio_path.to_path_buf(),
+        duration_seconds,
+        file_size_bytes: file_metadata.len(),
+        sample_rate,
+        channels,
+    };
+    Ok(RecordingMetadata::new(&info, started_at, started_at + duration))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::flac::FlacEncoder;
+    use crate::audio::flac_writer::FlacFileWriter;
+    use crate::audio::sidecar::RecordingContext;
+    use crate::audio::traits::AudioWriter;
+    use crate::audio::wav_writer::WavFileWriter;
+    use crate::audio::FilterConfig;
+    use std::f32::consts::PI;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("merlin_catalog_{}", uuid::Uuid::new_v4()))
+    }
+
+    fn record(dir: &Path, seconds: f32, amplitude: f32, tags: &[&str]) -> PathBuf {
+        let mut writer = WavFileWriter::new(dir).with_context(RecordingContext {
+            device: Some("test mic".to_string()),
+            filters: Some(FilterConfig::default()),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        });
+        writer.start_writing(16000, 1).unwrap();
+        let samples: Vec<f32> = (0..(seconds * 16000.0) as usize)
+            .map(|n| amplitude * (2.0 * PI * 1000.0 * n as f32 / 16000.0).sin())
+            .collect();
+        writer.write_samples(&samples).unwrap();
+        writer.finish_writing().unwrap().unwrap().file_path
+    }
+
+    #[test]
+    fn test_sidecar_written_and_queried() {
+        let dir = temp_dir();
+        let loud = record(&dir, 2.0, 0.5, &["wake"]);
+        let quiet = record(&dir, 1.0, 0.01, &[]);
+        let silent = record(&dir, 0.5, 0.0, &[]);
+
+        let sidecar = RecordingMetadata::load(sidecar_path(&loud)).unwrap();
+        assert_eq!(sidecar.device.as_deref(), Some("test mic"));
+        assert_eq!(sidecar.filters, Some(FilterConfig::default()));
+        assert_eq!((sidecar.sample_rate, sidecar.channels), (16000, 1));
+        assert!((sidecar.duration_seconds - 2.0).abs() < 1e-9);
+        assert!(sidecar.ended_at >= sidecar.started_at);
+        let levels = sidecar.levels.unwrap();
+        assert!((levels.peak_db - 20.0 * 0.5f32.log10()).abs() < 0.1);
+        assert!(levels.integrated_lufs.is_some());
+        assert_eq!(RecordingMetadata::load(sidecar_path(&silent)).unwrap().levels.unwrap().integrated_lufs, None);
+
+        let catalog = RecordingCatalog::open(&dir).unwrap();
+        assert_eq!(catalog.entries().len(), 3);
+        let files = |query: RecordingQuery| -> Vec<PathBuf> {
+            catalog.query(&query).iter().map(|e| e.audio_path.clone()).collect()
+        };
+        assert_eq!(files(RecordingQuery { tag: Some("WAKE".to_string()), ..Default::default() }), vec![loud.clone()]);
+        assert_eq!(files(RecordingQuery { min_duration_seconds: Some(0.9), max_duration_seconds: Some(1.1), ..Default::default() }), vec![quiet.clone()]);
+        assert_eq!(files(RecordingQuery { min_lufs: Some(-20.0), ..Default::default() }), vec![loud.clone()]);
+        assert_eq!(files(RecordingQuery { max_lufs: Some(-20.0), ..Default::default() }), vec![quiet.clone()]);
+        let now = Local::now();
+        assert_eq!(files(RecordingQuery { from: Some(now - chrono::Duration::hours(1)), to: Some(now), ..Default::default() }).len(), 3);
+        assert!(files(RecordingQuery { to: Some(now - chrono::Duration::hours(1)), ..Default::default() }).is_empty());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_catalog_annotate_and_export() {
+        let dir = temp_dir();
+        let recording = record(&dir, 0.5, 0.2, &[]);
+        // Crashed recording: no sidecar, indexed from its header and name
+        let orphan = dir.join("audio_20260101_120000.wav");
+        fs::copy(&recording, &orphan).unwrap();
+
+        let mut catalog = RecordingCatalog::open(&dir).unwrap();
+        // Recordings made after open() are added one at a time, in time order
+        let later = record(&dir, 0.25, 0.2, &["later"]);
+        assert_eq!(catalog.entries().len(), 2);
+        assert!(catalog.add(&later).unwrap().metadata.has_tag("later"));
+        catalog.add(&later).unwrap();
+        assert_eq!(catalog.entries().len(), 3);
+        assert_eq!(catalog.entries().last().unwrap().audio_path, later);
+        let entry = catalog.find("audio_20260101_120000.wav").unwrap();
+        assert!(!entry.has_sidecar);
+        assert_eq!(entry.metadata.started_at, parse_time("2026-01-01 12:00:00").unwrap());
+        assert!((entry.metadata.duration_seconds - 0.5).abs() < 1e-9);
+
+        let name = recording.file_name().unwrap().to_str().unwrap().to_string();
+        catalog
+            .annotate(&name, Some("merlin what time is it".to_string()), &["question".to_string()])
+            .unwrap();
+        let reloaded = RecordingCatalog::open(&dir).unwrap();
+        let annotated = reloaded.find(&name).unwrap();
+        assert_eq!(annotated.metadata.transcript.as_deref(), Some("merlin what time is it"));
+        assert!(annotated.metadata.has_tag("question"));
+
+        let dest = temp_dir();
+        let selected = reloaded.query(&RecordingQuery { tag: Some("question".to_string()), ..Default::default() });
+        let copied = RecordingCatalog::export(&selected, &dest).unwrap();
+        assert_eq!(copied, vec![dest.join(&name)]);
+        assert!(sidecar_path(&copied[0]).exists());
+        let manifest: Vec<RecordingMetadata> =
+            serde_json::from_str(&fs::read_to_string(dest.join(EXPORT_MANIFEST)).unwrap()).unwrap();
+        assert_eq!(manifest, vec![annotated.metadata.clone()]);
+        fs::remove_dir_all(&dir).ok();
+        fs::remove_dir_all(&dest).ok();
+    }
+
+    #[test]
+    fn test_flac_length_from_streaminfo() {
+        let dir = temp_dir();
+        let mut writer = FlacFileWriter::new(&dir);
+        writer.start_writing(16000, 2).unwrap();
+        writer.write_samples(&vec![0.1; 16000 * 2 * 3 / 2]).unwrap();
+        let finished = writer.finish_writing().unwrap().unwrap().file_path;
+        fs::remove_file(sidecar_path(&finished)).unwrap();
+
+        // Killed before its first commit: STREAMINFO still says 0 frames, the frames are counted instead
+        let mut encoder = FlacEncoder::new(16000, 1, 16);
+        let mut file = encoder.stream_info().header_bytes();
+        encoder.push(&vec![100; 5000], &mut file);
+        let killed = dir.join("audio_20260101_120000.flac");
+        fs::write(&killed, file).unwrap();
+
+        let catalog = RecordingCatalog::open(&dir).unwrap();
+        let entry = catalog.find(finished.file_name().unwrap().to_str().unwrap()).unwrap();
+        assert!(!entry.has_sidecar);
+        assert_eq!((entry.metadata.sample_rate, entry.metadata.channels), (16000, 2));
+        assert!((entry.metadata.duration_seconds - 1.5).abs() < 1e-9);
+        let entry = catalog.find("audio_20260101_120000.flac").unwrap();
+        assert!((entry.metadata.duration_seconds - 4096.0 / 16000.0).abs() < 1e-9);
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,117 @@
+//! Recording Crypto
+//!
+//! Thin layer over the RustCrypto crates behind encrypted recordings
+//! -ChaCha20-Poly1305 AEAD (RFC 8439) from chacha20poly1305
+//! -HMAC-SHA256 and PBKDF2-HMAC-SHA256 from hmac / sha2 / pbkdf2, tags checked in constant time
+//! -Keys are Zeroizing, wiped from memory when dropped
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::ChaCha20Poly1305;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+/// 256-bit key, zeroed on drop
+pub type Key = Zeroizing<[u8; 32]>;
+/// 96-bit nonce
+pub type Nonce = [u8; 12];
+
+pub const TAG_BYTES: usize = 16;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Authentication failed: wrong key, or the data / associated data was modified
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthenticationFailed;
+
+impl std::fmt::Display for AuthenticationFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "authentication failed")
+    }
+}
+
+impl std::error::Error for AuthenticationFailed {}
+
+/// Encrypt `plaintext`, returns ciphertext with the 16-byte tag appended
+pub fn seal(key: &Key, nonce: &Nonce, aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    ChaCha20Poly1305::new(key.as_ref().into())
+        .encrypt(nonce.into(), Payload { msg: plaintext, aad })
+        .expect("ChaCha20-Poly1305 seals up to 256 GiB per message")
+}
+
+/// Check the tag and decrypt, nothing is returned unless it verifies
+pub fn open(key: &Key, nonce: &Nonce, aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, AuthenticationFailed> {
+    ChaCha20Poly1305::new(key.as_ref().into())
+        .decrypt(nonce.into(), Payload { msg: sealed, aad })
+        .map_err(|_| AuthenticationFailed)
+}
+
+fn hmac(key: &[u8], parts: &[&[u8]]) -> HmacSha256 {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(key).expect("HMAC takes keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac
+}
+
+/// HMAC-SHA256 over the concatenated `parts`
+pub fn hmac_sha256(key: &[u8], parts: &[&[u8]]) -> Key {
+    Zeroizing::new(hmac(key, parts).finalize().into_bytes().into())
+}
+
+/// Constant-time check of a tag truncated to its first `tag.len()` bytes
+pub fn verify_hmac_sha256(key: &[u8], parts: &[&[u8]], tag: &[u8]) -> bool {
+    hmac(key, parts).verify_truncated_left(tag).is_ok()
+}
+
+pub fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32) -> Key {
+    let mut key = Zeroizing::new([0u8; 32]);
+    pbkdf2::pbkdf2_hmac::<Sha256>(password, salt, iterations, key.as_mut());
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_chacha20_poly1305_rfc8439_vector() {
+        let key: Key = Zeroizing::new(std::array::from_fn(|i| 0x80 + i as u8));
+        let nonce: Nonce = [0x07, 0, 0, 0, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47];
+        let aad = [0x50, 0x51, 0x52, 0x53, 0xc0, 0xc1, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7];
+        let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+
+        let sealed = seal(&key, &nonce, &aad, plaintext);
+        assert_eq!(sealed.len(), plaintext.len() + TAG_BYTES);
+        assert_eq!(hex(&sealed[..16]), "d31a8d34648e60db7b86afbc53ef7ec2");
+        assert_eq!(hex(&sealed[plaintext.len()..]), "1ae10b594f09e26a7e902ecbd0600691");
+        assert_eq!(open(&key, &nonce, &aad, &sealed).unwrap(), plaintext);
+
+        // Any flipped bit, in the ciphertext, tag or associated data, is rejected
+        let mut tampered = sealed.clone();
+        tampered[20] ^= 1;
+        assert_eq!(open(&key, &nonce, &aad, &tampered), Err(AuthenticationFailed));
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 0x80;
+        assert!(open(&key, &nonce, &aad, &tampered).is_err());
+        assert!(open(&key, &nonce, &aad[1..], &sealed).is_err());
+        assert!(open(&key, &nonce, &aad, &sealed[..TAG_BYTES - 1]).is_err());
+    }
+
+    #[test]
+    fn test_hmac_and_pbkdf2_vectors() {
+        // RFC 4231 test case 2
+        let tag = hmac_sha256(b"Jefe", &[b"what do ya want ", b"for nothing?"]);
+        assert_eq!(hex(tag.as_ref()), "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+        assert!(verify_hmac_sha256(b"Jefe", &[b"what do ya want for nothing?"], &tag[..16]));
+        assert!(!verify_hmac_sha256(b"jefe", &[b"what do ya want for nothing?"], &tag[..16]));
+        assert_eq!(
+            hex(pbkdf2_sha256(b"password", b"salt", 4096).as_ref()),
+            "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a"
+        );
+    }
+}
@@ -0,0 +1,716 @@
+use super::crypto::{self, Key, Nonce, TAG_BYTES};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+/// File magic, the trailing digit is the format version
+pub const ENCRYPTED_MAGIC: &[u8; 8] = b"MRLNENC2";
+pub const ENCRYPTED_EXTENSION: &str = "menc";
+/// OWASP's current recommendation for PBKDF2-HMAC-SHA256, paid once per recorder start
+pub const DEFAULT_PBKDF2_ITERATIONS: u32 = 600_000;
+
+/// Most plaintext per record, a flush seals whatever is pending early
+const RECORD_BYTES: usize = 64 * 1024;
+/// u64 plaintext offset + u32 length prefix + Poly1305 tag
+const RECORD_OVERHEAD: u64 = 8 + 4 + TAG_BYTES as u64;
+/// Kept free under a size limit: the final record and two commits of header patches
+const RESERVED_BYTES: u64 = RECORD_OVERHEAD + 8 * (RECORD_OVERHEAD + 64);
+/// magic, kdf, iterations, kdf salt, file salt, sample rate, channels, inner format, bits, key check
+const HEADER_BYTES: usize = 8 + 1 + 4 + 16 + 16 + 4 + 2 + 1 + 2 + 16;
+const FILE_KEY_CONTEXT: &[u8] = b"merlin-recording-v1";
+const KEY_CHECK_CONTEXT: &[u8] = b"key-check";
+/// Formats that can be inside, by their index in the header
+const INNER_EXTENSIONS: [&str; 2] = ["wav", "flac"];
+/// hound's WAV header: canonical PCM, or WAVE_FORMAT_EXTENSIBLE past 16 bits or 2 channels
+const WAV_HEADER_BYTES: u64 = 44;
+const WAV_EXTENSIBLE_HEADER_BYTES: u64 = 68;
+
+/// How the master key was made, stored in every file header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyDerivation {
+    /// Raw 256-bit key from a key file
+    KeyFile,
+    Pbkdf2 { salt: [u8; 16], iterations: u32 },
+}
+
+/// Master key for encrypted recordings, zeroed when each copy is dropped
+/// Each file gets its own key derived from this and a random per-file salt
+#[derive(Clone)]
+pub struct RecordingKey {
+    key: Key,
+    derivation: KeyDerivation,
+}
+
+/// Never print key material
+impl std::fmt::Debug for RecordingKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecordingKey").field("derivation", &self.derivation).finish_non_exhaustive()
+    }
+}
+
+impl RecordingKey {
+    /// Derive from a passphrase with a fresh random salt
+    pub fn from_passphrase(passphrase: &str) -> io::Result<Self> {
+        Self::from_passphrase_with_iterations(passphrase, DEFAULT_PBKDF2_ITERATIONS)
+    }
+
+    pub fn from_passphrase_with_iterations(passphrase: &str, iterations: u32) -> io::Result<Self> {
+        if passphrase.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Empty passphrase"));
+        }
+        let salt = random_bytes()?;
+        Ok(Self {
+            key: crypto::pbkdf2_sha256(passphrase.as_bytes(), &salt, iterations.max(1)),
+            derivation: KeyDerivation::Pbkdf2 { salt, iterations: iterations.max(1) },
+        })
+    }
+
+    /// Key file holding 64 hex characters (as written by `generate_key_file`) or 32 raw bytes
+    pub fn from_key_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let bytes = Zeroizing::new(fs::read(path)?);
+        let key = if let Some(key) = std::str::from_utf8(&bytes).ok().and_then(|text| parse_hex_key(text.trim())) {
+            key
+        } else if let Ok(raw) = <[u8; 32]>::try_from(bytes.as_slice()) {
+            Zeroizing::new(raw)
+        } else {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Key file must hold 64 hex characters or 32 raw bytes"));
+        };
+        Ok(Self { key, derivation: KeyDerivation::KeyFile })
+    }
+
+    /// Write a new random key file readable only by its owner
+    pub fn generate_key_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        use std::os::unix::fs::OpenOptionsExt;
+        let key: Key = Zeroizing::new(random_bytes()?);
+        let hex: Zeroizing<String> = Zeroizing::new(key.iter().map(|b| format!("{:02x}", b)).collect());
+        let mut file = File::options().write(true).create_new(true).mode(0o600).open(path)?;
+        writeln!(file, "{}", hex.as_str())?;
+        Ok(Self { key, derivation: KeyDerivation::KeyFile })
+    }
+
+    pub fn derivation(&self) -> KeyDerivation {
+        self.derivation
+    }
+
+    fn file_key(&self, file_salt: &[u8; 16]) -> Key {
+        crypto::hmac_sha256(self.key.as_ref(), &[FILE_KEY_CONTEXT, file_salt])
+    }
+}
+
+fn parse_hex_key(text: &str) -> Option<Key> {
+    if text.len() != 64 {
+        return None;
+    }
+    let mut key = Zeroizing::new([0u8; 32]);
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(text.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(key)
+}
+
+fn random_bytes<const N: usize>() -> io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    getrandom::fill(&mut bytes).map_err(|e| io::Error::other(e.to_string()))?;
+    Ok(bytes)
+}
+
+/// Check value proving the file key is right before any record is touched
+fn key_check(file_key: &Key) -> [u8; 16] {
+    crypto::hmac_sha256(file_key.as_ref(), &[KEY_CHECK_CONTEXT])[..16].try_into().expect("16 byte slice")
+}
+
+/// Record nonce: final flag, then the record index, so records can't be reordered, dropped or cut off unnoticed
+fn record_nonce(index: u64, last: bool) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[0] = last as u8;
+    nonce[4..].copy_from_slice(&index.to_be_bytes());
+    nonce
+}
+
+/// Associated data of a record: the file header and where the record's bytes go
+fn record_aad(header: &[u8], offset: u64) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(header.len() + 8);
+    aad.extend_from_slice(header);
+    aad.extend_from_slice(&offset.to_le_bytes());
+    aad
+}
+
+/// Encrypting file under a recording's format writer (see RecordingFileWriter::with_encryption)
+///
+/// Recordings never touch the disk in the clear, whatever the format
+/// -Writes are sealed with ChaCha20-Poly1305 in records of up to 64 KiB as they are flushed
+/// -Each record carries the plaintext offset of its bytes, so header patches (WAV sizes,
+///  FLAC STREAMINFO) append a small record instead of rewriting sealed data: no nonce is
+///  ever reused, and a crash can only cut off the tail
+/// -Per-file key from the master key (passphrase or key file) and a random salt in the header
+/// -Header and offset are authenticated with every record; record order and the final record
+///  are bound into the nonces
+pub struct EncryptedWriter {
+    file: BufWriter<File>,
+    file_key: Key,
+    header: Vec<u8>,
+    pending: Vec<u8>, //Plaintext not sealed yet
+    pending_offset: u64,
+    position: u64,
+    len: u64, //Plaintext length
+    record_index: u64,
+    bytes_written: u64, //Header + sealed records
+}
+
+impl EncryptedWriter {
+    /// New file at `path` holding an `extension` ("wav" / "flac") stream, fails if `path` exists
+    pub fn create(path: &Path, key: &RecordingKey, extension: &str, sample_rate: u32, channels: u16, bits_per_sample: u16) -> io::Result<Self> {
+        let inner_extension = INNER_EXTENSIONS
+            .into_iter()
+            .find(|known| *known == extension)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("Can't encrypt .{} recordings", extension)))?;
+        let file_salt: [u8; 16] = random_bytes()?;
+        let file_key = key.file_key(&file_salt);
+        let header = encode_header(&EncryptedHeader {
+            derivation: key.derivation,
+            file_salt,
+            sample_rate,
+            channels,
+            bits_per_sample,
+            inner_extension,
+            key_check: key_check(&file_key),
+        });
+        let mut file = BufWriter::new(File::options().write(true).create_new(true).open(path)?);
+        file.write_all(&header)?;
+        Ok(Self {
+            file,
+            file_key,
+            bytes_written: header.len() as u64,
+            header,
+            pending: Vec::with_capacity(RECORD_BYTES),
+            pending_offset: 0,
+            position: 0,
+            len: 0,
+            record_index: 0,
+        })
+    }
+
+    fn seal_pending(&mut self, last: bool) -> io::Result<()> {
+        let aad = record_aad(&self.header, self.pending_offset);
+        let sealed = crypto::seal(&self.file_key, &record_nonce(self.record_index, last), &aad, &self.pending);
+        self.file.write_all(&self.pending_offset.to_le_bytes())?;
+        self.file.write_all(&(sealed.len() as u32).to_le_bytes())?;
+        self.file.write_all(&sealed)?;
+        self.bytes_written += RECORD_OVERHEAD + self.pending.len() as u64;
+        self.record_index += 1;
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Size of the file once pending bytes are sealed
+    pub fn bytes_written(&self) -> u64 {
+        match self.pending.len() as u64 {
+            0 => self.bytes_written,
+            pending => self.bytes_written + RECORD_OVERHEAD + pending,
+        }
+    }
+
+    /// Plaintext bytes that can still be appended without the file passing `max_bytes`
+    /// Leaves room for record overhead, the final record and the header patches of a commit
+    pub fn room(&self, max_bytes: u64) -> u64 {
+        let available = max_bytes.saturating_sub(self.bytes_written() + RESERVED_BYTES);
+        available.saturating_sub(RECORD_OVERHEAD * (available / RECORD_BYTES as u64 + 2))
+    }
+
+    /// Seal pending bytes and fsync the data
+    pub fn sync_data(&mut self) -> io::Result<()> {
+        self.flush()?;
+        self.file.get_ref().sync_data()
+    }
+
+    /// Seal the final record (possibly empty), which marks the recording complete, and fsync
+    /// Nothing can be written after this
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            self.pending_offset = self.position;
+        }
+        self.seal_pending(true)?;
+        self.file.flush()?;
+        self.file.get_ref().sync_all()
+    }
+}
+
+impl Write for EncryptedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // A seek since the last write starts a new record
+        if !self.pending.is_empty() && self.position != self.pending_offset + self.pending.len() as u64 {
+            self.seal_pending(false)?;
+        }
+        if self.pending.is_empty() {
+            self.pending_offset = self.position;
+        }
+        let accepted = buf.len().min(RECORD_BYTES - self.pending.len());
+        self.pending.extend_from_slice(&buf[..accepted]);
+        self.position += accepted as u64;
+        self.len = self.len.max(self.position);
+        if self.pending.len() == RECORD_BYTES {
+            self.seal_pending(false)?;
+        }
+        Ok(accepted)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.pending.is_empty() {
+            self.seal_pending(false)?;
+        }
+        self.file.flush()
+    }
+}
+
+impl Seek for EncryptedWriter {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+        };
+        self.position = position.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Seek before the start of the recording"))?;
+        Ok(self.position)
+    }
+}
+
+/// Plaintext header of an encrypted recording
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedHeader {
+    pub derivation: KeyDerivation,
+    pub file_salt: [u8; 16],
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub bits_per_sample: u16,
+    /// Format of the decrypted file, "wav" or "flac"
+    pub inner_extension: &'static str,
+    key_check: [u8; 16],
+}
+
+fn encode_header(header: &EncryptedHeader) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HEADER_BYTES);
+    bytes.extend_from_slice(ENCRYPTED_MAGIC);
+    let (kind, iterations, salt) = match header.derivation {
+        KeyDerivation::KeyFile => (0u8, 0u32, [0u8; 16]),
+        KeyDerivation::Pbkdf2 { salt, iterations } => (1, iterations, salt),
+    };
+    bytes.push(kind);
+    bytes.extend_from_slice(&iterations.to_le_bytes());
+    bytes.extend_from_slice(&salt);
+    bytes.extend_from_slice(&header.file_salt);
+    bytes.extend_from_slice(&header.sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&header.channels.to_le_bytes());
+    let inner = INNER_EXTENSIONS.iter().position(|known| *known == header.inner_extension).unwrap_or(0);
+    bytes.push(inner as u8);
+    bytes.extend_from_slice(&header.bits_per_sample.to_le_bytes());
+    bytes.extend_from_slice(&header.key_check);
+    bytes
+}
+
+fn decode_header(bytes: &[u8; HEADER_BYTES]) -> io::Result<EncryptedHeader> {
+    if &bytes[..8] != ENCRYPTED_MAGIC {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Not an encrypted MERLIN recording (or an older format version)"));
+    }
+    let array = |range: std::ops::Range<usize>| -> [u8; 16] { bytes[range].try_into().expect("16 byte slice") };
+    let derivation = match bytes[8] {
+        0 => KeyDerivation::KeyFile,
+        1 => KeyDerivation::Pbkdf2 {
+            iterations: u32::from_le_bytes(bytes[9..13].try_into().expect("4 byte slice")),
+            salt: array(13..29),
+        },
+        other => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Unknown key derivation {}", other))),
+    };
+    let channels = u16::from_le_bytes([bytes[49], bytes[50]]);
+    if channels == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Header says 0 channels"));
+    }
+    let inner_extension = *INNER_EXTENSIONS
+        .get(bytes[51] as usize)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("Unknown inner format {}", bytes[51])))?;
+    Ok(EncryptedHeader {
+        derivation,
+        file_salt: array(29..45),
+        sample_rate: u32::from_le_bytes(bytes[45..49].try_into().expect("4 byte slice")),
+        channels,
+        bits_per_sample: u16::from_le_bytes([bytes[52], bytes[53]]),
+        inner_extension,
+        key_check: array(54..70),
+    })
+}
+
+/// Header plus an estimate of the frames inside, no key needed
+/// Used to index encrypted recordings that lost their sidecar: WAV length comes from the
+/// plaintext size, FLAC is compressed so it reads as 0 frames until decrypted
+pub fn read_encrypted_info(path: impl AsRef<Path>) -> io::Result<(EncryptedHeader, u64)> {
+    let mut file = File::open(path)?;
+    let mut header_bytes = [0u8; HEADER_BYTES];
+    file.read_exact(&mut header_bytes)?;
+    let header = decode_header(&header_bytes)?;
+    let file_len = file.metadata()?.len();
+    let mut position = HEADER_BYTES as u64;
+    let mut plaintext_bytes = 0u64;
+    let mut prefix = [0u8; 12];
+    while position + RECORD_OVERHEAD <= file_len {
+        file.seek(SeekFrom::Start(position))?;
+        file.read_exact(&mut prefix)?;
+        let offset = u64::from_le_bytes(prefix[..8].try_into().expect("8 byte slice"));
+        let sealed = u32::from_le_bytes(prefix[8..].try_into().expect("4 byte slice")) as u64;
+        if sealed < TAG_BYTES as u64 || position + 12 + sealed > file_len {
+            break;
+        }
+        plaintext_bytes = plaintext_bytes.max(offset.saturating_add(sealed - TAG_BYTES as u64));
+        position += 12 + sealed;
+    }
+    let frames = match header.inner_extension {
+        "wav" => {
+            let frame_bytes = (header.bits_per_sample as u64 / 8).max(1) * header.channels as u64;
+            let header_bytes = match header.channels > 2 || header.bits_per_sample > 16 {
+                true => WAV_EXTENSIBLE_HEADER_BYTES,
+                false => WAV_HEADER_BYTES,
+            };
+            plaintext_bytes.saturating_sub(header_bytes) / frame_bytes
+        }
+        _ => 0,
+    };
+    Ok((header, frames))
+}
+
+/// Where the decryptor gets the master key
+#[derive(Clone)]
+pub enum KeySource {
+    Passphrase(Zeroizing<String>),
+    KeyFile(PathBuf),
+}
+
+/// What decrypting a recording found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecryptedRecording {
+    pub header: EncryptedHeader,
+    /// Length of the decrypted WAV / FLAC file
+    pub bytes: u64,
+    /// False when the final record is missing: the recorder was killed, or the file was cut short
+    pub complete: bool,
+}
+
+/// Decrypts recordings one record at a time, remembering the last derived key so a
+/// directory of files from one passphrase pays for PBKDF2 once
+pub struct RecordingDecryptor {
+    source: KeySource,
+    cached: Option<(KeyDerivation, RecordingKey)>,
+}
+
+impl RecordingDecryptor {
+    pub fn new(source: KeySource) -> Self {
+        Self { source, cached: None }
+    }
+
+    fn master_key(&mut self, derivation: KeyDerivation) -> io::Result<RecordingKey> {
+        if let Some((cached_derivation, ref key)) = self.cached
+            && cached_derivation == derivation
+        {
+            return Ok(key.clone());
+        }
+        let key = match (&self.source, derivation) {
+            (KeySource::KeyFile(path), KeyDerivation::KeyFile) => RecordingKey::from_key_file(path)?,
+            (KeySource::Passphrase(passphrase), KeyDerivation::Pbkdf2 { salt, iterations }) => RecordingKey {
+                key: crypto::pbkdf2_sha256(passphrase.as_bytes(), &salt, iterations),
+                derivation,
+            },
+            (KeySource::KeyFile(_), _) => {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "Recording was encrypted with a passphrase, not a key file"));
+            }
+            (KeySource::Passphrase(_), _) => {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "Recording was encrypted with a key file, not a passphrase"));
+            }
+        };
+        self.cached = Some((derivation, key.clone()));
+        Ok(key)
+    }
+
+    /// Authenticate and decrypt a recording into `out`, one record in memory at a time
+    /// Any modified byte fails with InvalidData, a wrong key with PermissionDenied;
+    /// `out` may hold part of the plaintext when it fails
+    pub fn decrypt_to<W: Write + Seek>(&mut self, path: impl AsRef<Path>, out: &mut W) -> io::Result<DecryptedRecording> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut header_bytes = [0u8; HEADER_BYTES];
+        file.read_exact(&mut header_bytes)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Truncated header"))?;
+        let header = decode_header(&header_bytes)?;
+        let file_key = self.master_key(header.derivation)?.file_key(&header.file_salt);
+        if !crypto::verify_hmac_sha256(file_key.as_ref(), &[KEY_CHECK_CONTEXT], &header.key_check) {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "Wrong key (or corrupted header)"));
+        }
+
+        let mut prefix = [0u8; 12];
+        let mut sealed = Vec::with_capacity(RECORD_BYTES + TAG_BYTES);
+        let (mut index, mut bytes, mut complete) = (0u64, 0u64, false);
+        loop {
+            match file.read_exact(&mut prefix) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break, // end, or cut off mid-write
+                Err(e) => return Err(e),
+            }
+            if complete {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "Data after the final record"));
+            }
+            let offset = u64::from_le_bytes(prefix[..8].try_into().expect("8 byte slice"));
+            let length = u32::from_le_bytes(prefix[8..].try_into().expect("4 byte slice")) as usize;
+            if !(TAG_BYTES..=RECORD_BYTES + TAG_BYTES).contains(&length) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Record {} has an impossible length", index)));
+            }
+            sealed.resize(length, 0);
+            match file.read_exact(&mut sealed) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+            // Only the final record authenticates with the final flag set
+            let aad = record_aad(&header_bytes, offset);
+            let plaintext = match crypto::open(&file_key, &record_nonce(index, false), &aad, &sealed) {
+                Ok(plaintext) => plaintext,
+                Err(_) => {
+                    let plaintext = crypto::open(&file_key, &record_nonce(index, true), &aad, &sealed).map_err(|_| {
+                        io::Error::new(io::ErrorKind::InvalidData, format!("Record {} failed authentication (tampered or corrupt)", index))
+                    })?;
+                    complete = true;
+                    plaintext
+                }
+            };
+            out.seek(SeekFrom::Start(offset))?;
+            out.write_all(&plaintext)?;
+            bytes = bytes.max(offset + plaintext.len() as u64);
+            index += 1;
+        }
+        out.flush()?;
+        Ok(DecryptedRecording { header, bytes, complete })
+    }
+
+    /// Decrypt into `dest_dir`, named after the recording (audio_X.wav.menc -> dest/audio_X.wav)
+    /// -Written to a .partial file and renamed once every record authenticated, nothing is left on failure
+    /// -A truncated recording (no final record) fails with UnexpectedEof unless `allow_truncated`
+    /// -The sidecar is copied along if there is one
+    pub fn export(&mut self, path: impl AsRef<Path>, dest_dir: impl AsRef<Path>, allow_truncated: bool) -> io::Result<(PathBuf, DecryptedRecording)> {
+        let path = path.as_ref();
+        let dest_dir = dest_dir.as_ref();
+        fs::create_dir_all(dest_dir)?;
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "No file name"))?;
+        let partial = dest_dir.join(format!("{}.partial", stem));
+
+        let result = File::create(&partial).and_then(|file| {
+            let mut out = BufWriter::new(file);
+            let recording = self.decrypt_to(path, &mut out)?;
+            out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+            if !recording.complete && !allow_truncated {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Final record missing (cut short or recorder killed)"));
+            }
+            Ok(recording)
+        });
+        let recording = match result {
+            Ok(recording) => recording,
+            Err(e) => {
+                fs::remove_file(&partial).ok();
+                return Err(e);
+            }
+        };
+
+        let extension = recording.header.inner_extension;
+        let target = if Path::new(&stem).extension().is_some_and(|ext| ext == extension) {
+            dest_dir.join(&stem)
+        } else {
+            dest_dir.join(format!("{}.{}", stem, extension))
+        };
+        fs::rename(&partial, &target)?;
+
+        let sidecar = super::sidecar::sidecar_path(path);
+        if sidecar.exists() {
+            let mut metadata = super::sidecar::RecordingMetadata::load(&sidecar)?;
+            metadata.file = target.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            metadata.file_size_bytes = recording.bytes;
+            metadata.save(super::sidecar::sidecar_path(&target))?;
+        }
+        Ok((target, recording))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::flac::read_flac;
+    use crate::audio::traits::{AudioWriter, RecordingInfo};
+    use crate::audio::{FlacFileWriter, RotationPolicy, WavFileWriter};
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("merlin_enc_{}", uuid::Uuid::new_v4()))
+    }
+
+    fn record(writer: &mut impl AudioWriter<Error = Box<dyn std::error::Error>>, samples: &[f32]) -> RecordingInfo {
+        writer.start_writing(16000, 2).unwrap();
+        for chunk in samples.chunks(640) {
+            writer.write_samples(chunk).unwrap();
+        }
+        writer.finish_writing().unwrap().unwrap()
+    }
+
+    fn signal(len: usize) -> Vec<f32> {
+        (0..len).map(|i| ((i * 37) % 1000) as f32 / 1000.0 - 0.5).collect()
+    }
+
+    fn passphrase(text: &str) -> KeySource {
+        KeySource::Passphrase(text.to_string().into())
+    }
+
+    fn decrypt(decryptor: &mut RecordingDecryptor, path: &Path) -> io::Result<DecryptedRecording> {
+        decryptor.decrypt_to(path, &mut io::Cursor::new(Vec::new()))
+    }
+
+    #[test]
+    fn test_encrypted_wav_and_flac_round_trip() {
+        let dir = temp_dir();
+        let samples = signal(16000 * 2 * 3 + 64);
+
+        let key = RecordingKey::from_passphrase_with_iterations("correct horse", 1000).unwrap();
+        let mut writer = WavFileWriter::new(&dir).with_commit_interval(0.25).with_encryption(key);
+        let info = record(&mut writer, &samples);
+        assert!(info.file_path.to_string_lossy().ends_with(".wav.menc"));
+        assert!((info.duration_seconds - (16000.0 * 3.0 + 32.0) / 16000.0).abs() < 1e-9);
+        assert_eq!(info.file_size_bytes, fs::metadata(&info.file_path).unwrap().len());
+        // No plaintext sample pattern on disk
+        let raw = fs::read(&info.file_path).unwrap();
+        let needle: Vec<u8> = samples[100..104].iter().flat_map(|s| s.to_le_bytes()).collect();
+        assert!(!raw.windows(needle.len()).any(|w| w == needle.as_slice()));
+        let (header, frames) = read_encrypted_info(&info.file_path).unwrap();
+        assert_eq!((header.sample_rate, header.channels, header.inner_extension), (16000, 2, "wav"));
+        assert_eq!(frames, 16000 * 3 + 32);
+
+        let mut decryptor = RecordingDecryptor::new(passphrase("correct horse"));
+        assert!(decrypt(&mut decryptor, &info.file_path).unwrap().complete);
+        let wrong = decrypt(&mut RecordingDecryptor::new(passphrase("wrong horse")), &info.file_path);
+        assert_eq!(wrong.unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+
+        let out = dir.join("out");
+        let (wav, _) = decryptor.export(&info.file_path, &out, false).unwrap();
+        assert_eq!(wav.file_name().unwrap(), info.file_path.file_stem().unwrap());
+        let mut reader = hound::WavReader::open(&wav).unwrap();
+        assert_eq!(reader.samples::<f32>().map(|s| s.unwrap()).collect::<Vec<_>>(), samples);
+        assert!(super::super::sidecar::sidecar_path(&wav).exists());
+
+        // Any format goes through the same sink
+        let key_file = dir.join("recording.key");
+        let key = RecordingKey::generate_key_file(&key_file).unwrap();
+        let mut writer = FlacFileWriter::new(&dir).with_bits_per_sample(24).with_encryption(key);
+        let info = record(&mut writer, &samples[..32000]);
+        assert!(info.file_path.to_string_lossy().ends_with(".flac.menc"));
+        let mut decryptor = RecordingDecryptor::new(KeySource::KeyFile(key_file));
+        let (flac, decrypted) = decryptor.export(&info.file_path, &out, false).unwrap();
+        assert_eq!(decrypted.header.inner_extension, "flac");
+        let stream = read_flac(&flac).unwrap();
+        assert_eq!(stream.info.total_frames, 16000);
+        let error = stream.samples_f32().iter().zip(&samples).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max);
+        assert!(error < 1e-6);
+        assert!(!fs::read_dir(&out).unwrap().any(|e| e.unwrap().path().extension().unwrap() == "partial"));
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_tampering_and_truncation_are_detected() {
+        let dir = temp_dir();
+        let key_file = dir.join("k");
+        fs::create_dir_all(&dir).unwrap();
+        let key = RecordingKey::generate_key_file(&key_file).unwrap();
+        let mut writer = WavFileWriter::new(&dir).with_commit_interval(0.25).with_encryption(key.clone());
+        let info = record(&mut writer, &signal(16000 * 2 * 2));
+        let original = fs::read(&info.file_path).unwrap();
+        let mut decryptor = RecordingDecryptor::new(KeySource::KeyFile(key_file));
+        let tampered_path = dir.join("tampered.wav.menc");
+        let mut check = |bytes: &[u8]| {
+            fs::write(&tampered_path, bytes).unwrap();
+            decrypt(&mut decryptor, &tampered_path)
+        };
+
+        // One flipped bit in the audio
+        let mut bytes = original.clone();
+        bytes[HEADER_BYTES + 1000] ^= 0x01;
+        assert_eq!(check(&bytes).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // Header edits (ex: claiming a different sample rate) break every record
+        let mut bytes = original.clone();
+        bytes[45] ^= 0x01;
+        assert_eq!(check(&bytes).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // Pointing a record's bytes somewhere else in the file
+        let mut bytes = original.clone();
+        bytes[HEADER_BYTES] ^= 0x04;
+        assert_eq!(check(&bytes).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // Swapping two records
+        let record_len = |at: usize| u32::from_le_bytes(original[at + 8..at + 12].try_into().unwrap()) as usize + 12;
+        let second = HEADER_BYTES + record_len(HEADER_BYTES);
+        let third = second + record_len(second);
+        let mut bytes = original[..HEADER_BYTES].to_vec();
+        bytes.extend_from_slice(&original[second..third]);
+        bytes.extend_from_slice(&original[HEADER_BYTES..second]);
+        bytes.extend_from_slice(&original[third..]);
+        assert!(check(&bytes).is_err());
+
+        // Cutting off whole records decrypts what's left but is never reported complete
+        let cut = check(&original[..third]).unwrap();
+        assert!(!cut.complete);
+        assert!(cut.bytes > 0);
+        assert!(check(&original).unwrap().complete);
+
+        // Killed recorder: everything up to the last commit is a readable WAV, exported only on request
+        let mut writer = WavFileWriter::new(&dir).with_commit_interval(0.25).with_encryption(key);
+        writer.start_writing(16000, 1).unwrap();
+        writer.write_samples(&signal(16000)).unwrap();
+        let path = writer.current_file().unwrap().clone();
+        std::mem::forget(writer);
+        let out = dir.join("out");
+        assert_eq!(decryptor.export(&path, &out, false).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        assert!(fs::read_dir(&out).unwrap().next().is_none());
+        let (wav, recording) = decryptor.export(&path, &out, true).unwrap();
+        assert!(!recording.complete);
+        assert!(hound::WavReader::open(&wav).unwrap().duration() >= 12000);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_encrypted_rotation_by_size() {
+        let dir = temp_dir();
+        let max_bytes = 20_000;
+        let key = RecordingKey::from_passphrase_with_iterations("pw", 10).unwrap();
+        let mut writer = WavFileWriter::new(&dir)
+            .with_commit_interval(0.1)
+            .with_rotation(RotationPolicy { max_file_bytes: Some(max_bytes), ..Default::default() })
+            .with_encryption(key);
+        writer.start_writing(16000, 1).unwrap();
+        for chunk in signal(32000).chunks(500) {
+            writer.write_samples(chunk).unwrap();
+        }
+        writer.finish_writing().unwrap();
+
+        let mut decryptor = RecordingDecryptor::new(passphrase("pw"));
+        let mut files: Vec<PathBuf> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == ENCRYPTED_EXTENSION))
+            .collect();
+        files.sort();
+        assert!(files.len() >= 4, "{} files", files.len());
+        let mut joined = Vec::new();
+        for file in &files {
+            assert!(fs::metadata(file).unwrap().len() <= max_bytes);
+            let (wav, recording) = decryptor.export(file, dir.join("out"), false).unwrap();
+            assert!(recording.complete);
+            joined.extend(hound::WavReader::open(&wav).unwrap().samples::<f32>().map(|s| s.unwrap()));
+        }
+        assert_eq!(joined, signal(32000));
+        fs::remove_dir_all(&dir).ok();
+    }
+}
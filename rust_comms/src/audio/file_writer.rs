@@ -0,0 +1,483 @@
+use super::encrypted_writer::{EncryptedWriter, RecordingKey, ENCRYPTED_EXTENSION};
+use super::sidecar::{write_sidecar, LevelAccumulator, RecordingContext};
+use super::traits::{AudioWriter, RecordingInfo};
+use chrono::{DateTime, Local};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Audio at risk on power loss, a header patch + fsync a second is cheap even on SD cards
+pub const DEFAULT_COMMIT_INTERVAL_SECONDS: f64 = 1.0;
+/// Infos kept for files closed by rotation until take_rotated(), oldest dropped past this
+const MAX_ROTATED_INFOS: usize = 64;
+
+/// When a recording is closed and the next one started
+/// Files are cut on frame boundaries, so each one holds exactly the limit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RotationPolicy {
+    /// New file at midnight
+    pub daily: bool,
+    pub max_duration_seconds: Option<f64>,
+    /// Includes the header, files never exceed it
+    pub max_file_bytes: Option<u64>,
+}
+
+impl Default for RotationPolicy {
+    fn default() -> Self {
+        Self {
+            daily: true,
+            max_duration_seconds: None,
+            max_file_bytes: None,
+        }
+    }
+}
+
+/// Open recording file, shared by the recorder and its format writer
+/// -Clones are handles on the same file (hound's WavWriter owns one, the recorder keeps one to fsync)
+/// -Plain, or sealed on the way to disk by an EncryptedWriter, the format writer can't tell
+/// -Tracks the file length itself, so size checks don't flush the buffer
+#[derive(Clone)]
+pub struct RecordingSink {
+    state: Arc<Mutex<SinkState>>,
+}
+
+enum SinkState {
+    Plain { file: BufWriter<File>, position: u64, len: u64 },
+    Encrypted(EncryptedWriter),
+}
+
+impl RecordingSink {
+    /// New file, fails if `path` exists
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file = File::options().write(true).create_new(true).open(path)?;
+        Ok(Self::from_state(SinkState::Plain { file: BufWriter::new(file), position: 0, len: 0 }))
+    }
+
+    /// New encrypted file, `format` names the stream inside and the header records its layout
+    pub fn create_encrypted(path: &Path, key: &RecordingKey, format: &dyn FormatWriter, sample_rate: u32, channels: u16) -> io::Result<Self> {
+        let writer = EncryptedWriter::create(path, key, format.extension(), sample_rate, channels, format.bits_per_sample())?;
+        Ok(Self::from_state(SinkState::Encrypted(writer)))
+    }
+
+    fn from_state(state: SinkState) -> Self {
+        Self { state: Arc::new(Mutex::new(state)) }
+    }
+
+    /// Size of the file once buffered bytes land
+    pub fn bytes_written(&self) -> u64 {
+        match *self.state.lock().unwrap() {
+            SinkState::Plain { len, .. } => len,
+            SinkState::Encrypted(ref writer) => writer.bytes_written(),
+        }
+    }
+
+    /// Bytes that can still be appended without the file passing `max_bytes`
+    pub fn room(&self, max_bytes: u64) -> u64 {
+        match *self.state.lock().unwrap() {
+            SinkState::Plain { len, .. } => max_bytes.saturating_sub(len),
+            SinkState::Encrypted(ref writer) => writer.room(max_bytes),
+        }
+    }
+
+    /// Flush buffered bytes and fsync the data
+    pub fn sync_data(&self) -> io::Result<()> {
+        match *self.state.lock().unwrap() {
+            SinkState::Plain { ref mut file, .. } => {
+                file.flush()?;
+                file.get_ref().sync_data()
+            }
+            SinkState::Encrypted(ref mut writer) => writer.sync_data(),
+        }
+    }
+
+    /// Last flush + full fsync, called once the format writer is done
+    fn finish(&self) -> io::Result<()> {
+        match *self.state.lock().unwrap() {
+            SinkState::Plain { ref mut file, .. } => {
+                file.flush()?;
+                file.get_ref().sync_all()
+            }
+            SinkState::Encrypted(ref mut writer) => writer.finish(),
+        }
+    }
+}
+
+impl Write for RecordingSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self.state.lock().unwrap() {
+            SinkState::Plain { ref mut file, ref mut position, ref mut len } => {
+                let written = file.write(buf)?;
+                *position += written as u64;
+                *len = (*len).max(*position);
+                Ok(written)
+            }
+            SinkState::Encrypted(ref mut writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self.state.lock().unwrap() {
+            SinkState::Plain { ref mut file, .. } => file.flush(),
+            SinkState::Encrypted(ref mut writer) => writer.flush(),
+        }
+    }
+}
+
+impl Seek for RecordingSink {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match *self.state.lock().unwrap() {
+            SinkState::Plain { ref mut file, ref mut position, .. } => {
+                *position = file.seek(pos)?;
+                Ok(*position)
+            }
+            SinkState::Encrypted(ref mut writer) => writer.seek(pos),
+        }
+    }
+}
+
+/// Format half of a RecordingFileWriter: turns f32 samples into file bytes
+/// The recorder half owns naming, rotation, commits, levels and sidecars
+pub trait FormatWriter {
+    /// File extension, ex: "wav"
+    fn extension(&self) -> &'static str;
+
+    /// For the "Started Recording" log line
+    fn describe(&self) -> String;
+
+    /// Reject settings the format can't store, before any file is created
+    fn check(&self, sample_rate: u32, channels: u16) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Sample size stored in the file, recorded in encrypted headers
+    fn bits_per_sample(&self) -> u16;
+
+    /// Write the header into a new file
+    fn open(&mut self, sink: RecordingSink, sample_rate: u32, channels: u16) -> Result<(), Box<dyn std::error::Error>>;
+
+    fn write(&mut self, samples: &[f32]) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Make everything written so far readable from the file (header patched, buffers flushed)
+    /// Ok(false) if it can't yet, ex: a WAV in the middle of a frame
+    fn commit(&mut self) -> Result<bool, Box<dyn std::error::Error>>;
+
+    /// Final header, the sink is closed after this
+    fn finish(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Samples the file can take before growing by more than `room` bytes
+    fn samples_fitting(&self, room: u64, channels: u16) -> u64;
+}
+
+/// Recording file writer, the format comes from `F` (see WavFileWriter, FlacFileWriter)
+/// -Manual Control via start_writing and finish_writing FNs
+/// -Auto timestamped filenames (audio_YYYYMMDD_HHMMSS.<ext>)
+/// -Rotation by day, duration or size, a buffer crossing a limit is split across the two files
+/// -Committed and fsynced every commit interval, so a power cut loses at most that much
+/// -JSON sidecar with source, levels and wall-clock times written when each file closes
+/// -Optionally encrypted at rest whatever the format (audio_X.<ext>.menc, see EncryptedWriter)
+pub struct RecordingFileWriter<F: FormatWriter> {
+    format: F,
+    encryption: Option<RecordingKey>,
+    sink: Option<RecordingSink>,
+    current_file: Option<PathBuf>,
+    sample_count: u64, //Interleaved samples written, frames = sample_count / channels
+    sample_rate: u32,
+    channels: u16,
+    commit_interval_seconds: f64,
+    samples_since_commit: u64,
+    rotation: RotationPolicy,
+    context: RecordingContext,
+    levels: Option<LevelAccumulator>,
+    started_at: Option<DateTime<Local>>,
+    output_dir: PathBuf,
+    current_date: Option<String>, //Dated in string format YYYYMMDD
+    rotated: VecDeque<RecordingInfo>,
+}
+
+impl<F: FormatWriter + Default> RecordingFileWriter<F> {
+    /// # Arguments
+    /// - `output_dir`: Directory where recordings will be saved (ex: "./recordings")
+    pub fn new(output_dir: impl Into<PathBuf>) -> Self {
+        Self::with_format_writer(output_dir, F::default())
+    }
+}
+
+impl<F: FormatWriter> RecordingFileWriter<F> {
+    pub fn with_format_writer(output_dir: impl Into<PathBuf>, format: F) -> Self {
+        Self {
+            format,
+            encryption: None,
+            sink: None,
+            current_file: None,
+            sample_count: 0,
+            sample_rate: 44100,
+            channels: 1,
+            commit_interval_seconds: DEFAULT_COMMIT_INTERVAL_SECONDS,
+            samples_since_commit: 0,
+            rotation: RotationPolicy::default(),
+            context: RecordingContext::default(),
+            levels: None,
+            started_at: None,
+            output_dir: output_dir.into(),
+            current_date: None,
+            rotated: VecDeque::new(),
+        }
+    }
+
+    /// Seconds of audio between commits (header patched + fsync)
+    pub fn with_commit_interval(mut self, seconds: f64) -> Self {
+        self.commit_interval_seconds = seconds;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: RotationPolicy) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Device / filter settings / tags copied into every sidecar
+    pub fn with_context(mut self, context: RecordingContext) -> Self {
+        self.context = context;
+        self
+    }
+
+    pub fn set_context(&mut self, context: RecordingContext) {
+        self.context = context;
+    }
+
+    /// Seal recordings started after this call with a key from a passphrase or key file
+    pub fn with_encryption(mut self, key: RecordingKey) -> Self {
+        self.encryption = Some(key);
+        self
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.encryption.is_some()
+    }
+
+    pub(crate) fn format_writer(&self) -> &F {
+        &self.format
+    }
+
+    pub(crate) fn format_writer_mut(&mut self) -> &mut F {
+        &mut self.format
+    }
+
+    /// Length of the file being written, 0 when idle
+    pub fn recorded_seconds(&self) -> f64 {
+        if self.is_writing() {
+            self.frames_written() as f64 / self.sample_rate as f64
+        } else {
+            0.0
+        }
+    }
+
+    pub fn current_file(&self) -> Option<&PathBuf> {
+        self.current_file.as_ref()
+    }
+
+    /// Files closed by rotation since the last call, oldest first
+    pub fn take_rotated(&mut self) -> Vec<RecordingInfo> {
+        self.rotated.drain(..).collect()
+    }
+
+    /// Make everything written so far readable and push it to disk
+    /// The file is valid up to this point even if we never reach finish_writing
+    pub fn commit(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(ref sink) = self.sink
+            && self.format.commit()?
+        {
+            sink.sync_data()?;
+            self.samples_since_commit = 0;
+        }
+        Ok(())
+    }
+
+    /// Encode one slice into the open file, no rotation checks
+    fn write_chunk(&mut self, samples: &[f32]) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.is_writing() {
+            return Err("Not currently writing to a file".into());
+        }
+        self.format.write(samples)?;
+        if let Some(ref mut levels) = self.levels {
+            levels.push(samples);
+        }
+        self.sample_count += samples.len() as u64;
+        self.samples_since_commit += samples.len() as u64;
+        let commit_samples = self.commit_interval_seconds * self.sample_rate as f64 * self.channels as f64;
+        if self.samples_since_commit as f64 >= commit_samples {
+            self.commit()?;
+        }
+        Ok(())
+    }
+
+    fn frames_written(&self) -> u64 {
+        self.sample_count / self.channels as u64
+    }
+
+    /// Samples that still fit in the current file under the duration / size limits
+    fn samples_until_rotation(&self) -> u64 {
+        let channels = self.channels as u64;
+        let mut room = u64::MAX;
+        if let Some(seconds) = self.rotation.max_duration_seconds {
+            // Always room for one frame, or a tiny limit would never make progress
+            let limit_samples = ((seconds * self.sample_rate as f64) as u64).max(1).saturating_mul(channels);
+            room = limit_samples.saturating_sub(self.sample_count);
+        }
+        if let (Some(max_bytes), Some(sink)) = (self.rotation.max_file_bytes, self.sink.as_ref()) {
+            let mut fitting = self.format.samples_fitting(sink.room(max_bytes), self.channels);
+            if self.sample_count == 0 {
+                fitting = fitting.max(channels);
+            }
+            room = room.min(fitting);
+        }
+        room
+    }
+
+    ///Check if we crossed day boundary or filled the file
+    /// **Returns**: reason to rotate, if any
+    fn should_rotate(&self) -> Option<&'static str> {
+        if self.rotation.daily
+            && let Some(ref date) = self.current_date
+            && date != &current_date()
+        {
+            return Some("Day Boundary crossed");
+        }
+        if self.samples_until_rotation() == 0 {
+            return Some("Max file length reached");
+        }
+        None
+    }
+
+    ///Auto rotate to new file if day boundary crossed or the file is full
+    fn check_rotation(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.is_writing() {
+            return Ok(());
+        }
+        if let Some(reason) = self.should_rotate() {
+            println!("{}, Rotating file.", reason);
+            if let Some(info) = self.finish_writing()? {
+                println!("Closed: {:?} ({:.2}s)", info.file_path, info.duration_seconds);
+                if self.rotated.len() == MAX_ROTATED_INFOS {
+                    self.rotated.pop_front();
+                }
+                self.rotated.push_back(info);
+            }
+            // Same rate, channels and format as the file just closed
+            self.start_writing(self.sample_rate, self.channels)?;
+        }
+        Ok(())
+    }
+}
+
+impl<F: FormatWriter> AudioWriter for RecordingFileWriter<F> {
+    type Error = Box<dyn std::error::Error>;
+
+    fn start_writing(&mut self, sample_rate: u32, channels: u16) -> Result<(), Self::Error> {
+        if self.is_writing() {
+            return Err("Already writing".into());
+        }
+        if channels == 0 {
+            return Err("Channel count must be at least 1".into());
+        }
+        self.format.check(sample_rate, channels)?;
+
+        fs::create_dir_all(&self.output_dir)?; //Create output dir if it doesn't exist
+        let (path, sink) = match self.encryption {
+            Some(ref key) => {
+                let path = timestamped_path(&self.output_dir, &format!("{}.{}", self.format.extension(), ENCRYPTED_EXTENSION));
+                let sink = RecordingSink::create_encrypted(&path, key, &self.format, sample_rate, channels)?;
+                (path, sink)
+            }
+            None => {
+                let path = timestamped_path(&self.output_dir, self.format.extension());
+                (path.clone(), RecordingSink::create(&path)?)
+            }
+        };
+        self.format.open(sink.clone(), sample_rate, channels)?;
+
+        self.sink = Some(sink);
+        self.samples_since_commit = 0;
+        self.current_file = Some(path.clone());
+        self.sample_count = 0;
+        self.sample_rate = sample_rate;
+        self.channels = channels;
+        self.current_date = Some(current_date());
+        self.levels = Some(LevelAccumulator::new(sample_rate, channels));
+        self.started_at = Some(Local::now());
+
+        let encrypted = if self.encryption.is_some() { ", encrypted" } else { "" };
+        println!("Started Recording: {:?} ({}{}, {} Hz, {} ch)", path, self.format.describe(), encrypted, sample_rate, channels);
+        Ok(())
+    }
+
+    ///Write audio samples to file with auto rotation check
+    /// A buffer crossing a size / duration limit is split across the two files
+    fn write_samples(&mut self, samples: &[f32]) -> Result<(), Self::Error> {
+        let mut remaining = samples;
+        loop {
+            self.check_rotation()?;
+            let room = self.samples_until_rotation().min(remaining.len() as u64) as usize;
+            let (now, later) = remaining.split_at(room);
+            self.write_chunk(now)?;
+            if later.is_empty() {
+                return Ok(());
+            }
+            remaining = later;
+        }
+    }
+
+    fn finish_writing(&mut self) -> Result<Option<RecordingInfo>, Self::Error> {
+        let (Some(sink), Some(file_path)) = (self.sink.take(), self.current_file.take()) else {
+            return Ok(None);
+        };
+        self.format.finish()?;
+        sink.finish()?;
+
+        let info = RecordingInfo {
+            file_size_bytes: fs::metadata(&file_path)?.len(),
+            file_path,
+            duration_seconds: self.frames_written() as f64 / self.sample_rate as f64,
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+        };
+        println!("Recording Finished: {:.2}s, {} bytes", info.duration_seconds, info.file_size_bytes);
+        // The audio is safe already, a failed sidecar shouldn't lose the RecordingInfo
+        let levels = self.levels.take().map(|levels| levels.summary());
+        if let Err(e) = write_sidecar(&info, self.started_at.take(), &self.context, levels) {
+            eprintln!("Failed to write sidecar for {:?}: {}", info.file_path, e);
+        }
+        self.sample_count = 0; //Reset counter
+        self.current_date = None; //Reset date
+        Ok(Some(info))
+    }
+
+    fn is_writing(&self) -> bool {
+        self.sink.is_some()
+    }
+}
+
+impl<F: FormatWriter> Drop for RecordingFileWriter<F> {
+    fn drop(&mut self) {
+        if self.is_writing() && let Err(e) = self.finish_writing() {
+            eprintln!("Error finalizing {} file on drop: {}", self.format.extension(), e);
+        }
+    }
+}
+
+/// Timestamped name in `dir`, suffixed `_1`, `_2`... when rotation starts several files in one second
+pub(crate) fn timestamped_path(dir: &Path, extension: &str) -> PathBuf {
+    let timestamp = Local::now().format("%Y%m%d_%H%M%S");
+    let mut path = dir.join(format!("audio_{}.{}", timestamp, extension));
+    let mut suffix = 1;
+    while path.exists() {
+        path = dir.join(format!("audio_{}_{}.{}", timestamp, suffix, extension));
+        suffix += 1;
+    }
+    path
+}
+
+/// Today as YYYYMMDD, for daily rotation
+pub(crate) fn current_date() -> String {
+    Local::now().format("%Y%m%d").to_string()
+}
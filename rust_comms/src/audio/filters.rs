@@ -1,4 +1,5 @@
 use std::collections::VecDeque;
+use serde::{Deserialize, Serialize};
 /// Noise Gate filter 
 /// 
 /// -Convert sample amplitude to dB
@@ -28,7 +29,7 @@ impl NoiseGate {
     /// - threshold_db: dB level to open gate
     /// - attack_ms: time to open gate (ms)
     /// - release_ms: time to close gate (ms)
-    /// -sample_rate: audio sample rate (Hz)
+    /// - sample_rate: audio sample rate (Hz)
     pub fn new(threshold_db: f32, attack_ms: f32, release_ms: f32, sample_rate: f32) -> Self {
         let attack_samples = (attack_ms * sample_rate / 1000.0) as usize; // convert ms -> sample count
         let release_samples = (release_ms * sample_rate / 1000.0) as usize; //converting for samples/sec
@@ -84,6 +85,44 @@ impl NoiseGate {
         self.state = GateState::Closed;
         self.envelope = 0.0;
     }
+
+    pub fn threshold_db(&self) -> f32 {
+        self.threshold_db
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.state == GateState::Open
+    }
+
+    /// Current gate gain [0.0, 1.0]
+    pub fn envelope(&self) -> f32 {
+        self.envelope
+    }
+
+    /// Runtime state, so a restarted process carries on where this one stopped
+    pub fn snapshot(&self) -> GateSnapshot {
+        GateSnapshot {
+            open: self.is_open(),
+            envelope: self.envelope,
+        }
+    }
+
+    /// Continue from a snapshot, parameters stay as constructed
+    pub fn restore(&mut self, snapshot: &GateSnapshot) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&snapshot.envelope) {
+            return Err(format!("Gate envelope {} outside [0.0, 1.0]", snapshot.envelope));
+        }
+        self.state = if snapshot.open { GateState::Open } else { GateState::Closed };
+        self.envelope = snapshot.envelope;
+        Ok(())
+    }
+}
+
+/// NoiseGate runtime state (no parameters)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GateSnapshot {
+    pub open: bool,
+    pub envelope: f32,
 }
 
 /// Audio Normalizer
@@ -108,7 +147,7 @@ impl Normalizer {
     /// Args:
     /// - target_level_db
     /// - window_ms
-    /// -sample_rate
+    /// - sample_rate
     pub fn new(target_level_db: f32, window_ms: f32, sample_rate: f32) -> Self {
         //Convert window ms to sample count
         let window_size = (window_ms * sample_rate / 1000.0) as usize;
@@ -155,6 +194,74 @@ impl Normalizer {
         self.buffer.clear();
         self.current_gain = 1.0;
     }
+
+    /// Smoothed linear gain currently applied
+    pub fn current_gain(&self) -> f32 {
+        self.current_gain
+    }
+
+    /// Runtime state: RMS window contents and smoothed gain
+    pub fn snapshot(&self) -> NormalizerSnapshot {
+        NormalizerSnapshot {
+            buffer: self.buffer.iter().copied().collect(),
+            current_gain: self.current_gain,
+        }
+    }
+
+    /// Continue from a snapshot, a longer buffer (bigger window before) keeps its newest samples
+    pub fn restore(&mut self, snapshot: &NormalizerSnapshot) -> Result<(), String> {
+        if !snapshot.current_gain.is_finite() || snapshot.current_gain <= 0.0 {
+            return Err(format!("Normalizer gain {} must be a positive number", snapshot.current_gain));
+        }
+        if snapshot.buffer.iter().any(|x| !x.is_finite()) {
+            return Err("Normalizer buffer holds non-finite samples".into());
+        }
+        let skip = snapshot.buffer.len().saturating_sub(self.window_size);
+        self.buffer.clear();
+        self.buffer.extend(snapshot.buffer[skip..].iter().map(|x| x.abs()));
+        self.current_gain = snapshot.current_gain;
+        Ok(())
+    }
+}
+
+/// Normalizer runtime state (no parameters)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NormalizerSnapshot {
+    /// Magnitudes in the RMS window, oldest first
+    pub buffer: Vec<f32>,
+    pub current_gain: f32,
+}
+
+/// Filter chain settings, recorded next to every recording
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FilterConfig {
+    pub gate_threshold_db: f32,
+    pub gate_attack_ms: f32,
+    pub gate_release_ms: f32,
+    pub normalizer_target_db: f32,
+    pub normalizer_window_ms: f32,
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        Self {
+            gate_threshold_db: -40.0,
+            gate_attack_ms: 10.0,
+            gate_release_ms: 100.0,
+            normalizer_target_db: -20.0,
+            normalizer_window_ms: 200.0,
+        }
+    }
+}
+
+impl FilterConfig {
+    pub fn noise_gate(&self, sample_rate: f32) -> NoiseGate {
+        NoiseGate::new(self.gate_threshold_db, self.gate_attack_ms, self.gate_release_ms, sample_rate)
+    }
+
+    pub fn normalizer(&self, sample_rate: f32) -> Normalizer {
+        Normalizer::new(self.normalizer_target_db, self.normalizer_window_ms, sample_rate)
+    }
 }
 
 //═══════════════════════════════════════════════════════════════════════════
@@ -272,6 +379,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_snapshot_restore_continues_seamlessly() {
+        let input: Vec<f32> = (0..8000).map(|i| 0.2 * (i as f32 * 0.05).sin() * if i % 3000 < 1500 { 1.0 } else { 0.01 }).collect();
+        let (first, second) = input.split_at(5000);
+
+        let mut gate = NoiseGate::new(-30.0, 5.0, 50.0, 16000.0);
+        let mut normalizer = Normalizer::new(-20.0, 100.0, 16000.0);
+        let mut expected = input.clone();
+        gate.process(&mut expected);
+        normalizer.process(&mut expected);
+
+        // First half in one "process", then state moved through JSON into fresh filters
+        let mut gate = NoiseGate::new(-30.0, 5.0, 50.0, 16000.0);
+        let mut normalizer = Normalizer::new(-20.0, 100.0, 16000.0);
+        let mut head = first.to_vec();
+        gate.process(&mut head);
+        normalizer.process(&mut head);
+        let gate_json = serde_json::to_string(&gate.snapshot()).unwrap();
+        let normalizer_json = serde_json::to_string(&normalizer.snapshot()).unwrap();
+
+        let mut gate = NoiseGate::new(-30.0, 5.0, 50.0, 16000.0);
+        let mut normalizer = Normalizer::new(-20.0, 100.0, 16000.0);
+        gate.restore(&serde_json::from_str(&gate_json).unwrap()).unwrap();
+        normalizer.restore(&serde_json::from_str(&normalizer_json).unwrap()).unwrap();
+        let mut tail = second.to_vec();
+        gate.process(&mut tail);
+        normalizer.process(&mut tail);
+
+        head.extend(tail);
+        assert_eq!(head, expected);
+        assert!(gate.restore(&GateSnapshot { open: true, envelope: 2.0 }).is_err());
+        assert!(normalizer.restore(&NormalizerSnapshot { buffer: vec![], current_gain: f32::NAN }).is_err());
+    }
+
     #[test]
     fn test_normalizer_target_level() {
         // Verify normalizer actually reaches target level
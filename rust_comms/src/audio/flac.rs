@@ -0,0 +1,626 @@
+//! FLAC Codec
+//!
+//! Just enough FLAC encoding to record losslessly without pulling in libFLAC, reading is claxon's
+//! -Encoder: fixed predictors (order 0-4) with partitioned Rice residuals, best of those per subframe
+//! -Constant and verbatim subframes when they are smaller
+//! -Every frame is self-contained (CRC-8 header, CRC-16 footer), so a cut-off file is readable up to its last whole frame
+//! -FlacStream / read_flac decode with claxon, stopping cleanly at a cut-off last frame
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Frames per block, libFLAC's default for 16/24-bit audio
+pub const FLAC_BLOCK_SIZE: usize = 4096;
+
+/// "fLaC" + metadata block header + STREAMINFO, audio frames start right after
+pub const FLAC_HEADER_BYTES: u64 = 4 + 4 + STREAMINFO_BYTES as u64;
+
+const STREAMINFO_BYTES: usize = 34;
+const MAX_FIXED_ORDER: usize = 4;
+const MAX_PARTITION_ORDER: u32 = 8;
+/// Largest parameter of the 5-bit Rice coding method (31 is the escape code)
+const MAX_RICE_PARAMETER: u32 = 30;
+
+/// Stream parameters from the STREAMINFO block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamInfo {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub bits_per_sample: u16,
+    /// Samples per channel, 0 when unknown (recording never finished)
+    pub total_frames: u64,
+    pub min_block_size: u16,
+    pub max_block_size: u16,
+    /// Smallest / largest encoded frame in bytes, 0 when unknown
+    pub min_frame_bytes: u32,
+    pub max_frame_bytes: u32,
+}
+
+impl StreamInfo {
+    /// Full file header: magic, a last-block metadata header and this STREAMINFO
+    /// MD5 is left zero, which the format defines as "not computed"
+    pub fn header_bytes(&self) -> Vec<u8> {
+        let mut bits = BitWriter::with_capacity(FLAC_HEADER_BYTES as usize);
+        bits.write_bytes(b"fLaC");
+        bits.write(1, 1); // last metadata block
+        bits.write(0, 7); // STREAMINFO
+        bits.write(STREAMINFO_BYTES as u64, 24);
+        bits.write(self.min_block_size as u64, 16);
+        bits.write(self.max_block_size as u64, 16);
+        bits.write(self.min_frame_bytes as u64, 24);
+        bits.write(self.max_frame_bytes as u64, 24);
+        bits.write(self.sample_rate as u64, 20);
+        bits.write(self.channels as u64 - 1, 3);
+        bits.write(self.bits_per_sample as u64 - 1, 5);
+        bits.write(self.total_frames >> 32, 4);
+        bits.write(self.total_frames & 0xFFFF_FFFF, 32);
+        bits.write_bytes(&[0; 16]);
+        bits.into_bytes()
+    }
+
+    pub fn duration_seconds(&self) -> f64 {
+        self.total_frames as f64 / self.sample_rate as f64
+    }
+}
+
+/// Streaming encoder: integer samples in, FLAC frames out
+/// The caller writes `stream_info().header_bytes()` first and patches it once done
+#[derive(Debug, Clone)]
+pub struct FlacEncoder {
+    info: StreamInfo,
+    pending: Vec<i32>, // interleaved, less than one block
+    frame_number: u64,
+}
+
+impl FlacEncoder {
+    /// # Arguments
+    /// - `bits_per_sample`: 4 to 32, samples must fit in that many bits signed
+    pub fn new(sample_rate: u32, channels: u16, bits_per_sample: u16) -> Self {
+        Self {
+            info: StreamInfo {
+                sample_rate,
+                channels,
+                bits_per_sample,
+                total_frames: 0,
+                min_block_size: FLAC_BLOCK_SIZE as u16,
+                max_block_size: FLAC_BLOCK_SIZE as u16,
+                min_frame_bytes: 0,
+                max_frame_bytes: 0,
+            },
+            pending: Vec::with_capacity(FLAC_BLOCK_SIZE * channels as usize),
+            frame_number: 0,
+        }
+    }
+
+    /// Totals cover the frames encoded so far
+    pub fn stream_info(&self) -> &StreamInfo {
+        &self.info
+    }
+
+    /// Interleaved samples buffered towards the next block
+    pub fn pending_samples(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Interleaved samples that complete the next block
+    pub fn samples_until_frame(&self) -> usize {
+        FLAC_BLOCK_SIZE * self.info.channels as usize - self.pending.len()
+    }
+
+    /// Upper bound on one encoded frame: verbatim subframes plus headers
+    pub fn max_frame_bytes(&self) -> u64 {
+        let subframes = self.info.channels as u64 * (FLAC_BLOCK_SIZE as u64 * (self.info.bits_per_sample as u64 + 1) + 8);
+        // sync..blocksize (at most 14 bytes with a 7 byte frame number) + CRC-8 + CRC-16
+        subframes.div_ceil(8) + 17
+    }
+
+    /// Buffer interleaved samples, appending every completed frame to `out`
+    pub fn push(&mut self, samples: &[i32], out: &mut Vec<u8>) {
+        let block_samples = FLAC_BLOCK_SIZE * self.info.channels as usize;
+        let mut remaining = samples;
+        while !remaining.is_empty() {
+            let take = (block_samples - self.pending.len()).min(remaining.len());
+            self.pending.extend_from_slice(&remaining[..take]);
+            remaining = &remaining[take..];
+            if self.pending.len() == block_samples {
+                self.encode_pending(out);
+            }
+        }
+    }
+
+    /// Encode the partial block (whole frames only) as a final, shorter frame
+    /// A trailing partial frame can't be represented and is dropped
+    pub fn finish(&mut self, out: &mut Vec<u8>) {
+        let channels = self.info.channels as usize;
+        self.pending.truncate(self.pending.len() / channels * channels);
+        if !self.pending.is_empty() {
+            self.encode_pending(out);
+        }
+    }
+
+    fn encode_pending(&mut self, out: &mut Vec<u8>) {
+        let channels = self.info.channels as usize;
+        let block_size = self.pending.len() / channels;
+        let start = out.len();
+        let mut bits = BitWriter::with_capacity(block_size * channels * 2);
+
+        // Frame header
+        bits.write(0xFFF8, 16); // sync code, fixed block size stream
+        bits.write(0b0111, 4); // block size - 1 follows as 16 bits
+        bits.write(0b0000, 4); // sample rate from STREAMINFO
+        bits.write(channels as u64 - 1, 4); // independent channels
+        bits.write(sample_size_code(self.info.bits_per_sample) as u64, 3);
+        bits.write(0, 1);
+        bits.write_utf8(self.frame_number);
+        bits.write(block_size as u64 - 1, 16);
+        let crc = crc8(bits.bytes());
+        bits.write(crc as u64, 8);
+
+        let mut channel = vec![0i64; block_size];
+        for ch in 0..channels {
+            for (i, sample) in channel.iter_mut().enumerate() {
+                *sample = self.pending[i * channels + ch] as i64;
+            }
+            encode_subframe(&mut bits, &channel, self.info.bits_per_sample as u32);
+        }
+        bits.align();
+        let crc = crc16(bits.bytes());
+        bits.write(crc as u64, 16);
+        out.extend_from_slice(bits.bytes());
+
+        let frame_bytes = (out.len() - start) as u32;
+        self.info.min_frame_bytes = if self.info.min_frame_bytes == 0 {
+            frame_bytes
+        } else {
+            self.info.min_frame_bytes.min(frame_bytes)
+        };
+        self.info.max_frame_bytes = self.info.max_frame_bytes.max(frame_bytes);
+        self.info.total_frames += block_size as u64;
+        self.frame_number += 1;
+        self.pending.clear();
+    }
+}
+
+/// STREAMINFO sample size, or 000 ("see STREAMINFO") for depths without a code
+fn sample_size_code(bits_per_sample: u16) -> u8 {
+    match bits_per_sample {
+        8 => 0b001,
+        12 => 0b010,
+        16 => 0b100,
+        20 => 0b101,
+        24 => 0b110,
+        32 => 0b111,
+        _ => 0b000,
+    }
+}
+
+/// Smallest of constant / fixed order 0-4 / verbatim for one channel of one block
+fn encode_subframe(bits: &mut BitWriter, samples: &[i64], bits_per_sample: u32) {
+    if samples.iter().all(|&s| s == samples[0]) {
+        bits.write(0b0000_0000, 8);
+        bits.write_signed(samples[0], bits_per_sample);
+        return;
+    }
+
+    let verbatim_bits = samples.len() as u64 * bits_per_sample as u64;
+    let mut best: Option<(u64, usize, ResidualPlan, Vec<i64>)> = None;
+    let mut residual = samples.to_vec();
+    for order in 0..=MAX_FIXED_ORDER.min(samples.len() - 1) {
+        if order > 0 {
+            // Order k residual is the k-th difference of the signal
+            for i in (order..samples.len()).rev() {
+                residual[i] -= residual[i - 1];
+            }
+        }
+        let plan = ResidualPlan::new(&residual[order..], samples.len(), order);
+        let total = order as u64 * bits_per_sample as u64 + plan.bits;
+        if total < verbatim_bits && best.as_ref().is_none_or(|(best_total, ..)| total < *best_total) {
+            best = Some((total, order, plan, residual[order..].to_vec()));
+        }
+    }
+
+    let Some((_, order, plan, residual)) = best else {
+        bits.write(0b0000_0010, 8);
+        for &sample in samples {
+            bits.write_signed(sample, bits_per_sample);
+        }
+        return;
+    };
+
+    bits.write(0b0001_0000 | (order as u64) << 1, 8);
+    for &sample in &samples[..order] {
+        bits.write_signed(sample, bits_per_sample);
+    }
+    plan.write(bits, &residual);
+}
+
+/// Partition order and Rice parameters chosen for one residual
+struct ResidualPlan {
+    partition_order: u32,
+    parameters: Vec<u32>,
+    /// Residual section size, method and partition order fields included
+    bits: u64,
+    block_size: usize,
+    order: usize,
+}
+
+impl ResidualPlan {
+    /// `residual` starts after the `order` warm-up samples of a `block_size` block
+    fn new(residual: &[i64], block_size: usize, order: usize) -> Self {
+        let folded: Vec<u64> = residual.iter().map(|&r| fold(r)).collect();
+        let mut best: Option<Self> = None;
+        for partition_order in 0..=MAX_PARTITION_ORDER {
+            let partitions = 1usize << partition_order;
+            if !block_size.is_multiple_of(partitions) || block_size / partitions < order {
+                break;
+            }
+            let mut parameters = Vec::with_capacity(partitions);
+            let mut bits = 6u64;
+            let mut start = 0;
+            for p in 0..partitions {
+                let len = block_size / partitions - if p == 0 { order } else { 0 };
+                let (parameter, cost) = best_parameter(&folded[start..start + len]);
+                parameters.push(parameter);
+                bits += cost;
+                start += len;
+            }
+            let wide = parameters.iter().any(|&p| p >= 15);
+            bits += partitions as u64 * if wide { 5 } else { 4 };
+            if best.as_ref().is_none_or(|b| bits < b.bits) {
+                best = Some(Self { partition_order, parameters, bits, block_size, order });
+            }
+        }
+        best.expect("partition order 0 always fits")
+    }
+
+    fn write(&self, bits: &mut BitWriter, residual: &[i64]) {
+        let wide = self.parameters.iter().any(|&p| p >= 15);
+        bits.write(wide as u64, 2);
+        bits.write(self.partition_order as u64, 4);
+        let partition_len = self.block_size >> self.partition_order;
+        let mut start = 0;
+        for (p, &parameter) in self.parameters.iter().enumerate() {
+            let len = partition_len - if p == 0 { self.order } else { 0 };
+            bits.write(parameter as u64, if wide { 5 } else { 4 });
+            for &r in &residual[start..start + len] {
+                let value = fold(r);
+                bits.write_unary(value >> parameter);
+                bits.write(value & ((1u64 << parameter) - 1), parameter);
+            }
+            start += len;
+        }
+    }
+}
+
+/// Cheapest Rice parameter for a partition, and its size in bits (parameter field excluded)
+fn best_parameter(folded: &[u64]) -> (u32, u64) {
+    let mut best = (0, u64::MAX);
+    for parameter in 0..=MAX_RICE_PARAMETER {
+        let cost: u64 = folded.iter().map(|&v| (v >> parameter) + 1 + parameter as u64).sum();
+        if cost < best.1 {
+            best = (parameter, cost);
+        } else if cost > best.1 {
+            // Cost is convex in the parameter, past the minimum it only grows
+            break;
+        }
+    }
+    best
+}
+
+/// Signed to unsigned: 0, -1, 1, -2, 2... -> 0, 1, 2, 3, 4...
+fn fold(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// Decoded FLAC stream
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlacStream {
+    pub info: StreamInfo,
+    /// Interleaved, in the stream's bit depth
+    pub samples: Vec<i32>,
+    /// The file ended partway through a frame (ex: recording killed), that frame was dropped
+    pub truncated: bool,
+}
+
+impl FlacStream {
+    /// Samples scaled back to [-1.0, 1.0], the inverse of the recorder's quantization
+    pub fn samples_f32(&self) -> Vec<f32> {
+        let scale = ((1i64 << (self.info.bits_per_sample - 1)) - 1) as f32;
+        self.samples.iter().map(|&s| s as f32 / scale).collect()
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.samples.len() as u64 / self.info.channels as u64
+    }
+}
+
+impl From<claxon::metadata::StreamInfo> for StreamInfo {
+    fn from(info: claxon::metadata::StreamInfo) -> Self {
+        Self {
+            sample_rate: info.sample_rate,
+            channels: info.channels as u16,
+            bits_per_sample: info.bits_per_sample as u16,
+            total_frames: info.samples.unwrap_or(0),
+            min_block_size: info.min_block_size,
+            max_block_size: info.max_block_size,
+            min_frame_bytes: info.min_frame_size.unwrap_or(0),
+            max_frame_bytes: info.max_frame_size.unwrap_or(0),
+        }
+    }
+}
+
+fn decode_error(e: claxon::Error) -> io::Error {
+    match e {
+        claxon::Error::IoError(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, e),
+    }
+}
+
+/// Read just the STREAMINFO of a FLAC file
+pub fn read_streaminfo(path: impl AsRef<Path>) -> io::Result<StreamInfo> {
+    let reader = claxon::FlacReader::open(path).map_err(decode_error)?;
+    Ok(reader.streaminfo().into())
+}
+
+/// Decode a whole FLAC file
+pub fn read_flac(path: impl AsRef<Path>) -> io::Result<FlacStream> {
+    decode(fs::File::open(path)?)
+}
+
+/// Decode FLAC bytes, stopping cleanly at a truncated final frame
+/// Corrupt frames (bad CRC or syntax) mid-stream are an error
+pub fn decode_flac(data: &[u8]) -> io::Result<FlacStream> {
+    decode(io::Cursor::new(data))
+}
+
+fn decode(input: impl io::Read) -> io::Result<FlacStream> {
+    let mut reader = claxon::FlacReader::new(input).map_err(decode_error)?;
+    let info = StreamInfo::from(reader.streaminfo());
+    let mut samples = Vec::with_capacity(info.total_frames as usize * info.channels as usize);
+    let mut truncated = false;
+    let mut blocks = reader.blocks();
+    let mut buffer = Vec::new();
+    loop {
+        match blocks.read_next_or_eof(buffer) {
+            Ok(Some(block)) => {
+                for i in 0..block.duration() {
+                    for ch in 0..block.channels() {
+                        samples.push(block.sample(ch, i));
+                    }
+                }
+                buffer = block.into_buffer();
+            }
+            Ok(None) => break,
+            Err(claxon::Error::IoError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                truncated = true;
+                break;
+            }
+            Err(e) => return Err(decode_error(e)),
+        }
+    }
+    Ok(FlacStream { info, samples, truncated })
+}
+
+/// CRC-8, polynomial x^8 + x^2 + x + 1, used for frame headers
+fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for &byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// CRC-16, polynomial x^16 + x^15 + x^2 + 1, used for whole frames
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x8005 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// MSB-first bit packer
+struct BitWriter {
+    bytes: Vec<u8>,
+    accumulator: u64,
+    pending_bits: u32, // bits in accumulator not yet pushed, always < 8 between calls
+}
+
+impl BitWriter {
+    fn with_capacity(bytes: usize) -> Self {
+        Self { bytes: Vec::with_capacity(bytes), accumulator: 0, pending_bits: 0 }
+    }
+
+    /// Write the low `count` bits of `value`, count <= 32
+    fn write(&mut self, value: u64, count: u32) {
+        if count == 0 {
+            return;
+        }
+        let mask = (1u64 << count) - 1;
+        self.accumulator = (self.accumulator << count) | (value & mask);
+        self.pending_bits += count;
+        while self.pending_bits >= 8 {
+            self.pending_bits -= 8;
+            self.bytes.push((self.accumulator >> self.pending_bits) as u8);
+        }
+        self.accumulator &= (1u64 << self.pending_bits) - 1;
+    }
+
+    fn write_signed(&mut self, value: i64, count: u32) {
+        self.write(value as u64, count);
+    }
+
+    /// `value` zeros then a one
+    fn write_unary(&mut self, mut value: u64) {
+        while value >= 32 {
+            self.write(0, 32);
+            value -= 32;
+        }
+        self.write(1, value as u32 + 1);
+    }
+
+    /// Frame number in FLAC's extended UTF-8 coding (up to 36 bits, 7 bytes)
+    fn write_utf8(&mut self, value: u64) {
+        if value < 0x80 {
+            self.write(value, 8);
+            return;
+        }
+        let len = match value {
+            0x80..0x800 => 2,
+            0x800..0x1_0000 => 3,
+            0x1_0000..0x20_0000 => 4,
+            0x20_0000..0x400_0000 => 5,
+            0x400_0000..0x8000_0000 => 6,
+            _ => 7,
+        };
+        let prefix = (0xFF00u64 >> len) & 0xFF;
+        self.write(prefix | (value >> (6 * (len - 1))), 8);
+        for i in (0..len - 1).rev() {
+            self.write(0x80 | ((value >> (6 * i)) & 0x3F), 8);
+        }
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write(byte as u64, 8);
+        }
+    }
+
+    /// Zero-pad to the next byte boundary
+    fn align(&mut self) {
+        if self.pending_bits > 0 {
+            self.write(0, 8 - self.pending_bits);
+        }
+    }
+
+    /// Completed bytes, call after `align` for the full output
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn into_bytes(mut self) -> Vec<u8> {
+        self.align();
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(samples: &[i32], channels: u16, bits_per_sample: u16, chunk: usize) -> Vec<u8> {
+        let mut encoder = FlacEncoder::new(16000, channels, bits_per_sample);
+        let mut frames = Vec::new();
+        for piece in samples.chunks(chunk) {
+            encoder.push(piece, &mut frames);
+        }
+        encoder.finish(&mut frames);
+        let mut file = encoder.stream_info().header_bytes();
+        file.extend(frames);
+        file
+    }
+
+    /// Speech-like stereo: two tones plus deterministic noise, full range of the depth
+    fn test_signal(frames: usize, bits_per_sample: u16) -> Vec<i32> {
+        let max = ((1i64 << (bits_per_sample - 1)) - 1) as f64;
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let mut samples = Vec::with_capacity(frames * 2);
+        for n in 0..frames {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let noise = (state >> 11) as f64 / (1u64 << 53) as f64 - 0.5;
+            let t = n as f64 / 16000.0;
+            let left = 0.6 * (2.0 * std::f64::consts::PI * 220.0 * t).sin() + 0.05 * noise;
+            let right = 0.3 * (2.0 * std::f64::consts::PI * 1500.0 * t).sin() + 0.2 * noise;
+            samples.push((left * max).round() as i32);
+            samples.push((right * max).round() as i32);
+        }
+        // Full scale extremes survive too
+        samples[10] = max as i32;
+        samples[11] = -max as i32 - 1;
+        samples
+    }
+
+    #[test]
+    fn test_round_trip_is_lossless() {
+        for bits_per_sample in [16, 24] {
+            // Partial last block, and buffers that straddle block boundaries
+            let samples = test_signal(FLAC_BLOCK_SIZE * 3 + 1234, bits_per_sample);
+            let file = encode(&samples, 2, bits_per_sample, 999);
+            let decoded = decode_flac(&file).unwrap();
+            assert_eq!(decoded.info.bits_per_sample, bits_per_sample);
+            assert_eq!(decoded.info.total_frames, samples.len() as u64 / 2);
+            assert!(!decoded.truncated);
+            assert_eq!(decoded.samples, samples, "{}-bit", bits_per_sample);
+            // Actually compresses
+            assert!(file.len() < samples.len() * bits_per_sample as usize / 8);
+        }
+    }
+
+    #[test]
+    fn test_silence_noise_and_edge_blocks() {
+        // Constant subframes
+        let silence = vec![0; 5000];
+        assert_eq!(decode_flac(&encode(&silence, 1, 16, 5000)).unwrap().samples, silence);
+
+        // White noise at full scale falls back to verbatim, still lossless
+        let mut state = 1u32;
+        let noise: Vec<i32> = (0..3000)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 8) as i32 - (1 << 23)
+            })
+            .collect();
+        assert_eq!(decode_flac(&encode(&noise, 3, 24, 700)).unwrap().samples, noise);
+
+        // Tiny final blocks, shorter than the predictor order
+        for len in 1..6 {
+            let short: Vec<i32> = (0..FLAC_BLOCK_SIZE as i32 + len).map(|i| i * 7 - 300).collect();
+            assert_eq!(decode_flac(&encode(&short, 1, 16, 4096)).unwrap().samples, short);
+        }
+    }
+
+    #[test]
+    fn test_truncated_and_corrupt_streams() {
+        let samples = test_signal(FLAC_BLOCK_SIZE * 2, 16);
+        let file = encode(&samples, 2, 16, 4096);
+
+        // Cut in the second frame: first frame intact, flagged truncated
+        let cut = decode_flac(&file[..file.len() - 100]).unwrap();
+        assert!(cut.truncated);
+        assert_eq!(cut.samples, samples[..FLAC_BLOCK_SIZE * 2]);
+
+        // A flipped bit in the audio is caught by the frame CRC
+        let mut corrupt = file.clone();
+        let middle = FLAC_HEADER_BYTES as usize + 200;
+        corrupt[middle] ^= 0x10;
+        assert!(decode_flac(&corrupt).is_err());
+    }
+
+    #[test]
+    fn test_utf8_frame_numbers_and_crcs() {
+        let cases: [(u64, &[u8]); 5] = [
+            (0x7F, &[0x7F]),
+            (0x80, &[0xC2, 0x80]),
+            (0x800, &[0xE0, 0xA0, 0x80]),
+            (0x1_0000, &[0xF0, 0x90, 0x80, 0x80]),
+            (0xF_FFFF_FFFF, &[0xFE, 0xBF, 0xBF, 0xBF, 0xBF, 0xBF, 0xBF]),
+        ];
+        for (value, expected) in cases {
+            let mut bits = BitWriter::with_capacity(8);
+            bits.write_utf8(value);
+            assert_eq!(bits.into_bytes(), expected, "{:#x}", value);
+        }
+        // Check values for "123456789"
+        assert_eq!(crc8(b"123456789"), 0xF4);
+        assert_eq!(crc16(b"123456789"), 0xFEE8);
+    }
+}
@@ -0,0 +1,382 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use super::publisher::AudioFrame;
+
+/// Jitter Buffer
+///
+/// Turns bursty, reordered, lossy network packets into a steady sample stream
+/// -Packets reordered by sequence number, late and duplicate packets dropped
+/// -Playout starts once target delay is buffered, re-buffers after a long outage
+/// -Sequence restarts from the next packet after a long outage or a large backward jump (sender restarted)
+/// -Lost packets concealed by repeating the last packet with a decaying gain
+/// -Sender/receiver clock drift absorbed by a slowly adapted playout ratio
+#[derive(Debug, Clone)]
+pub struct JitterBuffer {
+    config: JitterConfig,
+    packets: BTreeMap<u64, Vec<f32>>, // waiting packets keyed by sequence
+    queued_samples: usize,            // total samples in `packets`
+    next_sequence: Option<u64>,
+    playing: bool,
+    current: VecDeque<f32>, // samples from the packet being played out
+    last_packet: Vec<f32>,  // source for concealment
+    consecutive_losses: u32,
+    last_capture_us: u64,
+    ratio: f64,     // input samples consumed per output sample
+    phase: f64,     // fractional read position into `current`
+    depth_avg: f64, // smoothed buffered samples
+    stats: JitterStats,
+}
+
+#[derive(Debug, Clone)]
+pub struct JitterConfig {
+    pub sample_rate: u32,
+    /// Buffered audio to hold before and during playout
+    pub target_delay_ms: f32,
+    /// Oldest packets are dropped past this depth
+    pub max_delay_ms: f32,
+    /// Consecutive concealed packets before giving up and re-buffering
+    pub max_concealed_packets: u32,
+    /// Largest playout speed correction, parts per million
+    pub max_drift_ppm: f64,
+    /// A packet this many sequence numbers behind playout means the sender restarted
+    pub restart_threshold: u64,
+}
+
+impl Default for JitterConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: 16000,
+            target_delay_ms: 60.0,
+            max_delay_ms: 300.0,
+            max_concealed_packets: 10,
+            max_drift_ppm: 5000.0,
+            restart_threshold: 50,
+        }
+    }
+}
+
+/// Counters for monitoring network audio health
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct JitterStats {
+    pub received: u64,
+    pub late: u64,
+    pub duplicate: u64,
+    pub overflow: u64,
+    pub concealed: u64,
+    pub underruns: u64,
+    /// Sender restarts detected (sequence jumped back)
+    pub restarts: u64,
+    pub depth_ms: f32,
+    pub drift_ppm: f32,
+}
+
+/// Per-packet gain applied to each consecutive concealed packet
+const CONCEALMENT_DECAY: f32 = 0.6;
+/// Depth smoothing, ~1s time constant at 100 pulls/s
+const DEPTH_SMOOTHING: f64 = 0.01;
+/// Ratio correction per unit of relative depth error
+const DRIFT_GAIN: f64 = 0.01;
+
+impl JitterBuffer {
+    pub fn new(config: JitterConfig) -> Self {
+        Self {
+            config,
+            packets: BTreeMap::new(),
+            queued_samples: 0,
+            next_sequence: None,
+            playing: false,
+            current: VecDeque::new(),
+            last_packet: Vec::new(),
+            consecutive_losses: 0,
+            last_capture_us: 0,
+            ratio: 1.0,
+            phase: 0.0,
+            depth_avg: 0.0,
+            stats: JitterStats::default(),
+        }
+    }
+
+    /// Add a packet from the network
+    pub fn insert(&mut self, frame: AudioFrame) {
+        if frame.samples.is_empty() {
+            return;
+        }
+        if let Some(next) = self.next_sequence
+            && frame.sequence < next
+        {
+            if next - frame.sequence < self.config.restart_threshold {
+                self.stats.late += 1; // already played or concealed
+                return;
+            }
+            // Too far back to be reordering: sender restarted its sequence, start over from this packet
+            self.reset();
+            self.stats.restarts += 1;
+        }
+        if self.packets.contains_key(&frame.sequence) {
+            self.stats.duplicate += 1;
+            return;
+        }
+
+        self.stats.received += 1;
+        self.last_capture_us = self.last_capture_us.max(frame.capture_timestamp_us);
+        self.queued_samples += frame.samples.len();
+        self.packets.insert(frame.sequence, frame.samples);
+
+        // Keep latency bounded if the sender bursts or we stall
+        let max_samples = self.ms_to_samples(self.config.max_delay_ms);
+        while self.queued_samples > max_samples {
+            let Some((sequence, samples)) = self.packets.pop_first() else { break };
+            self.queued_samples -= samples.len();
+            self.next_sequence = Some(sequence + 1);
+            self.stats.overflow += 1;
+        }
+    }
+
+    /// Produce exactly `n` output samples, concealing or re-buffering as needed
+    pub fn pull(&mut self, n: usize) -> Vec<f32> {
+        let mut out = Vec::with_capacity(n);
+
+        if !self.playing {
+            if self.depth() < self.ms_to_samples(self.config.target_delay_ms) {
+                out.resize(n, 0.0);
+                return out;
+            }
+            self.playing = true;
+            self.next_sequence = self.packets.keys().next().copied();
+        }
+
+        while out.len() < n {
+            // Need two samples for linear interpolation
+            while self.current.len() < 2 {
+                if !self.refill() {
+                    // Outage too long: go quiet and wait for the buffer to refill
+                    // Playout resumes at whatever sequence arrives next, the sender may have restarted
+                    self.playing = false;
+                    self.stats.underruns += 1;
+                    self.current.clear();
+                    self.phase = 0.0;
+                    self.next_sequence = None;
+                    out.resize(n, 0.0);
+                    return out;
+                }
+            }
+            let frac = self.phase as f32;
+            out.push(self.current[0] * (1.0 - frac) + self.current[1] * frac);
+            self.phase += self.ratio;
+            while self.phase >= 1.0 && !self.current.is_empty() {
+                self.current.pop_front();
+                self.phase -= 1.0;
+            }
+        }
+
+        self.update_drift();
+        out
+    }
+
+    /// Drop everything queued and wait for the target delay again, stats are kept
+    fn reset(&mut self) {
+        self.packets.clear();
+        self.queued_samples = 0;
+        self.next_sequence = None;
+        self.playing = false;
+        self.current.clear();
+        self.phase = 0.0;
+        self.consecutive_losses = 0;
+    }
+
+    /// Move the next packet (or a concealment packet) into `current`
+    /// Returns false once concealment has run out
+    fn refill(&mut self) -> bool {
+        let next = self.next_sequence.unwrap_or(0);
+        if let Some(samples) = self.packets.remove(&next) {
+            self.queued_samples -= samples.len();
+            self.current.extend(samples.iter().copied());
+            self.last_packet = samples;
+            self.consecutive_losses = 0;
+        } else {
+            if self.consecutive_losses >= self.config.max_concealed_packets || self.last_packet.is_empty() {
+                return false;
+            }
+            // Packet loss concealment: fade a copy of the last good packet
+            self.consecutive_losses += 1;
+            self.stats.concealed += 1;
+            let gain = CONCEALMENT_DECAY.powi(self.consecutive_losses as i32);
+            self.current.extend(self.last_packet.iter().map(|&s| s * gain));
+        }
+        self.next_sequence = Some(next + 1);
+        true
+    }
+
+    /// Nudge playout speed so the buffer sits at the target depth
+    /// Sender clock fast -> depth grows -> ratio > 1 consumes faster
+    fn update_drift(&mut self) {
+        let depth = self.depth() as f64;
+        self.depth_avg += (depth - self.depth_avg) * DEPTH_SMOOTHING;
+        let target = self.ms_to_samples(self.config.target_delay_ms).max(1) as f64;
+        let error = (self.depth_avg - target) / target;
+        let limit = self.config.max_drift_ppm * 1e-6;
+        self.ratio = 1.0 + (error * DRIFT_GAIN).clamp(-limit, limit);
+    }
+
+    /// Samples buffered and not yet played
+    pub fn depth(&self) -> usize {
+        self.queued_samples + self.current.len()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Capture time of the newest packet received
+    pub fn last_capture_timestamp_us(&self) -> u64 {
+        self.last_capture_us
+    }
+
+    pub fn stats(&self) -> JitterStats {
+        JitterStats {
+            depth_ms: self.depth() as f32 * 1000.0 / self.config.sample_rate as f32,
+            drift_ppm: ((self.ratio - 1.0) * 1e6) as f32,
+            ..self.stats
+        }
+    }
+
+    fn ms_to_samples(&self, ms: f32) -> usize {
+        (ms * self.config.sample_rate as f32 / 1000.0) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tiny deterministic PRNG so tests don't need a rand dependency
+    struct Lcg(u64);
+    impl Lcg {
+        fn next_f32(&mut self) -> f32 {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (self.0 >> 40) as f32 / (1u64 << 24) as f32
+        }
+    }
+
+    /// Simulated sender: 20ms packets of a ramp, with jitter, loss and clock skew
+    /// Returns (arrival_time_ms, frame) sorted by arrival
+    fn generate_packets(count: u64, jitter_ms: f32, loss: f32, skew_ppm: f64) -> (Vec<(f64, AudioFrame)>, u64) {
+        let mut rng = Lcg(7);
+        let mut packets = Vec::new();
+        let mut lost = 0;
+        for seq in 0..count {
+            let send_time = seq as f64 * 20.0 / (1.0 + skew_ppm * 1e-6);
+            // Keep the first and last packets so loss counts are exact
+            let droppable = seq > 0 && seq + 10 < count;
+            if droppable && rng.next_f32() < loss {
+                lost += 1;
+                continue;
+            }
+            let arrival = send_time + (rng.next_f32() * jitter_ms) as f64;
+            let frame = AudioFrame {
+                sequence: seq,
+                capture_timestamp_us: (send_time * 1000.0) as u64,
+                sample_rate: 16000,
+                samples: vec![0.5; 320],
+            };
+            packets.push((arrival, frame));
+        }
+        packets.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        (packets, lost)
+    }
+
+    /// Drive the buffer with a 10ms playout clock
+    fn simulate(buffer: &mut JitterBuffer, packets: Vec<(f64, AudioFrame)>, duration_ms: u64) -> Vec<f32> {
+        let mut pending = packets.into_iter().peekable();
+        let mut out = Vec::new();
+        for now in (0..duration_ms).step_by(10) {
+            while let Some((arrival, _)) = pending.peek() {
+                if *arrival > now as f64 {
+                    break;
+                }
+                buffer.insert(pending.next().unwrap().1);
+            }
+            out.extend(buffer.pull(160));
+        }
+        out
+    }
+
+    #[test]
+    fn test_jitter_buffer_reorders_and_conceals_loss() {
+        let mut buffer = JitterBuffer::new(JitterConfig::default());
+        // 40ms of jitter reorders packets; 5% random loss
+        let (packets, lost) = generate_packets(500, 40.0, 0.05, 0.0);
+        let out = simulate(&mut buffer, packets, 10_000);
+
+        let stats = buffer.stats();
+        assert_eq!(out.len(), 1000 * 160);
+        assert!(lost > 0);
+        // Every lost packet gets concealed exactly once (none arrive late)
+        assert_eq!(stats.concealed, lost, "{:?}", stats);
+        assert_eq!(stats.late, 0);
+        assert_eq!(stats.underruns, 0);
+        // Concealed audio stays audible at first rather than dropping to silence
+        let steady = &out[16000..];
+        let silent = steady.iter().filter(|s| s.abs() < 0.1).count();
+        assert!(silent < steady.len() / 50, "{} silent samples", silent);
+    }
+
+    #[test]
+    fn test_jitter_buffer_tracks_clock_drift() {
+        let config = JitterConfig::default();
+        let target_ms = config.target_delay_ms;
+        let mut buffer = JitterBuffer::new(config);
+        // Sender clock runs 1000 ppm fast: without correction +60ms after 60s
+        let (packets, _) = generate_packets(3000, 10.0, 0.0, 1000.0);
+        simulate(&mut buffer, packets, 59_000);
+
+        let stats = buffer.stats();
+        assert_eq!(stats.overflow, 0, "{:?}", stats);
+        assert_eq!(stats.underruns, 0, "{:?}", stats);
+        assert!(stats.drift_ppm > 500.0, "{:?}", stats);
+        assert!((stats.depth_ms - target_ms).abs() < 40.0, "{:?}", stats);
+    }
+
+    #[test]
+    fn test_jitter_buffer_drops_late_and_duplicate() {
+        let mut buffer = JitterBuffer::new(JitterConfig { target_delay_ms: 20.0, ..Default::default() });
+        let frame = |sequence| AudioFrame {
+            sequence,
+            capture_timestamp_us: 0,
+            sample_rate: 16000,
+            samples: vec![0.1; 320],
+        };
+        buffer.insert(frame(5));
+        buffer.insert(frame(5));
+        buffer.pull(320); // starts playout at seq 5
+        buffer.insert(frame(4));
+        let stats = buffer.stats();
+        assert_eq!((stats.received, stats.duplicate, stats.late), (1, 1, 1));
+    }
+
+    #[test]
+    fn test_jitter_buffer_follows_sender_restart() {
+        let mut buffer = JitterBuffer::new(JitterConfig::default());
+        let (packets, _) = generate_packets(200, 10.0, 0.0, 0.0);
+        let start = simulate(&mut buffer, packets, 4_000);
+        assert!(start[8000..].iter().all(|&s| s > 0.4));
+
+        // Sender restarts at 0 without a gap: playout follows after one target delay
+        let (packets, _) = generate_packets(100, 10.0, 0.0, 0.0);
+        let restarted = simulate(&mut buffer, packets, 2_000);
+        let stats = buffer.stats();
+        assert_eq!((stats.restarts, stats.late), (1, 0), "{:?}", stats);
+        assert!(restarted[4000..24000].iter().all(|&s| s > 0.4));
+
+        // Sender restarts after a long silence: the underrun already reset playout
+        let mut buffer = JitterBuffer::new(JitterConfig::default());
+        let (packets, _) = generate_packets(100, 10.0, 0.0, 0.0);
+        simulate(&mut buffer, packets, 3_000);
+        assert_eq!(buffer.stats().underruns, 1);
+        let (packets, _) = generate_packets(20, 10.0, 0.0, 0.0);
+        let restarted = simulate(&mut buffer, packets, 300);
+        let stats = buffer.stats();
+        assert_eq!((stats.restarts, stats.late), (0, 0), "{:?}", stats);
+        assert!(restarted.iter().filter(|&&s| s > 0.4).count() > 160 * 10);
+    }
+}
@@ -0,0 +1,207 @@
+use super::traits::{AudioWriter, RecordingInfo};
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Buffers queued between the audio thread and the writer (~1-2s at typical buffer sizes)
+const QUEUE_DEPTH: usize = 128;
+/// How often the writer checks for a disarm when no audio is arriving
+const IDLE_POLL: Duration = Duration::from_millis(100);
+
+/// Live Recording
+///
+/// Continuous recording of processed audio that can be armed / disarmed while running
+/// -The audio thread only queues buffers (never blocks), a writer thread owns the disk I/O
+/// -Arming starts a new file through the shared writer, disarming finishes it (header, sidecar)
+/// -Works with any AudioWriter, the binary passes its Recorder
+/// -Clones share the same state and writer thread (controls, metrics exporter)
+#[derive(Clone)]
+pub struct LiveRecording {
+    armed: Arc<AtomicBool>,
+    tap: RecordingTap,
+    last: Arc<Mutex<Option<RecordingInfo>>>,
+}
+
+/// Cheap handle for the audio callback, see AudioProcessor::set_recording_tap
+#[derive(Clone)]
+pub struct RecordingTap {
+    armed: Arc<AtomicBool>,
+    tx: SyncSender<Vec<f32>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl RecordingTap {
+    /// Queue a buffer if armed, dropped (and counted) when the writer falls behind
+    pub fn push(&self, samples: &[f32]) {
+        if !self.armed.load(Ordering::Relaxed) {
+            return;
+        }
+        if self.tx.try_send(samples.to_vec()).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl LiveRecording {
+    /// Start the writer thread, disarmed
+    /// Args:
+    /// - writer: shared writer, also usable elsewhere while disarmed (replay dumps)
+    /// - sample_rate: rate of the buffers the tap receives (Hz)
+    /// - channels: interleaved channel count of those buffers
+    pub fn start<W>(writer: Arc<Mutex<W>>, sample_rate: u32, channels: u16) -> Self
+    where
+        W: AudioWriter + Send + 'static,
+        W::Error: Display,
+    {
+        let armed = Arc::new(AtomicBool::new(false));
+        let last = Arc::new(Mutex::new(None));
+        let (tx, rx) = mpsc::sync_channel(QUEUE_DEPTH);
+        let thread_armed = Arc::clone(&armed);
+        let thread_last = Arc::clone(&last);
+        thread::spawn(move || Self::write_loop(rx, writer, thread_armed, thread_last, sample_rate, channels));
+        Self {
+            tap: RecordingTap {
+                armed: Arc::clone(&armed),
+                tx,
+                dropped: Arc::new(AtomicU64::new(0)),
+            },
+            armed,
+            last,
+        }
+    }
+
+    pub fn tap(&self) -> RecordingTap {
+        self.tap.clone()
+    }
+
+    pub fn arm(&self) {
+        self.armed.store(true, Ordering::Relaxed);
+    }
+
+    pub fn disarm(&self) {
+        self.armed.store(false, Ordering::Relaxed);
+    }
+
+    /// Flip armed state, returns the new state
+    pub fn toggle(&self) -> bool {
+        !self.armed.fetch_xor(true, Ordering::Relaxed)
+    }
+
+    pub fn is_armed(&self) -> bool {
+        self.armed.load(Ordering::Relaxed)
+    }
+
+    /// Buffers lost because the writer couldn't keep up
+    pub fn dropped_count(&self) -> u64 {
+        self.tap.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Most recently finished recording
+    pub fn last_recording(&self) -> Option<RecordingInfo> {
+        self.last.lock().unwrap().clone()
+    }
+
+    fn write_loop<W>(
+        rx: Receiver<Vec<f32>>,
+        writer: Arc<Mutex<W>>,
+        armed: Arc<AtomicBool>,
+        last: Arc<Mutex<Option<RecordingInfo>>>,
+        sample_rate: u32,
+        channels: u16,
+    ) where
+        W: AudioWriter,
+        W::Error: Display,
+    {
+        let mut recording = false; // a file of ours is open
+        loop {
+            let buffer = match rx.recv_timeout(IDLE_POLL) {
+                Ok(buffer) => Some(buffer),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            let mut writer = writer.lock().unwrap();
+            if recording && !armed.load(Ordering::Relaxed) {
+                // Buffers queued after the disarm are dropped, the file ends at the keypress
+                recording = false;
+                match writer.finish_writing() {
+                    Ok(Some(info)) => {
+                        info.print_summary();
+                        *last.lock().unwrap() = Some(info);
+                    }
+                    Ok(None) => {}
+                    Err(e) => eprintln!("Finishing recording failed: {}", e),
+                }
+                continue;
+            }
+            let Some(buffer) = buffer else { continue };
+            if !recording {
+                if !armed.load(Ordering::Relaxed) {
+                    continue; // left over from a previous arm
+                }
+                if writer.is_writing() {
+                    // Someone else's file (a replay dump), never interleave with it
+                    continue;
+                }
+                if let Err(e) = writer.start_writing(sample_rate, channels) {
+                    eprintln!("Starting recording failed: {}", e);
+                    armed.store(false, Ordering::Relaxed);
+                    continue;
+                }
+                recording = true;
+            }
+            if let Err(e) = writer.write_samples(&buffer) {
+                eprintln!("Recording write failed, disarming: {}", e);
+                armed.store(false, Ordering::Relaxed);
+            }
+        }
+        if recording && let Err(e) = writer.lock().unwrap().finish_writing() {
+            eprintln!("Finishing recording failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::WavFileWriter;
+
+    fn wait_for(mut done: impl FnMut() -> bool) {
+        for _ in 0..100 {
+            if done() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        panic!("timed out");
+    }
+
+    #[test]
+    fn test_arm_records_until_disarmed() {
+        let dir = std::env::temp_dir().join(format!("merlin_live_{}", std::process::id()));
+        let writer = Arc::new(Mutex::new(WavFileWriter::new(&dir)));
+        let live = LiveRecording::start(Arc::clone(&writer), 16000, 1);
+        let tap = live.tap();
+
+        tap.push(&[0.1; 160]); // disarmed, ignored
+        assert!(live.toggle());
+        for _ in 0..10 {
+            tap.push(&[0.25; 160]);
+        }
+        wait_for(|| writer.lock().unwrap().recorded_seconds() >= 0.1);
+        assert!(!live.toggle());
+        wait_for(|| live.last_recording().is_some());
+        assert!(!writer.lock().unwrap().is_writing());
+
+        let info = live.last_recording().unwrap();
+        let mut reader = hound::WavReader::open(&info.file_path).unwrap();
+        let samples: Vec<f32> = reader.samples::<f32>().map(Result::unwrap).collect();
+        assert_eq!(samples.len(), 1600);
+        assert!(samples.iter().all(|&s| s == 0.25));
+        assert_eq!(live.dropped_count(), 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
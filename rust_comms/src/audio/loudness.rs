@@ -0,0 +1,358 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// Loudness Meter (EBU R128 / ITU-R BS.1770-4)
+///
+/// Device-independent loudness, unlike the unweighted RMS dB in AudioMetrics
+/// -K-weighting: high shelf (head effect) + RLB highpass, per channel
+/// -Momentary (400ms) and short-term (3s) sliding windows, 100ms hop
+/// -Integrated: absolute gate -70 LUFS, relative gate -10 LU
+/// -Loudness range (EBU Tech 3342): short-term values, gates -70 LUFS / -20 LU, 10th-95th percentile
+///
+/// Gated history is kept as 0.1 LU histograms so memory stays fixed on an always-on device
+#[derive(Debug, Clone)]
+pub struct LoudnessMeter {
+    channels: usize,
+    filters: Vec<KWeighting>,
+    block_frames: usize,         // frames per 100ms sub-block
+    block_position: usize,       // frames accumulated in the current sub-block
+    block_sum: f64,              // sum over channels of squared K-weighted samples
+    sub_blocks: VecDeque<f64>,   // mean square of the last 30 sub-blocks
+    integrated: LoudnessHistogram, // 400ms gating blocks
+    range: LoudnessHistogram,      // 3s short-term values
+    reading: LoudnessReading,
+}
+
+/// Current loudness values, all in LUFS except the range (LU)
+/// f32::NEG_INFINITY until enough audio has been seen
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct LoudnessReading {
+    pub momentary_lufs: f32,
+    pub short_term_lufs: f32,
+    pub integrated_lufs: f32,
+    pub loudness_range_lu: f32,
+    pub max_momentary_lufs: f32,
+}
+
+impl Default for LoudnessReading {
+    fn default() -> Self {
+        Self {
+            momentary_lufs: f32::NEG_INFINITY,
+            short_term_lufs: f32::NEG_INFINITY,
+            integrated_lufs: f32::NEG_INFINITY,
+            loudness_range_lu: 0.0,
+            max_momentary_lufs: f32::NEG_INFINITY,
+        }
+    }
+}
+
+const MOMENTARY_BLOCKS: usize = 4; // 400ms
+const SHORT_TERM_BLOCKS: usize = 30; // 3s
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const INTEGRATED_RELATIVE_GATE_LU: f64 = -10.0;
+const RANGE_RELATIVE_GATE_LU: f64 = -20.0;
+
+impl LoudnessMeter {
+    /// Args:
+    /// - sample_rate: audio sample rate (Hz)
+    /// - channels: interleaved channel count, all weighted 1.0 (no surround channels here)
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        let channels = channels.max(1) as usize;
+        Self {
+            channels,
+            filters: vec![KWeighting::new(sample_rate as f64); channels],
+            block_frames: (sample_rate as usize / 10).max(1),
+            block_position: 0,
+            block_sum: 0.0,
+            sub_blocks: VecDeque::with_capacity(SHORT_TERM_BLOCKS),
+            integrated: LoudnessHistogram::new(),
+            range: LoudnessHistogram::new(),
+            reading: LoudnessReading::default(),
+        }
+    }
+
+    /// Feed interleaved samples, returns the reading after the last completed 100ms block
+    pub fn process(&mut self, samples: &[f32]) -> LoudnessReading {
+        for frame in samples.chunks_exact(self.channels) {
+            for (filter, &sample) in self.filters.iter_mut().zip(frame) {
+                let weighted = filter.process(sample as f64);
+                self.block_sum += weighted * weighted;
+            }
+            self.block_position += 1;
+            if self.block_position == self.block_frames {
+                self.close_block();
+            }
+        }
+        self.reading
+    }
+
+    fn close_block(&mut self) {
+        if self.sub_blocks.len() == SHORT_TERM_BLOCKS {
+            self.sub_blocks.pop_front();
+        }
+        self.sub_blocks.push_back(self.block_sum / self.block_frames as f64);
+        self.block_sum = 0.0;
+        self.block_position = 0;
+
+        if self.sub_blocks.len() >= MOMENTARY_BLOCKS {
+            let energy = self.mean_energy(MOMENTARY_BLOCKS);
+            let lufs = energy_to_lufs(energy);
+            self.reading.momentary_lufs = lufs as f32;
+            self.reading.max_momentary_lufs = self.reading.max_momentary_lufs.max(lufs as f32);
+            // Each 400ms window, 75% overlapped, is one gating block
+            self.integrated.add(lufs, energy);
+            self.reading.integrated_lufs = self.integrated.gated_mean(INTEGRATED_RELATIVE_GATE_LU) as f32;
+        }
+
+        if self.sub_blocks.len() == SHORT_TERM_BLOCKS {
+            let energy = self.mean_energy(SHORT_TERM_BLOCKS);
+            let lufs = energy_to_lufs(energy);
+            self.reading.short_term_lufs = lufs as f32;
+            self.range.add(lufs, energy);
+            self.reading.loudness_range_lu = self.range.range(RANGE_RELATIVE_GATE_LU) as f32;
+        }
+    }
+
+    fn mean_energy(&self, blocks: usize) -> f64 {
+        self.sub_blocks.iter().rev().take(blocks).sum::<f64>() / blocks as f64
+    }
+
+    pub fn reading(&self) -> LoudnessReading {
+        self.reading
+    }
+
+    /// Forget gated history (new programme), filters keep running
+    pub fn reset_integrated(&mut self) {
+        self.integrated = LoudnessHistogram::new();
+        self.range = LoudnessHistogram::new();
+        self.reading.integrated_lufs = f32::NEG_INFINITY;
+        self.reading.loudness_range_lu = 0.0;
+        self.reading.max_momentary_lufs = f32::NEG_INFINITY;
+    }
+}
+
+/// BS.1770: L = -0.691 + 10 log10(sum of channel mean squares)
+fn energy_to_lufs(energy: f64) -> f64 {
+    if energy <= 0.0 {
+        f64::NEG_INFINITY
+    } else {
+        -0.691 + 10.0 * energy.log10()
+    }
+}
+
+/// Two cascaded biquads, coefficients derived for any sample rate
+/// (pre-filter shelf + RLB highpass, BS.1770 Annex 1)
+#[derive(Debug, Clone)]
+struct KWeighting {
+    shelf: Biquad,
+    highpass: Biquad,
+}
+
+impl KWeighting {
+    fn new(sample_rate: f64) -> Self {
+        use std::f64::consts::PI;
+
+        // Stage 1: high shelf, +4 dB above ~1.7 kHz
+        let f0 = 1681.974450955533;
+        let gain_db = 3.999843853973347;
+        let q = 0.7071752369554196;
+        let k = (PI * f0 / sample_rate).tan();
+        let vh = 10f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad {
+            b: [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            z: [0.0; 2],
+        };
+
+        // Stage 2: RLB highpass at ~38 Hz
+        let f0 = 38.13547087602444;
+        let q = 0.5003270373238773;
+        let k = (PI * f0 / sample_rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let highpass = Biquad {
+            b: [1.0, -2.0, 1.0],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            z: [0.0; 2],
+        };
+
+        Self { shelf, highpass }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        self.highpass.process(self.shelf.process(x))
+    }
+}
+
+/// Transposed direct form II biquad, a0 normalized to 1
+#[derive(Debug, Clone)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+/// Loudness values above the absolute gate in 0.1 LU bins
+/// Energy sums per bin keep gated means exact apart from the boundary bin
+#[derive(Debug, Clone)]
+struct LoudnessHistogram {
+    counts: Vec<u64>,
+    energy: Vec<f64>,
+}
+
+const HISTOGRAM_MIN_LUFS: f64 = ABSOLUTE_GATE_LUFS;
+const HISTOGRAM_MAX_LUFS: f64 = 10.0;
+const HISTOGRAM_STEP_LU: f64 = 0.1;
+
+impl LoudnessHistogram {
+    fn new() -> Self {
+        let bins = ((HISTOGRAM_MAX_LUFS - HISTOGRAM_MIN_LUFS) / HISTOGRAM_STEP_LU) as usize;
+        Self {
+            counts: vec![0; bins],
+            energy: vec![0.0; bins],
+        }
+    }
+
+    fn bin(lufs: f64) -> usize {
+        let bin = ((lufs - HISTOGRAM_MIN_LUFS) / HISTOGRAM_STEP_LU) as usize;
+        bin.min(((HISTOGRAM_MAX_LUFS - HISTOGRAM_MIN_LUFS) / HISTOGRAM_STEP_LU) as usize - 1)
+    }
+
+    fn bin_floor(bin: usize) -> f64 {
+        HISTOGRAM_MIN_LUFS + bin as f64 * HISTOGRAM_STEP_LU
+    }
+
+    /// Absolute gate applied here: anything at or below -70 LUFS is ignored
+    fn add(&mut self, lufs: f64, energy: f64) {
+        if lufs <= ABSOLUTE_GATE_LUFS {
+            return;
+        }
+        let bin = Self::bin(lufs);
+        self.counts[bin] += 1;
+        self.energy[bin] += energy;
+    }
+
+    /// First bin passing a relative gate of `relative_lu` below the ungated mean
+    fn relative_gate_bin(&self, relative_lu: f64) -> Option<usize> {
+        let count: u64 = self.counts.iter().sum();
+        if count == 0 {
+            return None;
+        }
+        let mean = self.energy.iter().sum::<f64>() / count as f64;
+        let gate = energy_to_lufs(mean) + relative_lu;
+        Some(if gate <= HISTOGRAM_MIN_LUFS { 0 } else { Self::bin(gate) })
+    }
+
+    /// Integrated loudness: mean energy of blocks above both gates
+    fn gated_mean(&self, relative_lu: f64) -> f64 {
+        let Some(start) = self.relative_gate_bin(relative_lu) else {
+            return f64::NEG_INFINITY;
+        };
+        let count: u64 = self.counts[start..].iter().sum();
+        if count == 0 {
+            return f64::NEG_INFINITY;
+        }
+        energy_to_lufs(self.energy[start..].iter().sum::<f64>() / count as f64)
+    }
+
+    /// Loudness range: spread between 10th and 95th percentile of gated values
+    fn range(&self, relative_lu: f64) -> f64 {
+        let Some(start) = self.relative_gate_bin(relative_lu) else {
+            return 0.0;
+        };
+        let counts = &self.counts[start..];
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return 0.0;
+        }
+        let percentile = |p: f64| {
+            let target = (p * (total - 1) as f64).round() as u64;
+            let mut seen = 0;
+            for (i, &c) in counts.iter().enumerate() {
+                seen += c;
+                if seen > target {
+                    return Self::bin_floor(start + i) + HISTOGRAM_STEP_LU / 2.0;
+                }
+            }
+            Self::bin_floor(start + counts.len() - 1)
+        };
+        percentile(0.95) - percentile(0.10)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    /// Stereo 1 kHz sine at 48 kHz, each segment (seconds, peak dBFS)
+    /// Same construction as the EBU Tech 3341 / 3342 test signals
+    fn ebu_sine(segments: &[(f64, f64)]) -> Vec<f32> {
+        let mut out = Vec::new();
+        let mut n = 0u64;
+        for &(seconds, dbfs) in segments {
+            let amplitude = 10f64.powf(dbfs / 20.0);
+            for _ in 0..(seconds * 48000.0) as u64 {
+                let s = (amplitude * (2.0 * PI * 1000.0 * n as f64 / 48000.0).sin()) as f32;
+                out.push(s);
+                out.push(s);
+                n += 1;
+            }
+        }
+        out
+    }
+
+    fn measure(signal: &[f32]) -> LoudnessReading {
+        let mut meter = LoudnessMeter::new(48000, 2);
+        // 10ms buffers like the audio callback
+        for chunk in signal.chunks(960) {
+            meter.process(chunk);
+        }
+        meter.reading()
+    }
+
+    #[test]
+    fn test_ebu_3341_case_1_and_2_steady_sine() {
+        for level in [-23.0, -33.0] {
+            let reading = measure(&ebu_sine(&[(20.0, level)]));
+            let level = level as f32;
+            assert!((reading.momentary_lufs - level).abs() <= 0.1, "{:?}", reading);
+            assert!((reading.short_term_lufs - level).abs() <= 0.1, "{:?}", reading);
+            assert!((reading.integrated_lufs - level).abs() <= 0.1, "{:?}", reading);
+        }
+    }
+
+    #[test]
+    fn test_ebu_3341_case_3_relative_gate() {
+        // Quiet sections sit more than 10 LU down and are gated out
+        let reading = measure(&ebu_sine(&[(10.0, -36.0), (60.0, -23.0), (10.0, -36.0)]));
+        assert!((reading.integrated_lufs - -23.0).abs() <= 0.1, "{:?}", reading);
+    }
+
+    #[test]
+    fn test_ebu_3342_loudness_range() {
+        // Case 1: 20s at -20 then 20s at -30 -> LRA 10 LU
+        let reading = measure(&ebu_sine(&[(20.0, -20.0), (20.0, -30.0)]));
+        assert!((reading.loudness_range_lu - 10.0).abs() <= 1.0, "{:?}", reading);
+        // Case 3: -40 then -20 -> LRA 20 LU
+        let reading = measure(&ebu_sine(&[(20.0, -40.0), (20.0, -20.0)]));
+        assert!((reading.loudness_range_lu - 20.0).abs() <= 1.0, "{:?}", reading);
+    }
+
+    #[test]
+    fn test_silence_is_gated() {
+        let reading = measure(&vec![0.0; 48000 * 2 * 5]);
+        assert_eq!(reading.integrated_lufs, f32::NEG_INFINITY);
+        assert_eq!(reading.loudness_range_lu, 0.0);
+    }
+}
@@ -1,8 +1,24 @@
-#[derive(Debug, Clone, Copy)]
+use serde::{Serialize, Serializer};
+use std::collections::VecDeque;
+
+use super::loudness::LoudnessReading;
+use super::telemetry::PipelineTelemetry;
+
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct AudioMetrics {
     pub rms: f32,
     pub peak: f32,
     pub db: f32,
+    /// Rolling 1s/10s/60s statistics, so slow pollers don't miss anything
+    pub levels: LevelWindows,
+    /// Callback timing and latency for the buffer that produced these levels
+    pub telemetry: PipelineTelemetry,
+    /// EBU R128 loudness (K-weighted), comparable across devices
+    pub loudness: LoudnessReading,
+    /// Gate / normalizer state after the last buffer
+    pub filters: FilterState,
+    /// Per-channel levels of the last buffer (raw input)
+    pub channels: ChannelLevels,
 }
 
 impl AudioMetrics {
@@ -11,6 +27,11 @@ impl AudioMetrics {
             rms: 0.0,
             peak: 0.0,
             db: -60.0,
+            levels: LevelWindows::default(),
+            telemetry: PipelineTelemetry::default(),
+            loudness: LoudnessReading::default(),
+            filters: FilterState::default(),
+            channels: ChannelLevels::default(),
         }
     }
 
@@ -19,10 +40,391 @@ impl AudioMetrics {
         self.peak = peak;
         self.db = db;
     }
+
+    pub fn set_levels(&mut self, levels: LevelWindows) {
+        self.levels = levels;
+    }
+
+    pub fn set_telemetry(&mut self, telemetry: PipelineTelemetry) {
+        self.telemetry = telemetry;
+    }
+
+    pub fn set_loudness(&mut self, loudness: LoudnessReading) {
+        self.loudness = loudness;
+    }
+
+    pub fn set_filters(&mut self, filters: FilterState) {
+        self.filters = filters;
+    }
+
+    pub fn set_channels(&mut self, channels: ChannelLevels) {
+        self.channels = channels;
+    }
 }
 
 impl Default for AudioMetrics {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+/// Channels metered individually, any beyond this are left out
+pub const MAX_METER_CHANNELS: usize = 8;
+
+/// Levels of one channel over one buffer
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ChannelLevel {
+    pub rms_db: f32,
+    pub peak_db: f32,
+    /// Buffer had samples at or beyond CLIP_THRESHOLD
+    pub clipped: bool,
+}
+
+impl Default for ChannelLevel {
+    fn default() -> Self {
+        Self {
+            rms_db: -60.0,
+            peak_db: -60.0,
+            clipped: false,
+        }
+    }
+}
+
+/// Per-channel levels, fixed size so AudioMetrics stays Copy
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ChannelLevels {
+    levels: [ChannelLevel; MAX_METER_CHANNELS],
+    count: usize,
+}
+
+impl ChannelLevels {
+    /// Measure each channel of an interleaved buffer
+    pub fn measure(data: &[f32], channels: u16) -> Self {
+        let stride = channels.max(1) as usize;
+        let count = stride.min(MAX_METER_CHANNELS);
+        let mut levels = [ChannelLevel::default(); MAX_METER_CHANNELS];
+        let frames = data.len() / stride;
+        for (channel, level) in levels.iter_mut().enumerate().take(count) {
+            let samples = data.iter().skip(channel).step_by(stride).take(frames);
+            let (mut sum_squares, mut peak) = (0.0f64, 0.0f32);
+            for &x in samples {
+                sum_squares += (x * x) as f64;
+                peak = peak.max(x.abs());
+            }
+            let rms = (sum_squares / frames.max(1) as f64).sqrt() as f32;
+            *level = ChannelLevel {
+                rms_db: 20.0 * rms.max(1e-10).log10(),
+                peak_db: 20.0 * peak.max(1e-10).log10(),
+                clipped: peak >= CLIP_THRESHOLD,
+            };
+        }
+        Self { levels, count }
+    }
+
+    /// One entry per channel, empty before the first buffer
+    pub fn as_slice(&self) -> &[ChannelLevel] {
+        &self.levels[..self.count]
+    }
+}
+
+// Only the channels in use, as a list
+impl Serialize for ChannelLevels {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_slice().serialize(serializer)
+    }
+}
+
+/// What the filter chain is doing to the signal
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct FilterState {
+    pub gate_open: bool,
+    /// Gate envelope [0.0, 1.0]
+    pub gate_gain: f32,
+    /// Normalizer gain in dB
+    pub normalizer_gain_db: f32,
+    /// Gate and normalizer skipped, audio passes through as captured
+    pub bypassed: bool,
+}
+
+impl Default for FilterState {
+    fn default() -> Self {
+        Self {
+            gate_open: false,
+            gate_gain: 0.0,
+            normalizer_gain_db: 0.0,
+            bypassed: false,
+        }
+    }
+}
+
+/// Level statistics over one time window
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct LevelStats {
+    /// Quietest / loudest buffer RMS in the window
+    pub min_db: f32,
+    pub max_db: f32,
+    /// Energy average over the window
+    pub mean_db: f32,
+    /// Samples at or beyond full scale
+    pub clipped_samples: u64,
+    /// Share of time the buffer RMS was above the gate threshold [0.0, 1.0]
+    pub above_gate_fraction: f32,
+}
+
+impl Default for LevelStats {
+    fn default() -> Self {
+        Self {
+            min_db: -60.0,
+            max_db: -60.0,
+            mean_db: -60.0,
+            clipped_samples: 0,
+            above_gate_fraction: 0.0,
+        }
+    }
+}
+
+/// Snapshot of all rolling windows, copied into AudioMetrics
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct LevelWindows {
+    pub last_1s: LevelStats,
+    pub last_10s: LevelStats,
+    pub last_60s: LevelStats,
+    /// Peak hold in dBFS, held for a second then decays
+    pub peak_hold_db: f32,
+    /// Clipped samples since start
+    pub clipped_total: u64,
+}
+
+impl Default for LevelWindows {
+    fn default() -> Self {
+        Self {
+            last_1s: LevelStats::default(),
+            last_10s: LevelStats::default(),
+            last_60s: LevelStats::default(),
+            peak_hold_db: -60.0,
+            clipped_total: 0,
+        }
+    }
+}
+
+/// Anything at or above this magnitude counts as clipped
+pub const CLIP_THRESHOLD: f32 = 0.999;
+/// Seconds the peak marker holds before decaying
+const PEAK_HOLD_SECONDS: f32 = 1.0;
+const PEAK_DECAY_DB_PER_SECOND: f32 = 20.0;
+/// Statistics are binned at this resolution
+const BIN_SECONDS: f32 = 0.1;
+const BINS_1S: usize = 10;
+const BINS_10S: usize = 100;
+const BINS_60S: usize = 600;
+
+/// Accumulated levels for one 100ms bin
+#[derive(Debug, Clone, Copy)]
+struct LevelBin {
+    sum_squares: f64,
+    samples: u64,
+    min_db: f32,
+    max_db: f32,
+    clipped: u64,
+    above_gate_samples: u64,
+}
+
+impl LevelBin {
+    fn empty() -> Self {
+        Self {
+            sum_squares: 0.0,
+            samples: 0,
+            min_db: f32::INFINITY,
+            max_db: f32::NEG_INFINITY,
+            clipped: 0,
+            above_gate_samples: 0,
+        }
+    }
+}
+
+/// Rolling Levels
+///
+/// Keeps 60s of 100ms bins so every window is computed from the same history
+/// -Fed every buffer by the pipeline, produces a LevelWindows snapshot
+#[derive(Debug, Clone)]
+pub struct RollingLevels {
+    bins: VecDeque<LevelBin>, // closed bins, newest at back
+    current: LevelBin,
+    bin_samples: u64,
+    sample_rate: u32,
+    channels: u16,
+    gate_threshold_db: f32,
+    peak_hold_db: f32,
+    peak_hold_age: f32, // seconds since the hold was set
+    clipped_total: u64,
+}
+
+impl RollingLevels {
+    /// Args:
+    /// - sample_rate: audio sample rate (Hz)
+    /// - channels: interleaved channel count
+    /// - gate_threshold_db: level counted as "above gate"
+    pub fn new(sample_rate: u32, channels: u16, gate_threshold_db: f32) -> Self {
+        let channels = channels.max(1);
+        Self {
+            bins: VecDeque::with_capacity(BINS_60S),
+            current: LevelBin::empty(),
+            bin_samples: (BIN_SECONDS * sample_rate as f32) as u64 * channels as u64,
+            sample_rate,
+            channels,
+            gate_threshold_db,
+            peak_hold_db: -60.0,
+            peak_hold_age: 0.0,
+            clipped_total: 0,
+        }
+    }
+
+    /// Fold one buffer into the history and return the updated windows
+    pub fn update(&mut self, data: &[f32]) -> LevelWindows {
+        if data.is_empty() {
+            return self.snapshot();
+        }
+        let sum_squares: f64 = data.iter().map(|&x| (x * x) as f64).sum();
+        let rms = (sum_squares / data.len() as f64).sqrt() as f32;
+        let db = 20.0 * rms.max(1e-10).log10();
+        let peak = data.iter().map(|&x| x.abs()).fold(0.0f32, f32::max);
+        let clipped = data.iter().filter(|&&x| x.abs() >= CLIP_THRESHOLD).count() as u64;
+
+        let bin = &mut self.current;
+        bin.sum_squares += sum_squares;
+        bin.samples += data.len() as u64;
+        bin.min_db = bin.min_db.min(db);
+        bin.max_db = bin.max_db.max(db);
+        bin.clipped += clipped;
+        if db > self.gate_threshold_db {
+            bin.above_gate_samples += data.len() as u64;
+        }
+        self.clipped_total += clipped;
+
+        if self.current.samples >= self.bin_samples {
+            if self.bins.len() == BINS_60S {
+                self.bins.pop_front();
+            }
+            self.bins.push_back(self.current);
+            self.current = LevelBin::empty();
+        }
+
+        // Peak hold: jump up instantly, hold, then fall at a fixed rate
+        let seconds = data.len() as f32 / self.channels as f32 / self.sample_rate as f32;
+        let peak_db = 20.0 * peak.max(1e-10).log10();
+        if peak_db >= self.peak_hold_db {
+            self.peak_hold_db = peak_db;
+            self.peak_hold_age = 0.0;
+        } else {
+            self.peak_hold_age += seconds;
+            if self.peak_hold_age > PEAK_HOLD_SECONDS {
+                self.peak_hold_db = (self.peak_hold_db - PEAK_DECAY_DB_PER_SECOND * seconds).max(peak_db);
+            }
+        }
+
+        self.snapshot()
+    }
+
+    pub fn snapshot(&self) -> LevelWindows {
+        LevelWindows {
+            last_1s: self.window(BINS_1S),
+            last_10s: self.window(BINS_10S),
+            last_60s: self.window(BINS_60S),
+            peak_hold_db: self.peak_hold_db,
+            clipped_total: self.clipped_total,
+        }
+    }
+
+    /// Combine the in-progress bin with the newest closed bins
+    fn window(&self, bins: usize) -> LevelStats {
+        let mut acc = self.current;
+        // The partial bin stands in for the oldest closed one so the span stays ~constant
+        let closed = if self.current.samples > 0 { bins - 1 } else { bins };
+        for bin in self.bins.iter().rev().take(closed) {
+            acc.sum_squares += bin.sum_squares;
+            acc.samples += bin.samples;
+            acc.min_db = acc.min_db.min(bin.min_db);
+            acc.max_db = acc.max_db.max(bin.max_db);
+            acc.clipped += bin.clipped;
+            acc.above_gate_samples += bin.above_gate_samples;
+        }
+        if acc.samples == 0 {
+            return LevelStats::default();
+        }
+        let mean_rms = (acc.sum_squares / acc.samples as f64).sqrt() as f32;
+        LevelStats {
+            min_db: acc.min_db,
+            max_db: acc.max_db,
+            mean_db: 20.0 * mean_rms.max(1e-10).log10(),
+            clipped_samples: acc.clipped,
+            above_gate_fraction: acc.above_gate_samples as f32 / acc.samples as f32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_levels_split_interleaved_channels() {
+        // Left at half scale, right clipping
+        let data: Vec<f32> = (0..200).map(|i| if i % 2 == 0 { 0.5 } else { -1.0 }).collect();
+        let levels = ChannelLevels::measure(&data, 2);
+        let levels = levels.as_slice();
+        assert_eq!(levels.len(), 2);
+        assert!((levels[0].rms_db + 6.02).abs() < 0.01 && !levels[0].clipped);
+        assert!(levels[1].peak_db.abs() < 1e-6 && levels[1].clipped);
+        assert!(ChannelLevels::default().as_slice().is_empty());
+    }
+
+    #[test]
+    fn test_rolling_windows_keep_history() {
+        // 10ms buffers at 16 kHz mono
+        let mut levels = RollingLevels::new(16000, 1, -40.0);
+
+        // 5s of loud audio with one clipped sample per second, then 5s of near silence
+        for i in 0..500 {
+            let mut buffer = vec![0.5; 160];
+            if i % 100 == 0 {
+                buffer[0] = 1.0;
+            }
+            levels.update(&buffer);
+        }
+        let mut windows = LevelWindows::default();
+        for _ in 0..500 {
+            windows = levels.update(&[0.001; 160]);
+        }
+
+        // Last second only saw silence
+        assert!((windows.last_1s.max_db - -60.0).abs() < 0.1);
+        assert_eq!(windows.last_1s.above_gate_fraction, 0.0);
+        assert_eq!(windows.last_1s.clipped_samples, 0);
+
+        // Ten seconds still remember the loud half
+        assert!(windows.last_10s.max_db > -7.0);
+        assert!((windows.last_10s.min_db - -60.0).abs() < 0.1);
+        assert!((windows.last_10s.above_gate_fraction - 0.5).abs() < 0.02);
+        assert_eq!(windows.last_10s.clipped_samples, 5);
+        // Energy mean is dominated by the loud half: 0.5^2 / 2 -> about -9 dB
+        assert!((windows.last_10s.mean_db - -9.0).abs() < 0.5, "{:?}", windows.last_10s);
+        assert_eq!(windows.clipped_total, 5);
+    }
+
+    #[test]
+    fn test_peak_hold_decays_after_hold_time() {
+        let mut levels = RollingLevels::new(16000, 1, -40.0);
+        levels.update(&[0.5; 160]); // ~ -6 dB peak
+        // 0.5s later the hold hasn't moved
+        let mut windows = LevelWindows::default();
+        for _ in 0..50 {
+            windows = levels.update(&[0.01; 160]);
+        }
+        assert!((windows.peak_hold_db - -6.02).abs() < 0.1);
+        // 1s after the hold expires it has fallen ~20 dB
+        for _ in 0..150 {
+            windows = levels.update(&[0.01; 160]);
+        }
+        assert!((windows.peak_hold_db - -26.0).abs() < 1.0, "{}", windows.peak_hold_db);
+    }
+}
@@ -2,12 +2,52 @@ pub mod metrics;
 pub mod processor;
 pub mod traits;
 pub mod wav_writer;
+pub mod file_writer;
 pub mod filters;
+pub mod replay;
+pub mod resample;
+pub mod publisher;
+pub mod jitter;
+pub mod network_source;
+pub mod output;
+pub mod telemetry;
+pub mod loudness;
+pub mod repair;
+pub mod retention;
+pub mod sidecar;
+pub mod catalog;
+pub mod crypto;
+pub mod encrypted_writer;
+pub mod flac;
+pub mod flac_writer;
+pub mod recorder;
+pub mod batch;
+pub mod live_recording;
+pub mod pipeline;
 
-pub use metrics::AudioMetrics;
-pub use processor::AudioProcessor;
-pub use wav_writer::WavFileWriter;
-pub use filters::{NoiseGate, Normalizer};
+pub use metrics::{AudioMetrics, ChannelLevel, ChannelLevels, FilterState, LevelStats, LevelWindows, RollingLevels};
+pub use processor::{AudioProcessor, DeviceRequest};
+pub use wav_writer::{WavFileWriter, WavFormat};
+pub use file_writer::{FormatWriter, RecordingFileWriter, RecordingSink, RotationPolicy};
+pub use filters::{FilterConfig, GateSnapshot, NoiseGate, Normalizer, NormalizerSnapshot};
+pub use replay::{ReplayBuffer, ReplaySnapshot};
+pub use resample::Resampler;
+pub use publisher::{AudioFrame, AudioPublisher};
+pub use jitter::{JitterBuffer, JitterConfig, JitterStats};
+pub use network_source::{NetworkAudioSource, NetworkSourceConfig};
+pub use output::{AudioOutput, InputDucker, PlaybackState};
+pub use telemetry::PipelineTelemetry;
+pub use loudness::{LoudnessMeter, LoudnessReading};
+pub use repair::{repair_directory, repair_wav, RepairOutcome};
+pub use retention::{DeleteReason, DeletedRecording, RetentionManager, RetentionPolicy};
+pub use sidecar::{LevelSummary, RecordingContext, RecordingMetadata};
+pub use catalog::{RecordingCatalog, RecordingQuery};
+pub use encrypted_writer::{DecryptedRecording, EncryptedWriter, KeySource, RecordingDecryptor, RecordingKey};
+pub use flac_writer::FlacFileWriter;
+pub use recorder::{Recorder, RecordingFormat};
+pub use batch::{process_directory, process_wav, read_wav, ProcessReport, SignalStats};
+pub use live_recording::{LiveRecording, RecordingTap};
+pub use pipeline::{AudioPipeline, PipelineConfig, PipelineOutput, PipelineSnapshot, StageConfig, StageMetrics, StageSnapshot};
 
 #[allow(unused_imports)]
 pub use traits::{AudioWriter, RecordingInfo};
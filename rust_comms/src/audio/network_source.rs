@@ -0,0 +1,289 @@
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::jitter::{JitterBuffer, JitterConfig, JitterStats};
+use super::publisher::AudioFrame;
+
+/// Network Audio Source
+///
+/// Receives mic audio from the Quest over UDP and plays it out on a local clock
+/// -Packets use the same framing as the local publisher (AudioFrame, f32 PCM)
+/// -Jitter buffer handles reorder, loss concealment and clock drift
+/// -Output is handed to a callback in fixed-size buffers, like a cpal input stream
+/// -start() returns a handle that stops and joins both threads, like dropping a cpal stream
+pub struct NetworkAudioSource {
+    socket: UdpSocket,
+    config: NetworkSourceConfig,
+    jitter: Arc<Mutex<JitterBuffer>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NetworkSourceConfig {
+    /// UDP address to listen on (ex: "0.0.0.0:5005")
+    pub bind_addr: String,
+    /// Expected sender sample rate, packets at other rates are ignored
+    pub sample_rate: u32,
+    /// Playout buffer size handed to the callback, at least one sample
+    pub buffer_ms: f32,
+    pub jitter: JitterConfig,
+}
+
+impl Default for NetworkSourceConfig {
+    fn default() -> Self {
+        Self {
+            bind_addr: "0.0.0.0:5005".to_string(),
+            sample_rate: 16000,
+            buffer_ms: 10.0,
+            jitter: JitterConfig::default(),
+        }
+    }
+}
+
+/// Largest UDP datagram, senders normally use ~20ms packets
+const MAX_PACKET_BYTES: usize = 65_536;
+/// Receive errors in a row before the socket is given up on, retried with a doubling wait
+const MAX_RECEIVE_FAILURES: u32 = 10;
+const RECEIVE_RETRY_BASE: Duration = Duration::from_millis(10);
+/// How long a blocked receive waits before checking for stop
+const STOP_POLL: Duration = Duration::from_millis(100);
+
+/// Running receive + playout threads, stopped and joined on stop() or drop
+pub struct NetworkSourceHandle {
+    stop: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl NetworkSourceHandle {
+    /// Stop both threads and wait for them, the callback has returned for good after this
+    pub fn stop(mut self) {
+        self.join();
+    }
+
+    fn join(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        for thread in self.threads.drain(..) {
+            if thread.join().is_err() {
+                eprintln!("Network audio thread panicked");
+            }
+        }
+    }
+}
+
+impl Drop for NetworkSourceHandle {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+impl NetworkAudioSource {
+    pub fn bind(config: NetworkSourceConfig) -> io::Result<Self> {
+        if config.sample_rate == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "sample_rate must be above 0"));
+        }
+        if Self::buffer_samples(&config) == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("buffer_ms {} is less than one sample at {} Hz", config.buffer_ms, config.sample_rate),
+            ));
+        }
+        let socket = UdpSocket::bind(&config.bind_addr)?;
+        let jitter = JitterBuffer::new(JitterConfig {
+            sample_rate: config.sample_rate,
+            ..config.jitter.clone()
+        });
+        println!("Network audio source listening on {} ({} Hz)", socket.local_addr()?, config.sample_rate);
+        Ok(Self {
+            socket,
+            config,
+            jitter: Arc::new(Mutex::new(jitter)),
+        })
+    }
+
+    /// Samples per playout buffer, 0 for a non-finite or too small buffer_ms
+    fn buffer_samples(config: &NetworkSourceConfig) -> usize {
+        let samples = config.buffer_ms * config.sample_rate as f32 / 1000.0;
+        if samples.is_finite() { samples as usize } else { 0 }
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.config.sample_rate
+    }
+
+    pub fn stats(&self) -> JitterStats {
+        self.jitter.lock().map(|j| j.stats()).unwrap_or_default()
+    }
+
+    /// Start receive and playout threads
+    /// `on_buffer` runs on the playout thread with (mono samples, capture timestamp μs)
+    /// Both threads run until the returned handle is stopped or dropped
+    pub fn start<F>(&self, mut on_buffer: F) -> io::Result<NetworkSourceHandle>
+    where
+        F: FnMut(&[f32], u64) + Send + 'static,
+    {
+        let socket = self.socket.try_clone()?;
+        socket.set_read_timeout(Some(STOP_POLL))?;
+        let sample_rate = self.config.sample_rate;
+        let stop = Arc::new(AtomicBool::new(false));
+        let receive_stop = Arc::clone(&stop);
+        let receive_jitter = Arc::clone(&self.jitter);
+        let receiver = thread::spawn(move || {
+            let mut packet = vec![0u8; MAX_PACKET_BYTES];
+            let mut failures = 0;
+            while !receive_stop.load(Ordering::Relaxed) {
+                let len = match socket.recv(&mut packet) {
+                    Ok(len) => len,
+                    // Signal, stop poll timeout, or an ICMP error left by an earlier send: nothing wrong with the socket
+                    Err(e)
+                        if matches!(
+                            e.kind(),
+                            io::ErrorKind::Interrupted
+                                | io::ErrorKind::WouldBlock
+                                | io::ErrorKind::TimedOut
+                                | io::ErrorKind::ConnectionRefused
+                                | io::ErrorKind::ConnectionReset
+                        ) =>
+                    {
+                        continue;
+                    }
+                    Err(e) => {
+                        failures += 1;
+                        if failures >= MAX_RECEIVE_FAILURES {
+                            eprintln!("Network audio receive failed {} times, stopping: {}", failures, e);
+                            break;
+                        }
+                        eprintln!("Network audio receive error: {}", e);
+                        thread::sleep(RECEIVE_RETRY_BASE * 2u32.pow(failures - 1));
+                        continue;
+                    }
+                };
+                failures = 0;
+                match AudioFrame::read_from(&mut &packet[..len]) {
+                    Ok(frame) if frame.sample_rate == sample_rate => {
+                        receive_jitter.lock().unwrap().insert(frame);
+                    }
+                    Ok(frame) => eprintln!("Network audio: ignoring {} Hz packet", frame.sample_rate),
+                    Err(e) => eprintln!("Network audio: bad packet: {}", e),
+                }
+            }
+        });
+
+        // Playout clock: fixed period, scheduled against absolute deadlines so it doesn't drift
+        let buffer_samples = Self::buffer_samples(&self.config);
+        let period = Duration::from_secs_f64(buffer_samples as f64 / sample_rate as f64);
+        let playout_stop = Arc::clone(&stop);
+        let playout_jitter = Arc::clone(&self.jitter);
+        let playout = thread::spawn(move || {
+            let mut deadline = Instant::now();
+            while !playout_stop.load(Ordering::Relaxed) {
+                deadline += period;
+                let (samples, capture_us) = {
+                    let mut jitter = playout_jitter.lock().unwrap();
+                    (jitter.pull(buffer_samples), jitter.last_capture_timestamp_us())
+                };
+                on_buffer(&samples, capture_us);
+                let now = Instant::now();
+                match deadline.checked_duration_since(now) {
+                    Some(wait) => thread::sleep(wait),
+                    // Stalled for more than a period: start over from now rather than play the missed buffers in a burst
+                    None if now - deadline > period => deadline = now,
+                    None => {}
+                }
+            }
+        });
+
+        println!("Network audio playout started ({} samples per buffer)", buffer_samples);
+        Ok(NetworkSourceHandle {
+            stop,
+            threads: vec![receiver, playout],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_network_source_udp_loopback() {
+        let source = NetworkAudioSource::bind(NetworkSourceConfig {
+            bind_addr: "127.0.0.1:0".to_string(),
+            jitter: JitterConfig { target_delay_ms: 40.0, ..Default::default() },
+            ..Default::default()
+        })
+        .unwrap();
+        let addr = source.local_addr().unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let handle = source
+            .start(move |samples, _| {
+                tx.send(samples.iter().fold(0.0f32, |m, s| m.max(s.abs()))).ok();
+            })
+            .unwrap();
+
+        // Local packet generator: 20ms packets, sent out of order with one missing
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for sequence in [1u64, 0, 2, 4, 3, 6, 7, 8, 9] {
+            let frame = AudioFrame {
+                sequence,
+                capture_timestamp_us: sequence * 20_000,
+                sample_rate: 16000,
+                samples: vec![0.5; 320],
+            };
+            sender.send_to(&frame.encode(), addr).unwrap();
+        }
+
+        // Buffers arrive on the playout clock; audio shows up once target delay is reached
+        let peaks: Vec<f32> = (0..50)
+            .filter_map(|_| rx.recv_timeout(Duration::from_secs(1)).ok())
+            .collect();
+        assert!(peaks.iter().any(|&peak| peak > 0.4));
+
+        let stats = source.stats();
+        assert_eq!(stats.received, 9);
+        assert!(stats.concealed >= 1, "{:?}", stats);
+
+        // Stopping joins both threads, which drops the callback and its sender
+        handle.stop();
+        while rx.try_recv().is_ok() {}
+        assert_eq!(rx.try_recv(), Err(mpsc::TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn test_buffer_size_validated() {
+        for buffer_ms in [0.0, 0.01, f32::NAN] {
+            let config = NetworkSourceConfig { bind_addr: "127.0.0.1:0".to_string(), buffer_ms, ..Default::default() };
+            let error = NetworkAudioSource::bind(config).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn test_playout_skips_ahead_after_a_stall() {
+        let source = NetworkAudioSource::bind(NetworkSourceConfig { bind_addr: "127.0.0.1:0".to_string(), ..Default::default() }).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let mut calls = 0;
+        let handle = source
+            .start(move |_, _| {
+                calls += 1;
+                if calls == 3 {
+                    thread::sleep(Duration::from_millis(100)); // ten periods
+                }
+                tx.send(Instant::now()).ok();
+            })
+            .unwrap();
+        let times: Vec<Instant> = (0..8).map(|_| rx.recv_timeout(Duration::from_secs(1)).unwrap()).collect();
+        handle.stop();
+        // After the stall buffers come one period apart again, not nine back to back
+        let bursts = times[2..].windows(2).filter(|pair| pair[1] - pair[0] < Duration::from_millis(3)).count();
+        assert!(bursts <= 1, "{:?}", times.windows(2).map(|pair| pair[1] - pair[0]).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,489 @@
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{Device, StreamConfig};
+use std::collections::VecDeque;
+use std::io;
+use std::os::unix::net::UnixListener;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::publisher::AudioFrame;
+use super::replay::{ReplayBuffer, ReplaySnapshot};
+use super::resample::Resampler;
+
+/// Audio Output
+///
+/// Playback path for TTS so capture knows when MERLIN is talking
+/// -Queue of mono PCM at the output rate, rendered to every output channel
+/// -At most MAX_QUEUED_SECONDS queued: enqueue drops the oldest audio, socket clients wait for room
+/// -cpal output stream, or a null sink that just consumes in real time (tests, headless)
+/// -Shares a PlaybackState with the input pipeline for ducking and echo reference
+/// -Playback socket clients that half-close are answered by a close once their audio has played
+pub struct AudioOutput {
+    backend: OutputBackend,
+    sample_rate: u32,
+    channels: u16,
+    queue: Arc<Mutex<PlaybackQueue>>,
+    playback: Arc<PlaybackState>,
+}
+
+/// Samples waiting for the speaker
+struct PlaybackQueue {
+    samples: VecDeque<f32>,
+    capacity: usize,
+    queued_total: u64, // every sample ever queued, so a client can tell when its audio is through
+    dropped: u64,      // pushed out by newer audio
+}
+
+impl PlaybackQueue {
+    fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+            queued_total: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Append, dropping the oldest samples past capacity
+    fn extend(&mut self, samples: impl IntoIterator<Item = f32>) {
+        let before = self.samples.len();
+        self.samples.extend(samples);
+        self.queued_total += (self.samples.len() - before) as u64;
+        let excess = self.samples.len().saturating_sub(self.capacity);
+        self.samples.drain(..excess);
+        self.dropped += excess as u64;
+    }
+
+    fn has_room(&self, samples: usize) -> bool {
+        self.samples.len() + samples <= self.capacity
+    }
+
+    /// Samples played or cleared so far
+    fn consumed_total(&self) -> u64 {
+        self.queued_total - self.samples.len() as u64
+    }
+}
+
+enum OutputBackend {
+    Cpal { device: Device, config: StreamConfig },
+    Null,
+}
+
+pub const DEFAULT_PLAYBACK_SOCKET_PATH: &str = "/tmp/merlin_playback.sock";
+/// Seconds of played audio kept as the reference signal
+const REFERENCE_SECONDS: f32 = 2.0;
+/// Input stays ducked this long after output goes quiet (room tail)
+const DEFAULT_HOLD: Duration = Duration::from_millis(200);
+/// Null sink buffer period
+const NULL_BUFFER_MS: u64 = 10;
+/// How often a playback client's drain is checked
+const DRAIN_POLL: Duration = Duration::from_millis(10);
+/// Most audio waiting for the speaker, a TTS sentence or two
+pub const MAX_QUEUED_SECONDS: f32 = 5.0;
+
+/// What's playing right now, readable from any stage
+pub struct PlaybackState {
+    epoch: Instant,
+    last_active_us: AtomicU64, // since epoch, 0 = never active
+    hold: Duration,
+    reference: Mutex<ReplayBuffer>,
+}
+
+impl PlaybackState {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            epoch: Instant::now(),
+            last_active_us: AtomicU64::new(0),
+            hold: DEFAULT_HOLD,
+            reference: Mutex::new(ReplayBuffer::new(REFERENCE_SECONDS, sample_rate, 1)),
+        }
+    }
+
+    /// True while output is playing, and for the hold time after
+    pub fn is_active(&self) -> bool {
+        let last = self.last_active_us.load(Ordering::Relaxed);
+        if last == 0 {
+            return false;
+        }
+        let now = self.epoch.elapsed().as_micros() as u64;
+        now.saturating_sub(last) <= self.hold.as_micros() as u64
+    }
+
+    fn mark_active(&self) {
+        // +1 so the very first buffer isn't mistaken for "never"
+        let now = self.epoch.elapsed().as_micros() as u64 + 1;
+        self.last_active_us.store(now, Ordering::Relaxed);
+    }
+
+    /// Last `seconds` of what was sent to the speaker (mono, output rate)
+    /// Reference signal for echo cancellation or barge-in detection
+    pub fn reference(&self, seconds: Option<f32>) -> ReplaySnapshot {
+        self.reference.lock().unwrap().snapshot(seconds)
+    }
+}
+
+/// Pulls queued audio into output buffers, runs on the output thread
+pub struct OutputRenderer {
+    queue: Arc<Mutex<PlaybackQueue>>,
+    playback: Arc<PlaybackState>,
+    channels: usize,
+    mono: Vec<f32>,
+}
+
+impl OutputRenderer {
+    /// Fill an interleaved output buffer, silence when the queue runs dry
+    pub fn render(&mut self, out: &mut [f32]) {
+        let frames = out.len() / self.channels;
+        self.mono.clear();
+        if let Ok(mut queue) = self.queue.lock() {
+            let take = frames.min(queue.samples.len());
+            self.mono.extend(queue.samples.drain(..take));
+        }
+        if !self.mono.is_empty() {
+            self.playback.mark_active();
+        }
+        self.mono.resize(frames, 0.0);
+
+        for (frame, &sample) in out.chunks_exact_mut(self.channels).zip(&self.mono) {
+            frame.fill(sample);
+        }
+        if let Ok(mut reference) = self.playback.reference.lock() {
+            reference.push(&self.mono);
+        }
+    }
+}
+
+impl AudioOutput {
+    /// Open the default output device
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let host = cpal::default_host();
+        let device = host.default_output_device().ok_or("No output device available")?;
+        println!("Using output device: {}", device.name()?);
+        let config: StreamConfig = device.default_output_config()?.into();
+        Ok(Self::with_backend(
+            OutputBackend::Cpal { device, config: config.clone() },
+            config.sample_rate.0,
+            config.channels,
+        ))
+    }
+
+    /// Output that discards audio in real time, no device needed
+    pub fn null(sample_rate: u32, channels: u16) -> Self {
+        Self::with_backend(OutputBackend::Null, sample_rate, channels)
+    }
+
+    fn with_backend(backend: OutputBackend, sample_rate: u32, channels: u16) -> Self {
+        Self {
+            backend,
+            sample_rate,
+            channels: channels.max(1),
+            queue: Arc::new(Mutex::new(PlaybackQueue::new((sample_rate as f32 * MAX_QUEUED_SECONDS) as usize))),
+            playback: Arc::new(PlaybackState::new(sample_rate)),
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Shared playback state for ducking and reference
+    pub fn playback_state(&self) -> Arc<PlaybackState> {
+        Arc::clone(&self.playback)
+    }
+
+    /// Queue mono samples already at the output rate
+    /// Past MAX_QUEUED_SECONDS the oldest queued audio is dropped
+    pub fn enqueue(&self, samples: &[f32]) {
+        self.queue.lock().unwrap().extend(samples.iter().copied());
+    }
+
+    /// Samples dropped because the queue was full
+    pub fn dropped_samples(&self) -> u64 {
+        self.queue.lock().unwrap().dropped
+    }
+
+    pub fn queued_seconds(&self) -> f32 {
+        self.queue.lock().unwrap().samples.len() as f32 / self.sample_rate as f32
+    }
+
+    /// Drop everything queued (barge-in)
+    pub fn clear(&self) {
+        self.queue.lock().unwrap().samples.clear();
+    }
+
+    pub fn renderer(&self) -> OutputRenderer {
+        OutputRenderer {
+            queue: Arc::clone(&self.queue),
+            playback: Arc::clone(&self.playback),
+            channels: self.channels as usize,
+            mono: Vec::new(),
+        }
+    }
+
+    /// Start pulling from the queue on the output thread
+    pub fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut renderer = self.renderer();
+        match &self.backend {
+            OutputBackend::Cpal { device, config } => {
+                let stream = device.build_output_stream(
+                    config,
+                    move |data: &mut [f32], _: &cpal::OutputCallbackInfo| renderer.render(data),
+                    |err| eprintln!("Audio output error: {}", err),
+                    None,
+                )?;
+                stream.play()?;
+                // Same as the input stream: lives for the rest of the process
+                std::mem::forget(stream);
+            }
+            OutputBackend::Null => {
+                let frames = (self.sample_rate as u64 * NULL_BUFFER_MS / 1000) as usize;
+                let mut buffer = vec![0.0; frames * self.channels as usize];
+                thread::spawn(move || {
+                    let period = Duration::from_millis(NULL_BUFFER_MS);
+                    let mut deadline = Instant::now();
+                    loop {
+                        deadline += period;
+                        renderer.render(&mut buffer);
+                        if let Some(wait) = deadline.checked_duration_since(Instant::now()) {
+                            thread::sleep(wait);
+                        }
+                    }
+                });
+            }
+        }
+        println!("Audio output started ({} Hz, {} ch)", self.sample_rate, self.channels);
+        Ok(())
+    }
+
+    /// Accept PCM to play on a Unix socket, framed like the capture publisher
+    /// -Frames at other sample rates are resampled per connection
+    /// -A full queue stops reading from the client until there's room, so socket audio is never dropped
+    /// -Once the client shuts down its write side, the connection is closed when its audio has played (or was cleared)
+    pub fn listen(&self, socket_path: impl AsRef<Path>) -> io::Result<()> {
+        let socket_path = socket_path.as_ref();
+        if socket_path.exists() {
+            std::fs::remove_file(socket_path)?;
+        }
+        let listener = UnixListener::bind(socket_path)?;
+        let queue = Arc::clone(&self.queue);
+        let output_rate = self.sample_rate;
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let queue = Arc::clone(&queue);
+                thread::spawn(move || {
+                    let mut resampler: Option<Resampler> = None;
+                    while let Ok(frame) = AudioFrame::read_from(&mut stream) {
+                        if frame.sample_rate == output_rate {
+                            queue_when_room(&queue, frame.samples);
+                            continue;
+                        }
+                        let resampler = resampler
+                            .get_or_insert_with(|| Resampler::new(frame.sample_rate, output_rate, 1));
+                        let samples = resampler.process(&frame.samples);
+                        queue_when_room(&queue, samples);
+                    }
+                    // The resampler holds back its last few samples until it sees what follows
+                    if let Some(mut resampler) = resampler {
+                        let mut tail = Vec::new();
+                        resampler.flush_into(&mut tail);
+                        queue_when_room(&queue, tail);
+                    }
+                    // Client is done sending: hold the connection open until its last sample is out
+                    let sent = queue.lock().unwrap().queued_total;
+                    while queue.lock().unwrap().consumed_total() < sent {
+                        thread::sleep(DRAIN_POLL);
+                    }
+                });
+            }
+        });
+        println!("Playback socket listening on {:?}", socket_path);
+        Ok(())
+    }
+}
+
+/// Back-pressure for socket clients: wait for the speaker to make room rather than drop older audio
+/// A single chunk bigger than the whole queue goes in once it's empty, keeping its newest part
+fn queue_when_room(queue: &Mutex<PlaybackQueue>, samples: Vec<f32>) {
+    loop {
+        let mut queue = queue.lock().unwrap();
+        if queue.has_room(samples.len()) || queue.samples.is_empty() {
+            queue.extend(samples);
+            return;
+        }
+        drop(queue);
+        thread::sleep(DRAIN_POLL);
+    }
+}
+
+/// Input Ducker
+///
+/// Attenuates capture while output is active so MERLIN doesn't hear itself
+/// -Gain ramps between 1.0 and the duck gain to avoid clicks
+/// -Pass f32::NEG_INFINITY as duck_db to fully gate
+#[derive(Clone)]
+pub struct InputDucker {
+    playback: Arc<PlaybackState>,
+    duck_gain: f32,
+    gain: f32,
+    step: f32, // gain change per sample
+}
+
+impl InputDucker {
+    /// Args:
+    /// - playback: shared state from AudioOutput
+    /// - duck_db: attenuation while output plays (ex: -30.0)
+    /// - ramp_ms: time to move fully between gains
+    /// - sample_rate: input sample rate (Hz)
+    pub fn new(playback: Arc<PlaybackState>, duck_db: f32, ramp_ms: f32, sample_rate: f32) -> Self {
+        let ramp_samples = (ramp_ms * sample_rate / 1000.0).max(1.0);
+        Self {
+            playback,
+            duck_gain: 10.0_f32.powf(duck_db / 20.0),
+            gain: 1.0,
+            step: 1.0 / ramp_samples,
+        }
+    }
+
+    pub fn process(&mut self, samples: &mut [f32]) {
+        // State checked once per buffer, ramp runs per sample
+        let target = if self.playback.is_active() { self.duck_gain } else { 1.0 };
+        for sample in samples.iter_mut() {
+            if self.gain < target {
+                self.gain = (self.gain + self.step).min(target);
+            } else if self.gain > target {
+                self.gain = (self.gain - self.step).max(target);
+            }
+            *sample *= self.gain;
+        }
+    }
+
+    pub fn is_ducking(&self) -> bool {
+        self.gain < 1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renderer_plays_queue_and_records_reference() {
+        let output = AudioOutput::null(16000, 2);
+        let mut renderer = output.renderer();
+        let playback = output.playback_state();
+        assert!(!playback.is_active());
+
+        output.enqueue(&[0.5; 100]);
+        let mut buffer = vec![1.0; 320]; // 160 stereo frames
+        renderer.render(&mut buffer);
+
+        // Queued audio on both channels, then silence
+        assert!(buffer[..200].iter().all(|&s| s == 0.5));
+        assert!(buffer[200..].iter().all(|&s| s == 0.0));
+        assert!(playback.is_active());
+        assert_eq!(output.queued_seconds(), 0.0);
+
+        let reference = playback.reference(None);
+        assert_eq!(reference.samples.len(), 160);
+        assert_eq!(reference.samples[99], 0.5);
+    }
+
+    #[test]
+    fn test_ducker_follows_playback() {
+        let output = AudioOutput::null(16000, 1);
+        let mut renderer = output.renderer();
+        let mut ducker = InputDucker::new(output.playback_state(), -30.0, 5.0, 16000.0);
+
+        // Nothing playing: input untouched
+        let mut input = vec![0.5; 160];
+        ducker.process(&mut input);
+        assert!(input.iter().all(|&s| s == 0.5));
+
+        // TTS starts: input ramps down to -30 dB
+        output.enqueue(&[0.3; 1600]);
+        renderer.render(&mut [0.0; 160]);
+        let mut input = vec![0.5; 320];
+        ducker.process(&mut input);
+        assert!(ducker.is_ducking());
+        assert!((input[319] - 0.5 * 0.0316).abs() < 1e-3, "got {}", input[319]);
+    }
+
+    #[test]
+    fn test_playback_socket_closes_once_played() {
+        use std::io::{Read, Write};
+        use std::os::unix::net::UnixStream;
+
+        let path = std::env::temp_dir().join(format!("merlin_playback_{}.sock", uuid::Uuid::new_v4()));
+        let output = AudioOutput::null(16000, 1);
+        output.start().unwrap();
+        output.listen(&path).unwrap();
+        let playback = output.playback_state();
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let sent = Instant::now();
+        // 200ms at 22.05 kHz, resampled to the output rate on the way in
+        let frame = AudioFrame { sequence: 0, capture_timestamp_us: 0, sample_rate: 22050, samples: vec![0.2; 4410] };
+        client.write_all(&frame.encode()).unwrap();
+        client.shutdown(std::net::Shutdown::Write).unwrap();
+
+        // Closed only after the audio went out, all of it: 200ms at the output rate
+        assert_eq!(client.read(&mut [0u8; 1]).unwrap(), 0);
+        assert_eq!(playback.reference(None).samples.iter().filter(|&&s| s != 0.0).count(), 3200);
+        assert!(sent.elapsed() >= Duration::from_millis(150), "{:?}", sent.elapsed());
+        assert_eq!(output.queued_seconds(), 0.0);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_queue_is_capped() {
+        use std::io::Write;
+        use std::os::unix::net::UnixStream;
+
+        // enqueue keeps the newest MAX_QUEUED_SECONDS
+        let output = AudioOutput::null(1000, 1);
+        let ramp: Vec<f32> = (0..6000).map(|i| i as f32).collect();
+        output.enqueue(&ramp);
+        assert_eq!(output.queued_seconds(), MAX_QUEUED_SECONDS);
+        assert_eq!(output.dropped_samples(), 1000);
+        let mut buffer = [0.0; 1];
+        output.renderer().render(&mut buffer);
+        assert_eq!(buffer[0], 1000.0);
+        output.clear();
+
+        // Socket clients are held back instead
+        let path = std::env::temp_dir().join(format!("merlin_playback_{}.sock", uuid::Uuid::new_v4()));
+        output.listen(&path).unwrap();
+        let mut client = UnixStream::connect(&path).unwrap();
+        let sender = thread::spawn(move || {
+            for sequence in 0..60 {
+                let frame = AudioFrame { sequence, capture_timestamp_us: 0, sample_rate: 1000, samples: vec![0.2; 100] };
+                client.write_all(&frame.encode()).unwrap();
+            }
+            client
+        });
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while output.queued_seconds() < MAX_QUEUED_SECONDS {
+            assert!(Instant::now() < deadline, "queue never filled");
+            thread::sleep(DRAIN_POLL);
+        }
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(output.queued_seconds(), MAX_QUEUED_SECONDS);
+        assert_eq!(output.dropped_samples(), 1000);
+
+        // Barge-in makes room and the rest flows in
+        output.clear();
+        let _client = sender.join().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while output.queued_seconds() < 1.0 {
+            assert!(Instant::now() < deadline, "held back audio never arrived");
+            thread::sleep(DRAIN_POLL);
+        }
+        assert_eq!(output.dropped_samples(), 1000);
+        std::fs::remove_file(&path).ok();
+    }
+}
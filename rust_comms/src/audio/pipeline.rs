@@ -0,0 +1,460 @@
+//! Configurable Filter Pipeline
+//!
+//! Any sequence of gate / normalizer / resample / metrics stages, built from JSON
+//! -Backs merlin_audio.PyAudioPipeline so Python makes one call per chunk instead of chaining filters
+//! -Stages keep their state between calls, so chunks can be streamed through
+//! -Each stage reports what it did under its name, metrics stages measure the audio at that point
+
+use super::filters::{FilterConfig, GateSnapshot, NoiseGate, Normalizer, NormalizerSnapshot};
+use super::loudness::{LoudnessMeter, LoudnessReading};
+use super::metrics::{ChannelLevels, CLIP_THRESHOLD};
+use super::resample::Resampler;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// Pipeline settings
+///
+/// {"sample_rate": 16000, "channels": 1, "stages": [{"type": "gate", "threshold_db": -45}, {"type": "normalizer"}]}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineConfig {
+    /// Rate of the audio passed to process (Hz)
+    pub sample_rate: u32,
+    /// Interleaved channel count of the input
+    #[serde(default = "default_channels")]
+    pub channels: u16,
+    pub stages: Vec<StageConfig>,
+}
+
+/// One stage, tagged by "type"; unset parameters take the live pipeline defaults
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum StageConfig {
+    Gate {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        #[serde(default = "default_gate_threshold")]
+        threshold_db: f32,
+        #[serde(default = "default_gate_attack")]
+        attack_ms: f32,
+        #[serde(default = "default_gate_release")]
+        release_ms: f32,
+    },
+    Normalizer {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        #[serde(default = "default_normalizer_target")]
+        target_db: f32,
+        #[serde(default = "default_normalizer_window")]
+        window_ms: f32,
+    },
+    /// Downmixes to mono, like the live capture path
+    Resample {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        rate: u32,
+    },
+    Metrics {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+}
+
+fn default_channels() -> u16 {
+    1
+}
+
+fn default_gate_threshold() -> f32 {
+    FilterConfig::default().gate_threshold_db
+}
+
+fn default_gate_attack() -> f32 {
+    FilterConfig::default().gate_attack_ms
+}
+
+fn default_gate_release() -> f32 {
+    FilterConfig::default().gate_release_ms
+}
+
+fn default_normalizer_target() -> f32 {
+    FilterConfig::default().normalizer_target_db
+}
+
+fn default_normalizer_window() -> f32 {
+    FilterConfig::default().normalizer_window_ms
+}
+
+impl StageConfig {
+    /// Metrics key, the stage type unless named
+    pub fn name(&self) -> &str {
+        let (name, kind) = match self {
+            StageConfig::Gate { name, .. } => (name, "gate"),
+            StageConfig::Normalizer { name, .. } => (name, "normalizer"),
+            StageConfig::Resample { name, .. } => (name, "resample"),
+            StageConfig::Metrics { name } => (name, "metrics"),
+        };
+        name.as_deref().unwrap_or(kind)
+    }
+}
+
+/// What one stage reported for the last chunk
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum StageMetrics {
+    Gate {
+        open: bool,
+        /// Envelope at the end of the chunk [0.0, 1.0]
+        gain: f32,
+        /// Share of the chunk's samples processed with the gate open, 0-100
+        open_percent: f32,
+    },
+    Normalizer {
+        gain_db: f32,
+    },
+    Resample {
+        input_rate: u32,
+        output_rate: u32,
+        frames: usize,
+    },
+    Levels {
+        rms_db: f32,
+        peak_db: f32,
+        clipped_samples: u64,
+        channels: ChannelLevels,
+        /// Running loudness of everything seen by this stage
+        loudness: LoudnessReading,
+    },
+}
+
+/// Processed chunk plus per-stage metrics
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PipelineOutput {
+    #[serde(skip)]
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub stages: BTreeMap<String, StageMetrics>,
+}
+
+/// Filter state of the stages that have any, keyed by stage name
+/// Resample and metrics stages start fresh after a restore
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipelineSnapshot {
+    pub stages: BTreeMap<String, StageSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StageSnapshot {
+    Gate(GateSnapshot),
+    Normalizer(NormalizerSnapshot),
+}
+
+#[derive(Clone)]
+enum Stage {
+    Gate(NoiseGate),
+    Normalizer(Normalizer),
+    Resample(Resampler),
+    Metrics(LoudnessMeter),
+}
+
+/// Stateful stage chain built from a PipelineConfig
+pub struct AudioPipeline {
+    config: PipelineConfig,
+    stages: Vec<(String, Stage)>,
+    output_rate: u32,
+    output_channels: u16,
+}
+
+impl AudioPipeline {
+    /// Build the stages, rejecting bad rates, parameters and duplicate names
+    pub fn new(config: PipelineConfig) -> Result<Self, String> {
+        if config.sample_rate == 0 {
+            return Err("sample_rate must be above 0".into());
+        }
+        if config.channels == 0 {
+            return Err("channels must be above 0".into());
+        }
+        let mut names = HashSet::new();
+        let (mut rate, mut channels) = (config.sample_rate, config.channels);
+        let mut stages = Vec::with_capacity(config.stages.len());
+        for stage in &config.stages {
+            let name = stage.name().to_string();
+            if !names.insert(name.clone()) {
+                return Err(format!("Duplicate stage name '{}', give repeated stages a \"name\"", name));
+            }
+            let built = match *stage {
+                StageConfig::Gate { threshold_db, attack_ms, release_ms, .. } => {
+                    check_finite(&name, &[threshold_db, attack_ms, release_ms])?;
+                    Stage::Gate(NoiseGate::new(threshold_db, attack_ms, release_ms, rate as f32))
+                }
+                StageConfig::Normalizer { target_db, window_ms, .. } => {
+                    check_finite(&name, &[target_db, window_ms])?;
+                    Stage::Normalizer(Normalizer::new(target_db, window_ms, rate as f32))
+                }
+                StageConfig::Resample { rate: output_rate, .. } => {
+                    if output_rate == 0 {
+                        return Err(format!("Stage '{}': rate must be above 0", name));
+                    }
+                    let resampler = Resampler::new(rate, output_rate, channels);
+                    (rate, channels) = (output_rate, 1);
+                    Stage::Resample(resampler)
+                }
+                StageConfig::Metrics { .. } => Stage::Metrics(LoudnessMeter::new(rate, channels)),
+            };
+            stages.push((name, built));
+        }
+        Ok(Self {
+            config,
+            stages,
+            output_rate: rate,
+            output_channels: channels,
+        })
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let config = serde_json::from_str(json).map_err(|e| format!("Bad pipeline config: {}", e))?;
+        Self::new(config)
+    }
+
+    pub fn config(&self) -> &PipelineConfig {
+        &self.config
+    }
+
+    /// Rate after the last resample stage (the input rate without one)
+    pub fn output_rate(&self) -> u32 {
+        self.output_rate
+    }
+
+    pub fn output_channels(&self) -> u16 {
+        self.output_channels
+    }
+
+    /// Run one chunk of interleaved samples through every stage in order
+    pub fn process(&mut self, samples: &[f32]) -> Result<PipelineOutput, String> {
+        if !samples.len().is_multiple_of(self.config.channels as usize) {
+            return Err(format!(
+                "{} samples is not a whole number of {}-channel frames",
+                samples.len(),
+                self.config.channels
+            ));
+        }
+        let mut audio = samples.to_vec();
+        let (mut rate, mut channels) = (self.config.sample_rate, self.config.channels);
+        let mut metrics = BTreeMap::new();
+        for (name, stage) in &mut self.stages {
+            let reported = match stage {
+                Stage::Gate(gate) => {
+                    let mut open_samples = 0usize;
+                    for sample in audio.chunks_mut(1) {
+                        gate.process(sample);
+                        if gate.is_open() {
+                            open_samples += 1;
+                        }
+                    }
+                    StageMetrics::Gate {
+                        open: gate.is_open(),
+                        gain: gate.envelope(),
+                        open_percent: 100.0 * open_samples as f32 / audio.len().max(1) as f32,
+                    }
+                }
+                Stage::Normalizer(normalizer) => {
+                    normalizer.process(&mut audio);
+                    StageMetrics::Normalizer {
+                        gain_db: 20.0 * normalizer.current_gain().max(1e-10).log10(),
+                    }
+                }
+                Stage::Resample(resampler) => {
+                    audio = resampler.process(&audio);
+                    (rate, channels) = (resampler.output_rate(), 1);
+                    StageMetrics::Resample {
+                        input_rate: resampler.input_rate(),
+                        output_rate: rate,
+                        frames: audio.len(),
+                    }
+                }
+                Stage::Metrics(loudness) => measure(&audio, channels, loudness),
+            };
+            metrics.insert(name.clone(), reported);
+        }
+        Ok(PipelineOutput {
+            samples: audio,
+            sample_rate: rate,
+            channels,
+            stages: metrics,
+        })
+    }
+
+    pub fn snapshot(&self) -> PipelineSnapshot {
+        let stages = self
+            .stages
+            .iter()
+            .filter_map(|(name, stage)| {
+                let snapshot = match stage {
+                    Stage::Gate(gate) => StageSnapshot::Gate(gate.snapshot()),
+                    Stage::Normalizer(normalizer) => StageSnapshot::Normalizer(normalizer.snapshot()),
+                    Stage::Resample(_) | Stage::Metrics(_) => return None,
+                };
+                Some((name.clone(), snapshot))
+            })
+            .collect();
+        PipelineSnapshot { stages }
+    }
+
+    /// Load filter state by stage name; stages the snapshot doesn't mention keep theirs
+    /// All or nothing: any unknown name, type mismatch or bad value leaves the pipeline as it was
+    pub fn restore(&mut self, snapshot: &PipelineSnapshot) -> Result<(), String> {
+        let mut stages = self.stages.clone();
+        for (name, saved) in &snapshot.stages {
+            let (_, stage) = stages
+                .iter_mut()
+                .find(|(stage_name, _)| stage_name == name)
+                .ok_or_else(|| format!("No stage named '{}' in this pipeline", name))?;
+            match (stage, saved) {
+                (Stage::Gate(gate), StageSnapshot::Gate(saved)) => gate.restore(saved)?,
+                (Stage::Normalizer(normalizer), StageSnapshot::Normalizer(saved)) => normalizer.restore(saved)?,
+                _ => return Err(format!("Stage '{}' is a different type in this pipeline", name)),
+            }
+        }
+        self.stages = stages;
+        Ok(())
+    }
+
+    /// Clear every stage's state (envelopes, gain, resampler history, loudness)
+    pub fn reset(&mut self) {
+        let (mut rate, mut channels) = (self.config.sample_rate, self.config.channels);
+        for (_, stage) in &mut self.stages {
+            match stage {
+                Stage::Gate(gate) => gate.reset(),
+                Stage::Normalizer(normalizer) => normalizer.reset(),
+                Stage::Resample(resampler) => {
+                    resampler.reset();
+                    (rate, channels) = (resampler.output_rate(), 1);
+                }
+                Stage::Metrics(loudness) => *loudness = LoudnessMeter::new(rate, channels),
+            }
+        }
+    }
+}
+
+fn check_finite(name: &str, values: &[f32]) -> Result<(), String> {
+    if values.iter().all(|value| value.is_finite()) {
+        Ok(())
+    } else {
+        Err(format!("Stage '{}': parameters must be finite numbers", name))
+    }
+}
+
+fn measure(audio: &[f32], channels: u16, loudness: &mut LoudnessMeter) -> StageMetrics {
+    let sum_squares: f64 = audio.iter().map(|&x| x as f64 * x as f64).sum();
+    let rms = (sum_squares / audio.len().max(1) as f64).sqrt() as f32;
+    let peak = audio.iter().map(|&x| x.abs()).fold(0.0f32, f32::max);
+    StageMetrics::Levels {
+        rms_db: 20.0 * rms.max(1e-10).log10(),
+        peak_db: 20.0 * peak.max(1e-10).log10(),
+        clipped_samples: audio.iter().filter(|x| x.abs() >= CLIP_THRESHOLD).count() as u64,
+        channels: ChannelLevels::measure(audio, channels),
+        loudness: loudness.process(audio),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_config_defaults_and_validation() {
+        let pipeline = AudioPipeline::from_json(
+            r#"{"sample_rate": 48000, "channels": 2, "stages": [{"type": "gate"}, {"type": "resample", "rate": 16000}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            pipeline.config().stages[0],
+            StageConfig::Gate {
+                name: None,
+                threshold_db: -40.0,
+                attack_ms: 10.0,
+                release_ms: 100.0
+            }
+        );
+        assert_eq!((pipeline.output_rate(), pipeline.output_channels()), (16000, 1));
+
+        for bad in [
+            r#"{"sample_rate": 16000, "stages": [{"type": "echo"}]}"#,
+            r#"{"sample_rate": 16000, "stages": [{"type": "gate", "treshold_db": -40}]}"#,
+            r#"{"sample_rate": 16000, "stages": [{"type": "metrics"}, {"type": "metrics"}]}"#,
+            r#"{"sample_rate": 16000, "stages": [{"type": "resample", "rate": 0}]}"#,
+        ] {
+            assert!(AudioPipeline::from_json(bad).is_err(), "accepted {}", bad);
+        }
+    }
+
+    #[test]
+    fn test_pipeline_matches_chained_filters() {
+        let json = r#"{"sample_rate": 16000, "stages": [
+            {"type": "metrics", "name": "input"},
+            {"type": "gate", "threshold_db": -45, "attack_ms": 5},
+            {"type": "normalizer"},
+            {"type": "metrics", "name": "output"}
+        ]}"#;
+        let mut pipeline = AudioPipeline::from_json(json).unwrap();
+        let tone: Vec<f32> = (0..3200).map(|i| 0.1 * (2.0 * PI * 440.0 * i as f32 / 16000.0).sin()).collect();
+
+        let mut expected = tone.clone();
+        NoiseGate::new(-45.0, 5.0, 100.0, 16000.0).process(&mut expected);
+        FilterConfig::default().normalizer(16000.0).process(&mut expected);
+
+        let output = pipeline.process(&tone).unwrap();
+        assert_eq!(output.samples, expected);
+        assert_eq!(output.stages.len(), 4);
+        match (&output.stages["input"], &output.stages["output"]) {
+            (StageMetrics::Levels { rms_db: before, .. }, StageMetrics::Levels { rms_db: after, .. }) => {
+                assert!((before - -23.0).abs() < 0.1, "input rms {}", before);
+                assert!(after > before, "normalizer should raise a quiet tone");
+            }
+            other => panic!("unexpected metrics {:?}", other),
+        }
+        assert!(pipeline.process(&[0.0; 3]).is_ok());
+    }
+
+    #[test]
+    fn test_snapshot_restores_by_stage_name() {
+        let json = r#"{"sample_rate": 16000, "stages": [{"type": "gate", "threshold_db": -50}, {"type": "normalizer"}, {"type": "metrics"}]}"#;
+        let mut warm = AudioPipeline::from_json(json).unwrap();
+        let speech: Vec<f32> = (0..8000).map(|i| 0.05 * (i as f32 * 0.07).sin()).collect();
+        warm.process(&speech).unwrap();
+
+        let snapshot: PipelineSnapshot = serde_json::from_str(&serde_json::to_string(&warm.snapshot()).unwrap()).unwrap();
+        assert_eq!(snapshot.stages.len(), 2);
+        let mut restored = AudioPipeline::from_json(json).unwrap();
+        restored.restore(&snapshot).unwrap();
+        assert_eq!(
+            restored.process(&speech[..1600]).unwrap().samples,
+            warm.process(&speech[..1600]).unwrap().samples
+        );
+
+        let mut other = AudioPipeline::from_json(r#"{"sample_rate": 16000, "stages": [{"type": "normalizer", "name": "gate"}]}"#).unwrap();
+        assert!(other.restore(&snapshot).is_err());
+    }
+
+    #[test]
+    fn test_resample_stage_downmixes_and_streams() {
+        let mut pipeline = AudioPipeline::from_json(
+            r#"{"sample_rate": 48000, "channels": 2, "stages": [{"type": "resample", "rate": 16000}, {"type": "metrics"}]}"#,
+        )
+        .unwrap();
+        assert!(pipeline.process(&[0.0; 3]).is_err());
+        let mut frames = 0;
+        for _ in 0..100 {
+            let output = pipeline.process(&[0.25; 960]).unwrap();
+            assert_eq!((output.sample_rate, output.channels), (16000, 1));
+            frames += output.samples.len();
+        }
+        // 1s in, minus the resampler's startup window
+        assert!((15980..=16000).contains(&frames), "got {} frames", frames);
+        let json = serde_json::to_value(pipeline.process(&[0.25; 960]).unwrap()).unwrap();
+        assert_eq!(json["stages"]["resample"]["output_rate"], 16000);
+        assert!(json["stages"]["metrics"]["loudness"]["momentary_lufs"].is_number());
+    }
+}
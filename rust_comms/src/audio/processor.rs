@@ -1,15 +1,158 @@
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{Device, StreamConfig};
-use std::sync::{Arc, Mutex};
-use super::metrics::AudioMetrics;
-use super::filters::{NoiseGate, Normalizer};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex, TryLockError};
+use super::metrics::{AudioMetrics, ChannelLevels, FilterState, RollingLevels};
+use super::filters::{FilterConfig, NoiseGate, Normalizer};
+use super::replay::ReplayBuffer;
+use super::publisher::AudioPublisher;
+use super::network_source::{NetworkAudioSource, NetworkSourceConfig, NetworkSourceHandle};
+use super::output::{InputDucker, PlaybackState};
+use super::telemetry::{self, TelemetryTracker};
+use super::loudness::LoudnessMeter;
+use super::live_recording::RecordingTap;
+
+/// Seconds of processed audio kept for "what did I just say?" replays
+pub const DEFAULT_REPLAY_SECONDS: f32 = 30.0;
 
 pub struct AudioProcessor {
-    device: Device,
-    config: StreamConfig,
+    input: AudioInput,
+    pipeline: Pipeline,
+    device_name: Arc<Mutex<String>>,
+}
+
+/// Input device change requested while running, see AudioProcessor::run
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceRequest {
+    /// Next device in the host's input device list, wrapping around
+    Next,
+    Named(String),
+}
+
+/// Where audio comes from, everything downstream is shared
+enum AudioInput {
+    /// Local cpal capture device
+    Local { device: Device, config: StreamConfig },
+    /// Quest mic streamed over the network
+    Network(NetworkAudioSource),
+}
+
+/// Running input, stops when dropped
+#[allow(dead_code)] // held only for its Drop
+enum InputStream {
+    Local(cpal::Stream),
+    Network(NetworkSourceHandle),
+}
+
+/// Per-buffer processing shared by every input source
+/// filters -> ducking -> replay -> publisher -> recording -> metrics
+#[derive(Clone)]
+struct Pipeline {
+    sample_rate: u32,
+    channels: u16,
     metrics: Arc<Mutex<AudioMetrics>>,
+    filter_config: FilterConfig,
     noise_gate: NoiseGate,
     normalizer: Normalizer,
+    bypass: Arc<AtomicBool>,
+    ducker: Option<InputDucker>,
+    replay: Arc<Mutex<ReplayBuffer>>,
+    replay_dropped: u64, //buffers skipped while a snapshot held the replay lock
+    publisher: Option<AudioPublisher>,
+    recording: Option<RecordingTap>,
+    levels: RollingLevels,
+    loudness: LoudnessMeter,
+    telemetry: TelemetryTracker,
+}
+
+impl Pipeline {
+    fn new(metrics: Arc<Mutex<AudioMetrics>>, sample_rate: u32, channels: u16) -> Self {
+        let filter_config = FilterConfig::default();
+        let noise_gate = filter_config.noise_gate(sample_rate as f32);
+        println!(
+            "NoiseGate: {}dB threshold, {}ms attack, {}ms release",
+            filter_config.gate_threshold_db, filter_config.gate_attack_ms, filter_config.gate_release_ms
+        );
+        let normalizer = filter_config.normalizer(sample_rate as f32);
+        println!(
+            "Normalizer: {}dB target, {}ms RMS window",
+            filter_config.normalizer_target_db, filter_config.normalizer_window_ms
+        );
+        let replay = ReplayBuffer::new(DEFAULT_REPLAY_SECONDS, sample_rate, channels);
+        println!("Replay buffer: last {:.0}s of processed audio", DEFAULT_REPLAY_SECONDS);
+        let levels = RollingLevels::new(sample_rate, channels, noise_gate.threshold_db());
+        Self {
+            sample_rate,
+            channels,
+            metrics,
+            filter_config,
+            noise_gate,
+            normalizer,
+            bypass: Arc::new(AtomicBool::new(false)),
+            ducker: None,
+            replay: Arc::new(Mutex::new(replay)),
+            replay_dropped: 0,
+            publisher: None,
+            recording: None,
+            levels,
+            loudness: LoudnessMeter::new(sample_rate, channels),
+            telemetry: TelemetryTracker::new(sample_rate, channels),
+        }
+    }
+
+    fn process(&mut self, data: &[f32], capture_timestamp_us: u64) {
+        let started = self.telemetry.begin(data.len());
+        let mut samples = data.to_vec(); //create mutable copy for filter processing
+        let bypassed = self.bypass.load(Ordering::Relaxed);
+        if !bypassed {
+            self.noise_gate.process(&mut samples); //apply noise gate
+            self.normalizer.process(&mut samples); //apply normalization
+        }
+        if let Some(ref mut ducker) = self.ducker {
+            ducker.process(&mut samples); //quiet the mic while MERLIN talks, after normalizer so it isn't undone
+        }
+        // Never wait on the replay lock: a snapshot copying 30s out would stall the callback
+        match self.replay.try_lock() {
+            Ok(mut replay) => replay.push(&samples), //keep processed audio for replay
+            Err(TryLockError::WouldBlock) => self.replay_dropped += 1,
+            Err(TryLockError::Poisoned(_)) => {}
+        }
+        if let Some(ref publisher) = self.publisher {
+            publisher.publish(&samples, capture_timestamp_us);
+        }
+        if let Some(ref recording) = self.recording {
+            recording.push(&samples);
+        }
+        let sum_squares: f32 = data.iter().map(|&x| x * x).sum();
+        let rms = (sum_squares / data.len() as f32).sqrt();
+        let peak = data.iter().map(|&x| x.abs()).fold(0.0f32, f32::max);
+        // Convert RMS to dbs
+        // Formula: dB = 20 * log10(RMS)
+        // Adding 1e-10 prevents log10(0) = -infinity
+        let db = 20.0 * rms.max(1e-10).log10();
+        let levels = self.levels.update(data);
+        let channel_levels = ChannelLevels::measure(data, self.channels);
+        let loudness = self.loudness.process(data); //raw input, so devices can be compared
+
+        let filters = FilterState {
+            gate_open: self.noise_gate.is_open(),
+            gate_gain: self.noise_gate.envelope(),
+            normalizer_gain_db: 20.0 * self.normalizer.current_gain().max(1e-10).log10(),
+            bypassed,
+        };
+
+        let dropped = self.publisher.as_ref().map_or(0, |p| p.dropped_count()) + self.replay_dropped;
+        let stats = self.telemetry.finish(started, capture_timestamp_us, dropped);
+        if let Ok(mut metrics) = self.metrics.lock() {
+            metrics.update(rms, peak, db);
+            metrics.set_levels(levels);
+            metrics.set_telemetry(stats);
+            metrics.set_loudness(loudness);
+            metrics.set_filters(filters);
+            metrics.set_channels(channel_levels);
+        }
+    }
 }
 
 impl AudioProcessor {
@@ -20,71 +163,265 @@ impl AudioProcessor {
         let device = host.default_input_device().ok_or("No input device available")?;
         println!("Using input device: {}", device.name()?);
 
-        // Get default input config 
+        // Get default input config
         let config = device.default_input_config()?;
-        let sample_rate = config.sample_rate().0 as f32;
         println!("Audio config: {:?}", config);
-        let noise_gate = NoiseGate::new(
-            -40.0,
-            10.0,
-            100.0,
-            sample_rate,
-        );
-        println!("NoiseGate: -40dB threshold, 10ms attack, 100ms release");
-        let normalizer = Normalizer::new(
-            -20.0,
-            200.0,
-            sample_rate,
-        );
-        println!("Normalizer: -20dB target, 200ms RMS window");
+        let pipeline = Pipeline::new(metrics, config.sample_rate().0, config.channels());
         Ok(Self {
-            device,
-            config: config.into(),
-            metrics,
-            noise_gate,
-            normalizer,
+            device_name: Arc::new(Mutex::new(device.name().unwrap_or_else(|_| "unknown".to_string()))),
+            input: AudioInput::Local {
+                device,
+                config: config.into(),
+            },
+            pipeline,
         })
     }
 
+    /// Create an audio processor fed by network packets instead of a local device
+    /// Same filter chain, replay and metrics as a local mic
+    pub fn new_network(
+        metrics: Arc<Mutex<AudioMetrics>>,
+        config: NetworkSourceConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let source = NetworkAudioSource::bind(config)?;
+        let pipeline = Pipeline::new(metrics, source.sample_rate(), 1);
+        let name = match source.local_addr() {
+            Ok(addr) => format!("udp:{}", addr),
+            Err(_) => "udp".to_string(),
+        };
+        Ok(Self {
+            input: AudioInput::Network(source),
+            pipeline,
+            device_name: Arc::new(Mutex::new(name)),
+        })
+    }
+
+    /// Publish processed audio to local subscribers, set before start
+    pub fn set_publisher(&mut self, publisher: AudioPublisher) {
+        self.pipeline.publisher = Some(publisher);
+    }
+
+    /// Duck input by `duck_db` while output is playing, set before start
+    pub fn set_playback(&mut self, playback: Arc<PlaybackState>, duck_db: f32) {
+        let ducker = InputDucker::new(playback, duck_db, 10.0, self.pipeline.sample_rate as f32);
+        println!("Input ducking: {}dB while output is active", duck_db);
+        self.pipeline.ducker = Some(ducker);
+    }
+
+    /// Feed processed audio to a LiveRecording while it is armed, set before start
+    pub fn set_recording_tap(&mut self, tap: RecordingTap) {
+        self.pipeline.recording = Some(tap);
+    }
+
+    /// Capture device name, or the UDP address for network input
+    pub fn device_name(&self) -> String {
+        self.device_name.lock().unwrap().clone()
+    }
+
+    /// Shared device name, follows device switches made by run
+    pub fn device_name_handle(&self) -> Arc<Mutex<String>> {
+        Arc::clone(&self.device_name)
+    }
+
+    /// Set true to pass audio through without the gate and normalizer (ducking still applies)
+    pub fn filter_bypass(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.pipeline.bypass)
+    }
+
+    /// Names of the host's capture devices
+    pub fn input_device_names() -> Vec<String> {
+        cpal::default_host()
+            .input_devices()
+            .map(|devices| devices.filter_map(|d| d.name().ok()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn filter_config(&self) -> FilterConfig {
+        self.pipeline.filter_config
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        match &self.input {
+            AudioInput::Local { config, .. } => config.sample_rate.0,
+            AudioInput::Network(source) => source.sample_rate(),
+        }
+    }
+
+    pub fn channels(&self) -> u16 {
+        match &self.input {
+            AudioInput::Local { config, .. } => config.channels,
+            AudioInput::Network(_) => 1,
+        }
+    }
+
+    /// Shared handle to the always-on replay buffer
+    /// Grab before moving the processor onto its thread
+    pub fn replay_buffer(&self) -> Arc<Mutex<ReplayBuffer>> {
+        Arc::clone(&self.pipeline.replay)
+    }
+
     ///Start audio capture and processing
     /// **
     /// -Builds audio input with callback
     /// -callback exe on audio thread (low latency)
     /// -updates shared metrics on every audio buffer
     pub fn start(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        // Clone Arc for audio callback
-        let metrics_clone = Arc::clone(&self.metrics);
-        let mut noise_gate = self.noise_gate.clone();
-        let mut normalizer = self.normalizer.clone();
-        let stream = self.device.build_input_stream(
-            &self.config,
-            move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                let mut samples = data.to_vec(); //create mutable copy for filter processing
-                noise_gate.process(&mut samples); //apply noise gate
-                normalizer.process(&mut samples); //apply normalization
-                let sum_squares: f32 = data.iter().map(|&x| x * x).sum();
-                let rms = (sum_squares / data.len() as f32).sqrt();
-                let peak = data.iter().map(|&x| x.abs()).fold(0.0f32, f32::max);
-                // Convert RMS to dbs
-                // Formula: dB = 20 * log10(RMS)
-                // Adding 1e-10 prevents log10(0) = -infinity
-                let db = 20.0 * rms.max(1e-10).log10();
-
-                if let Ok(mut metrics) = metrics_clone.lock() {
-                    metrics.update(rms, peak, db);
+        let live = Arc::new(Mutex::new(self.pipeline.clone()));
+        //Keep the stream (or network threads) running for the rest of the process
+        std::mem::forget(self.open_stream(&live)?);
+        println!("Audio processing started.");
+        Ok(())
+    }
+
+    /// Start, then switch input devices on request until the sender is dropped
+    /// The new device is opened with the current sample rate / channels so everything downstream
+    /// stays valid; a device that can't do that is reported and the old one kept
+    /// Filter envelopes, levels and loudness carry over, every stream feeds the same live pipeline
+    /// cpal streams can't leave the thread that built them, so this owns the calling thread
+    pub fn run(mut self, requests: Receiver<DeviceRequest>) -> Result<(), Box<dyn std::error::Error>> {
+        let live = Arc::new(Mutex::new(self.pipeline.clone()));
+        let mut stream = Some(self.open_stream(&live)?);
+        println!("Audio processing started.");
+        for request in requests {
+            let AudioInput::Local { device, .. } = &self.input else {
+                println!("Device switching needs a local input (network input in use)");
+                continue;
+            };
+            let current = device.name().unwrap_or_default();
+            let names = Self::input_device_names();
+            let wanted = match request {
+                DeviceRequest::Next => {
+                    let position = names.iter().position(|name| *name == current);
+                    match position {
+                        Some(i) => names[(i + 1) % names.len()].clone(),
+                        None => match names.first() {
+                            Some(name) => name.clone(),
+                            None => continue,
+                        },
+                    }
+                }
+                DeviceRequest::Named(name) => name,
+            };
+            // Asking for the current device again retries it after a failed reopen
+            if wanted == current && stream.is_some() {
+                continue;
+            }
+            let Some(next) = cpal::default_host()
+                .input_devices()
+                .ok()
+                .and_then(|mut devices| devices.find(|d| d.name().is_ok_and(|name| name == wanted)))
+            else {
+                println!("No input device named {:?}", wanted);
+                continue;
+            };
+
+            // Build on the new device while the old one still runs, so a device that won't open costs nothing
+            // Backends that give a device to one stream at a time get the old stream released and a second try
+            let previous = self.replace_device(next);
+            let built = self.build_stream(&live).or_else(|_| {
+                drop(stream.take());
+                self.build_stream(&live)
+            });
+            let opened = built.and_then(|built| {
+                drop(stream.take());
+                built.play()?;
+                Ok(built)
+            });
+            match opened {
+                Ok(opened) => {
+                    stream = Some(InputStream::Local(opened));
+                    println!("Switched input device to {}", wanted);
+                    *self.device_name.lock().unwrap() = wanted;
                 }
+                Err(e) => {
+                    println!("Can't open {} ({}), staying on {}", wanted, e, current);
+                    self.replace_device(previous);
+                    if stream.is_none() {
+                        match self.open_stream(&live) {
+                            Ok(reopened) => stream = Some(reopened),
+                            Err(e) => eprintln!("Can't reopen {} ({}), no input until another device is picked", current, e),
+                        }
+                    }
+                }
+            }
+        }
+        drop(stream);
+        Ok(())
+    }
+
+    /// Swap the local capture device, keeping the stream config
+    fn replace_device(&mut self, device: Device) -> Device {
+        match &mut self.input {
+            AudioInput::Local { device: current, .. } => std::mem::replace(current, device),
+            AudioInput::Network(_) => device,
+        }
+    }
+
+    /// Build and play the input stream, or start the network source's threads
+    fn open_stream(&self, live: &Arc<Mutex<Pipeline>>) -> Result<InputStream, Box<dyn std::error::Error>> {
+        match &self.input {
+            AudioInput::Local { .. } => {
+                let stream = self.build_stream(live)?;
+                stream.play()?;
+                Ok(InputStream::Local(stream))
+            }
+            AudioInput::Network(source) => {
+                let pipeline = Arc::clone(live);
+                let handle = source.start(move |data, capture_us| pipeline.lock().unwrap().process(data, capture_us))?;
+                Ok(InputStream::Network(handle))
+            }
+        }
+    }
+
+    /// Input stream on the local device feeding `live`, not played yet
+    fn build_stream(&self, live: &Arc<Mutex<Pipeline>>) -> Result<cpal::Stream, Box<dyn std::error::Error>> {
+        let AudioInput::Local { device, config } = &self.input else {
+            return Err("No local input device (network input in use)".into());
+        };
+        let errors = live.lock().unwrap().telemetry.stream_error_counter();
+        let pipeline = Arc::clone(live);
+        let stream = device.build_input_stream(
+            config,
+            move |data: &[f32], info: &cpal::InputCallbackInfo| {
+                pipeline.lock().unwrap().process(data, capture_timestamp_us(info));
             },
-            |err| {
+            move |err| {
+                errors.record(&err);
                 eprintln!("Audio stream error: {}", err);
             },
             None,
         )?;
-        stream.play()?;
-        println!("Audio processing started.");
+        Ok(stream)
+    }
+}
 
-        //Keep stream alive by moving into infinite loop
-        // Stream will drop once processor is dropped
-        std::mem::forget(stream);
-        Ok(())
+/// Wall clock time the buffer was captured, in μs since UNIX epoch
+/// cpal timestamps are stream-relative, so back the callback delay off "now"
+fn capture_timestamp_us(info: &cpal::InputCallbackInfo) -> u64 {
+    let now = telemetry::now_us();
+    let ts = info.timestamp();
+    let delay = ts.callback.duration_since(&ts.capture).unwrap_or_default();
+    now.saturating_sub(delay.as_micros() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_busy_replay_buffer_is_skipped_and_counted() {
+        let metrics = Arc::new(Mutex::new(AudioMetrics::new()));
+        let mut pipeline = Pipeline::new(Arc::clone(&metrics), 16000, 1);
+        let replay = Arc::clone(&pipeline.replay);
+        let buffer = vec![0.1; 160];
+        pipeline.process(&buffer, telemetry::now_us());
+        {
+            let _snapshot = replay.lock().unwrap();
+            pipeline.process(&buffer, telemetry::now_us());
+        }
+        pipeline.process(&buffer, telemetry::now_us());
+
+        assert_eq!(replay.lock().unwrap().snapshot(None).samples.len(), 320);
+        assert_eq!(metrics.lock().unwrap().telemetry.dropped_buffers, 1);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,319 @@
+use std::io::{self, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use super::resample::Resampler;
+
+/// Local audio publisher
+///
+/// One process owns the mic, everyone else subscribes over a Unix socket
+/// -Processed audio resampled to 16 kHz mono
+/// -Each frame carries a sequence number and capture timestamp
+/// -Gaps in sequence numbers mean buffers were dropped (slow consumer or full queue)
+/// -A buffer too short to produce output still goes out as an empty frame, so it isn't mistaken for a gap
+/// -The audio callback only copies into a recycled buffer and queues it, socket writes happen on the send thread
+/// -Socket file removed when the last handle is dropped
+///
+/// Wire format, all little-endian:
+/// ```text
+/// magic        [u8; 4]  "MRLN"
+/// sequence     u64
+/// capture_us   u64      wall clock, microseconds since UNIX epoch
+/// sample_rate  u32
+/// num_samples  u32
+/// samples      [f32; num_samples]
+/// ```
+pub const DEFAULT_SOCKET_PATH: &str = "/tmp/merlin_audio.sock";
+pub const PUBLISH_SAMPLE_RATE: u32 = 16000;
+pub const FRAME_MAGIC: [u8; 4] = *b"MRLN";
+pub const FRAME_HEADER_LEN: usize = 28;
+
+/// Buffers queued between the audio callback and the socket thread, all allocated up front
+const QUEUE_DEPTH: usize = 64;
+/// Starting capacity of each queued buffer, grown once if the device delivers more
+const BUFFER_SECONDS: f32 = 0.1;
+/// A subscriber that can't take a frame in this long is dropped
+const CLIENT_WRITE_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// One framed chunk of published audio
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioFrame {
+    pub sequence: u64,
+    pub capture_timestamp_us: u64,
+    pub sample_rate: u32,
+    pub samples: Vec<f32>,
+}
+
+impl AudioFrame {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(FRAME_HEADER_LEN + self.samples.len() * 4);
+        bytes.extend_from_slice(&FRAME_MAGIC);
+        bytes.extend_from_slice(&self.sequence.to_le_bytes());
+        bytes.extend_from_slice(&self.capture_timestamp_us.to_le_bytes());
+        bytes.extend_from_slice(&self.sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&(self.samples.len() as u32).to_le_bytes());
+        for &sample in &self.samples {
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Read exactly one frame from a byte stream
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut header = [0u8; FRAME_HEADER_LEN];
+        reader.read_exact(&mut header)?;
+        if header[0..4] != FRAME_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "bad frame magic"));
+        }
+        let sequence = u64::from_le_bytes(header[4..12].try_into().unwrap());
+        let capture_timestamp_us = u64::from_le_bytes(header[12..20].try_into().unwrap());
+        let sample_rate = u32::from_le_bytes(header[20..24].try_into().unwrap());
+        let num_samples = u32::from_le_bytes(header[24..28].try_into().unwrap()) as usize;
+
+        let mut payload = vec![0u8; num_samples * 4];
+        reader.read_exact(&mut payload)?;
+        let samples = payload
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+
+        Ok(Self {
+            sequence,
+            capture_timestamp_us,
+            sample_rate,
+            samples,
+        })
+    }
+}
+
+/// Raw processed buffer handed off from the audio callback
+struct CapturedBuffer {
+    sequence: u64,
+    capture_timestamp_us: u64,
+    samples: Vec<f32>,
+}
+
+/// Handle to a running publisher, cheap to clone into the audio callback
+#[derive(Clone)]
+pub struct AudioPublisher {
+    tx: SyncSender<CapturedBuffer>,
+    free: Arc<Mutex<Receiver<Vec<f32>>>>, //empty buffers handed back by the send thread
+    next_sequence: Arc<AtomicU64>,
+    dropped: Arc<AtomicU64>,
+    clients: Arc<AtomicUsize>,
+    socket: Arc<SocketFile>,
+}
+
+/// Unlinks the socket path once every publisher handle is gone
+struct SocketFile(PathBuf);
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.0)
+            && e.kind() != io::ErrorKind::NotFound
+        {
+            eprintln!("Error removing audio socket {:?}: {}", self.0, e);
+        }
+    }
+}
+
+impl AudioPublisher {
+    /// Bind the socket and start the accept + send threads
+    /// Args:
+    /// - socket_path: Unix socket path, a stale file there is replaced
+    /// - input_rate: sample rate of the buffers passed to publish (Hz)
+    /// - channels: interleaved channel count of those buffers
+    pub fn bind(socket_path: impl AsRef<Path>, input_rate: u32, channels: u16) -> io::Result<Self> {
+        let socket_path = socket_path.as_ref().to_path_buf();
+        if socket_path.exists() {
+            std::fs::remove_file(&socket_path)?; // left over from a previous run
+        }
+        let listener = UnixListener::bind(&socket_path)?;
+        let clients = Arc::new(AtomicUsize::new(0));
+
+        // New subscribers go to the send thread, which owns every client stream
+        let (client_tx, client_rx) = mpsc::channel();
+        let accept_clients = Arc::clone(&clients);
+        thread::spawn(move || Self::accept_loop(listener, client_tx, accept_clients));
+
+        let (tx, rx) = mpsc::sync_channel(QUEUE_DEPTH);
+        let (free_tx, free_rx) = mpsc::sync_channel(QUEUE_DEPTH);
+        let capacity = (input_rate as f32 * BUFFER_SECONDS) as usize * channels as usize;
+        for _ in 0..QUEUE_DEPTH {
+            free_tx.send(Vec::with_capacity(capacity)).ok();
+        }
+        let send_clients = Arc::clone(&clients);
+        let resampler = Resampler::new(input_rate, PUBLISH_SAMPLE_RATE, channels);
+        thread::spawn(move || Self::send_loop(rx, free_tx, resampler, client_rx, send_clients));
+
+        println!("Audio publisher listening on {:?} ({} Hz mono)", socket_path, PUBLISH_SAMPLE_RATE);
+        Ok(Self {
+            tx,
+            free: Arc::new(Mutex::new(free_rx)),
+            next_sequence: Arc::new(AtomicU64::new(0)),
+            dropped: Arc::new(AtomicU64::new(0)),
+            clients,
+            socket: Arc::new(SocketFile(socket_path)),
+        })
+    }
+
+    /// Queue a processed buffer, never blocks or allocates (past a buffer's first growth)
+    /// Buffers are dropped when every queued buffer is in use, leaving a sequence gap
+    pub fn publish(&self, samples: &[f32], capture_timestamp_us: u64) {
+        let sequence = self.next_sequence.fetch_add(1, Ordering::Relaxed);
+        // Only contended while two streams overlap during a device switch
+        let recycled = self.free.try_lock().ok().and_then(|free| free.try_recv().ok());
+        let Some(mut buffer) = recycled else {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        };
+        buffer.clear();
+        buffer.extend_from_slice(samples);
+        let buffer = CapturedBuffer {
+            sequence,
+            capture_timestamp_us,
+            samples: buffer,
+        };
+        // Stopped send thread: drop rather than stall the audio thread
+        if self.tx.try_send(buffer).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Buffers dropped because the send queue was full
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.clients.load(Ordering::Relaxed)
+    }
+
+    pub fn socket_path(&self) -> &Path {
+        &self.socket.0
+    }
+
+    fn accept_loop(listener: UnixListener, new_clients: Sender<UnixStream>, clients: Arc<AtomicUsize>) {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(e) = stream.set_write_timeout(Some(CLIENT_WRITE_TIMEOUT)) {
+                        eprintln!("Audio subscriber setup failed: {}", e);
+                        continue;
+                    }
+                    println!("Audio subscriber connected");
+                    clients.fetch_add(1, Ordering::Relaxed);
+                    if new_clients.send(stream).is_err() {
+                        return; // send thread gone
+                    }
+                }
+                Err(e) => eprintln!("Audio subscriber accept error: {}", e),
+            }
+        }
+    }
+
+    fn send_loop(
+        rx: Receiver<CapturedBuffer>,
+        free: SyncSender<Vec<f32>>,
+        mut resampler: Resampler,
+        new_clients: Receiver<UnixStream>,
+        client_count: Arc<AtomicUsize>,
+    ) {
+        let mut clients: Vec<UnixStream> = Vec::new();
+        let mut frame = AudioFrame {
+            sequence: 0,
+            capture_timestamp_us: 0,
+            sample_rate: PUBLISH_SAMPLE_RATE,
+            samples: Vec::new(),
+        };
+        while let Ok(buffer) = rx.recv() {
+            frame.samples.clear();
+            resampler.process_into(&buffer.samples, &mut frame.samples);
+            free.try_send(buffer.samples).ok(); // back to the pool for the audio callback
+            frame.sequence = buffer.sequence;
+            frame.capture_timestamp_us = buffer.capture_timestamp_us;
+            let bytes = frame.encode();
+
+            clients.extend(new_clients.try_iter());
+            // Drop anyone who disconnected or can't keep up
+            clients.retain_mut(|client| match client.write_all(&bytes) {
+                Ok(()) => true,
+                Err(e) => {
+                    println!("Audio subscriber dropped: {}", e);
+                    client_count.fetch_sub(1, Ordering::Relaxed);
+                    false
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_round_trip() {
+        let frame = AudioFrame {
+            sequence: 42,
+            capture_timestamp_us: 1_700_000_000_000_000,
+            sample_rate: PUBLISH_SAMPLE_RATE,
+            samples: vec![0.0, 0.5, -0.25, 1.0],
+        };
+        let bytes = frame.encode();
+        assert_eq!(bytes.len(), FRAME_HEADER_LEN + 16);
+        let decoded = AudioFrame::read_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(decoded, frame);
+    }
+
+    #[test]
+    fn test_publisher_streams_16k_frames() {
+        let path = std::env::temp_dir().join(format!("merlin_pub_{}.sock", uuid::Uuid::new_v4()));
+        let publisher = AudioPublisher::bind(&path, 48000, 2).unwrap();
+        let mut subscriber = UnixStream::connect(&path).unwrap();
+        subscriber.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+
+        // Wait for the accept thread to register us
+        let deadline = std::time::Instant::now() + Duration::from_secs(2);
+        while publisher.client_count() == 0 {
+            assert!(std::time::Instant::now() < deadline, "subscriber never registered");
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        // 10ms of 48 kHz stereo per buffer
+        for i in 0..5 {
+            publisher.publish(&vec![0.1; 960], 1000 + i);
+        }
+
+        let mut total = 0;
+        for i in 0..5 {
+            let frame = AudioFrame::read_from(&mut subscriber).unwrap();
+            assert_eq!(frame.sequence, i);
+            assert_eq!(frame.capture_timestamp_us, 1000 + i);
+            assert_eq!(frame.sample_rate, PUBLISH_SAMPLE_RATE);
+            total += frame.samples.len();
+        }
+        // 50ms at 16 kHz is 800 samples, minus the interpolation window still buffered
+        assert!((780..=800).contains(&total), "got {} samples", total);
+
+        // Too short to resample into anything: still sent, so the sequence has no gap
+        publisher.publish(&[], 2000);
+        let frame = AudioFrame::read_from(&mut subscriber).unwrap();
+        assert_eq!((frame.sequence, frame.samples.len()), (5, 0));
+
+        // Queued buffers are recycled, so a consumer keeping up never sees drops past the pool size
+        for i in 6..6 + 3 * QUEUE_DEPTH as u64 {
+            publisher.publish(&vec![0.1; 960], i);
+            assert_eq!(AudioFrame::read_from(&mut subscriber).unwrap().sequence, i);
+        }
+        assert_eq!(publisher.dropped_count(), 0);
+
+        drop(publisher);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,187 @@
+use super::encrypted_writer::RecordingKey;
+use super::flac_writer::FlacFileWriter;
+use super::sidecar::RecordingContext;
+use super::traits::{AudioWriter, RecordingInfo};
+use super::file_writer::RotationPolicy;
+use super::wav_writer::{WavFileWriter, WavFormat};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Container + sample encoding for new recordings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingFormat {
+    Wav(WavFormat),
+    /// Lossless compressed, 16 or 24-bit
+    Flac { bits_per_sample: u16 },
+}
+
+impl Default for RecordingFormat {
+    fn default() -> Self {
+        RecordingFormat::Wav(WavFormat::default())
+    }
+}
+
+/// Parses "flac" / "flac16", "flac24" or any WavFormat name (ex: from MERLIN_RECORD_FORMAT)
+impl FromStr for RecordingFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "flac" | "flac16" => Ok(RecordingFormat::Flac { bits_per_sample: 16 }),
+            "flac24" => Ok(RecordingFormat::Flac { bits_per_sample: 24 }),
+            other => other
+                .parse()
+                .map(RecordingFormat::Wav)
+                .map_err(|_| format!("Unknown recording format: {}", other)),
+        }
+    }
+}
+
+/// Same names FromStr accepts
+impl fmt::Display for RecordingFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RecordingFormat::Wav(WavFormat::Pcm16 { dither: false }) => "pcm16",
+            RecordingFormat::Wav(WavFormat::Pcm16 { dither: true }) => "pcm16-dither",
+            RecordingFormat::Wav(WavFormat::Pcm24) => "pcm24",
+            RecordingFormat::Wav(WavFormat::Float32) => "float32",
+            RecordingFormat::Flac { bits_per_sample: 24 } => "flac24",
+            RecordingFormat::Flac { .. } => "flac",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Audio file extensions the recorders produce
+pub const RECORDING_EXTENSIONS: [&str; 3] = ["wav", "flac", "menc"];
+
+/// True for files a recorder could have written (by extension)
+pub fn is_recording_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| RECORDING_EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known)))
+}
+
+/// Recorder whose format is picked at runtime
+/// -Same AudioWriter interface, naming, rotation, encryption and sidecars whichever writer is inside
+pub enum Recorder {
+    Wav(WavFileWriter),
+    Flac(FlacFileWriter),
+}
+
+impl Recorder {
+    pub fn new(output_dir: impl Into<PathBuf>, format: RecordingFormat) -> Self {
+        match format {
+            RecordingFormat::Wav(format) => Recorder::Wav(WavFileWriter::new(output_dir).with_format(format)),
+            RecordingFormat::Flac { bits_per_sample } => {
+                Recorder::Flac(FlacFileWriter::new(output_dir).with_bits_per_sample(bits_per_sample))
+            }
+        }
+    }
+
+
+    pub fn with_rotation(self, rotation: RotationPolicy) -> Self {
+        match self {
+            Recorder::Wav(writer) => Recorder::Wav(writer.with_rotation(rotation)),
+            Recorder::Flac(writer) => Recorder::Flac(writer.with_rotation(rotation)),
+        }
+    }
+
+    /// Encrypted at rest, the key comes from a passphrase or key file
+    pub fn with_encryption(self, key: RecordingKey) -> Self {
+        match self {
+            Recorder::Wav(writer) => Recorder::Wav(writer.with_encryption(key)),
+            Recorder::Flac(writer) => Recorder::Flac(writer.with_encryption(key)),
+        }
+    }
+
+    pub fn format(&self) -> RecordingFormat {
+        match self {
+            Recorder::Wav(writer) => RecordingFormat::Wav(writer.format()),
+            Recorder::Flac(writer) => RecordingFormat::Flac { bits_per_sample: writer.bits_per_sample() },
+        }
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        match self {
+            Recorder::Wav(writer) => writer.is_encrypted(),
+            Recorder::Flac(writer) => writer.is_encrypted(),
+        }
+    }
+
+    pub fn set_context(&mut self, context: RecordingContext) {
+        match self {
+            Recorder::Wav(writer) => writer.set_context(context),
+            Recorder::Flac(writer) => writer.set_context(context),
+        }
+    }
+
+    /// Length of the file being written, 0 when idle
+    pub fn recorded_seconds(&self) -> f64 {
+        match self {
+            Recorder::Wav(writer) => writer.recorded_seconds(),
+            Recorder::Flac(writer) => writer.recorded_seconds(),
+        }
+    }
+
+    pub fn current_file(&self) -> Option<&PathBuf> {
+        match self {
+            Recorder::Wav(writer) => writer.current_file(),
+            Recorder::Flac(writer) => writer.current_file(),
+        }
+    }
+}
+
+impl AudioWriter for Recorder {
+    type Error = Box<dyn std::error::Error>;
+
+    fn start_writing(&mut self, sample_rate: u32, channels: u16) -> Result<(), Self::Error> {
+        match self {
+            Recorder::Wav(writer) => writer.start_writing(sample_rate, channels),
+            Recorder::Flac(writer) => writer.start_writing(sample_rate, channels),
+        }
+    }
+
+    fn write_samples(&mut self, samples: &[f32]) -> Result<(), Self::Error> {
+        match self {
+            Recorder::Wav(writer) => writer.write_samples(samples),
+            Recorder::Flac(writer) => writer.write_samples(samples),
+        }
+    }
+
+    fn finish_writing(&mut self) -> Result<Option<RecordingInfo>, Self::Error> {
+        match self {
+            Recorder::Wav(writer) => writer.finish_writing(),
+            Recorder::Flac(writer) => writer.finish_writing(),
+        }
+    }
+
+    fn is_writing(&self) -> bool {
+        match self {
+            Recorder::Wav(writer) => writer.is_writing(),
+            Recorder::Flac(writer) => writer.is_writing(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_recording_format() {
+        assert_eq!("FLAC".parse(), Ok(RecordingFormat::Flac { bits_per_sample: 16 }));
+        assert_eq!("flac24".parse(), Ok(RecordingFormat::Flac { bits_per_sample: 24 }));
+        assert_eq!("pcm16-dither".parse(), Ok(RecordingFormat::Wav(WavFormat::Pcm16 { dither: true })));
+        assert!("ogg".parse::<RecordingFormat>().is_err());
+        for name in ["pcm16", "pcm16-dither", "pcm24", "float32", "flac", "flac24"] {
+            assert_eq!(name.parse::<RecordingFormat>().unwrap().to_string(), name);
+        }
+
+        let recorder = Recorder::new("/tmp/unused", "flac24".parse().unwrap());
+        assert_eq!(recorder.format(), RecordingFormat::Flac { bits_per_sample: 24 });
+        assert!(!recorder.is_encrypted());
+        assert!(is_recording_file(Path::new("audio_1.FLAC")));
+        assert!(!is_recording_file(Path::new("audio_1.json")));
+    }
+}
@@ -0,0 +1,385 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// WAV Repair
+///
+/// Fixes recordings left behind by a crash or power loss
+/// -Walks the RIFF chunks to find fmt and data
+/// -Only touches a data chunk whose size is unset (0 / 0xFFFFFFFF), runs past the end of the file,
+///  or is followed by bytes that aren't chunks (audio written after the last header commit)
+/// -The data chunk ends at the next valid chunk header (ex: a trailing LIST), or at EOF minus any partial frame
+/// -Patches the RIFF and data sizes in place, audio bytes are never rewritten
+#[derive(Debug, Clone, PartialEq)]
+pub enum RepairOutcome {
+    /// Header already matched the file
+    Intact,
+    /// Header patched, `recovered_bytes` of audio beyond the old header are now readable
+    Repaired { data_bytes: u64, recovered_bytes: u64, trimmed_bytes: u64 },
+    /// Not enough of the header made it to disk, left untouched
+    Unrecoverable(String),
+    /// Possibly still being written, left untouched
+    Skipped(String),
+}
+
+/// Where the pieces of a WAV header live
+struct WavLayout {
+    block_align: u64,
+    data_size_offset: u64, // offset of the data chunk's size field
+    data_offset: u64,
+    declared_data_bytes: u64,
+    declared_riff_bytes: u64,
+}
+
+/// RIFF and data chunk sizes are u32
+const MAX_DATA_BYTES: u64 = u32::MAX as u64;
+/// Data size written by streaming writers that never came back to patch it
+const UNSET_DATA_BYTES: u64 = u32::MAX as u64;
+
+/// Files modified more recently than this may still be open in a writer (ours commit every second)
+pub const REPAIR_MIN_AGE: Duration = Duration::from_secs(10);
+
+/// Repair a single WAV file in place
+pub fn repair_wav(path: impl AsRef<Path>) -> io::Result<RepairOutcome> {
+    let path = path.as_ref();
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let file_len = file.metadata()?.len();
+
+    let layout = match read_layout(&mut file, file_len) {
+        Ok(layout) => layout,
+        Err(reason) => return Ok(RepairOutcome::Unrecoverable(reason)),
+    };
+
+    let data_end = layout.data_offset + layout.declared_data_bytes;
+    let declared_ok = layout.declared_data_bytes != 0
+        && layout.declared_data_bytes != UNSET_DATA_BYTES
+        && data_end <= file_len;
+    let after_data = data_end + (layout.declared_data_bytes & 1);
+    if declared_ok && (after_data >= file_len || chunks_reach_eof(&mut file, after_data, file_len)?) {
+        return Ok(RepairOutcome::Intact);
+    }
+
+    // Audio runs up to the next chunk, or to the end of the file
+    let next_chunk = find_next_chunk(&mut file, layout.data_offset, file_len)?;
+    let (data_bytes, riff_bytes) = match next_chunk {
+        // The chunk stays where it is, the audio before it can't be trimmed
+        Some(chunk) => ((chunk - layout.data_offset).min(MAX_DATA_BYTES), file_len - 8),
+        None => {
+            let on_disk = (file_len - layout.data_offset).min(MAX_DATA_BYTES);
+            let data_bytes = on_disk - on_disk % layout.block_align;
+            (data_bytes, layout.data_offset + data_bytes - 8)
+        }
+    };
+    if layout.declared_data_bytes == data_bytes && layout.declared_riff_bytes == riff_bytes {
+        return Ok(RepairOutcome::Intact);
+    }
+
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&(riff_bytes.min(MAX_DATA_BYTES) as u32).to_le_bytes())?;
+    file.seek(SeekFrom::Start(layout.data_size_offset))?;
+    file.write_all(&(data_bytes as u32).to_le_bytes())?;
+    let mut trimmed_bytes = 0;
+    if next_chunk.is_none() {
+        trimmed_bytes = file_len - (layout.data_offset + data_bytes);
+        if trimmed_bytes > 0 {
+            file.set_len(layout.data_offset + data_bytes)?;
+        }
+    }
+    file.sync_all()?;
+
+    let recovered_bytes = match layout.declared_data_bytes {
+        UNSET_DATA_BYTES => data_bytes,
+        declared => data_bytes.saturating_sub(declared),
+    };
+    Ok(RepairOutcome::Repaired { data_bytes, recovered_bytes, trimmed_bytes })
+}
+
+/// Repair every .wav in `dir` (not recursive), sorted by name
+/// -`skip` is left alone, pass the file currently being recorded
+/// -Files modified within `min_age` are reported as Skipped, another writer may still have them open
+/// -A file that can't be read or patched is logged and the rest are still repaired
+pub fn repair_directory(dir: impl AsRef<Path>, skip: Option<&Path>, min_age: Option<Duration>) -> io::Result<Vec<(PathBuf, RepairOutcome)>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("wav")))
+        .filter(|path| Some(path.as_path()) != skip)
+        .collect();
+    paths.sort();
+
+    let mut results = Vec::with_capacity(paths.len());
+    for path in paths {
+        if let Some(min_age) = min_age
+            && let Some(age) = modified_age(&path)
+            && age < min_age
+        {
+            results.push((path, RepairOutcome::Skipped(format!("modified {:.0}s ago", age.as_secs_f64()))));
+            continue;
+        }
+        match repair_wav(&path) {
+            Ok(outcome) => results.push((path, outcome)),
+            Err(e) => eprintln!("Error repairing {:?}: {}", path, e),
+        }
+    }
+    Ok(results)
+}
+
+/// Time since `path` was last written, None if the filesystem can't tell
+fn modified_age(path: &Path) -> Option<Duration> {
+    let modified = fs::metadata(path).and_then(|meta| meta.modified()).ok()?;
+    Some(SystemTime::now().duration_since(modified).unwrap_or_default())
+}
+
+/// Chunk IDs are four printable ASCII characters (ex: "LIST", "id3 ", "cue ")
+fn is_chunk_id(id: &[u8]) -> bool {
+    id.iter().all(|&b| b.is_ascii_alphanumeric() || b == b' ' || b == b'_')
+        && id.iter().any(|b| b.is_ascii_alphabetic())
+}
+
+/// True if well-formed chunks run from `position` exactly to the end of the file
+fn chunks_reach_eof(file: &mut File, mut position: u64, file_len: u64) -> io::Result<bool> {
+    while position + 8 <= file_len {
+        let mut header = [0u8; 8];
+        file.seek(SeekFrom::Start(position))?;
+        file.read_exact(&mut header)?;
+        if !is_chunk_id(&header[0..4]) {
+            return Ok(false);
+        }
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as u64;
+        position += 8 + size + (size & 1);
+        // The last chunk's pad byte is sometimes left off
+        if position == file_len || (size & 1 == 1 && position == file_len + 1) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// First word-aligned offset after `data_offset` where chunks run to the end of the file
+fn find_next_chunk(file: &mut File, data_offset: u64, file_len: u64) -> io::Result<Option<u64>> {
+    const BLOCK: usize = 64 * 1024;
+    let mut buffer = vec![0u8; BLOCK + 4];
+    let mut start = data_offset;
+    while start + 8 <= file_len {
+        let len = (file_len - start).min(buffer.len() as u64) as usize;
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut buffer[..len])?;
+        // Chunks start on even offsets, the data chunk body does too
+        for i in (0..len.saturating_sub(3).min(BLOCK)).step_by(2) {
+            let position = start + i as u64;
+            if is_chunk_id(&buffer[i..i + 4]) && chunks_reach_eof(file, position, file_len)? {
+                return Ok(Some(position));
+            }
+        }
+        start += BLOCK as u64;
+    }
+    Ok(None)
+}
+
+fn read_layout(file: &mut File, file_len: u64) -> Result<WavLayout, String> {
+    let mut riff = [0u8; 12];
+    file.read_exact(&mut riff).map_err(|_| "missing RIFF header".to_string())?;
+    if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+        return Err("not a RIFF/WAVE file".to_string());
+    }
+    let declared_riff_bytes = u32::from_le_bytes([riff[4], riff[5], riff[6], riff[7]]) as u64;
+
+    let mut block_align = None;
+    let mut position = 12u64;
+    loop {
+        let mut header = [0u8; 8];
+        file.seek(SeekFrom::Start(position)).map_err(|e| e.to_string())?;
+        file.read_exact(&mut header).map_err(|_| "no data chunk".to_string())?;
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as u64;
+        let body = position + 8;
+
+        match &header[0..4] {
+            b"fmt " => {
+                let mut fmt = [0u8; 16];
+                file.read_exact(&mut fmt).map_err(|_| "truncated fmt chunk".to_string())?;
+                let align = u16::from_le_bytes([fmt[12], fmt[13]]) as u64;
+                if align == 0 {
+                    return Err("fmt chunk has zero block align".to_string());
+                }
+                block_align = Some(align);
+            }
+            b"data" => {
+                let block_align = block_align.ok_or("data chunk before fmt chunk")?;
+                return Ok(WavLayout {
+                    block_align,
+                    data_size_offset: position + 4,
+                    data_offset: body,
+                    declared_data_bytes: size,
+                    declared_riff_bytes,
+                });
+            }
+            _ => {}
+        }
+
+        // Chunks are padded to even length
+        position = body + size + (size & 1);
+        if position >= file_len {
+            return Err("no data chunk".to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::traits::AudioWriter;
+    use crate::audio::wav_writer::{WavFileWriter, WavFormat};
+    use std::os::unix::fs::FileExt;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("merlin_repair_{}", uuid::Uuid::new_v4()))
+    }
+
+    fn ramp(len: usize) -> Vec<f32> {
+        (0..len).map(|i| (i % 1000) as f32 / 1000.0 - 0.5).collect()
+    }
+
+    /// Write like the recorder does, then "kill" the process: no finalize, no Drop
+    fn killed_recording(dir: &Path, format: WavFormat, samples: &[f32]) -> PathBuf {
+        let mut writer = WavFileWriter::new(dir).with_format(format).with_commit_interval(0.25);
+        writer.start_writing(16000, 2).unwrap();
+        for chunk in samples.chunks(320) {
+            writer.write_samples(chunk).unwrap();
+        }
+        let path = writer.current_file().unwrap().clone();
+        std::mem::forget(writer);
+        path
+    }
+
+    #[test]
+    fn test_killed_recording_readable_up_to_last_commit() {
+        let dir = temp_dir();
+        // 1.1s stereo, commits every 0.25s: the last 0.1s is only partly on disk
+        let samples = ramp(16000 * 2 * 11 / 10);
+        let path = killed_recording(&dir, WavFormat::Float32, &samples);
+
+        // Without any repair the committed header already describes most of the audio
+        let reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.len() as usize, 16000 * 2);
+
+        // Repair picks up whatever reached the disk after the last commit
+        let outcome = repair_wav(&path).unwrap();
+        let mut reader = hound::WavReader::open(&path).unwrap();
+        let recovered: Vec<f32> = reader.samples::<f32>().map(|s| s.unwrap()).collect();
+        assert_eq!(recovered.len() % 2, 0);
+        assert_eq!(&recovered[..], &samples[..recovered.len()]);
+        match outcome {
+            RepairOutcome::Repaired { data_bytes, recovered_bytes, .. } => {
+                assert_eq!(data_bytes, recovered.len() as u64 * 4);
+                assert!(recovered_bytes > 0);
+            }
+            other => panic!("expected repair, got {:?}", other),
+        }
+        // Second pass has nothing to do
+        assert_eq!(repair_wav(&path).unwrap(), RepairOutcome::Intact);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_repair_uncommitted_header_and_partial_frame() {
+        let dir = temp_dir();
+        let samples = ramp(20000);
+        // Commit interval longer than the recording: header still says 0 bytes
+        let mut writer = WavFileWriter::new(&dir).with_format(WavFormat::Pcm16 { dither: false }).with_commit_interval(60.0);
+        writer.start_writing(16000, 2).unwrap();
+        writer.write_samples(&samples).unwrap();
+        let path = writer.current_file().unwrap().clone();
+        std::mem::forget(writer);
+
+        // Power cut halfway through a frame
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[0x12, 0x34, 0x56]).unwrap();
+        drop(file);
+
+        match repair_wav(&path).unwrap() {
+            RepairOutcome::Repaired { recovered_bytes, trimmed_bytes, .. } => {
+                assert!(recovered_bytes > 0);
+                assert!(trimmed_bytes > 0 && trimmed_bytes < 4);
+            }
+            other => panic!("expected repair, got {:?}", other),
+        }
+        let mut reader = hound::WavReader::open(&path).unwrap();
+        let recovered: Vec<i16> = reader.samples::<i16>().map(|s| s.unwrap()).collect();
+        assert!(!recovered.is_empty());
+        assert_eq!(recovered.len() % 2, 0);
+        assert_eq!(recovered[1], (samples[1] as f64 * 32767.0).round() as i16);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_repair_directory_skips_garbage() {
+        let dir = temp_dir();
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("audio_broken.wav"), b"RIFF").unwrap();
+        fs::write(dir.join("notes.txt"), b"not audio").unwrap();
+
+        let mut writer = WavFileWriter::new(&dir);
+        writer.start_writing(16000, 1).unwrap();
+        writer.write_samples(&ramp(1600)).unwrap();
+        writer.finish_writing().unwrap();
+
+        // Unreadable files are logged and the rest still repaired
+        fs::create_dir(dir.join("audio_dir.wav")).unwrap();
+
+        let results = repair_directory(&dir, None, None).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[0].1, RepairOutcome::Intact));
+        assert!(matches!(results[1].1, RepairOutcome::Unrecoverable(_)));
+
+        // Anything just written might still be open in another writer
+        let results = repair_directory(&dir, None, Some(REPAIR_MIN_AGE)).unwrap();
+        assert!(results.iter().all(|(_, outcome)| matches!(outcome, RepairOutcome::Skipped(_))));
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    /// Finished recording with a LIST chunk after the audio, as tagging tools write it
+    fn recording_with_list_chunk(dir: &Path, samples: &[f32]) -> (PathBuf, u64) {
+        let mut writer = WavFileWriter::new(dir).with_format(WavFormat::Pcm16 { dither: false });
+        writer.start_writing(16000, 1).unwrap();
+        writer.write_samples(samples).unwrap();
+        let path = writer.finish_writing().unwrap().unwrap().file_path;
+        let mut list = b"LIST".to_vec();
+        list.extend_from_slice(&18u32.to_le_bytes());
+        list.extend_from_slice(b"INFOINAM\x05\x00\x00\x00take1\x00");
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&list).unwrap();
+        let riff_bytes = file.metadata().unwrap().len() - 8;
+        drop(file);
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.write_all_at(&(riff_bytes as u32).to_le_bytes(), 4).unwrap();
+        (path, riff_bytes + 8)
+    }
+
+    #[test]
+    fn test_trailing_chunks_are_kept() {
+        let dir = temp_dir();
+        let samples = ramp(1601);
+
+        // Valid file: left alone
+        let (path, file_len) = recording_with_list_chunk(&dir, &samples);
+        assert_eq!(repair_wav(&path).unwrap(), RepairOutcome::Intact);
+        assert_eq!(fs::metadata(&path).unwrap().len(), file_len);
+
+        // Streaming writer that never patched the data size: audio ends at the LIST chunk
+        for unset in [0u32, u32::MAX] {
+            let file = OpenOptions::new().write(true).open(&path).unwrap();
+            file.write_all_at(&unset.to_le_bytes(), 40).unwrap();
+            drop(file);
+            match repair_wav(&path).unwrap() {
+                RepairOutcome::Repaired { data_bytes, trimmed_bytes, .. } => {
+                    assert_eq!((data_bytes, trimmed_bytes), (1601 * 2, 0));
+                }
+                other => panic!("expected repair, got {:?}", other),
+            }
+            assert_eq!(fs::metadata(&path).unwrap().len(), file_len);
+            let mut reader = hound::WavReader::open(&path).unwrap();
+            assert_eq!(reader.samples::<i16>().count(), 1601);
+        }
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,192 @@
+use super::traits::{AudioWriter, RecordingInfo};
+
+/// Replay Buffer
+///
+/// Always-on circular buffer holding the last N seconds of processed audio
+/// -Fixed size ring allocated up front, no allocation on the audio thread
+/// -Oldest samples overwritten once the ring is full
+/// -Snapshot copies out in chronological order; capture skips (and counts) buffers arriving meanwhile instead of waiting
+#[derive(Debug, Clone)]
+pub struct ReplayBuffer {
+    ring: Vec<f32>,
+    write_pos: usize, // next slot to write
+    filled: usize,    // number of valid samples in ring
+    sample_rate: u32,
+    channels: u16,
+}
+
+/// Chronological copy of the replay buffer, safe to write out without holding the lock
+#[derive(Debug, Clone)]
+pub struct ReplaySnapshot {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+impl ReplayBuffer {
+    /// Create a replay buffer
+    /// Args:
+    /// - seconds: how much audio history to keep
+    /// - sample_rate: audio sample rate (Hz)
+    /// - channels: interleaved channel count
+    pub fn new(seconds: f32, sample_rate: u32, channels: u16) -> Self {
+        let channels = channels.max(1);
+        let frames = (seconds.max(0.0) * sample_rate as f32) as usize;
+        Self {
+            ring: vec![0.0; (frames * channels as usize).max(channels as usize)],
+            write_pos: 0,
+            filled: 0,
+            sample_rate,
+            channels,
+        }
+    }
+
+    /// Append interleaved samples, overwriting the oldest once full
+    pub fn push(&mut self, samples: &[f32]) {
+        let capacity = self.ring.len();
+        // Only the tail of an oversized buffer can survive
+        let samples = if samples.len() > capacity {
+            &samples[samples.len() - capacity..]
+        } else {
+            samples
+        };
+
+        // Copy in at most two slices (before and after wrap)
+        let first = samples.len().min(capacity - self.write_pos);
+        self.ring[self.write_pos..self.write_pos + first].copy_from_slice(&samples[..first]);
+        let rest = samples.len() - first;
+        self.ring[..rest].copy_from_slice(&samples[first..]);
+
+        self.write_pos = (self.write_pos + samples.len()) % capacity;
+        self.filled = (self.filled + samples.len()).min(capacity);
+    }
+
+    /// Copy out the last `seconds` of audio (or everything if None)
+    pub fn snapshot(&self, seconds: Option<f32>) -> ReplaySnapshot {
+        let wanted = match seconds {
+            Some(s) => {
+                let frames = (s.max(0.0) * self.sample_rate as f32) as usize;
+                (frames * self.channels as usize).min(self.filled)
+            }
+            None => self.filled,
+        };
+        let capacity = self.ring.len();
+        let start = (self.write_pos + capacity - wanted) % capacity;
+
+        let mut samples = Vec::with_capacity(wanted);
+        let first = wanted.min(capacity - start);
+        samples.extend_from_slice(&self.ring[start..start + first]);
+        samples.extend_from_slice(&self.ring[..wanted - first]);
+
+        ReplaySnapshot {
+            samples,
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+        }
+    }
+
+    /// Seconds of audio currently held
+    pub fn buffered_seconds(&self) -> f32 {
+        let frames = self.filled / self.channels as usize;
+        frames as f32 / self.sample_rate as f32
+    }
+
+    /// Maximum seconds of audio the ring can hold
+    pub fn capacity_seconds(&self) -> f32 {
+        let frames = self.ring.len() / self.channels as usize;
+        frames as f32 / self.sample_rate as f32
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    pub fn clear(&mut self) {
+        self.write_pos = 0;
+        self.filled = 0;
+    }
+}
+
+impl ReplaySnapshot {
+    pub fn duration_seconds(&self) -> f64 {
+        let frames = self.samples.len() / self.channels.max(1) as usize;
+        frames as f64 / self.sample_rate as f64
+    }
+
+    /// Write the snapshot as one complete recording through any AudioWriter
+    pub fn write_to<W: AudioWriter>(&self, writer: &mut W) -> Result<Option<RecordingInfo>, W::Error> {
+        writer.start_writing(self.sample_rate, self.channels)?;
+        writer.write_samples(&self.samples)?;
+        writer.finish_writing()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::WavFileWriter;
+
+    #[test]
+    fn test_replay_keeps_only_last_n_seconds() {
+        // 1 second at 10 Hz mono = 10 samples
+        let mut replay = ReplayBuffer::new(1.0, 10, 1);
+        let samples: Vec<f32> = (0..25).map(|i| i as f32).collect();
+
+        // Push in uneven chunks to exercise wrap-around
+        replay.push(&samples[..7]);
+        replay.push(&samples[7..19]);
+        replay.push(&samples[19..]);
+
+        let snap = replay.snapshot(None);
+        let expected: Vec<f32> = (15..25).map(|i| i as f32).collect();
+        assert_eq!(snap.samples, expected);
+        assert!((replay.buffered_seconds() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_replay_partial_snapshot() {
+        let mut replay = ReplayBuffer::new(2.0, 10, 2);
+        let samples: Vec<f32> = (0..16).map(|i| i as f32).collect();
+        replay.push(&samples);
+
+        // 0.5s of stereo at 10 Hz = 5 frames = 10 samples
+        let snap = replay.snapshot(Some(0.5));
+        let expected: Vec<f32> = (6..16).map(|i| i as f32).collect();
+        assert_eq!(snap.samples, expected);
+        assert_eq!(snap.channels, 2);
+
+        // Asking for more than is buffered returns what exists
+        assert_eq!(replay.snapshot(Some(10.0)).samples.len(), 16);
+    }
+
+    #[test]
+    fn test_replay_oversized_push() {
+        let mut replay = ReplayBuffer::new(0.5, 10, 1);
+        let samples: Vec<f32> = (0..12).map(|i| i as f32).collect();
+        replay.push(&samples);
+        assert_eq!(replay.snapshot(None).samples, vec![7.0, 8.0, 9.0, 10.0, 11.0]);
+    }
+
+    #[test]
+    fn test_replay_dump_to_wav() {
+        let dir = std::env::temp_dir().join(format!("merlin_replay_{}", uuid::Uuid::new_v4()));
+        let mut replay = ReplayBuffer::new(1.0, 16000, 1);
+        replay.push(&vec![0.25; 8000]);
+
+        let mut writer = WavFileWriter::new(&dir);
+        let info = replay
+            .snapshot(None)
+            .write_to(&mut writer)
+            .unwrap()
+            .expect("dump should produce a recording");
+
+        assert!((info.duration_seconds - 0.5).abs() < 1e-6);
+        let reader = hound::WavReader::open(&info.file_path).unwrap();
+        assert_eq!(reader.len(), 8000);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+/// Streaming Resampler
+///
+/// Converts interleaved audio at any device rate to mono at a target rate
+/// -Downmix channels by averaging
+/// -Windowed-sinc interpolation, cutoff lowered when downsampling (anti-alias)
+/// -Keeps history between calls so buffer edges don't click
+#[derive(Debug, Clone)]
+pub struct Resampler {
+    input_rate: u32,
+    output_rate: u32,
+    channels: u16,
+    step: f64,              // input samples advanced per output sample
+    position: f64,          // next output position, relative to history[0]
+    history: VecDeque<f32>, // mono input not yet fully consumed
+    cutoff: f32,            // normalized to input Nyquist
+}
+
+/// Taps on each side of the interpolation point
+const HALF_TAPS: usize = 16;
+
+impl Resampler {
+    /// Create a resampler
+    /// Args:
+    /// - input_rate: device sample rate (Hz)
+    /// - output_rate: wanted sample rate (Hz)
+    /// - channels: interleaved input channel count
+    pub fn new(input_rate: u32, output_rate: u32, channels: u16) -> Self {
+        let step = input_rate as f64 / output_rate as f64;
+        // Leave a little headroom below the output Nyquist
+        let cutoff = if output_rate < input_rate {
+            0.95 * output_rate as f32 / input_rate as f32
+        } else {
+            1.0
+        };
+        let mut history = VecDeque::with_capacity(input_rate as usize / 10);
+        // Prime with silence so the first output sample has a full left window
+        history.extend(std::iter::repeat_n(0.0, HALF_TAPS));
+        Self {
+            input_rate,
+            output_rate,
+            channels: channels.max(1),
+            step,
+            position: HALF_TAPS as f64,
+            history,
+            cutoff,
+        }
+    }
+
+    /// Resample one buffer of interleaved input, appending mono output to `out`
+    pub fn process_into(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        let channels = self.channels as usize;
+        for frame in input.chunks_exact(channels) {
+            self.history.push_back(frame.iter().sum::<f32>() / channels as f32);
+        }
+
+        self.emit(out);
+
+        // Drop input no longer needed for the left-hand window
+        let consumed = (self.position.floor() as usize).saturating_sub(HALF_TAPS);
+        self.history.drain(..consumed);
+        self.position -= consumed as f64;
+    }
+
+    /// End of stream: emit the output still waiting on its right-hand window, as if silence followed
+    /// Without this the last HALF_TAPS input samples never come out; the resampler is reset after
+    pub fn flush_into(&mut self, out: &mut Vec<f32>) {
+        self.history.extend(std::iter::repeat_n(0.0, HALF_TAPS));
+        self.emit(out);
+        self.reset();
+    }
+
+    /// Emit every output sample whose right-hand window is available
+    fn emit(&mut self, out: &mut Vec<f32>) {
+        while self.position + (HALF_TAPS as f64) < self.history.len() as f64 {
+            out.push(self.interpolate(self.position));
+            self.position += self.step;
+        }
+    }
+
+    /// Convenience wrapper returning a fresh buffer
+    pub fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        let mut out = Vec::with_capacity(
+            (input.len() as f64 / self.channels as f64 / self.step) as usize + 1,
+        );
+        self.process_into(input, &mut out);
+        out
+    }
+
+    fn interpolate(&self, position: f64) -> f32 {
+        let center = position.floor() as usize;
+        let frac = (position - center as f64) as f32;
+        let mut acc = 0.0;
+        for k in 0..2 * HALF_TAPS {
+            let idx = center + k + 1 - HALF_TAPS;
+            // distance from the interpolation point, in input samples
+            let x = (k as f32 + 1.0 - HALF_TAPS as f32) - frac;
+            acc += self.history[idx] * self.kernel(x);
+        }
+        acc
+    }
+
+    /// Lowpass sinc (unity DC gain) with a Hann window across the tap span
+    fn kernel(&self, x: f32) -> f32 {
+        let sinc = if x.abs() < 1e-6 {
+            self.cutoff
+        } else {
+            (PI * self.cutoff * x).sin() / (PI * x)
+        };
+        let window = 0.5 + 0.5 * (PI * x / HALF_TAPS as f32).cos();
+        sinc * window
+    }
+
+    pub fn input_rate(&self) -> u32 {
+        self.input_rate
+    }
+
+    pub fn output_rate(&self) -> u32 {
+        self.output_rate
+    }
+
+    pub fn reset(&mut self) {
+        self.history.clear();
+        self.history.extend(std::iter::repeat_n(0.0, HALF_TAPS));
+        self.position = HALF_TAPS as f64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resampler_48k_stereo_to_16k_mono() {
+        let mut resampler = Resampler::new(48000, 16000, 2);
+        // 1 second of a 440 Hz tone on both channels
+        let input: Vec<f32> = (0..48000)
+            .flat_map(|i| {
+                let s = 0.5 * (2.0 * PI * 440.0 * i as f32 / 48000.0).sin();
+                [s, s]
+            })
+            .collect();
+
+        // Feed in 10ms buffers like the audio callback would
+        let mut out = Vec::new();
+        for chunk in input.chunks(960) {
+            resampler.process_into(chunk, &mut out);
+        }
+
+        assert!((15980..=16000).contains(&out.len()), "got {} samples", out.len());
+        // Flushing at the end of the stream brings out the rest, exactly one second in total
+        let tail = out.len();
+        resampler.flush_into(&mut out);
+        assert_eq!(out.len(), 16000);
+        assert!(out[tail..].iter().all(|s| s.abs() < 0.6));
+        // Passband tone keeps its level once past the startup window
+        let peak = out[1000..].iter().map(|s| s.abs()).fold(0.0f32, f32::max);
+        assert!((peak - 0.5).abs() < 0.02, "peak {:.3}", peak);
+    }
+
+    #[test]
+    fn test_resampler_rejects_above_output_nyquist() {
+        let mut resampler = Resampler::new(48000, 16000, 1);
+        // 12 kHz would alias straight back to 4 kHz without the lowpass
+        let input: Vec<f32> = (0..48000)
+            .map(|i| 0.5 * (2.0 * PI * 12000.0 * i as f32 / 48000.0).sin())
+            .collect();
+        let out = resampler.process(&input);
+        let peak = out[1000..].iter().map(|s| s.abs()).fold(0.0f32, f32::max);
+        assert!(peak < 0.05, "aliased peak {:.3}", peak);
+    }
+}
@@ -0,0 +1,308 @@
+use std::ffi::CString;
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use super::recorder::is_recording_file;
+use super::sidecar::sidecar_path;
+
+/// Retention Manager
+///
+/// Keeps the recordings dir from filling the SD card
+/// -Max age, max total bytes and min free disk space, each optional
+/// -Always deletes the oldest recordings first
+/// -Every deletion goes through the callback (logs by default)
+/// -A recording's JSON sidecar goes with it
+/// -Files still being written (the open recording, anything modified in the last min_age) are kept
+/// -A file that can't be deleted is logged and the next oldest tried
+pub struct RetentionManager {
+    dir: PathBuf,
+    policy: RetentionPolicy,
+    min_age: Duration,
+    on_delete: Box<dyn FnMut(&DeletedRecording) + Send>,
+}
+
+/// Recordings modified more recently than this may still be open (live recordings, replay exports)
+pub const RETENTION_MIN_AGE: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RetentionPolicy {
+    pub max_total_bytes: Option<u64>,
+    pub max_age: Option<Duration>,
+    /// Free space to keep on the filesystem holding the recordings
+    pub min_free_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeleteReason {
+    MaxAge,
+    MaxTotalBytes,
+    MinFreeSpace,
+}
+
+/// One recording removed by the retention manager
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeletedRecording {
+    pub path: PathBuf,
+    /// Audio plus sidecar
+    pub size_bytes: u64,
+    pub modified: SystemTime,
+    pub reason: DeleteReason,
+}
+
+/// A recording on disk, oldest first once sorted
+#[derive(Debug, Clone)]
+struct Candidate {
+    path: PathBuf,
+    size_bytes: u64,
+    modified: SystemTime,
+}
+
+impl RetentionManager {
+    pub fn new(dir: impl Into<PathBuf>, policy: RetentionPolicy) -> Self {
+        Self {
+            dir: dir.into(),
+            policy,
+            min_age: RETENTION_MIN_AGE,
+            on_delete: Box::new(|deleted| {
+                println!(
+                    "Retention: deleted {:?} ({} bytes, {:?})",
+                    deleted.path, deleted.size_bytes, deleted.reason
+                );
+            }),
+        }
+    }
+
+    /// Replace the default log line with a custom report
+    pub fn on_delete(mut self, callback: impl FnMut(&DeletedRecording) + Send + 'static) -> Self {
+        self.on_delete = Box::new(callback);
+        self
+    }
+
+    /// Leave recordings modified less than `min_age` ago alone, RETENTION_MIN_AGE by default
+    pub fn with_min_age(mut self, min_age: Duration) -> Self {
+        self.min_age = min_age;
+        self
+    }
+
+    pub fn policy(&self) -> &RetentionPolicy {
+        &self.policy
+    }
+
+    /// Apply the policy once, `skip` is the recording currently open
+    /// Returns what was deleted, oldest first
+    pub fn enforce(&mut self, skip: Option<&Path>) -> io::Result<Vec<DeletedRecording>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let (mut candidates, busy_bytes) = self.candidates(skip)?;
+        let mut deleted = Vec::new();
+
+        if let Some(max_age) = self.policy.max_age {
+            let now = SystemTime::now();
+            while let Some(oldest) = candidates.first() {
+                let age = now.duration_since(oldest.modified).unwrap_or_default();
+                if age <= max_age {
+                    break;
+                }
+                let oldest = candidates.remove(0);
+                deleted.extend(self.delete(oldest, DeleteReason::MaxAge));
+            }
+        }
+
+        if let Some(max_total) = self.policy.max_total_bytes {
+            // Open files count towards the total even though they can't be deleted
+            let mut total: u64 = busy_bytes + candidates.iter().map(|c| c.size_bytes).sum::<u64>();
+            while total > max_total && !candidates.is_empty() {
+                let oldest = candidates.remove(0);
+                let size = oldest.size_bytes;
+                if let Some(removed) = self.delete(oldest, DeleteReason::MaxTotalBytes) {
+                    total -= size;
+                    deleted.push(removed);
+                }
+            }
+        }
+
+        if let Some(min_free) = self.policy.min_free_bytes {
+            while !candidates.is_empty() && free_bytes(&self.dir)? < min_free {
+                let oldest = candidates.remove(0);
+                deleted.extend(self.delete(oldest, DeleteReason::MinFreeSpace));
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    /// Deletable recordings oldest first, and the bytes held by ones still being written
+    fn candidates(&self, skip: Option<&Path>) -> io::Result<(Vec<Candidate>, u64)> {
+        let now = SystemTime::now();
+        let mut candidates = Vec::new();
+        let mut busy_bytes = 0;
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if !is_recording_file(&path) {
+                continue;
+            }
+            // Gone since read_dir (another cleanup, a rename), nothing to do
+            let Ok(metadata) = fs::metadata(&path) else { continue };
+            let modified = metadata.modified()?;
+            let sidecar_bytes = fs::metadata(sidecar_path(&path)).map_or(0, |m| m.len());
+            let size_bytes = metadata.len() + sidecar_bytes;
+            let recent = now.duration_since(modified).unwrap_or_default() < self.min_age;
+            if recent || Some(path.as_path()) == skip {
+                busy_bytes += size_bytes;
+                continue;
+            }
+            candidates.push(Candidate { path, size_bytes, modified });
+        }
+        // Names are timestamps, so they break mtime ties in recording order
+        candidates.sort_by(|a, b| a.modified.cmp(&b.modified).then_with(|| a.path.cmp(&b.path)));
+        Ok((candidates, busy_bytes))
+    }
+
+    /// Remove one recording and its sidecar, None (and a log line) if that fails
+    fn delete(&mut self, candidate: Candidate, reason: DeleteReason) -> Option<DeletedRecording> {
+        if let Err(e) = fs::remove_file(&candidate.path) {
+            eprintln!("Retention: can't delete {:?}: {}", candidate.path, e);
+            return None;
+        }
+        let sidecar = sidecar_path(&candidate.path);
+        if let Err(e) = fs::remove_file(&sidecar)
+            && e.kind() != io::ErrorKind::NotFound
+        {
+            eprintln!("Retention: can't delete {:?}: {}", sidecar, e);
+        }
+        let deleted = DeletedRecording {
+            path: candidate.path,
+            size_bytes: candidate.size_bytes,
+            modified: candidate.modified,
+            reason,
+        };
+        (self.on_delete)(&deleted);
+        Some(deleted)
+    }
+}
+
+/// Bytes available to unprivileged users on the filesystem holding `path`
+pub fn free_bytes(path: &Path) -> io::Result<u64> {
+    let c_path = CString::new(path.as_os_str().as_bytes()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: c_path is NUL terminated and stat is a valid out pointer
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::sync::{Arc, Mutex};
+
+    /// Recording named `name` of `size` bytes, last modified `age_secs` ago
+    fn recording(dir: &Path, name: &str, size: usize, age_secs: u64) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, vec![0u8; size]).unwrap();
+        let modified = SystemTime::now() - Duration::from_secs(age_secs);
+        File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        path
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("merlin_retention_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_retention_deletes_oldest_first_and_reports() {
+        let dir = temp_dir();
+        recording(&dir, "audio_a.wav", 1000, 4 * 3600); // too old
+        recording(&dir, "audio_b.wav", 1000, 3 * 3600);
+        recording(&dir, "audio_c.wav", 1000, 2 * 3600);
+        let open = recording(&dir, "audio_d.wav", 1000, 0);
+        recording(&dir, "notes.txt", 5000, 10 * 3600);
+
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&reported);
+        let mut manager = RetentionManager::new(
+            &dir,
+            RetentionPolicy {
+                max_age: Some(Duration::from_secs(3 * 3600 + 1800)),
+                max_total_bytes: Some(2500),
+                min_free_bytes: None,
+            },
+        )
+        .on_delete(move |deleted| log.lock().unwrap().push((deleted.path.clone(), deleted.reason)));
+
+        let deleted = manager.enforce(Some(&open)).unwrap();
+        let reasons: Vec<(PathBuf, DeleteReason)> =
+            deleted.iter().map(|d| (d.path.clone(), d.reason)).collect();
+        assert_eq!(
+            reasons,
+            vec![
+                (dir.join("audio_a.wav"), DeleteReason::MaxAge),
+                (dir.join("audio_b.wav"), DeleteReason::MaxTotalBytes),
+            ]
+        );
+        assert_eq!(*reported.lock().unwrap(), reasons);
+        assert!(dir.join("audio_c.wav").exists());
+        assert!(open.exists());
+        assert!(dir.join("notes.txt").exists());
+
+        // Within policy now, nothing more to do
+        assert!(manager.enforce(Some(&open)).unwrap().is_empty());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_retention_keeps_files_being_written_and_skips_failures() {
+        let dir = temp_dir();
+        // Can't be removed with remove_file, stands in for a permission error
+        let stuck = dir.join("audio_0.wav");
+        fs::create_dir(&stuck).unwrap();
+        File::open(&stuck).unwrap().set_modified(SystemTime::now() - Duration::from_secs(7200)).unwrap();
+        recording(&dir, "audio_1.wav", 10, 3600);
+        let writing = recording(&dir, "audio_2.wav", 10, 2);
+
+        let mut manager = RetentionManager::new(
+            &dir,
+            RetentionPolicy { max_age: Some(Duration::from_secs(1)), ..Default::default() },
+        );
+        let deleted = manager.enforce(None).unwrap();
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].path, dir.join("audio_1.wav"));
+        assert!(stuck.exists());
+        assert!(writing.exists());
+
+        // Old enough once the writer is done
+        let mut manager = manager.with_min_age(Duration::ZERO);
+        assert_eq!(manager.enforce(None).unwrap().len(), 1);
+        assert!(!writing.exists());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_retention_min_free_space() {
+        let dir = temp_dir();
+        recording(&dir, "audio_1.wav", 10, 60);
+        recording(&dir, "audio_2.wav", 10, 30);
+        let open = recording(&dir, "audio_3.wav", 10, 0);
+        assert!(free_bytes(&dir).unwrap() > 0);
+
+        // Unsatisfiable free space target: everything but the open file goes
+        let mut manager = RetentionManager::new(
+            &dir,
+            RetentionPolicy { min_free_bytes: Some(u64::MAX), ..Default::default() },
+        );
+        let deleted = manager.enforce(Some(&open)).unwrap();
+        assert_eq!(deleted.len(), 2);
+        assert!(deleted.iter().all(|d| d.reason == DeleteReason::MinFreeSpace));
+        assert_eq!(deleted[0].path, dir.join("audio_1.wav"));
+        assert!(open.exists());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
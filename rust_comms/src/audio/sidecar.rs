@@ -0,0 +1,177 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::filters::FilterConfig;
+use super::loudness::LoudnessMeter;
+use super::metrics::CLIP_THRESHOLD;
+use super::traits::RecordingInfo;
+
+/// Recording Sidecar
+///
+/// JSON file written next to each finished recording (audio_X.wav -> audio_X.json)
+/// -Where it came from: device and filter settings
+/// -What's in it: levels and loudness measured over the whole file
+/// -When: wall-clock start and end
+/// -Transcript and tags can be filled in later (ex: by the voice brain)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordingMetadata {
+    /// File name of the audio, relative to the sidecar
+    pub file: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub duration_seconds: f64,
+    pub file_size_bytes: u64,
+    pub started_at: DateTime<Local>,
+    pub ended_at: DateTime<Local>,
+    #[serde(default)]
+    pub device: Option<String>,
+    #[serde(default)]
+    pub filters: Option<FilterConfig>,
+    #[serde(default)]
+    pub levels: Option<LevelSummary>,
+    #[serde(default)]
+    pub transcript: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Whole-file level statistics
+/// Loudness values are None for silence (below the -70 LUFS gate)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LevelSummary {
+    pub rms_db: f32,
+    pub peak_db: f32,
+    pub clipped_samples: u64,
+    pub integrated_lufs: Option<f32>,
+    pub loudness_range_lu: Option<f32>,
+}
+
+/// What the writer knows about the source, copied into every sidecar
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordingContext {
+    pub device: Option<String>,
+    pub filters: Option<FilterConfig>,
+    pub tags: Vec<String>,
+}
+
+/// Sidecar path for a recording: same name, .json extension
+pub fn sidecar_path(audio_path: impl AsRef<Path>) -> PathBuf {
+    audio_path.as_ref().with_extension("json")
+}
+
+/// Write the sidecar for a recording that just closed
+/// `started_at` falls back to now when the writer never recorded it
+pub(crate) fn write_sidecar(
+    info: &RecordingInfo,
+    started_at: Option<DateTime<Local>>,
+    context: &RecordingContext,
+    levels: Option<LevelSummary>,
+) -> io::Result<()> {
+    let ended_at = Local::now();
+    let mut metadata = RecordingMetadata::new(info, started_at.unwrap_or(ended_at), ended_at);
+    metadata.device = context.device.clone();
+    metadata.filters = context.filters;
+    metadata.tags = context.tags.clone();
+    metadata.levels = levels;
+    metadata.save(sidecar_path(&info.file_path))
+}
+
+impl RecordingMetadata {
+    pub fn new(info: &RecordingInfo, started_at: DateTime<Local>, ended_at: DateTime<Local>) -> Self {
+        Self {
+            file: info
+                .file_path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            sample_rate: info.sample_rate,
+            channels: info.channels,
+            duration_seconds: info.duration_seconds,
+            file_size_bytes: info.file_size_bytes,
+            started_at,
+            ended_at,
+            device: None,
+            filters: None,
+            levels: None,
+            transcript: None,
+            tags: Vec::new(),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        serde_json::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Write atomically (temp file + rename) so a crash never leaves half a sidecar
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let json = serde_json::to_string_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, json)?;
+        fs::rename(&temp, path)
+    }
+
+    /// Load, edit and save the sidecar of `audio_path`
+    pub fn update(audio_path: impl AsRef<Path>, edit: impl FnOnce(&mut Self)) -> io::Result<Self> {
+        let path = sidecar_path(audio_path);
+        let mut metadata = Self::load(&path)?;
+        edit(&mut metadata);
+        metadata.save(&path)?;
+        Ok(metadata)
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+}
+
+/// Accumulates LevelSummary while a recording is written
+#[derive(Debug, Clone)]
+pub struct LevelAccumulator {
+    sum_squares: f64,
+    samples: u64,
+    peak: f32,
+    clipped: u64,
+    loudness: LoudnessMeter,
+}
+
+impl LevelAccumulator {
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            sum_squares: 0.0,
+            samples: 0,
+            peak: 0.0,
+            clipped: 0,
+            loudness: LoudnessMeter::new(sample_rate, channels),
+        }
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        for &sample in samples {
+            let level = sample.abs();
+            self.sum_squares += (sample as f64) * (sample as f64);
+            self.peak = self.peak.max(level);
+            if level >= CLIP_THRESHOLD {
+                self.clipped += 1;
+            }
+        }
+        self.samples += samples.len() as u64;
+        self.loudness.process(samples);
+    }
+
+    pub fn summary(&self) -> LevelSummary {
+        let rms = if self.samples > 0 { (self.sum_squares / self.samples as f64).sqrt() as f32 } else { 0.0 };
+        let reading = self.loudness.reading();
+        LevelSummary {
+            rms_db: 20.0 * rms.max(1e-10).log10(),
+            peak_db: 20.0 * self.peak.max(1e-10).log10(),
+            clipped_samples: self.clipped,
+            integrated_lufs: Some(reading.integrated_lufs).filter(|v| v.is_finite()),
+            loudness_range_lu: Some(reading.loudness_range_lu).filter(|_| reading.integrated_lufs.is_finite()),
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Pipeline Telemetry
+///
+/// Is the audio callback keeping up?
+/// -Callback period vs the buffer duration (jitter)
+/// -Processing time vs the buffer deadline (load, overruns)
+/// -Late callbacks, backend errors and disconnects, buffers dropped downstream
+/// -Capture -> metrics latency, to check the < 50ms target
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct PipelineTelemetry {
+    pub buffers: u64,
+    /// Expected callback period from buffer size
+    pub buffer_ms: f32,
+    /// Smoothed |actual period - expected period|
+    pub period_jitter_ms: f32,
+    pub max_period_jitter_ms: f32,
+    /// Smoothed time spent in the pipeline per buffer
+    pub processing_ms: f32,
+    pub max_processing_ms: f32,
+    /// processing_ms / buffer_ms, above 1.0 we can't keep up
+    pub load: f32,
+    /// Buffers whose processing took longer than their duration
+    pub overruns: u64,
+    /// Callbacks arriving more than 1.5 periods apart (input likely overflowed)
+    pub late_callbacks: u64,
+    /// Other stream errors reported by the audio backend
+    /// cpal recovers from xruns without reporting them, those show up as late_callbacks
+    pub backend_errors: u64,
+    /// Input device went away (unplugged)
+    pub disconnects: u64,
+    /// Buffers dropped by full downstream queues or skipped by a busy replay buffer
+    pub dropped_buffers: u64,
+    /// Smoothed capture -> metrics update latency
+    pub latency_ms: f32,
+    pub max_latency_ms: f32,
+}
+
+/// EMA weight for the smoothed values, ~20 buffer time constant
+const SMOOTHING: f32 = 0.05;
+
+/// Updates PipelineTelemetry from inside the pipeline
+#[derive(Debug, Clone)]
+pub struct TelemetryTracker {
+    sample_rate: u32,
+    channels: u16,
+    last_callback: Option<Instant>,
+    stream_errors: StreamErrorCounter,
+    stats: PipelineTelemetry,
+}
+
+impl TelemetryTracker {
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            sample_rate,
+            channels: channels.max(1),
+            last_callback: None,
+            stream_errors: StreamErrorCounter::default(),
+            stats: PipelineTelemetry::default(),
+        }
+    }
+
+    /// Counters for the backend error callback to bump
+    pub fn stream_error_counter(&self) -> StreamErrorCounter {
+        self.stream_errors.clone()
+    }
+
+    /// Call at the top of the callback, returns the start time for `finish`
+    pub fn begin(&mut self, samples: usize) -> Instant {
+        let now = Instant::now();
+        let frames = samples / self.channels as usize;
+        let expected_ms = frames as f32 * 1000.0 / self.sample_rate as f32;
+        self.stats.buffer_ms = expected_ms;
+
+        if let Some(last) = self.last_callback {
+            let period_ms = now.duration_since(last).as_secs_f32() * 1000.0;
+            let jitter = (period_ms - expected_ms).abs();
+            self.stats.period_jitter_ms += (jitter - self.stats.period_jitter_ms) * SMOOTHING;
+            self.stats.max_period_jitter_ms = self.stats.max_period_jitter_ms.max(jitter);
+            if period_ms > expected_ms * 1.5 {
+                self.stats.late_callbacks += 1;
+            }
+        }
+        self.last_callback = Some(now);
+        now
+    }
+
+    /// Call once metrics are published for this buffer
+    /// Args:
+    /// - started: value returned by `begin`
+    /// - capture_timestamp_us: wall clock capture time of the buffer
+    /// - dropped_buffers: running total from downstream queues
+    pub fn finish(&mut self, started: Instant, capture_timestamp_us: u64, dropped_buffers: u64) -> PipelineTelemetry {
+        let processing_ms = started.elapsed().as_secs_f32() * 1000.0;
+        let stats = &mut self.stats;
+        stats.buffers += 1;
+        stats.processing_ms += (processing_ms - stats.processing_ms) * SMOOTHING;
+        stats.max_processing_ms = stats.max_processing_ms.max(processing_ms);
+        if stats.buffer_ms > 0.0 {
+            stats.load = stats.processing_ms / stats.buffer_ms;
+            if processing_ms > stats.buffer_ms {
+                stats.overruns += 1;
+            }
+        }
+
+        if capture_timestamp_us > 0 {
+            let latency_ms = now_us().saturating_sub(capture_timestamp_us) as f32 / 1000.0;
+            if stats.buffers == 1 {
+                stats.latency_ms = latency_ms;
+            }
+            stats.latency_ms += (latency_ms - stats.latency_ms) * SMOOTHING;
+            stats.max_latency_ms = stats.max_latency_ms.max(latency_ms);
+        }
+
+        stats.backend_errors = self.stream_errors.backend_errors.load(Ordering::Relaxed);
+        stats.disconnects = self.stream_errors.disconnects.load(Ordering::Relaxed);
+        stats.dropped_buffers = dropped_buffers;
+        *stats
+    }
+}
+
+/// Stream errors by kind, shared with the cpal error callback
+#[derive(Debug, Clone, Default)]
+pub struct StreamErrorCounter {
+    backend_errors: Arc<AtomicU64>,
+    disconnects: Arc<AtomicU64>,
+}
+
+impl StreamErrorCounter {
+    pub fn record(&self, err: &cpal::StreamError) {
+        let counter = match err {
+            cpal::StreamError::DeviceNotAvailable => &self.disconnects,
+            cpal::StreamError::BackendSpecific { .. } => &self.backend_errors,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Wall clock in μs since UNIX epoch
+pub fn now_us() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_telemetry_flags_late_callbacks_and_overruns() {
+        // 160 frames at 16 kHz = 10ms buffers
+        let mut tracker = TelemetryTracker::new(16000, 1);
+        let errors = tracker.stream_error_counter();
+
+        let started = tracker.begin(160);
+        tracker.finish(started, now_us(), 0);
+
+        // Next callback 30ms later and processing blows the 10ms deadline
+        thread::sleep(Duration::from_millis(30));
+        let started = tracker.begin(160);
+        thread::sleep(Duration::from_millis(12));
+        errors.record(&cpal::StreamError::DeviceNotAvailable);
+        for _ in 0..2 {
+            let err = cpal::BackendSpecificError { description: "poll failed".to_string() };
+            errors.record(&cpal::StreamError::BackendSpecific { err });
+        }
+        let stats = tracker.finish(started, now_us() - 5_000, 3);
+
+        assert_eq!(stats.buffers, 2);
+        assert!((stats.buffer_ms - 10.0).abs() < 1e-3);
+        assert_eq!(stats.late_callbacks, 1);
+        assert_eq!(stats.overruns, 1);
+        assert_eq!((stats.backend_errors, stats.disconnects), (2, 1));
+        assert_eq!(stats.dropped_buffers, 3);
+        assert!(stats.max_period_jitter_ms >= 19.0, "{:?}", stats);
+        assert!(stats.max_latency_ms >= 5.0, "{:?}", stats);
+    }
+}
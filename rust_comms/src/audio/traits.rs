@@ -1,6 +1,6 @@
+use serde::Serialize;
 use std::path::PathBuf;
 ///Traits for writing audio data to files
-
 pub trait AudioWriter {
     type Error;
     fn start_writing(&mut self, sample_rate: u32, channels: u16) -> Result<(), Self::Error>;
@@ -9,7 +9,7 @@ pub trait AudioWriter {
     fn is_writing(&self) -> bool;
 }
  /// Metadata for a completed recording
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct RecordingInfo {
     pub file_path: PathBuf,
     pub duration_seconds: f64,
@@ -1,150 +1,366 @@
-use super::traits::{AudioWriter, RecordingInfo};
+use super::file_writer::{FormatWriter, RecordingFileWriter, RecordingSink};
 use hound::{WavWriter, WavSpec};
-use std::path::PathBuf;
-use std::fs;
-use chrono::Local;
+use std::str::FromStr;
+
+/// Sample encoding for new recordings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WavFormat {
+    /// 16-bit PCM, optional TPDF dither to decorrelate quantization noise from quiet speech
+    Pcm16 { dither: bool },
+    /// 24-bit PCM
+    Pcm24,
+    /// 32-bit IEEE float, lossless for the pipeline's f32 samples
+    #[default]
+    Float32,
+}
+
+impl WavFormat {
+    pub fn bits_per_sample(&self) -> u16 {
+        match self {
+            WavFormat::Pcm16 { .. } => 16,
+            WavFormat::Pcm24 => 24,
+            WavFormat::Float32 => 32,
+        }
+    }
+
+    fn sample_format(&self) -> hound::SampleFormat {
+        match self {
+            WavFormat::Float32 => hound::SampleFormat::Float,
+            _ => hound::SampleFormat::Int,
+        }
+    }
+}
+
+pub(crate) const PCM16_MAX: f64 = 32767.0;
+pub(crate) const PCM24_MAX: f64 = 8_388_607.0;
+
+/// Parses "pcm16", "pcm16-dither", "pcm24", "float32" (ex: from MERLIN_WAV_FORMAT)
+impl FromStr for WavFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "pcm16" | "s16" => Ok(WavFormat::Pcm16 { dither: false }),
+            "pcm16-dither" | "s16-dither" => Ok(WavFormat::Pcm16 { dither: true }),
+            "pcm24" | "s24" => Ok(WavFormat::Pcm24),
+            "float32" | "f32" => Ok(WavFormat::Float32),
+            other => Err(format!("Unknown WAV format: {}", other)),
+        }
+    }
+}
 
 //Implementation, like setting up repo for audio data to WAV files
 /// WAV file writer with auto timestamped filenames and daily rotation
 /// -Manual Control via start_writing and finish_writing FNs
 /// -Auto timestamp filenames
-pub struct WavFileWriter {
-    ///Active WAV writer wrapped in option for safe state management
-    writer: Option<WavWriter<std::io::BufWriter<std::fs::File>>>, 
-    current_file: Option<PathBuf>, //track current file path
-    sample_count: u64,
-    sample_rate: u32,
-    output_dir: PathBuf, //Dir where WAV files are saved.
-    current_date: Option<String>, //Dated in string format YYYYMMDD
-}
+/// -Header committed and fsynced every commit interval, so a power cut loses at most that much
+/// -JSON sidecar with source, levels and wall-clock times written when each file closes
+pub type WavFileWriter = RecordingFileWriter<WavFormatWriter>;
 
 impl WavFileWriter {
-    ///Create a new WAV file writer with specified output dir
-    /// # Arguments
-    /// - `output_dir`: Directory where WAV files will be saved (ex: "./recordings")
-    pub fn new(output_dir: impl Into<PathBuf>) -> Self {
-        Self {
-            writer: None,
-            current_file: None,
-            sample_count: 0,
-            sample_rate: 44100, //Default state
-            output_dir: output_dir.into(),
-            current_date: None,
-        }
+    /// Encoding for recordings started after this call
+    pub fn with_format(mut self, format: WavFormat) -> Self {
+        self.set_format(format);
+        self
     }
 
-    fn generate_filename() -> String {
-        let timestamp = Local::now().format("%Y%m%d_%H%M%S");
-        format!("audio_{}.wav", timestamp)
+    pub fn set_format(&mut self, format: WavFormat) {
+        self.format_writer_mut().encoding = format;
     }
 
-    fn get_current_date() -> String {
-        Local::now().format("%Y%m%d").to_string()
+    pub fn format(&self) -> WavFormat {
+        self.format_writer().encoding
     }
+}
 
-    ///Check if we crossed day boundary
-    /// **Returns**: true if date changed since file was created
-    fn should_rotate(&self) -> bool {
-        if let Some(ref date) = self.current_date {
-            date != &Self::get_current_date()
-        } else {
-            false
-        }
-    }
+/// WAV half of WavFileWriter, hound writes the header and patches its sizes on every commit
+#[derive(Default)]
+pub struct WavFormatWriter {
+    encoding: WavFormat,
+    dither: Dither,
+    writer: Option<WavWriter<RecordingSink>>,
+    channels: u16,
+    sample_count: u64,
+}
 
-    ///Auto rotate to new file if day boundary crossed
-    fn check_rotation(&mut self, channels: u16) -> Result<(), Box<dyn std::error::Error>> {
-        if self.is_writing() && self.should_rotate() { //called auto by these Args to ensure cont. recording
-            println!("Day Boundary crossed, Rotating file.");
-            let info = self.finish_writing()?;
-            if let Some(info) = info {
-                println!("Closed: {:?} ({:.2}s)", info.file_path, info.duration_seconds);
-            }
+impl FormatWriter for WavFormatWriter {
+    fn extension(&self) -> &'static str {
+        "wav"
+    }
 
-            self.start_writing(self.sample_rate, channels)?;
-        }
-        Ok(())
+    fn describe(&self) -> String {
+        format!("{:?}", self.encoding)
     }
-}
 
-impl AudioWriter for WavFileWriter {
-    type Error = Box<dyn std::error::Error>;
-    fn start_writing(&mut self, sample_rate: u32, channels: u16) -> Result<(), Self::Error>{
-        if self.is_writing() {
-            return Err("Already writing".into());
-        }
-        
-        fs::create_dir_all(&self.output_dir)?; //Create output dir if it doesn't exist
+    fn bits_per_sample(&self) -> u16 {
+        self.encoding.bits_per_sample()
+    }
 
-        let filename = Self::generate_filename();
-        let path = self.output_dir.join(filename);
+    fn check(&self, _sample_rate: u32, _channels: u16) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
 
+    fn open(&mut self, sink: RecordingSink, sample_rate: u32, channels: u16) -> Result<(), Box<dyn std::error::Error>> {
         let spec = WavSpec {
             channels,
             sample_rate,
-            bits_per_sample: 32,              //Using 32 bits for float samples
-            sample_format: hound::SampleFormat::Float,
+            bits_per_sample: self.encoding.bits_per_sample(),
+            sample_format: self.encoding.sample_format(),
         };
-
-        let writer = WavWriter::create(&path, spec)?; //Creating WAV writer..open con
-        //Store state
+        let mut writer = WavWriter::new(sink, spec)?;
+        writer.flush()?; //Header on disk before any audio
         self.writer = Some(writer);
-        self.current_file = Some(path.clone());
+        self.channels = channels;
         self.sample_count = 0;
-        self.sample_rate = sample_rate;
-        self.current_date = Some(Self::get_current_date());
-
-        println!("Started Recording: {:?}", path);
         Ok(())
+    }
 
+    fn write(&mut self, samples: &[f32]) -> Result<(), Box<dyn std::error::Error>> {
+        let writer = self.writer.as_mut().ok_or("Not currently writing to a file")?;
+        match self.encoding {
+            WavFormat::Float32 => {
+                for &sample in samples {
+                    writer.write_sample(sample)?;
+                }
+            }
+            WavFormat::Pcm24 => {
+                for &sample in samples {
+                    writer.write_sample(quantize(sample as f64 * PCM24_MAX, PCM24_MAX) as i32)?;
+                }
+            }
+            WavFormat::Pcm16 { dither } => {
+                for &sample in samples {
+                    let noise = if dither { self.dither.next() } else { 0.0 };
+                    writer.write_sample(quantize(sample as f64 * PCM16_MAX + noise, PCM16_MAX) as i16)?;
+                }
+            }
+        }
+        self.sample_count += samples.len() as u64;
+        Ok(())
     }
 
-    ///Write audio samples to file with auto rotation check
-    fn write_samples(&mut self, samples: &[f32]) -> Result<(), Self::Error> {
-        self.check_rotation(1)?;
-        if let Some(ref mut writer) = self.writer {
-            for &sample in samples {
-                writer.write_sample(sample)?;
+    /// Patch the RIFF/data sizes for everything written so far
+    fn commit(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+        // hound refuses to describe a partial frame, wait for the next whole one
+        if !self.sample_count.is_multiple_of(self.channels.max(1) as u64) {
+            return Ok(false);
+        }
+        match self.writer {
+            Some(ref mut writer) => {
+                writer.flush()?;
+                Ok(true)
             }
-            self.sample_count += samples.len() as u64;
-            Ok(())
-        } else {
-            Err("Not currently writing to a file".into())
+            None => Ok(false),
         }
     }
 
-    fn finish_writing(&mut self) -> Result<Option<RecordingInfo>, Self::Error> {
-        if let (Some(writer), Some(file_path)) = (self.writer.take(), self.current_file.take()) {
+    fn finish(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(writer) = self.writer.take() {
             writer.finalize()?;
-            let file_size = fs::metadata(&file_path)?.len();
-            let duration_seconds = self.sample_count as f64 / self.sample_rate as f64;
-            let info = RecordingInfo {
-                file_path,
-                duration_seconds,
-                file_size_bytes: file_size,
-                sample_rate: self.sample_rate,
-                channels: 1,
-            };
-            println!("Recording Finished: {:.2}s, {} bytes", info.duration_seconds, info.file_size_bytes);
-            self.sample_count = 0; //Reset counter
-            self.current_date = None; //Reset date
-            Ok(Some(info))
-        } else {
-            Ok(None)
         }
+        Ok(())
     }
 
-    fn is_writing(&self) -> bool {
-        self.writer.is_some()
+    /// Sizes are patched in place, the file only grows by the samples
+    fn samples_fitting(&self, room: u64, channels: u16) -> u64 {
+        let frame_bytes = self.encoding.bits_per_sample() as u64 / 8 * channels as u64;
+        room / frame_bytes * channels as u64
     }
 }
 
-impl Drop for WavFileWriter {
-    fn drop(&mut self) {
-        if self.is_writing() {
-            if let Err(e) = self.finish_writing() {
-                eprintln!("Error finalizing WAV file on drop: {}", e);
+/// Round an already scaled sample and clamp to the integer range [-max - 1, max]
+pub(crate) fn quantize(value: f64, max: f64) -> f64 {
+    value.round().clamp(-max - 1.0, max)
+}
+
+/// TPDF dither: difference of two uniform values, ±1 LSB triangular
+/// xorshift is plenty for noise and keeps the writer allocation-free
+#[derive(Debug, Clone)]
+struct Dither {
+    state: u64,
+}
+
+impl Default for Dither {
+    fn default() -> Self {
+        Self { state: 0x9E37_79B9_7F4A_7C15 }
+    }
+}
+
+impl Dither {
+
+    fn uniform(&mut self) -> f64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn next(&mut self) -> f64 {
+        self.uniform() - self.uniform()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::file_writer::RotationPolicy;
+    use crate::audio::traits::{AudioWriter, RecordingInfo};
+    use std::f32::consts::PI;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Stereo test signal: 440 Hz left, 1 kHz right at half scale, plus a clipped sample
+    fn stereo_signal(frames: usize, sample_rate: u32) -> Vec<f32> {
+        let mut samples = Vec::with_capacity(frames * 2);
+        for n in 0..frames {
+            let t = n as f32 / sample_rate as f32;
+            samples.push(0.5 * (2.0 * PI * 440.0 * t).sin());
+            samples.push(0.5 * (2.0 * PI * 1000.0 * t).sin());
+        }
+        samples[0] = 1.5;
+        samples
+    }
+
+    fn round_trip(format: WavFormat) -> (RecordingInfo, hound::WavSpec, Vec<f32>) {
+        let dir = std::env::temp_dir().join(format!("merlin_wav_{}", uuid::Uuid::new_v4()));
+        let mut writer = WavFileWriter::new(&dir).with_format(format);
+        writer.start_writing(48000, 2).unwrap();
+        writer.write_samples(&stereo_signal(4800, 48000)).unwrap();
+        let info = writer.finish_writing().unwrap().unwrap();
+
+        let mut reader = hound::WavReader::open(&info.file_path).unwrap();
+        let spec = reader.spec();
+        let samples: Vec<f32> = match spec.sample_format {
+            hound::SampleFormat::Float => reader.samples::<f32>().map(|s| s.unwrap()).collect(),
+            hound::SampleFormat::Int => {
+                // Writer maps 1.0 to the largest positive code
+                let scale = ((1i64 << (spec.bits_per_sample - 1)) - 1) as f32;
+                reader.samples::<i32>().map(|s| s.unwrap() as f32 / scale).collect()
             }
+        };
+        fs::remove_dir_all(&dir).ok();
+        (info, spec, samples)
+    }
+
+    fn max_error(samples: &[f32]) -> f32 {
+        let expected = stereo_signal(4800, 48000);
+        // Skip the deliberately clipped first sample
+        samples[1..]
+            .iter()
+            .zip(&expected[1..])
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn test_float32_round_trip() {
+        let (info, spec, samples) = round_trip(WavFormat::Float32);
+        assert_eq!(spec.bits_per_sample, 32);
+        assert_eq!(spec.sample_format, hound::SampleFormat::Float);
+        assert_eq!((info.sample_rate, info.channels), (48000, 2));
+        assert!((info.duration_seconds - 0.1).abs() < 1e-9);
+        assert_eq!(samples.len(), 9600);
+        assert_eq!(max_error(&samples), 0.0);
+    }
+
+    #[test]
+    fn test_pcm24_round_trip() {
+        let (info, spec, samples) = round_trip(WavFormat::Pcm24);
+        assert_eq!((spec.bits_per_sample, spec.channels, spec.sample_rate), (24, 2, 48000));
+        assert_eq!(info.channels, 2);
+        assert!((info.duration_seconds - 0.1).abs() < 1e-9);
+        assert!(max_error(&samples) <= 0.5 / PCM24_MAX as f32 + 1e-7);
+        // Out of range input clamps to full scale instead of wrapping
+        assert!((samples[0] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_pcm16_round_trip_with_and_without_dither() {
+        let (info, spec, plain) = round_trip(WavFormat::Pcm16 { dither: false });
+        assert_eq!((spec.bits_per_sample, spec.channels), (16, 2));
+        assert_eq!(info.channels, 2);
+        assert!(max_error(&plain) <= 0.5 / PCM16_MAX as f32 + 1e-6);
+        assert_eq!(plain[0], 1.0);
+
+        // Dither adds at most 1 LSB of noise on top of rounding
+        let (_, _, dithered) = round_trip(WavFormat::Pcm16 { dither: true });
+        let error = max_error(&dithered);
+        assert!(error > max_error(&plain));
+        assert!(error <= 1.5 / PCM16_MAX as f32 + 1e-6);
+    }
+
+    fn wav_files(dir: &std::path::Path) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "wav"))
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_rotation_by_duration_splits_on_frame_boundary() {
+        let dir = std::env::temp_dir().join(format!("merlin_wav_{}", uuid::Uuid::new_v4()));
+        let mut writer = WavFileWriter::new(&dir).with_rotation(RotationPolicy {
+            max_duration_seconds: Some(1.0),
+            ..Default::default()
+        });
+        writer.start_writing(16000, 2).unwrap();
+        // 2.5s in 300-frame buffers, which don't divide a second evenly
+        let signal: Vec<f32> = (0..16000 * 2 * 5 / 2).map(|i| i as f32 / 100_000.0).collect();
+        for chunk in signal.chunks(600) {
+            writer.write_samples(chunk).unwrap();
+        }
+        let last = writer.finish_writing().unwrap().unwrap();
+        assert_eq!(last.channels, 2);
+        assert!((last.duration_seconds - 0.5).abs() < 1e-9);
+        let rotated = writer.take_rotated();
+        assert_eq!(rotated.iter().map(|info| info.duration_seconds).collect::<Vec<_>>(), vec![1.0, 1.0]);
+        assert!(writer.take_rotated().is_empty());
+
+        // Same-second files get unique names, and nothing is lost across the cuts
+        let files = wav_files(&dir);
+        assert_eq!(files.len(), 3);
+        assert_eq!(vec![rotated[0].file_path.clone(), rotated[1].file_path.clone(), last.file_path], files);
+        let mut joined = Vec::new();
+        for file in &files {
+            let mut reader = hound::WavReader::open(file).unwrap();
+            assert_eq!(reader.spec().channels, 2);
+            joined.extend(reader.samples::<f32>().map(|s| s.unwrap()));
+        }
+        assert_eq!(joined, signal);
+        assert_eq!(hound::WavReader::open(&files[0]).unwrap().duration(), 16000);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_rotation_by_size() {
+        let dir = std::env::temp_dir().join(format!("merlin_wav_{}", uuid::Uuid::new_v4()));
+        let max_bytes = 10_000;
+        let mut writer = WavFileWriter::new(&dir)
+            .with_format(WavFormat::Pcm16 { dither: false })
+            .with_rotation(RotationPolicy { max_file_bytes: Some(max_bytes), ..Default::default() });
+        writer.start_writing(16000, 1).unwrap();
+        writer.write_samples(&vec![0.1; 16000]).unwrap();
+        writer.finish_writing().unwrap();
+
+        let files = wav_files(&dir);
+        assert!(files.len() >= 4, "{} files", files.len());
+        let mut total = 0;
+        for file in &files {
+            assert!(fs::metadata(file).unwrap().len() <= max_bytes);
+            total += hound::WavReader::open(file).unwrap().len();
         }
+        assert_eq!(total, 16000);
+        fs::remove_dir_all(&dir).ok();
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("pcm16-dither".parse(), Ok(WavFormat::Pcm16 { dither: true }));
+        assert_eq!("PCM24".parse(), Ok(WavFormat::Pcm24));
+        assert_eq!("float32".parse(), Ok(WavFormat::Float32));
+        assert!("mp3".parse::<WavFormat>().is_err());
+    }
+}
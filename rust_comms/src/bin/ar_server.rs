@@ -1,13 +1,53 @@
-use p1::ar::ARBridgeServer;
+use merlin_audio::ar::codec::{compare, sample_frame};
+use merlin_audio::ar::{ARBridgeServer, StreamConfig, DEFAULT_FRAME_SOCKET_PATH};
+use merlin_audio::monitoring::MetricsExporter;
 
 #[tokio::main]
 async fn main() -> Result<(), Box <dyn std::error::Error>> {
+    // `ar_server bench [iterations]`: size and speed of each wire format for a frame with both hands
+    if std::env::args().nth(1).as_deref() == Some("bench") {
+        let iterations = std::env::args().nth(2).and_then(|n| n.parse().ok()).unwrap_or(10_000);
+        return bench_wire_formats(iterations);
+    }
+
     println!("Merlin AR Bridge Server");
     println!();
 
-    let server = ARBridgeServer::new("0.0.0.0:8765");
+    // MERLIN_AR_COMPRESSION=1 deflates binary frames for clients that accept it
+    let server = ARBridgeServer::new("0.0.0.0:8765").with_config(StreamConfig::from_env());
+
+    // Optional OpenMetrics endpoint, MERLIN_BRIDGE_METRICS_ADDR=0.0.0.0:9465 to enable
+    if let Ok(metrics_addr) = std::env::var("MERLIN_BRIDGE_METRICS_ADDR") {
+        let exporter = MetricsExporter::new().with_bridge(server.stats());
+        if let Err(e) = exporter.serve(&metrics_addr) {
+            eprintln!("Metrics endpoint disabled ({}): {}", metrics_addr, e);
+        }
+    }
+
+    // Frames from the CV pipeline (merlin_audio.PyARPublisher.connect), MERLIN_AR_FRAME_SOCKET to move it
+    let frame_socket = std::env::var("MERLIN_AR_FRAME_SOCKET").unwrap_or_else(|_| DEFAULT_FRAME_SOCKET_PATH.to_string());
+    if let Err(e) = server.feed().listen(&frame_socket) {
+        eprintln!("AR frame socket disabled ({}): {}", frame_socket, e);
+    }
 
     // Run server
     server.run().await?;
     Ok(())
-}
\ No newline at end of file
+}
+
+fn bench_wire_formats(iterations: u32) -> Result<(), Box<dyn std::error::Error>> {
+    let frame = sample_frame(0);
+    println!("ServerMessage::Frame with 3 objects and 2 hands (21 joints), {} iterations", iterations);
+    println!("{:<16} {:>8} {:>14} {:>12} {:>12}", "encoding", "bytes", "kbit/s @30fps", "encode/s", "decode/s");
+    for report in compare(&frame, iterations)? {
+        println!(
+            "{:<16} {:>8} {:>14.1} {:>12.0} {:>12.0}",
+            report.encoding.name(),
+            report.bytes,
+            report.kbps_at(30),
+            report.encode_per_sec,
+            report.decode_per_sec
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,687 @@
+//! Command line of the rust_comms binary: live run options and the offline subcommands (no audio device needed)
+
+use merlin_audio::audio::catalog::{parse_time, CatalogEntry};
+use merlin_audio::audio::encrypted_writer::ENCRYPTED_EXTENSION;
+use merlin_audio::audio::{
+    process_directory, process_wav, read_wav, FilterConfig, KeySource, ProcessReport, RecordingCatalog, RecordingDecryptor,
+    RecordingKey, RecordingQuery,
+};
+use merlin_audio::display::sink::DEFAULT_JSON_RATE_HZ;
+use merlin_audio::display::terminal;
+use merlin_audio::display::{DisplayMode, Spectrogram, SpectrogramConfig};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Bad command line, main prints it with the usage text and exits 2
+#[derive(Debug)]
+pub struct UsageError {
+    message: String,
+    usage: &'static str,
+}
+
+impl UsageError {
+    fn new(message: impl Into<String>, usage: &'static str) -> Self {
+        Self { message: message.into(), usage }
+    }
+}
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n{}", self.message, self.usage)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
+/// Some inputs failed, each one already reported, main exits 1
+#[derive(Debug)]
+pub struct PartialFailure {
+    pub failed: usize,
+    pub total: usize,
+}
+
+impl fmt::Display for PartialFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} of {} files failed", self.failed, self.total)
+    }
+}
+
+impl std::error::Error for PartialFailure {}
+
+/// Exit status for a failed subcommand: 2 for a bad command line, 1 for anything else
+pub fn exit_code(error: &(dyn std::error::Error + 'static)) -> i32 {
+    if error.is::<UsageError>() { 2 } else { 1 }
+}
+
+pub const RECORDINGS_USAGE: &str = "\
+Usage: rust_comms recordings [list | export <dest>] [options]
+  --dir <dir>             recordings directory (default ./recordings)
+  --from <time>           recordings overlapping this time or later
+  --to <time>             recordings overlapping this time or earlier
+  --min-duration <secs>   --max-duration <secs>
+  --min-lufs <lufs>       --max-lufs <lufs>
+  --tag <tag>
+  --json                  print full sidecar metadata as JSON lines
+Times: YYYY-MM-DD, \"YYYY-MM-DD HH:MM:SS\" (local) or RFC 3339";
+
+pub const DECRYPT_USAGE: &str = "\
+Usage: rust_comms decrypt <file.menc | dir> [options]
+  --out <dir>             where decrypted WAV / FLAC files go (default ./decrypted)
+  --key-file <path>       key file the recordings were made with
+  --allow-truncated       also export recordings missing their final record
+Without --key-file the passphrase is read from MERLIN_RECORD_PASSPHRASE
+Create a key file with: rust_comms keygen <path>";
+
+pub const KEYGEN_USAGE: &str = "Usage: rust_comms keygen <path>";
+
+pub const PROCESS_USAGE: &str = "\
+Usage: rust_comms process <file.wav | dir> [options]
+  --out <dir>             where processed WAVs go (default ./processed), same file names
+  --config <file.json>    filter settings (FilterConfig fields, as in recording sidecars)
+  --gate-threshold <dB>   --gate-attack <ms>   --gate-release <ms>
+  --target <dB>           normalizer target level
+  --window <ms>           normalizer RMS window
+  --report <file.json>    also write the settings and per-file reports as JSON
+Flags override --config, anything unset uses the live defaults";
+
+pub const RUN_USAGE: &str = "\
+Usage: rust_comms [--output dashboard|line|json|spectrogram] [--rate <hz>] [spectrogram options]
+  --output <mode>   dashboard (default on a terminal), line meter (default otherwise),
+                    json: JSON lines on stdout for systemd / log tooling,
+                    spectrogram: full-screen scrolling spectrogram
+  --rate <hz>       json metrics lines per second (default 1)
+MERLIN_DISPLAY / MERLIN_TELEMETRY_RATE set the same, flags win";
+
+pub const SPECTROGRAM_USAGE: &str = "\
+Usage: rust_comms spectrogram <file.wav> [options]
+  --width <columns>       default: terminal width, else 80
+  --rows <rows>           text rows of waterfall (default 40)
+Spectrogram options (also for the live --output spectrogram):
+  --min-hz <hz>  --max-hz <hz>   frequency range (default 50-8000, capped at Nyquist)
+  --scale log|linear             frequency axis (default log)
+  --floor <dB>  --ceiling <dB>   level range of the color map (default -100..-20)
+  --fft <frames>                 FFT length (default 1024)
+  --colors heat|gray|none        color map (default heat)
+  --glyphs half|braille|shade    characters (default half blocks)";
+
+/// Env var holding the recording passphrase, read by the recorder and `decrypt`
+pub const PASSPHRASE_ENV: &str = "MERLIN_RECORD_PASSPHRASE";
+
+/// Parsed options for the live run (no subcommand)
+#[derive(Debug, PartialEq)]
+pub struct RunArgs {
+    /// None: pick from MERLIN_DISPLAY, then dashboard on a terminal, line meter otherwise
+    pub output: Option<DisplayMode>,
+    pub rate_hz: f32,
+    pub spectrogram: SpectrogramConfig,
+}
+
+fn parse_run_args(args: &[String]) -> Result<RunArgs, String> {
+    let mut parsed = RunArgs {
+        output: None,
+        rate_hz: DEFAULT_JSON_RATE_HZ,
+        spectrogram: SpectrogramConfig::default(),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--output" => parsed.output = Some(value()?.parse()?),
+            "--rate" => parsed.rate_hz = parse_number(arg, &value()?)?,
+            flag if parse_spectrogram_flag(flag, &mut value, &mut parsed.spectrogram)? => {}
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+    if !parsed.rate_hz.is_finite() || parsed.rate_hz <= 0.0 {
+        return Err(format!("--rate must be above 0, got {}", parsed.rate_hz));
+    }
+    check_spectrogram(&parsed.spectrogram)?;
+    Ok(parsed)
+}
+
+/// Apply one spectrogram option, false if `flag` isn't one
+fn parse_spectrogram_flag(
+    flag: &str,
+    value: &mut impl FnMut() -> Result<String, String>,
+    config: &mut SpectrogramConfig,
+) -> Result<bool, String> {
+    match flag {
+        "--min-hz" => config.min_hz = parse_number(flag, &value()?)?,
+        "--max-hz" => config.max_hz = parse_number(flag, &value()?)?,
+        "--scale" => config.scale = value()?.parse()?,
+        "--floor" => config.floor_db = parse_number(flag, &value()?)?,
+        "--ceiling" => config.ceiling_db = parse_number(flag, &value()?)?,
+        "--fft" => config.fft_size = parse_number(flag, &value()?)?,
+        "--colors" => config.colors = value()?.parse()?,
+        "--glyphs" => config.glyphs = value()?.parse()?,
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+fn check_spectrogram(config: &SpectrogramConfig) -> Result<(), String> {
+    if config.min_hz.is_nan() || config.max_hz.is_nan() || config.min_hz <= 0.0 || config.min_hz >= config.max_hz {
+        return Err(format!("Frequency range {}-{}Hz is empty", config.min_hz, config.max_hz));
+    }
+    if config.floor_db.is_nan() || config.ceiling_db.is_nan() || config.floor_db >= config.ceiling_db {
+        return Err(format!("--floor {} must be below --ceiling {}", config.floor_db, config.ceiling_db));
+    }
+    if !(16..=65536).contains(&config.fft_size) {
+        return Err(format!("--fft {} out of range (16-65536)", config.fft_size));
+    }
+    Ok(())
+}
+
+/// Parsed `spectrogram` command line
+#[derive(Debug, PartialEq)]
+struct SpectrogramArgs {
+    input: Option<PathBuf>,
+    width: Option<usize>,
+    rows: usize,
+    config: SpectrogramConfig,
+}
+
+fn parse_spectrogram_args(args: &[String]) -> Result<SpectrogramArgs, String> {
+    let mut parsed = SpectrogramArgs {
+        input: None,
+        width: None,
+        rows: 40,
+        config: SpectrogramConfig::default(),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--width" => parsed.width = Some(parse_number(arg, &value()?)?),
+            "--rows" => parsed.rows = parse_number(arg, &value()?)?,
+            flag if parse_spectrogram_flag(flag, &mut value, &mut parsed.config)? => {}
+            flag if flag.starts_with("--") => return Err(format!("Unknown argument: {}", flag)),
+            input if parsed.input.is_none() => parsed.input = Some(PathBuf::from(input)),
+            extra => return Err(format!("Unexpected argument: {}", extra)),
+        }
+    }
+    if parsed.input.is_none() {
+        return Err("Missing input WAV".to_string());
+    }
+    if parsed.rows == 0 || parsed.width == Some(0) {
+        return Err("--rows / --width must be above 0".to_string());
+    }
+    check_spectrogram(&parsed.config)?;
+    Ok(parsed)
+}
+
+/// Width reserved for the time labels left of the waterfall
+const TIME_LABEL_WIDTH: usize = 8;
+
+/// `rust_comms spectrogram <file.wav>`: print a recording's spectrogram, time running down
+pub fn run_spectrogram(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let args = parse_spectrogram_args(args).map_err(|e| UsageError::new(e, SPECTROGRAM_USAGE))?;
+    let input = args.input.expect("checked by parse_spectrogram_args");
+    let (spec, samples) = read_wav(&input)?;
+    let frames = samples.len() / spec.channels.max(1) as usize;
+    let duration = frames as f64 / spec.sample_rate as f64;
+    let width = args.width.unwrap_or_else(|| terminal::stdout_size().map_or(80, |(columns, _)| columns as usize));
+    let columns = width.saturating_sub(TIME_LABEL_WIDTH).max(10);
+
+    let mut spectrogram = Spectrogram::new(args.config);
+    let slices = args.rows * spectrogram.slices_per_row();
+    spectrogram.push_recording(&samples, spec.channels, spec.sample_rate, columns, slices);
+    let (min_hz, max_hz) = spectrogram.range_hz();
+    let shown = SpectrogramConfig {
+        min_hz,
+        max_hz,
+        ..spectrogram.config().clone()
+    };
+    println!(
+        "{}  {} Hz, {} ch, {:.1}s  {}",
+        input.display(),
+        spec.sample_rate,
+        spec.channels,
+        duration,
+        shown
+    );
+    for (row, line) in spectrogram.render(columns, args.rows).iter().enumerate() {
+        // Time at the end of the row
+        let seconds = duration * (row + 1) as f64 / args.rows as f64;
+        println!("{:>6.1}s {}", seconds, line);
+    }
+    println!("{:width$}{}", "", spectrogram.axis(columns), width = TIME_LABEL_WIDTH);
+    Ok(())
+}
+
+/// Live run options from the command line, MERLIN_DISPLAY / MERLIN_TELEMETRY_RATE fill in what it leaves out
+pub fn run_args(args: &[String]) -> Result<RunArgs, UsageError> {
+    let mut env_args = Vec::new();
+    if let Ok(mode) = std::env::var("MERLIN_DISPLAY") {
+        env_args.extend(["--output".to_string(), mode]);
+    }
+    if let Ok(rate) = std::env::var("MERLIN_TELEMETRY_RATE") {
+        env_args.extend(["--rate".to_string(), rate]);
+    }
+    // Flags come after the env values so they win
+    env_args.extend(args.iter().cloned());
+    parse_run_args(&env_args).map_err(|e| UsageError::new(e, RUN_USAGE))
+}
+
+/// Parsed `decrypt` command line
+#[derive(Debug, Default, PartialEq)]
+struct DecryptArgs {
+    input: Option<PathBuf>,
+    out: Option<PathBuf>,
+    key_file: Option<PathBuf>,
+    allow_truncated: bool,
+}
+
+fn parse_decrypt_args(args: &[String]) -> Result<DecryptArgs, String> {
+    let mut parsed = DecryptArgs::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().map(PathBuf::from).ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--out" => parsed.out = Some(value()?),
+            "--key-file" => parsed.key_file = Some(value()?),
+            "--allow-truncated" => parsed.allow_truncated = true,
+            flag if flag.starts_with("--") => return Err(format!("Unknown argument: {}", flag)),
+            input if parsed.input.is_none() => parsed.input = Some(PathBuf::from(input)),
+            extra => return Err(format!("Unexpected argument: {}", extra)),
+        }
+    }
+    if parsed.input.is_none() {
+        return Err("Missing input file or directory".to_string());
+    }
+    Ok(parsed)
+}
+
+/// `rust_comms decrypt ...`: authenticate and export encrypted recordings in their original format
+/// A recording that fails authentication is reported and skipped, the exit status says if any did
+pub fn run_decrypt(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let args = parse_decrypt_args(args).map_err(|e| UsageError::new(e, DECRYPT_USAGE))?;
+    let source = match args.key_file {
+        Some(path) => KeySource::KeyFile(path),
+        None => KeySource::Passphrase(
+            std::env::var(PASSPHRASE_ENV)
+                .map_err(|_| format!("Pass --key-file or set {}", PASSPHRASE_ENV))?
+                .into(),
+        ),
+    };
+    let input = args.input.expect("checked by parse_decrypt_args");
+    let files = if input.is_dir() {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(&input)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == ENCRYPTED_EXTENSION))
+            .collect();
+        files.sort();
+        files
+    } else {
+        vec![input]
+    };
+    let out = args.out.unwrap_or_else(|| PathBuf::from("./decrypted"));
+
+    let mut decryptor = RecordingDecryptor::new(source);
+    let mut failures = 0;
+    for file in &files {
+        match decrypt_one(&mut decryptor, file, &out, args.allow_truncated) {
+            Ok(message) => println!("{}", message),
+            Err(e) => {
+                eprintln!("FAILED    {:?}: {}", file, e);
+                failures += 1;
+            }
+        }
+    }
+    println!("{} of {} recordings decrypted to {:?}", files.len() - failures, files.len(), out);
+    if failures > 0 {
+        return Err(PartialFailure { failed: failures, total: files.len() }.into());
+    }
+    Ok(())
+}
+
+fn decrypt_one(decryptor: &mut RecordingDecryptor, file: &Path, out: &Path, allow_truncated: bool) -> Result<String, Box<dyn std::error::Error>> {
+    // Streams record by record, the export only gets its real name once every record authenticated
+    let (target, recording) = decryptor.export(file, out, allow_truncated).map_err(|e| match e.kind() {
+        std::io::ErrorKind::UnexpectedEof => format!("{}, rerun with --allow-truncated to export anyway", e),
+        _ => e.to_string(),
+    })?;
+    let status = if recording.complete { "OK" } else { "TRUNCATED" };
+    Ok(format!("{:<9} {:?} -> {:?} ({} bytes)", status, file, target, recording.bytes))
+}
+
+/// `rust_comms keygen <path>`: new random key file for MERLIN_RECORD_KEY_FILE
+pub fn run_keygen(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(path) = args.first() else {
+        return Err(UsageError::new("Missing key file path", KEYGEN_USAGE).into());
+    };
+    RecordingKey::generate_key_file(path)?;
+    println!("Wrote key file {} (keep a copy somewhere safe, recordings can't be decrypted without it)", path);
+    Ok(())
+}
+
+/// Parsed `process` command line
+#[derive(Debug, Default, PartialEq)]
+struct ProcessArgs {
+    input: Option<PathBuf>,
+    out: Option<PathBuf>,
+    report: Option<PathBuf>,
+    filters: FilterConfig,
+}
+
+fn parse_process_args(args: &[String]) -> Result<ProcessArgs, String> {
+    let mut parsed = ProcessArgs::default();
+    // --config is applied first so flags override it wherever they appear
+    if let Some(position) = args.iter().position(|arg| arg == "--config") {
+        let path = args.get(position + 1).ok_or("--config needs a value")?;
+        let json = std::fs::read_to_string(path).map_err(|e| format!("Can't read {}: {}", path, e))?;
+        parsed.filters = serde_json::from_str(&json).map_err(|e| format!("Bad filter config {}: {}", path, e))?;
+    }
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--out" => parsed.out = Some(PathBuf::from(value()?)),
+            "--report" => parsed.report = Some(PathBuf::from(value()?)),
+            "--config" => {
+                value()?;
+            }
+            "--gate-threshold" => parsed.filters.gate_threshold_db = parse_number(arg, &value()?)?,
+            "--gate-attack" => parsed.filters.gate_attack_ms = parse_number(arg, &value()?)?,
+            "--gate-release" => parsed.filters.gate_release_ms = parse_number(arg, &value()?)?,
+            "--target" => parsed.filters.normalizer_target_db = parse_number(arg, &value()?)?,
+            "--window" => parsed.filters.normalizer_window_ms = parse_number(arg, &value()?)?,
+            flag if flag.starts_with("--") => return Err(format!("Unknown argument: {}", flag)),
+            input if parsed.input.is_none() => parsed.input = Some(PathBuf::from(input)),
+            extra => return Err(format!("Unexpected argument: {}", extra)),
+        }
+    }
+    if parsed.input.is_none() {
+        return Err("Missing input file or directory".to_string());
+    }
+    Ok(parsed)
+}
+
+/// `rust_comms process ...`: run WAVs through the filter chain offline and report before/after levels
+/// Run it twice with different settings and --report to compare parameter sets on the same files
+pub fn run_process(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let args = parse_process_args(args).map_err(|e| UsageError::new(e, PROCESS_USAGE))?;
+    let input = args.input.expect("checked by parse_process_args");
+    let out = args.out.unwrap_or_else(|| PathBuf::from("./processed"));
+    let f = &args.filters;
+    println!(
+        "Gate {}dB ({}ms attack, {}ms release), normalizer {}dB ({}ms window)",
+        f.gate_threshold_db, f.gate_attack_ms, f.gate_release_ms, f.normalizer_target_db, f.normalizer_window_ms
+    );
+
+    let results = if input.is_dir() {
+        if same_dir(&input, &out) {
+            return Err("--out must differ from the input directory (files keep their names)".into());
+        }
+        process_directory(&input, &out, f)?
+    } else {
+        let output = out.join(input.file_name().ok_or("Input has no file name")?);
+        if output == input {
+            return Err("--out must differ from the input's directory (files keep their names)".into());
+        }
+        let report = process_wav(&input, &output, f).map_err(|e| e.to_string());
+        vec![(input, report)]
+    };
+
+    let total = results.len();
+    let mut reports = Vec::new();
+    let mut failures = 0;
+    for (path, result) in results {
+        match result {
+            Ok(report) => {
+                println!("{}", report_line(&report));
+                reports.push(report);
+            }
+            Err(e) => {
+                eprintln!("FAILED    {:?}: {}", path, e);
+                failures += 1;
+            }
+        }
+    }
+    if !reports.is_empty() {
+        println!("{}", aggregate_line(&reports));
+    }
+    if let Some(path) = args.report {
+        let report = serde_json::json!({ "filters": args.filters, "files": reports });
+        std::fs::write(&path, serde_json::to_string_pretty(&report)?)?;
+        println!("Report written to {:?}", path);
+    }
+    if failures > 0 {
+        return Err(PartialFailure { failed: failures, total }.into());
+    }
+    Ok(())
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn report_line(report: &ProcessReport) -> String {
+    let (b, a) = (&report.before, &report.after);
+    let name = report.input.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    format!(
+        "{}  {:.1}s  rms {:.1} -> {:.1} dB  peak {:.1} -> {:.1} dB  snr {:.1} -> {:.1} dB  gate open {:.0}%  clipped {} -> {}",
+        name,
+        report.duration_seconds,
+        b.rms_db,
+        a.rms_db,
+        b.peak_db,
+        a.peak_db,
+        b.snr_db,
+        a.snr_db,
+        report.gate_open_percent,
+        b.clipped_samples,
+        a.clipped_samples
+    )
+}
+
+fn aggregate_line(reports: &[ProcessReport]) -> String {
+    let count = reports.len() as f32;
+    let mean = |value: fn(&ProcessReport) -> f32| reports.iter().map(value).sum::<f32>() / count;
+    format!(
+        "{} files  mean rms {:.1} -> {:.1} dB  mean snr {:.1} -> {:.1} dB  mean gate open {:.0}%  clipped {} -> {}",
+        reports.len(),
+        mean(|r| r.before.rms_db),
+        mean(|r| r.after.rms_db),
+        mean(|r| r.before.snr_db),
+        mean(|r| r.after.snr_db),
+        mean(|r| r.gate_open_percent),
+        reports.iter().map(|r| r.before.clipped_samples).sum::<u64>(),
+        reports.iter().map(|r| r.after.clipped_samples).sum::<u64>()
+    )
+}
+
+/// Parsed `recordings` command line
+#[derive(Debug, Default, PartialEq)]
+struct RecordingsArgs {
+    dir: Option<String>,
+    export_to: Option<String>,
+    query: RecordingQuery,
+    json: bool,
+}
+
+fn parse_recordings_args(args: &[String]) -> Result<RecordingsArgs, String> {
+    let mut parsed = RecordingsArgs::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "list" => {}
+            "export" => parsed.export_to = Some(value()?),
+            "--dir" => parsed.dir = Some(value()?),
+            "--from" => parsed.query.from = Some(parse_time(&value()?)?),
+            "--to" => parsed.query.to = Some(parse_time(&value()?)?),
+            "--min-duration" => parsed.query.min_duration_seconds = Some(parse_number(arg, &value()?)?),
+            "--max-duration" => parsed.query.max_duration_seconds = Some(parse_number(arg, &value()?)?),
+            "--min-lufs" => parsed.query.min_lufs = Some(parse_number(arg, &value()?)?),
+            "--max-lufs" => parsed.query.max_lufs = Some(parse_number(arg, &value()?)?),
+            "--tag" => parsed.query.tag = Some(value()?),
+            "--json" => parsed.json = true,
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+    Ok(parsed)
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("{} expects a number, got {}", flag, value))
+}
+
+/// `rust_comms recordings ...`: list, filter and export recordings from their sidecars
+pub fn run_recordings(args: &[String], default_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let args = parse_recordings_args(args).map_err(|e| UsageError::new(e, RECORDINGS_USAGE))?;
+    let catalog = RecordingCatalog::open(args.dir.as_deref().unwrap_or(default_dir))?;
+    let entries = catalog.query(&args.query);
+
+    if let Some(dest) = args.export_to {
+        let copied = RecordingCatalog::export(&entries, &dest)?;
+        println!("Exported {} recordings to {}", copied.len(), dest);
+        return Ok(());
+    }
+
+    for entry in &entries {
+        if args.json {
+            println!("{}", serde_json::to_string(&entry.metadata)?);
+        } else {
+            println!("{}", summary_line(entry));
+        }
+    }
+    if !args.json {
+        let total: f64 = entries.iter().map(|e| e.metadata.duration_seconds).sum();
+        println!("{} recordings, {:.1}s total", entries.len(), total);
+    }
+    Ok(())
+}
+
+fn summary_line(entry: &CatalogEntry) -> String {
+    let m = &entry.metadata;
+    let lufs = m
+        .levels
+        .and_then(|levels| levels.integrated_lufs)
+        .map_or("   -- LUFS".to_string(), |lufs| format!("{:6.1} LUFS", lufs));
+    let mut line = format!(
+        "{}  {:8.1}s  {}  {}",
+        m.started_at.format("%Y-%m-%d %H:%M:%S"),
+        m.duration_seconds,
+        lufs,
+        m.file
+    );
+    if !m.tags.is_empty() {
+        line.push_str(&format!("  [{}]", m.tags.join(", ")));
+    }
+    if !entry.has_sidecar {
+        line.push_str("  (no sidecar)");
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use merlin_audio::display::{ColorMap, FrequencyScale, Glyphs};
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_recordings_args() {
+        let parsed = parse_recordings_args(&args("export /tmp/out --tag wake --min-duration 2.5 --max-lufs -18 --from 2026-01-01")).unwrap();
+        assert_eq!(parsed.export_to.as_deref(), Some("/tmp/out"));
+        assert_eq!(parsed.query.tag.as_deref(), Some("wake"));
+        assert_eq!(parsed.query.min_duration_seconds, Some(2.5));
+        assert_eq!(parsed.query.max_lufs, Some(-18.0));
+        assert_eq!(parsed.query.from, Some(parse_time("2026-01-01").unwrap()));
+
+        assert!(parse_recordings_args(&args("--min-lufs loud")).is_err());
+        assert!(parse_recordings_args(&args("--tag")).is_err());
+        assert!(parse_recordings_args(&args("--bogus")).is_err());
+    }
+
+    #[test]
+    fn test_bad_command_lines_exit_2() {
+        for result in [
+            run_recordings(&args("--bogus"), "/nonexistent"),
+            run_decrypt(&args("")),
+            run_keygen(&args("")),
+            run_process(&args("--out /tmp")),
+            run_spectrogram(&args("--rows 0")),
+        ] {
+            let error = result.unwrap_err();
+            assert!(error.is::<UsageError>(), "{}", error);
+            assert_eq!(exit_code(error.as_ref()), 2);
+        }
+        for rate in ["0", "NaN", "inf", "-1"] {
+            assert!(run_args(&args(&format!("--rate {}", rate))).is_err(), "{}", rate);
+        }
+        assert_eq!(exit_code(&PartialFailure { failed: 1, total: 3 }), 1);
+    }
+
+    #[test]
+    fn test_parse_decrypt_args() {
+        let parsed = parse_decrypt_args(&args("recordings --key-file /etc/merlin.key --allow-truncated")).unwrap();
+        assert_eq!(parsed.input, Some(PathBuf::from("recordings")));
+        assert_eq!(parsed.key_file, Some(PathBuf::from("/etc/merlin.key")));
+        assert!(parsed.allow_truncated);
+        assert!(parse_decrypt_args(&args("--out /tmp")).is_err());
+        assert!(parse_decrypt_args(&args("a.menc b.menc")).is_err());
+    }
+
+    #[test]
+    fn test_parse_run_args() {
+        let defaults = parse_run_args(&args("")).unwrap();
+        assert_eq!((defaults.output, defaults.rate_hz), (None, 1.0));
+        assert_eq!(defaults.spectrogram, SpectrogramConfig::default());
+        let parsed = parse_run_args(&args("--output line --output json --rate 5")).unwrap();
+        assert_eq!((parsed.output, parsed.rate_hz), (Some(DisplayMode::Json), 5.0));
+        assert!(parse_run_args(&args("--output html")).is_err());
+        assert!(parse_run_args(&args("--rate 0")).is_err());
+        assert!(parse_run_args(&args("--rate")).is_err());
+        assert!(parse_run_args(&args("--verbose")).is_err());
+    }
+
+    #[test]
+    fn test_parse_spectrogram_args() {
+        let parsed = parse_spectrogram_args(&args("take.wav --max-hz 4000 --scale linear --glyphs braille --colors none --rows 20"))
+        .unwrap();
+        assert_eq!(parsed.input, Some(PathBuf::from("take.wav")));
+        assert_eq!((parsed.width, parsed.rows), (None, 20));
+        assert_eq!(parsed.config.max_hz, 4000.0);
+        assert_eq!(parsed.config.scale, FrequencyScale::Linear);
+        assert_eq!(parsed.config.glyphs, Glyphs::Braille);
+        assert_eq!(parsed.config.colors, ColorMap::None);
+
+        assert!(parse_spectrogram_args(&args("--rows 20")).is_err());
+        assert!(parse_spectrogram_args(&args("take.wav --min-hz 9000")).is_err());
+        assert!(parse_spectrogram_args(&args("take.wav --floor -10 --ceiling -20")).is_err());
+        assert!(parse_spectrogram_args(&args("take.wav --scale mel")).is_err());
+
+        let live = parse_run_args(&args("--output waterfall --glyphs shade")).unwrap();
+        assert_eq!(live.output, Some(DisplayMode::Spectrogram));
+        assert_eq!(live.spectrogram.glyphs, Glyphs::Shade);
+    }
+
+    #[test]
+    fn test_parse_process_args() {
+        let config = std::env::temp_dir().join(format!("merlin_filters_{}.json", std::process::id()));
+        let mut filters = FilterConfig { gate_threshold_db: -50.0, ..FilterConfig::default() };
+        std::fs::write(&config, serde_json::to_string(&filters).unwrap()).unwrap();
+
+        let line = format!("--target -16 takes --config {} --out /tmp/out --window 400", config.display());
+        let parsed = parse_process_args(&args(&line)).unwrap();
+        filters.normalizer_target_db = -16.0;
+        filters.normalizer_window_ms = 400.0;
+        assert_eq!(parsed.filters, filters);
+        assert_eq!(parsed.input, Some(PathBuf::from("takes")));
+        assert_eq!(parsed.out, Some(PathBuf::from("/tmp/out")));
+        std::fs::remove_file(&config).unwrap();
+
+        assert_eq!(parse_process_args(&args("a.wav")).unwrap().filters, FilterConfig::default());
+        assert!(parse_process_args(&args("a.wav --gate-threshold loud")).is_err());
+        assert!(parse_process_args(&args("--config /nonexistent.json a.wav")).is_err());
+        assert!(parse_process_args(&args("--out /tmp")).is_err());
+    }
+}
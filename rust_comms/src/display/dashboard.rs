@@ -0,0 +1,488 @@
+use crate::ar::ClientStats;
+use crate::audio::{AudioMetrics, ReplaySnapshot};
+use super::meter::AudioMeter;
+use super::sink::{DisplayAction, DisplaySink, DisplaySnapshot, RecordingStatus};
+use super::spectrum::SpectrumAnalyzer;
+use super::terminal::{sanitize, LogBuffer, Terminal, DEFAULT_LOG_LINES};
+use std::io;
+use std::time::{Duration, Instant};
+
+/// Level bar and spectrum span this many dB below full scale
+const METER_RANGE_DB: f32 = 60.0;
+/// Spectrum display range
+const SPECTRUM_MIN_HZ: f32 = 50.0;
+const SPECTRUM_MAX_HZ: f32 = 20_000.0;
+const SPECTRUM_FFT_SIZE: usize = 2048;
+/// How far a spectrum band may fall per frame, keeps the display readable
+const SPECTRUM_FALL_DB: f32 = 4.0;
+/// Client rows shown before "+N more"
+const MAX_CLIENT_ROWS: usize = 4;
+/// Eighth blocks for bar heights, index = eighths filled
+const BLOCKS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RED: &str = "\x1b[31m";
+const DIM: &str = "\x1b[2m";
+const BOLD: &str = "\x1b[1m";
+const INVERSE: &str = "\x1b[7m";
+const RESET: &str = "\x1b[0m";
+
+/// Dashboard redraw interval (20fps), also how often keys are read
+pub const DASHBOARD_FRAME: Duration = Duration::from_millis(50);
+/// Audio handed to the spectrum each frame
+const SPECTRUM_SECONDS: f32 = 0.05;
+
+impl DisplayAction {
+    /// r: arm/disarm recording, b: filter bypass, d: next input device, s: save replay, q / Ctrl-C: quit
+    pub fn from_key(key: u8) -> Option<Self> {
+        match key {
+            b'r' | b'R' => Some(DisplayAction::ToggleRecording),
+            b'b' | b'B' => Some(DisplayAction::ToggleBypass),
+            b'd' | b'D' => Some(DisplayAction::NextDevice),
+            b's' | b'S' => Some(DisplayAction::SaveReplay),
+            b'q' | b'Q' | 0x03 => Some(DisplayAction::Quit),
+            _ => None,
+        }
+    }
+}
+
+/// Dashboard
+///
+/// Full-screen view of the live pipeline, rendered to a string of ANSI rows
+/// -Level meter per channel (AudioMeter), gate / normalizer state
+/// -Spectrum of the processed audio on a log frequency axis
+/// -Recording status, AR bridge clients with per-client FPS, log pane, key help
+/// -Rendering is pure (no terminal access) so it can be tested, Terminal puts it on screen
+pub struct Dashboard {
+    analyzer: SpectrumAnalyzer,
+    bands: Vec<f32>,
+    meter: AudioMeter,
+    last_frame: Option<Instant>,
+}
+
+impl Dashboard {
+    pub fn new() -> Self {
+        Self {
+            analyzer: SpectrumAnalyzer::new(SPECTRUM_FFT_SIZE),
+            bands: Vec::new(),
+            meter: AudioMeter::new(),
+            last_frame: None,
+        }
+    }
+
+    /// Render one frame of exactly `height` rows, none wider than `width` columns
+    /// `log` lines fill the log pane, newest last
+    pub fn render(&mut self, snapshot: &DisplaySnapshot, log: &[String], width: u16, height: u16) -> String {
+        let width = width.max(40) as usize;
+        let height = height.max(12) as usize;
+        let m = &snapshot.metrics;
+
+        let mut top = Vec::new();
+        let bypass = if m.filters.bypassed { format!("  {}{} FILTERS BYPASSED {}", RED, INVERSE, RESET) } else { String::new() };
+        top.push(format!(
+            "{}{}{}{}",
+            BOLD,
+            fit(&format!("MERLIN audio  {}", snapshot.device), width.saturating_sub(22)),
+            RESET,
+            bypass
+        ));
+        top.extend(self.level_rows(m, width));
+        top.push(gate_row(m, width));
+        let t = &m.telemetry;
+        top.push(fit(
+            &format!(
+                "LUFS   M {:6.1}  S {:6.1}  I {:6.1}   lat {:.1}ms  load {:.0}%  err {}  disc {}  over {}  drop {}  clip {}",
+                m.loudness.momentary_lufs.max(-99.9),
+                m.loudness.short_term_lufs.max(-99.9),
+                m.loudness.integrated_lufs.max(-99.9),
+                t.latency_ms,
+                t.load * 100.0,
+                t.backend_errors,
+                t.disconnects,
+                t.overruns,
+                t.dropped_buffers,
+                m.levels.clipped_total
+            ),
+            width,
+        ));
+
+        let mut bottom = Vec::new();
+        bottom.extend(recording_rows(&snapshot.recording, width));
+        bottom.extend(client_rows(snapshot.clients.as_deref(), width));
+        let help = format!("{}[r] record  [b] bypass filters  [d] next device  [s] save replay  [q] quit{}", DIM, RESET);
+
+        // Spectrum and log share whatever rows are left, log gets at least 2
+        let fixed = top.len() + bottom.len() + 4; // spectrum title, log title, blank, help
+        let free = height.saturating_sub(fixed);
+        let spectrum_rows = (free / 2).clamp(2, 10).min(free.saturating_sub(2));
+        let log_rows = free - spectrum_rows;
+
+        let mut rows = top;
+        rows.push(format!(
+            "{}Spectrum {:.0}Hz-{:.0}kHz{}",
+            DIM,
+            SPECTRUM_MIN_HZ,
+            SPECTRUM_MAX_HZ / 1000.0,
+            RESET
+        ));
+        rows.extend(self.spectrum_rows(snapshot.audio.as_ref(), width, spectrum_rows));
+        rows.extend(bottom);
+        rows.push(format!("{}Log{}", DIM, RESET));
+        for i in 0..log_rows {
+            let index = (log.len() + i).checked_sub(log_rows);
+            rows.push(index.map_or(String::new(), |index| log_row(&log[index], width)));
+        }
+        rows.push(String::new());
+        rows.push(fit_ansi(&help, width));
+        rows.truncate(height);
+        rows.join("\n")
+    }
+
+    /// One meter row per channel, bars stretched to the width
+    fn level_rows(&mut self, m: &AudioMetrics, width: usize) -> Vec<String> {
+        let now = Instant::now();
+        let elapsed = self.last_frame.map_or(Duration::ZERO, |last| now - last);
+        self.last_frame = Some(now);
+        // Label, brackets, readouts and CLIP take 37 columns
+        self.meter.set_bar_length(width.saturating_sub(37).max(10));
+        self.meter
+            .channel_rows(m, elapsed)
+            .iter()
+            .map(|row| fit_ansi(row, width))
+            .collect()
+    }
+
+    fn spectrum_rows(&mut self, audio: Option<&ReplaySnapshot>, width: usize, rows: usize) -> Vec<String> {
+        let columns = width.saturating_sub(1);
+        let fresh = match audio {
+            Some(audio) if !audio.samples.is_empty() => self.analyzer.log_bands(
+                &audio.samples,
+                audio.channels,
+                audio.sample_rate,
+                columns,
+                SPECTRUM_MIN_HZ,
+                SPECTRUM_MAX_HZ,
+            ),
+            _ => vec![-METER_RANGE_DB; columns],
+        };
+        if self.bands.len() != columns {
+            self.bands = fresh;
+        } else {
+            for (shown, new) in self.bands.iter_mut().zip(fresh) {
+                *shown = new.max(*shown - SPECTRUM_FALL_DB);
+            }
+        }
+
+        // Eighths of a row per band, top row first
+        let eighths: Vec<usize> = self
+            .bands
+            .iter()
+            .map(|&db| (fraction(db) * (rows * 8) as f32).round() as usize)
+            .collect();
+        (0..rows)
+            .rev()
+            .map(|row| {
+                let color = match row * 3 / rows.max(1) {
+                    0 => GREEN,
+                    1 => YELLOW,
+                    _ => RED,
+                };
+                let cells: String = eighths
+                    .iter()
+                    .map(|&filled| BLOCKS[filled.saturating_sub(row * 8).min(8)])
+                    .collect();
+                format!(" {}{}{}", color, cells, RESET)
+            })
+            .collect()
+    }
+}
+
+impl Default for Dashboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Dashboard on the terminal, owns the screen and keyboard while it lives
+pub struct DashboardSink {
+    terminal: Terminal,
+    dashboard: Dashboard,
+    log: LogBuffer,
+}
+
+impl DashboardSink {
+    /// Take over the terminal, printed output goes to the log pane until drop
+    pub fn enter() -> io::Result<Self> {
+        let log = LogBuffer::new(DEFAULT_LOG_LINES);
+        Ok(Self {
+            terminal: Terminal::enter(log.clone())?,
+            dashboard: Dashboard::new(),
+            log,
+        })
+    }
+}
+
+impl DisplaySink for DashboardSink {
+    fn update(&mut self, snapshot: &DisplaySnapshot) -> io::Result<()> {
+        let (width, height) = self.terminal.size();
+        let log = self.log.tail(height as usize);
+        let frame = self.dashboard.render(snapshot, &log, width, height);
+        self.terminal.draw(&frame)
+    }
+
+    fn actions(&mut self) -> Vec<DisplayAction> {
+        self.terminal.read_input().into_iter().filter_map(DisplayAction::from_key).collect()
+    }
+
+    fn interval(&self) -> Duration {
+        DASHBOARD_FRAME
+    }
+
+    fn audio_seconds(&self) -> Option<f32> {
+        Some(SPECTRUM_SECONDS)
+    }
+}
+
+/// Position of `db` on the meter scale [0.0, 1.0]
+fn fraction(db: f32) -> f32 {
+    ((db + METER_RANGE_DB) / METER_RANGE_DB).clamp(0.0, 1.0)
+}
+
+fn gate_row(m: &AudioMetrics, width: usize) -> String {
+    let f = &m.filters;
+    let (state, color) = if f.bypassed {
+        ("BYPASS", DIM)
+    } else if f.gate_open {
+        ("OPEN  ", GREEN)
+    } else {
+        ("CLOSED", YELLOW)
+    };
+    let gain_cells = 10;
+    let gain_filled = (f.gate_gain.clamp(0.0, 1.0) * gain_cells as f32).round() as usize;
+    let text = format!(
+        "Gate   {}{}{} [{}{}] {:.2}   Normalizer {:+6.1} dB   above gate {:.0}% (10s)",
+        color,
+        state,
+        RESET,
+        "█".repeat(gain_filled),
+        " ".repeat(gain_cells - gain_filled),
+        f.gate_gain,
+        f.normalizer_gain_db,
+        m.levels.last_10s.above_gate_fraction * 100.0
+    );
+    fit_ansi(&text, width)
+}
+
+fn recording_rows(status: &RecordingStatus, width: usize) -> Vec<String> {
+    let first = match &status.file {
+        Some(file) => format!(
+            "Recording  {}● REC{} {:.1}s  {}  {}",
+            RED,
+            RESET,
+            status.seconds,
+            status.format,
+            file.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+        ),
+        None if status.armed => format!("Recording  {}● ARMED{} waiting for audio  {}", RED, RESET, status.format),
+        None => format!("Recording  {}idle{}  {}", DIM, RESET, status.format),
+    };
+    let mut second = match &status.last {
+        Some(last) => format!(
+            "           last: {} ({:.1}s, {:.1} MB)",
+            last.file_path.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned()),
+            last.duration_seconds,
+            last.file_size_bytes as f64 / (1024.0 * 1024.0)
+        ),
+        None => String::new(),
+    };
+    if status.dropped_buffers > 0 {
+        second.push_str(&format!("  {} buffers dropped", status.dropped_buffers));
+    }
+    vec![fit_ansi(&first, width), fit(&second, width)]
+}
+
+fn client_rows(clients: Option<&[ClientStats]>, width: usize) -> Vec<String> {
+    let Some(clients) = clients else {
+        let text = format!("{}AR bridge  not running here (set MERLIN_AR_BRIDGE_ADDR){}", DIM, RESET);
+        return vec![fit_ansi(&text, width)];
+    };
+    let mut rows = vec![format!("AR bridge  {} client{}", clients.len(), if clients.len() == 1 { "" } else { "s" })];
+    for client in clients.iter().take(MAX_CLIENT_ROWS) {
+        let up = client.connected_at.elapsed().as_secs();
+        rows.push(fit(
+            &format!(
+                "  {:<20} {:5.1} fps  {:>8} frames  {} err  up {:02}:{:02}:{:02}",
+                fit(client.name(), 20),
+                client.fps,
+                client.frames_sent,
+                client.send_errors,
+                up / 3600,
+                up / 60 % 60,
+                up % 60
+            ),
+            width,
+        ));
+    }
+    if clients.len() > MAX_CLIENT_ROWS {
+        rows.push(format!("  +{} more", clients.len() - MAX_CLIENT_ROWS));
+    }
+    rows
+}
+
+fn log_row(line: &str, width: usize) -> String {
+    let lower = line.to_ascii_lowercase();
+    let text = fit(line, width);
+    if lower.contains("error") || lower.contains("failed") || lower.contains("panicked") {
+        format!("{}{}{}", RED, text, RESET)
+    } else {
+        text
+    }
+}
+
+/// Printed width (ANSI sequences don't count)
+fn visible_width(text: &str) -> usize {
+    sanitize(text).chars().count()
+}
+
+/// Truncate plain text to `width` characters
+fn fit(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(width.saturating_sub(1)).collect();
+    out.push('…');
+    out
+}
+
+/// Truncate text containing ANSI sequences to `width` visible characters
+fn fit_ansi(text: &str, width: usize) -> String {
+    if visible_width(text) <= width {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut shown = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            out.push(c);
+            for c in chars.by_ref() {
+                out.push(c);
+                if ('@'..='~').contains(&c) && c != '[' {
+                    break;
+                }
+            }
+        } else if shown + 1 < width {
+            out.push(c);
+            shown += 1;
+        } else {
+            break;
+        }
+    }
+    out.push('…');
+    out.push_str(RESET);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ar::BridgeStats;
+    use std::path::PathBuf;
+
+    fn log() -> Vec<String> {
+        (0..30).map(|i| format!("line {}", i)).collect()
+    }
+
+    fn snapshot() -> DisplaySnapshot {
+        let mut metrics = AudioMetrics::new();
+        metrics.db = -30.0;
+        metrics.peak = 0.251; // -12dB
+        metrics.levels.clipped_total = 3;
+        metrics.filters.gate_open = true;
+        metrics.filters.gate_gain = 0.5;
+        metrics.filters.normalizer_gain_db = 6.0;
+        let tone: Vec<f32> = (0..4096).map(|i| 0.5 * (i as f32 * 0.2).sin()).collect();
+        let stats = BridgeStats::new();
+        stats.client_connected("session-1");
+        stats.set_client_id("session-1", "quest-1");
+        let mut client = stats.clients().remove(0);
+        client.fps = 29.7;
+        client.frames_sent = 900;
+        DisplaySnapshot {
+            metrics,
+            device: "USB Mic".to_string(),
+            audio: Some(ReplaySnapshot {
+                samples: tone,
+                sample_rate: 48000,
+                channels: 1,
+            }),
+            recording: RecordingStatus {
+                armed: true,
+                file: Some(PathBuf::from("recordings/audio_20260101_120000.flac")),
+                seconds: 12.5,
+                format: "flac".to_string(),
+                ..Default::default()
+            },
+            clients: Some(vec![client]),
+        }
+    }
+
+    #[test]
+    fn test_render_fits_and_shows_every_panel() {
+        let mut dashboard = Dashboard::new();
+        let frame = dashboard.render(&snapshot(), &log(), 100, 40);
+        let rows: Vec<&str> = frame.lines().collect();
+        assert_eq!(rows.len(), 40);
+        assert!(rows.iter().all(|row| visible_width(row) <= 100), "{}", frame);
+
+        let plain = sanitize(&frame.replace('\n', " | "));
+        assert!(plain.contains("MERLIN audio  USB Mic"));
+        assert!(plain.contains("-30.0 dB pk  -12.0"));
+        assert!(plain.contains("drop 0  clip 3"));
+        assert!(plain.contains("OPEN"));
+        assert!(plain.contains("Normalizer   +6.0 dB"));
+        assert!(plain.contains("● REC 12.5s  flac  audio_20260101_120000.flac"));
+        assert!(plain.contains("quest-1"));
+        assert!(plain.contains("29.7 fps"));
+        assert!(plain.contains("line 29") && !plain.contains("line 0 "));
+        assert!(plain.contains("[r] record"));
+        assert!(!plain.contains("BYPASSED"));
+
+        // Level bar: -30dB fills half of the bar, peak marker at -12dB
+        let level = sanitize(rows[1]);
+        let bar: Vec<char> = level[level.find('[').unwrap() + 1..level.find(']').unwrap()].chars().collect();
+        assert_eq!(bar.iter().filter(|&&c| c == '█').count(), (bar.len() as f32 * 0.5).round() as usize);
+        assert_eq!(bar.iter().position(|&c| c == '|'), Some((bar.len() as f32 * 0.8).round() as usize));
+
+        // Tone shows up as a tall column in the spectrum
+        assert!(plain.contains('█'));
+    }
+
+    #[test]
+    fn test_render_small_terminal_and_no_bridge() {
+        let mut snapshot = snapshot();
+        snapshot.clients = None;
+        snapshot.audio = None;
+        snapshot.metrics.filters.bypassed = true;
+        snapshot.recording = RecordingStatus::default();
+        let mut dashboard = Dashboard::new();
+        let frame = dashboard.render(&snapshot, &log(), 40, 12);
+        let rows: Vec<&str> = frame.lines().collect();
+        assert_eq!(rows.len(), 12);
+        assert!(rows.iter().all(|row| visible_width(row) <= 40), "{}", frame);
+        let plain = sanitize(&frame.replace('\n', " | "));
+        assert!(plain.contains("BYPASS"));
+        assert!(plain.contains("idle"));
+        assert!(plain.contains("AR bridge  not running"));
+    }
+
+    #[test]
+    fn test_keys_map_to_actions() {
+        assert_eq!(DisplayAction::from_key(b'r'), Some(DisplayAction::ToggleRecording));
+        assert_eq!(DisplayAction::from_key(b'B'), Some(DisplayAction::ToggleBypass));
+        assert_eq!(DisplayAction::from_key(b'd'), Some(DisplayAction::NextDevice));
+        assert_eq!(DisplayAction::from_key(0x03), Some(DisplayAction::Quit));
+        assert_eq!(DisplayAction::from_key(b'x'), None);
+    }
+}
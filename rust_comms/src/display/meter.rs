@@ -1,55 +1,431 @@
-use crate::audio::AudioMetrics;
+use crate::audio::metrics::CLIP_THRESHOLD;
+use crate::audio::{AudioMetrics, ChannelLevel};
 use std::io::{self, Write};
+use std::time::{Duration, Instant};
 
+/// Terminal colors the meter can use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeterColor {
+    Default,
+    Green,
+    Yellow,
+    Red,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl MeterColor {
+    fn ansi(self) -> &'static str {
+        match self {
+            MeterColor::Default => "\x1b[39m",
+            MeterColor::Green => "\x1b[32m",
+            MeterColor::Yellow => "\x1b[33m",
+            MeterColor::Red => "\x1b[31m",
+            MeterColor::Blue => "\x1b[34m",
+            MeterColor::Magenta => "\x1b[35m",
+            MeterColor::Cyan => "\x1b[36m",
+            MeterColor::White => "\x1b[37m",
+        }
+    }
+}
+
+/// Colors per meter zone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MeterColors {
+    pub normal: MeterColor,
+    pub warn: MeterColor,
+    pub danger: MeterColor,
+    pub peak: MeterColor,
+    pub clip: MeterColor,
+}
+
+impl Default for MeterColors {
+    fn default() -> Self {
+        Self {
+            normal: MeterColor::Green,
+            warn: MeterColor::Yellow,
+            danger: MeterColor::Red,
+            peak: MeterColor::White,
+            clip: MeterColor::Red,
+        }
+    }
+}
+
+/// Meter scale, zones, labels and ballistics
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeterConfig {
+    /// Bottom and top of the bar (dBFS)
+    pub min_db: f32,
+    pub max_db: f32,
+    /// Bar cells per channel
+    pub bar_length: usize,
+    /// Cells at or above these levels use the warn / danger colors
+    pub warn_db: f32,
+    pub danger_db: f32,
+    /// Signal label: SILENCE below low_db, LOW below good_db, GOOD below loud_db, else LOUD
+    pub low_db: f32,
+    pub good_db: f32,
+    pub loud_db: f32,
+    /// Peak marker holds this long, then falls at peak_decay_db_per_second
+    pub peak_hold: Duration,
+    pub peak_decay_db_per_second: f32,
+    /// None for plain text (no ANSI colors)
+    pub colors: Option<MeterColors>,
+}
+
+impl Default for MeterConfig {
+    fn default() -> Self {
+        Self {
+            min_db: -60.0,
+            max_db: 0.0,
+            bar_length: 40,
+            warn_db: -18.0,
+            danger_db: -6.0,
+            low_db: -50.0,
+            good_db: -35.0,
+            loud_db: -20.0,
+            peak_hold: Duration::from_secs(1),
+            peak_decay_db_per_second: 20.0,
+            colors: Some(MeterColors::default()),
+        }
+    }
+}
+
+/// Decaying peak marker for one channel
+#[derive(Debug, Clone, Copy)]
+struct PeakHold {
+    db: f32,
+    age: Duration,
+}
+
+/// Audio Meter
+///
+/// Terminal level meter, one bar per channel on a dBFS scale
+/// -Bar shows the buffer RMS, zones colored at warn_db / danger_db
+/// -Peak marker jumps up instantly, holds, then decays
+/// -CLIP latches once a channel clips and stays until reset_clip
 pub struct AudioMeter {
-    frame_count: u64,
-    bar_length: usize,
-    initialized: bool,
+    config: MeterConfig,
+    peaks: Vec<PeakHold>,
+    clip_latched: Vec<bool>,
+    last_frame: Option<Instant>,
+    rows_drawn: usize,
 }
 
 impl AudioMeter {
     pub fn new() -> Self {
         Self {
-            frame_count: 0,
-            bar_length: 40,
-            initialized: false,
+            config: MeterConfig::default(),
+            peaks: Vec::new(),
+            clip_latched: Vec::new(),
+            last_frame: None,
+            rows_drawn: 0,
         }
     }
 
+    pub fn with_config(mut self, config: MeterConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn config(&self) -> &MeterConfig {
+        &self.config
+    }
+
+    /// Bar cells per channel, ex: to fit a resized terminal
+    pub fn set_bar_length(&mut self, bar_length: usize) {
+        self.config.bar_length = bar_length.max(1);
+    }
+
+    /// True if any channel has clipped since the last reset
+    pub fn clip_latched(&self) -> bool {
+        self.clip_latched.iter().any(|&clipped| clipped)
+    }
+
+    pub fn reset_clip(&mut self) {
+        self.clip_latched.iter_mut().for_each(|clipped| *clipped = false);
+    }
+
+    /// Draw the meter in place on stdout (redraws over the previous frame)
     pub fn display(&mut self, metrics: &AudioMetrics) {
-        self.frame_count += 1;
-        if !self.initialized {
-            println!();
-            self.initialized = true;
-        }
-        let normalized_level = (metrics.rms * 100.0).min(1.0);
-        let filled_length = (normalized_level * self.bar_length as f32) as usize;
-        let bar = self.create_bar(filled_length);
-        
-        let signal_strength = self.get_signal_strength(metrics.db);
-        print!("\x1b[2K\r");
-        print!("Audio: [{}] RMS:{:.3} {:.0}dB | {} |",
-               bar,
-               metrics.rms,
-               metrics.db,
-               signal_strength
-            );
+        let now = Instant::now();
+        let elapsed = self.last_frame.map_or(Duration::ZERO, |last| now - last);
+        self.last_frame = Some(now);
+        let frame = self.render(metrics, elapsed);
 
+        let mut out = String::new();
+        if self.rows_drawn == 0 {
+            out.push('\n');
+        } else if self.rows_drawn > 1 {
+            out.push_str(&format!("\x1b[{}A", self.rows_drawn - 1)); // back to the first row
+        }
+        for (i, row) in frame.lines().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            out.push_str("\x1b[2K\r");
+            out.push_str(row);
+        }
+        self.rows_drawn = frame.lines().count();
+        print!("{}", out);
         io::stdout().flush().unwrap();
     }
 
-    fn create_bar(&self, filled_length: usize) -> String {
-        let filled_char = "█";
-        let empty_char = " ";
-        filled_char.repeat(filled_length) + &empty_char.repeat(self.bar_length - filled_length)
+    /// Meter frame as text, `elapsed` since the previous frame drives the peak decay
+    /// Mono is one line, more channels get a row each plus a status row
+    pub fn render(&mut self, metrics: &AudioMetrics, elapsed: Duration) -> String {
+        let mut rows = self.channel_rows(metrics, elapsed);
+        let status = self.status(metrics);
+        if rows.len() == 1 {
+            rows[0] = format!("{} | {}", rows[0], status);
+        } else {
+            rows.push(status);
+        }
+        rows.join("\n")
     }
 
-    fn get_signal_strength(&self, db: f32) -> &'static str {
+    /// Just the bars, one row per channel (used by the dashboard)
+    pub fn channel_rows(&mut self, metrics: &AudioMetrics, elapsed: Duration) -> Vec<String> {
+        let levels: Vec<ChannelLevel> = if metrics.channels.as_slice().is_empty() {
+            // Metrics from before per-channel levels existed, meter the mix
+            vec![ChannelLevel {
+                rms_db: metrics.db,
+                peak_db: 20.0 * metrics.peak.max(1e-10).log10(),
+                clipped: metrics.peak >= CLIP_THRESHOLD,
+            }]
+        } else {
+            metrics.channels.as_slice().to_vec()
+        };
+        if self.peaks.len() != levels.len() {
+            self.peaks = vec![PeakHold { db: f32::NEG_INFINITY, age: Duration::ZERO }; levels.len()];
+            self.clip_latched = vec![false; levels.len()];
+        }
+
+        let mono = levels.len() == 1;
+        let mut rows = Vec::with_capacity(levels.len());
+        for (channel, level) in levels.iter().enumerate() {
+            let peak_db = self.update_peak(channel, level.peak_db, elapsed);
+            self.clip_latched[channel] |= level.clipped;
+            let label = if mono { "Audio:".to_string() } else { format!("Ch{:<3}", channel + 1) };
+            let clip = if self.clip_latched[channel] {
+                self.paint(" CLIP", self.config.colors.map(|c| c.clip))
+            } else {
+                "     ".to_string()
+            };
+            rows.push(format!(
+                "{} [{}] {:6.1} dB pk {:6.1}{}",
+                label,
+                self.bar(level.rms_db, peak_db),
+                level.rms_db.max(-99.9),
+                peak_db.max(-99.9),
+                clip
+            ));
+        }
+        rows
+    }
+
+    fn update_peak(&mut self, channel: usize, peak_db: f32, elapsed: Duration) -> f32 {
+        let hold = &mut self.peaks[channel];
+        if peak_db >= hold.db {
+            hold.db = peak_db;
+            hold.age = Duration::ZERO;
+        } else {
+            let before = hold.age;
+            hold.age += elapsed;
+            if hold.age > self.config.peak_hold {
+                // Only the time past the hold counts towards the fall
+                let falling = hold.age - before.max(self.config.peak_hold);
+                hold.db = (hold.db - self.config.peak_decay_db_per_second * falling.as_secs_f32()).max(peak_db);
+            }
+        }
+        hold.db
+    }
+
+    /// Position of `db` on the bar [0.0, 1.0]
+    fn fraction(&self, db: f32) -> f32 {
+        let range = (self.config.max_db - self.config.min_db).max(1e-3);
+        ((db - self.config.min_db) / range).clamp(0.0, 1.0)
+    }
+
+    fn bar(&self, rms_db: f32, peak_db: f32) -> String {
+        let length = self.config.bar_length;
+        let filled = (self.fraction(rms_db) * length as f32).round() as usize;
+        let peak = ((self.fraction(peak_db) * length as f32).round() as usize).min(length.saturating_sub(1));
+        let show_peak = peak_db > self.config.min_db && peak >= filled;
+        let range = self.config.max_db - self.config.min_db;
+
+        let mut bar = String::new();
+        for cell in 0..length {
+            let cell_db = self.config.min_db + (cell as f32 + 0.5) / length as f32 * range;
+            let zone = self.config.colors.map(|c| {
+                if cell_db >= self.config.danger_db {
+                    c.danger
+                } else if cell_db >= self.config.warn_db {
+                    c.warn
+                } else {
+                    c.normal
+                }
+            });
+            if cell < filled {
+                bar.push_str(&self.paint("█", zone));
+            } else if show_peak && cell == peak {
+                bar.push_str(&self.paint("|", self.config.colors.map(|c| c.peak)));
+            } else {
+                bar.push(' ');
+            }
+        }
+        bar
+    }
+
+    fn paint(&self, text: &str, color: Option<MeterColor>) -> String {
+        match color {
+            Some(color) => format!("{}{}\x1b[0m", color.ansi(), text),
+            None => text.to_string(),
+        }
+    }
+
+    fn status(&self, metrics: &AudioMetrics) -> String {
+        let t = &metrics.telemetry;
+        format!(
+            "{} | M:{:.1} I:{:.1} LUFS | lat:{:.1}ms load:{:.0}% jit:{:.1}ms err:{} disc:{} over:{} late:{} drop:{}",
+            self.signal_strength(metrics.db),
+            metrics.loudness.momentary_lufs.max(-99.9),
+            metrics.loudness.integrated_lufs.max(-99.9),
+            t.latency_ms,
+            t.load * 100.0,
+            t.period_jitter_ms,
+            t.backend_errors,
+            t.disconnects,
+            t.overruns,
+            t.late_callbacks,
+            t.dropped_buffers
+        )
+    }
+
+    fn signal_strength(&self, db: f32) -> &'static str {
         match db {
-            db if db > -20.0 => "LOUDDDDD",
-            db if db > -35.0 => "GOOD",
-            db if db > -50.0 => "LOW",
-            _ => "SILENCE"
+            db if db >= self.config.loud_db => "LOUD",
+            db if db >= self.config.good_db => "GOOD",
+            db if db >= self.config.low_db => "LOW",
+            _ => "SILENCE",
         }
     }
-}
\ No newline at end of file
+}
+
+impl Default for AudioMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::ChannelLevels;
+
+    fn plain(bar_length: usize) -> AudioMeter {
+        AudioMeter::new().with_config(MeterConfig {
+            bar_length,
+            colors: None,
+            ..MeterConfig::default()
+        })
+    }
+
+    /// Stereo metrics with the given per-channel sample values
+    fn stereo(left: f32, right: f32) -> AudioMetrics {
+        let mut metrics = AudioMetrics::new();
+        let data: Vec<f32> = (0..64).flat_map(|_| [left, right]).collect();
+        metrics.set_channels(ChannelLevels::measure(&data, 2));
+        metrics.db = -30.0;
+        metrics
+    }
+
+    #[test]
+    fn test_mono_bar_uses_db_scale() {
+        let mut meter = plain(10);
+        let mut metrics = AudioMetrics::new();
+        metrics.db = -30.0; // half way up a -60..0 scale
+        metrics.peak = 0.251; // -12dB, marker 80% along
+        let frame = meter.render(&metrics, Duration::ZERO);
+        assert!(frame.starts_with("Audio: [█████   | ]  -30.0 dB pk  -12.0      | GOOD | M:"), "{}", frame);
+        assert_eq!(frame.lines().count(), 1);
+
+        // The old linear mapping showed anything above 0.01 RMS as full, now -10dB is 5/6
+        metrics.db = -10.0;
+        metrics.peak = 0.5;
+        let frame = meter.render(&metrics, Duration::ZERO);
+        assert!(frame.starts_with("Audio: [████████ |]"), "{}", frame);
+        assert!(frame.contains("| LOUD |"));
+
+        // Backend errors and slow processing are different problems, shown apart
+        metrics.telemetry.backend_errors = 2;
+        metrics.telemetry.disconnects = 1;
+        metrics.telemetry.overruns = 5;
+        let frame = meter.render(&metrics, Duration::ZERO);
+        assert!(frame.contains( " err:2 disc:1 over:5 "), "{}", frame);
+    }
+
+    #[test]
+    fn test_peak_holds_then_decays() {
+        let mut meter = plain(60); // 1dB per cell
+        let marker = |frame: &str| frame.lines().next().unwrap().chars().position(|c| c == '|').unwrap() - 7;
+
+        let mut metrics = stereo(0.5, 0.01); // left peak ~-6dB
+        meter.render(&metrics, Duration::ZERO);
+        metrics = stereo(0.01, 0.01); // quiet again
+        let frame = meter.render(&metrics, Duration::from_millis(900));
+        assert_eq!(marker(&frame), 54); // still held at -6dB
+        let frame = meter.render(&metrics, Duration::from_millis(600));
+        assert_eq!(marker(&frame), 44); // 0.5s past the hold at 20dB/s, down 10dB
+    }
+
+    #[test]
+    fn test_clip_latches_per_channel_until_reset() {
+        let mut meter = plain(20);
+        meter.render(&stereo(0.1, 1.0), Duration::ZERO);
+        let frame = meter.render(&stereo(0.1, 0.1), Duration::from_millis(50));
+        let rows: Vec<&str> = frame.lines().collect();
+        assert_eq!(rows.len(), 3); // two channels + status
+        assert!(rows[0].starts_with("Ch1 ") && !rows[0].contains("CLIP"));
+        assert!(rows[1].starts_with("Ch2 ") && rows[1].ends_with(" CLIP"));
+        assert!(rows[2].starts_with("GOOD | M:"));
+        assert!(meter.clip_latched());
+
+        meter.reset_clip();
+        let frame = meter.render(&stereo(0.1, 0.1), Duration::from_millis(50));
+        assert!(!frame.contains("CLIP"));
+    }
+
+    #[test]
+    fn test_custom_scale_thresholds_and_colors() {
+        let config = MeterConfig {
+            min_db: -40.0,
+            max_db: 0.0,
+            bar_length: 4,
+            warn_db: -30.0,
+            danger_db: -10.0,
+            good_db: -45.0,
+            colors: Some(MeterColors {
+                normal: MeterColor::Cyan,
+                warn: MeterColor::Magenta,
+                danger: MeterColor::Blue,
+                ..MeterColors::default()
+            }),
+            ..MeterConfig::default()
+        };
+        let mut meter = AudioMeter::new().with_config(config);
+        let mut metrics = AudioMetrics::new();
+        metrics.db = 0.0;
+        let frame = meter.render(&metrics, Duration::ZERO);
+        let cyan = "\x1b[36m█\x1b[0m";
+        let magenta = "\x1b[35m█\x1b[0m";
+        let blue = "\x1b[34m█\x1b[0m";
+        assert!(frame.starts_with(&format!("Audio: [{}{}{}{}]", cyan, magenta, magenta, blue)), "{:?}", frame);
+
+        metrics.db = -44.0;
+        let frame = meter.render(&metrics, Duration::ZERO);
+        assert!(frame.starts_with("Audio: [    ]") && frame.contains("| GOOD |"), "{:?}", frame);
+    }
+}
@@ -1,3 +1,16 @@
 pub mod meter;
+pub mod spectrum;
+pub mod spectrogram;
+pub mod terminal;
+pub mod dashboard;
+pub mod sink;
 
-pub use meter::AudioMeter;
\ No newline at end of file
+pub use meter::{AudioMeter, MeterColor, MeterColors, MeterConfig};
+pub use spectrum::{FrequencyScale, SpectrumAnalyzer};
+pub use spectrogram::{ColorMap, Glyphs, Spectrogram, SpectrogramConfig, SpectrogramSink};
+pub use terminal::{LogBuffer, Terminal};
+pub use dashboard::{Dashboard, DashboardSink};
+pub use sink::{
+    DisplayAction, DisplayEvent, DisplayMode, DisplaySink, DisplaySnapshot, JsonLinesSink, LineMeterSink, RecordingStatus,
+    RecordingWatcher,
+};
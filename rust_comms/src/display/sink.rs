@@ -0,0 +1,442 @@
+use crate::ar::ClientStats;
+use crate::audio::{AudioMetrics, RecordingInfo, ReplaySnapshot};
+use super::meter::AudioMeter;
+use super::terminal::{capture_lines, sanitize};
+use chrono::{Local, SecondsFormat};
+use serde_json::{json, Value};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+use std::os::fd::FromRawFd;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+/// Line meter redraw interval (20fps)
+pub const LINE_METER_INTERVAL: Duration = Duration::from_millis(50);
+/// JSON lines per second unless --rate says otherwise
+pub const DEFAULT_JSON_RATE_HZ: f32 = 1.0;
+
+/// How the live view is shown, picked with --output or MERLIN_DISPLAY
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayMode {
+    /// Full-screen terminal dashboard
+    Dashboard,
+    /// In-place ANSI meter, what runs without a TTY
+    Line,
+    /// Headless JSON lines on stdout, for systemd / log tooling
+    Json,
+    /// Full-screen scrolling spectrogram
+    Spectrogram,
+}
+
+impl FromStr for DisplayMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "dashboard" | "tui" => Ok(DisplayMode::Dashboard),
+            "line" | "meter" => Ok(DisplayMode::Line),
+            "json" => Ok(DisplayMode::Json),
+            "spectrogram" | "waterfall" => Ok(DisplayMode::Spectrogram),
+            other => Err(format!("Unknown output mode {:?} (expected dashboard, line, json or spectrogram)", other)),
+        }
+    }
+}
+
+impl fmt::Display for DisplayMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DisplayMode::Dashboard => "dashboard",
+            DisplayMode::Line => "line",
+            DisplayMode::Json => "json",
+            DisplayMode::Spectrogram => "spectrogram",
+        })
+    }
+}
+
+/// Recording panel / telemetry contents
+#[derive(Debug, Clone, Default)]
+pub struct RecordingStatus {
+    pub armed: bool,
+    /// File being written, None when idle
+    pub file: Option<PathBuf>,
+    pub seconds: f64,
+    /// Ex: "flac", "pcm16", "flac+encrypted"
+    pub format: String,
+    pub last: Option<RecordingInfo>,
+    /// Buffers the recording thread couldn't keep up with
+    pub dropped_buffers: u64,
+}
+
+/// Everything one update shows, gathered by the caller every tick
+#[derive(Debug, Clone, Default)]
+pub struct DisplaySnapshot {
+    pub metrics: AudioMetrics,
+    pub device: String,
+    /// Newest processed audio, only gathered for sinks that ask for it (DisplaySink::audio_seconds)
+    pub audio: Option<ReplaySnapshot>,
+    pub recording: RecordingStatus,
+    /// Connected AR clients, None when the bridge isn't running in this process
+    pub clients: Option<Vec<ClientStats>>,
+}
+
+/// Things that happen between updates
+#[derive(Debug, Clone, PartialEq)]
+pub enum DisplayEvent {
+    RecordingArmed,
+    RecordingDisarmed,
+    RecordingStarted { file: PathBuf },
+    RecordingFinished { file: PathBuf, duration_seconds: f64, file_size_bytes: u64 },
+    /// Line printed to stdout while the sink owns it
+    Log(String),
+    /// Line printed to stderr while the sink owns it
+    Error(String),
+}
+
+/// Actions a sink can ask for, ex: dashboard key presses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayAction {
+    ToggleRecording,
+    ToggleBypass,
+    NextDevice,
+    SaveReplay,
+    Quit,
+}
+
+/// Display Sink
+///
+/// Where the live view goes, the binary drives whichever one was picked
+/// -update is called every interval() with a fresh snapshot
+/// -event is called for recording changes as they're noticed
+/// -Interactive sinks hand back user actions, the others keep the defaults
+pub trait DisplaySink {
+    fn update(&mut self, snapshot: &DisplaySnapshot) -> io::Result<()>;
+
+    fn event(&mut self, _event: &DisplayEvent) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Actions requested since the last call, never blocks
+    fn actions(&mut self) -> Vec<DisplayAction> {
+        Vec::new()
+    }
+
+    /// Time between updates
+    fn interval(&self) -> Duration;
+
+    /// Seconds of recent audio the snapshot should carry, None if unused
+    fn audio_seconds(&self) -> Option<f32> {
+        None
+    }
+}
+
+/// Turns successive RecordingStatus into DisplayEvents
+#[derive(Debug, Default)]
+pub struct RecordingWatcher {
+    armed: bool,
+    file: Option<PathBuf>,
+    last: Option<PathBuf>,
+}
+
+impl RecordingWatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events for what changed since the previous status
+    pub fn changes(&mut self, status: &RecordingStatus) -> Vec<DisplayEvent> {
+        let mut events = Vec::new();
+        if status.armed != self.armed {
+            self.armed = status.armed;
+            events.push(if status.armed { DisplayEvent::RecordingArmed } else { DisplayEvent::RecordingDisarmed });
+        }
+        if status.file != self.file {
+            self.file = status.file.clone();
+            if let Some(file) = &status.file {
+                events.push(DisplayEvent::RecordingStarted { file: file.clone() });
+            }
+        }
+        let last = status.last.as_ref().map(|info| &info.file_path);
+        if last != self.last.as_ref()
+            && let Some(info) = &status.last
+        {
+            self.last = Some(info.file_path.clone());
+            events.push(DisplayEvent::RecordingFinished {
+                file: info.file_path.clone(),
+                duration_seconds: info.duration_seconds,
+                file_size_bytes: info.file_size_bytes,
+            });
+        }
+        events
+    }
+}
+
+/// The in-place ANSI meter
+pub struct LineMeterSink {
+    meter: AudioMeter,
+}
+
+impl LineMeterSink {
+    pub fn new(meter: AudioMeter) -> Self {
+        Self { meter }
+    }
+}
+
+impl DisplaySink for LineMeterSink {
+    fn update(&mut self, snapshot: &DisplaySnapshot) -> io::Result<()> {
+        self.meter.display(&snapshot.metrics);
+        Ok(())
+    }
+
+    fn interval(&self) -> Duration {
+        LINE_METER_INTERVAL
+    }
+}
+
+/// JSON Lines Sink
+///
+/// One JSON object per line, for journald / log shippers / the gateway
+/// -"metrics" lines at the configured rate: AudioMetrics, gate state, recording, AR clients
+/// -"recording_*" lines as recordings are armed, started and finished
+/// -"log" / "error" lines for anything else printed to stdout / stderr (see JsonLinesSink::stdout)
+/// -Every line has "type" and a local RFC 3339 "timestamp"
+pub struct JsonLinesSink<W: Write> {
+    out: W,
+    interval: Duration,
+    captured: Option<Receiver<DisplayEvent>>,
+}
+
+impl<W: Write> JsonLinesSink<W> {
+    /// Args:
+    /// - out: where the lines go
+    /// - rate_hz: metrics lines per second, clamped to 0.01-100, DEFAULT_JSON_RATE_HZ if not finite
+    pub fn new(out: W, rate_hz: f32) -> Self {
+        let rate_hz = if rate_hz.is_finite() { rate_hz } else { DEFAULT_JSON_RATE_HZ };
+        Self {
+            out,
+            interval: Duration::from_secs_f32(1.0 / rate_hz.clamp(0.01, 100.0)),
+            captured: None,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Lines printed to stdout / stderr since the last call
+    fn write_captured(&mut self) -> io::Result<()> {
+        let captured: Vec<DisplayEvent> = self.captured.as_ref().map_or(Vec::new(), |rx| rx.try_iter().collect());
+        for event in &captured {
+            self.write_line(event_line(event))?;
+        }
+        Ok(())
+    }
+
+    fn write_line(&mut self, mut line: Value) -> io::Result<()> {
+        line["timestamp"] = json!(Local::now().to_rfc3339_opts(SecondsFormat::Millis, false));
+        serde_json::to_writer(&mut self.out, &line)?;
+        self.out.write_all(b"\n")?;
+        self.out.flush()
+    }
+}
+
+impl JsonLinesSink<File> {
+    /// Take over stdout for JSON, for the rest of the process
+    /// Other prints no longer reach stdout, they come back as "log" (stdout) / "error" (stderr) lines
+    pub fn stdout(rate_hz: f32) -> io::Result<Self> {
+        io::stdout().flush()?;
+        let fd = unsafe { libc::dup(libc::STDOUT_FILENO) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let out = unsafe { File::from_raw_fd(fd) };
+
+        let (tx, rx) = mpsc::channel();
+        let log_tx = tx.clone();
+        capture_lines(&[libc::STDOUT_FILENO], move |line| {
+            let line = sanitize(line);
+            if !line.trim().is_empty() {
+                let _ = log_tx.send(DisplayEvent::Log(line.trim().to_string()));
+            }
+        })?;
+        capture_lines(&[libc::STDERR_FILENO], move |line| {
+            let line = sanitize(line);
+            if !line.trim().is_empty() {
+                let _ = tx.send(DisplayEvent::Error(line.trim().to_string()));
+            }
+        })?;
+
+        let mut sink = Self::new(out, rate_hz);
+        sink.captured = Some(rx);
+        Ok(sink)
+    }
+}
+
+impl<W: Write> DisplaySink for JsonLinesSink<W> {
+    fn update(&mut self, snapshot: &DisplaySnapshot) -> io::Result<()> {
+        self.write_captured()?;
+        let filters = &snapshot.metrics.filters;
+        let gate = if filters.bypassed {
+            "bypass"
+        } else if filters.gate_open {
+            "open"
+        } else {
+            "closed"
+        };
+        let recording = &snapshot.recording;
+        let clients = snapshot.clients.as_ref().map(|clients| {
+            clients
+                .iter()
+                .map(|client| {
+                    json!({
+                        "session_id": client.session_id,
+                        "client_id": client.client_id,
+                        "connected_seconds": client.connected_at.elapsed().as_secs_f64(),
+                        "frames_sent": client.frames_sent,
+                        "send_errors": client.send_errors,
+                        "fps": client.fps,
+                    })
+                })
+                .collect::<Vec<_>>()
+        });
+        self.write_line(json!({
+            "type": "metrics",
+            "device": snapshot.device,
+            "metrics": snapshot.metrics,
+            "gate": {
+                "state": gate,
+                "gain": filters.gate_gain,
+                "normalizer_gain_db": filters.normalizer_gain_db,
+            },
+            "recording": {
+                "armed": recording.armed,
+                "file": recording.file,
+                "seconds": recording.seconds,
+                "format": recording.format,
+                "dropped_buffers": recording.dropped_buffers,
+            },
+            "clients": clients,
+        }))
+    }
+
+    fn event(&mut self, event: &DisplayEvent) -> io::Result<()> {
+        // Output printed before the event was noticed goes first, keeps the lines in order
+        self.write_captured()?;
+        self.write_line(event_line(event))
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+}
+
+fn event_line(event: &DisplayEvent) -> Value {
+    match event {
+        DisplayEvent::RecordingArmed => json!({ "type": "recording_armed" }),
+        DisplayEvent::RecordingDisarmed => json!({ "type": "recording_disarmed" }),
+        DisplayEvent::RecordingStarted { file } => json!({ "type": "recording_started", "file": file }),
+        DisplayEvent::RecordingFinished { file, duration_seconds, file_size_bytes } => json!({
+            "type": "recording_finished",
+            "file": file,
+            "duration_seconds": duration_seconds,
+            "file_size_bytes": file_size_bytes,
+        }),
+        DisplayEvent::Log(message) => json!({ "type": "log", "message": message }),
+        DisplayEvent::Error(message) => json!({ "type": "error", "message": message }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(sink: JsonLinesSink<Vec<u8>>) -> Vec<Value> {
+        String::from_utf8(sink.into_inner())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_json_lines_carry_metrics_gate_and_events() {
+        let mut snapshot = DisplaySnapshot {
+            device: "USB Mic".to_string(),
+            ..Default::default()
+        };
+        snapshot.metrics.db = -23.5;
+        snapshot.metrics.loudness.integrated_lufs = f32::NEG_INFINITY; // not enough audio yet
+        snapshot.metrics.filters.gate_open = true;
+        snapshot.metrics.filters.gate_gain = 0.75;
+        snapshot.recording.armed = true;
+        snapshot.recording.format = "flac".to_string();
+
+        let mut sink = JsonLinesSink::new(Vec::new(), 4.0);
+        assert_eq!(sink.interval(), Duration::from_millis(250));
+        assert_eq!(JsonLinesSink::new(Vec::new(), f32::NAN).interval(), Duration::from_secs(1));
+        sink.update(&snapshot).unwrap();
+        sink.event(&DisplayEvent::RecordingStarted { file: PathBuf::from("recordings/a.flac") }).unwrap();
+        sink.event(&DisplayEvent::Error("Stream error: overrun".to_string())).unwrap();
+
+        let lines = lines(sink);
+        assert_eq!(lines.len(), 3);
+        let metrics = &lines[0];
+        assert_eq!(metrics["type"], "metrics");
+        assert!(metrics["timestamp"].as_str().unwrap().starts_with("20"));
+        assert_eq!(metrics["device"], "USB Mic");
+        assert_eq!(metrics["metrics"]["db"], -23.5);
+        assert!(metrics["metrics"]["loudness"]["integrated_lufs"].is_null());
+        assert!(metrics["metrics"]["channels"].as_array().unwrap().is_empty());
+        assert_eq!(metrics["gate"]["state"], "open");
+        assert_eq!(metrics["gate"]["gain"], 0.75);
+        assert_eq!(metrics["recording"]["armed"], true);
+        assert_eq!(metrics["recording"]["format"], "flac");
+        assert!(metrics["clients"].is_null());
+
+        assert_eq!(lines[1]["type"], "recording_started");
+        assert_eq!(lines[1]["file"], "recordings/a.flac");
+        assert_eq!(lines[2]["type"], "error");
+        assert_eq!(lines[2]["message"], "Stream error: overrun");
+    }
+
+    #[test]
+    fn test_recording_watcher_reports_changes_once() {
+        let mut watcher = RecordingWatcher::new();
+        let mut status = RecordingStatus::default();
+        assert!(watcher.changes(&status).is_empty());
+
+        status.armed = true;
+        status.file = Some(PathBuf::from("a.wav"));
+        assert_eq!(
+            watcher.changes(&status),
+            vec![DisplayEvent::RecordingArmed, DisplayEvent::RecordingStarted { file: PathBuf::from("a.wav") }]
+        );
+        assert!(watcher.changes(&status).is_empty());
+
+        status.armed = false;
+        status.file = None;
+        status.last = Some(RecordingInfo {
+            file_path: PathBuf::from("a.wav"),
+            duration_seconds: 2.0,
+            file_size_bytes: 64044,
+            sample_rate: 16000,
+            channels: 1,
+        });
+        assert_eq!(
+            watcher.changes(&status),
+            vec![
+                DisplayEvent::RecordingDisarmed,
+                DisplayEvent::RecordingFinished {
+                    file: PathBuf::from("a.wav"),
+                    duration_seconds: 2.0,
+                    file_size_bytes: 64044
+                }
+            ]
+        );
+        assert!(watcher.changes(&status).is_empty());
+        assert_eq!(DisplayMode::from_str("JSON"), Ok(DisplayMode::Json));
+        assert!(DisplayMode::from_str("curses").is_err());
+    }
+}
@@ -0,0 +1,517 @@
+use super::sink::{DisplayAction, DisplaySink, DisplaySnapshot};
+use super::spectrum::{clamp_range, FrequencyScale, SpectrumAnalyzer};
+use super::terminal::{LogBuffer, Terminal, DEFAULT_LOG_LINES};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Time slices kept for the live view, more than any terminal shows
+const MAX_HISTORY: usize = 1024;
+/// Lowest sample rate the live view expects, sizes the audio requested per frame
+const MIN_SAMPLE_RATE: f32 = 8000.0;
+/// Live redraw interval, one time slice per frame
+const SPECTROGRAM_FRAME: Duration = Duration::from_millis(50);
+/// Braille dots light at or above this fraction of the floor..ceiling range
+const BRAILLE_ON: f32 = 0.25;
+/// 256-color ramps, quietest first
+const HEAT: [u8; 26] = [
+    16, 17, 18, 19, 20, 21, 57, 93, 129, 165, 201, 200, 199, 198, 197, 196, 202, 208, 214, 220, 226, 227, 228, 229,
+    230, 231,
+];
+const SHADES: [char; 5] = [' ', '░', '▒', '▓', '█'];
+const BLACK: u8 = 16;
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// Colors for levels between floor and ceiling
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMap {
+    /// Black -> blue -> magenta -> red -> yellow -> white
+    #[default]
+    Heat,
+    Gray,
+    /// No ANSI colors, levels shown by shade characters only
+    None,
+}
+
+impl FromStr for ColorMap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "heat" => Ok(ColorMap::Heat),
+            "gray" | "grey" => Ok(ColorMap::Gray),
+            "none" => Ok(ColorMap::None),
+            other => Err(format!("Unknown color map {:?} (expected heat, gray or none)", other)),
+        }
+    }
+}
+
+/// Characters the spectrogram is drawn with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Glyphs {
+    /// ▀ with separate foreground / background colors, 2 time slices per row
+    #[default]
+    HalfBlock,
+    /// Braille dots, 2 bands x 4 time slices per cell, finest detail
+    Braille,
+    /// ░▒▓█ shades, 1 time slice per row, readable without colors
+    Shade,
+}
+
+impl FromStr for Glyphs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "half" | "halfblock" | "block" => Ok(Glyphs::HalfBlock),
+            "braille" => Ok(Glyphs::Braille),
+            "shade" => Ok(Glyphs::Shade),
+            other => Err(format!("Unknown glyphs {:?} (expected half, braille or shade)", other)),
+        }
+    }
+}
+
+/// Frequency range, axis, level range and look of the spectrogram
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpectrogramConfig {
+    /// Shown frequency range, max is capped at Nyquist
+    pub min_hz: f32,
+    pub max_hz: f32,
+    pub scale: FrequencyScale,
+    /// Levels map onto the color ramp between these (dBFS per bin)
+    pub floor_db: f32,
+    pub ceiling_db: f32,
+    /// FFT length in frames, rounded up to a power of two
+    pub fft_size: usize,
+    pub colors: ColorMap,
+    pub glyphs: Glyphs,
+}
+
+impl Default for SpectrogramConfig {
+    fn default() -> Self {
+        Self {
+            min_hz: 50.0,
+            max_hz: 8000.0,
+            scale: FrequencyScale::Log,
+            floor_db: -100.0,
+            ceiling_db: -20.0,
+            fft_size: 1024,
+            colors: ColorMap::Heat,
+            glyphs: Glyphs::HalfBlock,
+        }
+    }
+}
+
+impl fmt::Display for SpectrogramConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{} {}  {:.0}..{:.0} dB  FFT {}",
+            hz_label(self.min_hz),
+            hz_label(self.max_hz),
+            self.scale,
+            self.floor_db,
+            self.ceiling_db,
+            self.fft_size
+        )
+    }
+}
+
+/// Spectrogram
+///
+/// Scrolling FFT magnitude history (waterfall) drawn with Unicode characters
+/// -Frequency runs left to right on a log or linear axis, time runs down, newest at the bottom
+/// -Each slice is the spectrum of the newest fft_size frames when it was pushed
+/// -Fed live from the pipeline (SpectrogramSink) or from a whole recording (push_recording)
+pub struct Spectrogram {
+    config: SpectrogramConfig,
+    analyzer: SpectrumAnalyzer,
+    /// Oldest first, each slice one level per band in dBFS
+    history: VecDeque<Vec<f32>>,
+    sample_rate: u32,
+}
+
+impl Spectrogram {
+    pub fn new(config: SpectrogramConfig) -> Self {
+        Self {
+            analyzer: SpectrumAnalyzer::new(config.fft_size),
+            config,
+            history: VecDeque::new(),
+            sample_rate: 0,
+        }
+    }
+
+    pub fn config(&self) -> &SpectrogramConfig {
+        &self.config
+    }
+
+    /// Switch the frequency axis, history is cleared since its bands no longer line up
+    pub fn set_scale(&mut self, scale: FrequencyScale) {
+        if scale != self.config.scale {
+            self.config.scale = scale;
+            self.history.clear();
+        }
+    }
+
+    /// Frequency range actually shown, once a sample rate is known
+    pub fn range_hz(&self) -> (f32, f32) {
+        match self.sample_rate {
+            0 => (self.config.min_hz, self.config.max_hz),
+            rate => clamp_range((self.config.min_hz, self.config.max_hz), rate),
+        }
+    }
+
+    /// Time slices per text row
+    pub fn slices_per_row(&self) -> usize {
+        match self.glyphs() {
+            Glyphs::HalfBlock => 2,
+            Glyphs::Braille => 4,
+            Glyphs::Shade => 1,
+        }
+    }
+
+    /// Add one slice from the newest audio, sized to fill `width` columns
+    /// Args:
+    /// - samples: interleaved f32 samples, newest last
+    /// - channels: interleaved channel count
+    /// - sample_rate: rate of `samples` (Hz)
+    /// - width: text columns the slice will be drawn across
+    pub fn push(&mut self, samples: &[f32], channels: u16, sample_rate: u32, width: usize) {
+        self.sample_rate = sample_rate;
+        let bands = width * self.bands_per_cell();
+        let levels = self.analyzer.bands(
+            samples,
+            channels,
+            sample_rate,
+            bands,
+            (self.config.min_hz, self.config.max_hz),
+            self.config.scale,
+        );
+        self.push_levels(levels);
+    }
+
+    /// Add a slice of band levels (dBFS), a different band count than before clears the history (resize)
+    pub fn push_levels(&mut self, levels: Vec<f32>) {
+        if self.history.back().is_some_and(|last| last.len() != levels.len()) {
+            self.history.clear();
+        }
+        if self.history.len() == MAX_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(levels);
+    }
+
+    /// Replace the history with `slices` evenly spaced slices covering a whole recording
+    pub fn push_recording(&mut self, samples: &[f32], channels: u16, sample_rate: u32, width: usize, slices: usize) {
+        self.history.clear();
+        let channels_usize = channels.max(1) as usize;
+        let frames = samples.len() / channels_usize;
+        for slice in 0..slices {
+            // Each slice's window ends at the end of its share of the recording
+            let end = (frames * (slice + 1)).div_ceil(slices.max(1));
+            self.push(&samples[..end * channels_usize], channels, sample_rate, width);
+        }
+    }
+
+    /// The newest `rows` rows of the waterfall, `width` columns each, oldest row first
+    pub fn render(&self, width: usize, rows: usize) -> Vec<String> {
+        let per_row = self.slices_per_row();
+        let shown = (rows * per_row).min(self.history.len());
+        // Blank slices on top until the history fills the view
+        let mut slices: Vec<Option<&[f32]>> = vec![None; rows * per_row - shown];
+        slices.extend(self.history.iter().skip(self.history.len() - shown).map(|s| Some(s.as_slice())));
+
+        slices
+            .chunks(per_row)
+            .map(|row| match self.glyphs() {
+                Glyphs::HalfBlock => self.half_block_row(row[0], row[1], width),
+                Glyphs::Braille => self.braille_row(row, width),
+                Glyphs::Shade => self.shade_row(row[0], width),
+            })
+            .collect()
+    }
+
+    /// Frequency labels under the waterfall, `width` columns
+    pub fn axis(&self, width: usize) -> String {
+        let (min_hz, max_hz) = self.range_hz();
+        let ticks: Vec<f32> = match self.config.scale {
+            FrequencyScale::Log => [20.0, 50.0, 100.0, 200.0, 500.0, 1e3, 2e3, 5e3, 10e3, 20e3].to_vec(),
+            FrequencyScale::Linear => {
+                let step = nice_step((max_hz - min_hz) / (width as f32 / 10.0).max(1.0));
+                let first = (min_hz / step).ceil() as i64;
+                let last = (max_hz / step).floor() as i64;
+                (first..=last).map(|k| k as f32 * step).collect()
+            }
+        };
+
+        let mut axis = vec![' '; width];
+        let mut free_from = 0; // first column a label may start at
+        for hz in ticks.into_iter().filter(|&hz| hz >= min_hz && hz <= max_hz) {
+            let label: Vec<char> = hz_label(hz).chars().collect();
+            let column = (self.config.scale.position_of(hz, min_hz, max_hz) * width as f32) as usize;
+            let start = column.min(width.saturating_sub(label.len()));
+            if start < free_from {
+                continue;
+            }
+            axis[start..start + label.len()].copy_from_slice(&label);
+            free_from = start + label.len() + 1;
+        }
+        axis.into_iter().collect()
+    }
+
+    fn glyphs(&self) -> Glyphs {
+        // Half blocks need colors to show anything
+        match (self.config.glyphs, self.config.colors) {
+            (Glyphs::HalfBlock, ColorMap::None) => Glyphs::Shade,
+            (glyphs, _) => glyphs,
+        }
+    }
+
+    fn bands_per_cell(&self) -> usize {
+        if self.glyphs() == Glyphs::Braille { 2 } else { 1 }
+    }
+
+    /// Position of `db` between floor and ceiling [0.0, 1.0]
+    fn fraction(&self, db: f32) -> f32 {
+        let range = (self.config.ceiling_db - self.config.floor_db).max(1e-3);
+        ((db - self.config.floor_db) / range).clamp(0.0, 1.0)
+    }
+
+    /// 256-color index for a level fraction, None without colors
+    fn color(&self, fraction: f32) -> Option<u8> {
+        match self.config.colors {
+            ColorMap::Heat => Some(HEAT[(fraction * (HEAT.len() - 1) as f32).round() as usize]),
+            ColorMap::Gray => Some(232 + (fraction * 23.0).round() as u8),
+            ColorMap::None => None,
+        }
+    }
+
+    /// Level of `band` in a slice, missing slices / bands read as silence
+    fn level(&self, slice: Option<&[f32]>, band: usize) -> f32 {
+        slice.and_then(|s| s.get(band)).map_or(0.0, |&db| self.fraction(db))
+    }
+
+    fn half_block_row(&self, top: Option<&[f32]>, bottom: Option<&[f32]>, width: usize) -> String {
+        let mut row = String::new();
+        let mut current = None;
+        for column in 0..width {
+            let fg = self.color(self.level(top, column)).unwrap_or(BLACK);
+            let bg = self.color(self.level(bottom, column)).unwrap_or(BLACK);
+            // Only emit colors when they change, keeps wide rows small
+            if current != Some((fg, bg)) {
+                row.push_str(&format!("\x1b[38;5;{}m\x1b[48;5;{}m", fg, bg));
+                current = Some((fg, bg));
+            }
+            row.push('▀');
+        }
+        row.push_str(RESET);
+        row
+    }
+
+    fn braille_row(&self, slices: &[Option<&[f32]>], width: usize) -> String {
+        // Dot bits by (column, slice) in a 2x4 braille cell
+        const DOTS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+        let mut row = String::new();
+        for column in 0..width {
+            let (mut bits, mut loudest) = (0, 0.0f32);
+            for (dx, dots) in DOTS.iter().enumerate() {
+                for (dy, &bit) in dots.iter().enumerate() {
+                    let level = self.level(slices[dy], column * 2 + dx);
+                    loudest = loudest.max(level);
+                    if level >= BRAILLE_ON {
+                        bits |= bit;
+                    }
+                }
+            }
+            let cell = char::from_u32(0x2800 + bits).unwrap_or(' ');
+            row.push_str(&self.paint(cell, loudest));
+        }
+        if self.config.colors != ColorMap::None {
+            row.push_str(RESET);
+        }
+        row
+    }
+
+    fn shade_row(&self, slice: Option<&[f32]>, width: usize) -> String {
+        let mut row = String::new();
+        for column in 0..width {
+            let level = self.level(slice, column);
+            let shade = SHADES[(level * (SHADES.len() - 1) as f32).round() as usize];
+            row.push_str(&self.paint(shade, level));
+        }
+        if self.config.colors != ColorMap::None {
+            row.push_str(RESET);
+        }
+        row
+    }
+
+    fn paint(&self, cell: char, fraction: f32) -> String {
+        match self.color(fraction) {
+            Some(color) => format!("\x1b[38;5;{}m{}", color, cell),
+            None => cell.to_string(),
+        }
+    }
+}
+
+/// 1, 2 or 5 x 10^n at or above `raw`
+fn nice_step(raw: f32) -> f32 {
+    let magnitude = 10f32.powf(raw.max(1e-3).log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|&step| step >= raw)
+        .unwrap_or(10.0 * magnitude)
+}
+
+/// Ex: 50 -> "50", 1500 -> "1.5k", 8000 -> "8k"
+fn hz_label(hz: f32) -> String {
+    if hz >= 1000.0 {
+        let khz = format!("{:.1}", hz / 1000.0);
+        format!("{}k", khz.trim_end_matches(".0"))
+    } else {
+        format!("{:.0}", hz)
+    }
+}
+
+/// Live spectrogram on the terminal, owns the screen and keyboard while it lives
+pub struct SpectrogramSink {
+    terminal: Terminal,
+    spectrogram: Spectrogram,
+    log: LogBuffer,
+}
+
+impl SpectrogramSink {
+    /// Take over the terminal, printed output shows one line at a time under the axis
+    pub fn enter(config: SpectrogramConfig) -> io::Result<Self> {
+        let log = LogBuffer::new(DEFAULT_LOG_LINES);
+        Ok(Self {
+            terminal: Terminal::enter(log.clone())?,
+            spectrogram: Spectrogram::new(config),
+            log,
+        })
+    }
+}
+
+impl DisplaySink for SpectrogramSink {
+    fn update(&mut self, snapshot: &DisplaySnapshot) -> io::Result<()> {
+        let (width, height) = self.terminal.size();
+        let (width, height) = (width.max(20) as usize, height.max(6) as usize);
+        if let Some(audio) = snapshot.audio.as_ref().filter(|audio| !audio.samples.is_empty()) {
+            self.spectrogram.push(&audio.samples, audio.channels, audio.sample_rate, width);
+        }
+
+        let (min_hz, max_hz) = self.spectrogram.range_hz();
+        let config = SpectrogramConfig {
+            min_hz,
+            max_hz,
+            ..self.spectrogram.config().clone()
+        };
+        let title = format!("MERLIN spectrogram  {}  {}", snapshot.device, config);
+        let mut rows = vec![title.chars().take(width).collect::<String>()];
+        rows.extend(self.spectrogram.render(width, height - 4)); // title, axis, log, help
+        rows.push(self.spectrogram.axis(width));
+        let last_log = self.log.tail(1).pop().unwrap_or_default();
+        rows.push(format!("{}{}{}", DIM, last_log.chars().take(width).collect::<String>(), RESET));
+        let help = "[l] log/linear  [r] record  [b] bypass filters  [q] quit";
+        rows.push(format!("{}{}{}", DIM, help.chars().take(width).collect::<String>(), RESET));
+        self.terminal.draw(&rows.join("\n"))
+    }
+
+    fn actions(&mut self) -> Vec<DisplayAction> {
+        let mut actions = Vec::new();
+        for key in self.terminal.read_input() {
+            if key == b'l' || key == b'L' {
+                let scale = match self.spectrogram.config().scale {
+                    FrequencyScale::Log => FrequencyScale::Linear,
+                    FrequencyScale::Linear => FrequencyScale::Log,
+                };
+                self.spectrogram.set_scale(scale);
+            } else if let Some(action) = DisplayAction::from_key(key) {
+                actions.push(action);
+            }
+        }
+        actions
+    }
+
+    fn interval(&self) -> Duration {
+        SPECTROGRAM_FRAME
+    }
+
+    fn audio_seconds(&self) -> Option<f32> {
+        Some(self.spectrogram.config().fft_size.next_power_of_two() as f32 / MIN_SAMPLE_RATE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    fn plain(glyphs: Glyphs) -> Spectrogram {
+        Spectrogram::new(SpectrogramConfig {
+            floor_db: -80.0,
+            ceiling_db: 0.0,
+            colors: ColorMap::None,
+            glyphs,
+            ..SpectrogramConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_history_scrolls_up_with_newest_at_the_bottom() {
+        let mut spectrogram = plain(Glyphs::Shade);
+        spectrogram.push_levels(vec![0.0, -20.0, -40.0, -60.0, -80.0]);
+        assert_eq!(spectrogram.render(5, 3), vec!["     ", "     ", "█▓▒░ "]);
+        spectrogram.push_levels(vec![-80.0; 5]);
+        assert_eq!(spectrogram.render(5, 3), vec!["     ", "█▓▒░ ", "     "]);
+
+        // Resize starts over
+        spectrogram.push_levels(vec![0.0; 3]);
+        assert_eq!(spectrogram.render(3, 2), vec!["   ", "███"]);
+    }
+
+    #[test]
+    fn test_braille_and_half_block_cells() {
+        let mut spectrogram = plain(Glyphs::Braille);
+        for _ in 0..4 {
+            spectrogram.push_levels(vec![0.0, -80.0]); // left band loud, right band silent
+        }
+        assert_eq!(spectrogram.slices_per_row(), 4);
+        assert_eq!(spectrogram.render(1, 1), vec!["⡇"]);
+
+        let mut spectrogram = Spectrogram::new(SpectrogramConfig {
+            floor_db: -80.0,
+            ceiling_db: 0.0,
+            ..SpectrogramConfig::default()
+        });
+        spectrogram.push_levels(vec![0.0, 0.0]);
+        spectrogram.push_levels(vec![-80.0, -80.0]);
+        assert_eq!(spectrogram.render(2, 1), vec!["\x1b[38;5;231m\x1b[48;5;16m▀▀\x1b[0m"]);
+    }
+
+    #[test]
+    fn test_tone_lands_under_its_axis_label() {
+        // 1kHz tone at 16kHz, shown 50Hz-8kHz
+        let samples: Vec<f32> = (0..16000).map(|i| 0.5 * (2.0 * PI * 1000.0 * i as f32 / 16000.0).sin()).collect();
+        for scale in [FrequencyScale::Log, FrequencyScale::Linear] {
+            let mut spectrogram = Spectrogram::new(SpectrogramConfig {
+                scale,
+                colors: ColorMap::None,
+                glyphs: Glyphs::Shade,
+                ..SpectrogramConfig::default()
+            });
+            spectrogram.push_recording(&samples, 1, 16000, 80, 4);
+            let rows = spectrogram.render(80, 4);
+            assert_eq!(rows.len(), 4);
+            let loudest = rows[3].chars().position(|c| c == '█').unwrap();
+            let axis = spectrogram.axis(80);
+            let label = axis.find("1k").unwrap();
+            assert!(loudest.abs_diff(label) <= 1, "{} scale: tone at {}, label at {}\n{}\n{}", scale, loudest, label, rows[3], axis);
+        }
+        assert_eq!(hz_label(1500.0), "1.5k");
+        assert_eq!(nice_step(730.0), 1000.0);
+    }
+}
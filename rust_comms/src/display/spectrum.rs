@@ -0,0 +1,258 @@
+use std::f32::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+/// Lowest dB reported, quieter bins are clamped here
+pub const SPECTRUM_FLOOR_DB: f32 = -120.0;
+
+/// How band edges are spaced between the lowest and highest frequency
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrequencyScale {
+    /// Equal width per octave, what the ear hears
+    #[default]
+    Log,
+    /// Equal width in Hz, easier to spot harmonics and hum
+    Linear,
+}
+
+impl FrequencyScale {
+    /// Frequency at `position` [0.0, 1.0] along the axis
+    pub fn frequency_at(self, position: f32, min_hz: f32, max_hz: f32) -> f32 {
+        match self {
+            FrequencyScale::Log => min_hz * (max_hz / min_hz).powf(position),
+            FrequencyScale::Linear => min_hz + (max_hz - min_hz) * position,
+        }
+    }
+
+    /// Inverse of frequency_at
+    pub fn position_of(self, hz: f32, min_hz: f32, max_hz: f32) -> f32 {
+        match self {
+            FrequencyScale::Log => (hz / min_hz).ln() / (max_hz / min_hz).ln(),
+            FrequencyScale::Linear => (hz - min_hz) / (max_hz - min_hz),
+        }
+    }
+}
+
+impl FromStr for FrequencyScale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "log" => Ok(FrequencyScale::Log),
+            "linear" | "lin" => Ok(FrequencyScale::Linear),
+            other => Err(format!("Unknown frequency scale {:?} (expected log or linear)", other)),
+        }
+    }
+}
+
+impl fmt::Display for FrequencyScale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FrequencyScale::Log => "log",
+            FrequencyScale::Linear => "linear",
+        })
+    }
+}
+
+/// In-place FFT (iterative radix-2 Cooley-Tukey)
+/// Args:
+/// - re / im: real and imaginary parts, same length, a power of two
+pub fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    assert_eq!(n, im.len(), "fft: re and im differ in length");
+    assert!(n.is_power_of_two(), "fft: length {} is not a power of two", n);
+
+    // Bit-reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    // Butterflies, twiddles computed in f64 per stage so large sizes stay accurate
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * std::f64::consts::PI / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f64).sin_cos();
+                let (w_re, w_im) = (cos as f32, sin as f32);
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Spectrum Analyzer
+///
+/// Magnitude spectrum of the newest audio, for the dashboard and spectrogram
+/// -Channels mixed to mono, Hann window, zero padded when short
+/// -Scaled to dBFS: a full-scale sine centred on a bin reads 0dB
+/// -Bands group bins on a log frequency axis, each band shows its loudest bin
+#[derive(Debug, Clone)]
+pub struct SpectrumAnalyzer {
+    size: usize,
+    window: Vec<f32>,
+    re: Vec<f32>,
+    im: Vec<f32>,
+}
+
+impl SpectrumAnalyzer {
+    /// Args:
+    /// - size: FFT length in frames, rounded up to a power of two
+    pub fn new(size: usize) -> Self {
+        let size = size.max(2).next_power_of_two();
+        let window = (0..size)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / size as f32).cos())
+            .collect();
+        Self {
+            size,
+            window,
+            re: vec![0.0; size],
+            im: vec![0.0; size],
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Magnitude in dBFS of bins 0..=size/2, from the last `size` frames of `samples`
+    /// Args:
+    /// - samples: interleaved f32 samples, newest last
+    /// - channels: interleaved channel count
+    pub fn magnitudes_db(&mut self, samples: &[f32], channels: u16) -> Vec<f32> {
+        let channels = channels.max(1) as usize;
+        let frames = samples.len() / channels;
+        let used = frames.min(self.size);
+        let newest = &samples[(frames - used) * channels..frames * channels];
+
+        self.re.iter_mut().for_each(|x| *x = 0.0);
+        self.im.iter_mut().for_each(|x| *x = 0.0);
+        for (i, frame) in newest.chunks_exact(channels).enumerate() {
+            let mono = frame.iter().sum::<f32>() / channels as f32;
+            self.re[i] = mono * self.window[i];
+        }
+        fft(&mut self.re, &mut self.im);
+
+        // Hann coherent gain is 0.5, so a unit sine peaks at size / 4
+        let scale = 4.0 / self.size as f32;
+        (0..=self.size / 2)
+            .map(|k| {
+                let magnitude = (self.re[k] * self.re[k] + self.im[k] * self.im[k]).sqrt() * scale;
+                (20.0 * magnitude.max(1e-10).log10()).max(SPECTRUM_FLOOR_DB)
+            })
+            .collect()
+    }
+
+    /// `bands` log-spaced bands between min_hz and max_hz (capped at Nyquist), in dBFS
+    pub fn log_bands(
+        &mut self,
+        samples: &[f32],
+        channels: u16,
+        sample_rate: u32,
+        bands: usize,
+        min_hz: f32,
+        max_hz: f32,
+    ) -> Vec<f32> {
+        self.bands(samples, channels, sample_rate, bands, (min_hz, max_hz), FrequencyScale::Log)
+    }
+
+    /// `bands` bands spaced on `scale` across range_hz (capped at Nyquist), in dBFS
+    pub fn bands(
+        &mut self,
+        samples: &[f32],
+        channels: u16,
+        sample_rate: u32,
+        bands: usize,
+        range_hz: (f32, f32),
+        scale: FrequencyScale,
+    ) -> Vec<f32> {
+        let magnitudes = self.magnitudes_db(samples, channels);
+        let (min_hz, max_hz) = clamp_range(range_hz, sample_rate);
+        let edge = |i: usize| scale.frequency_at(i as f32 / bands as f32, min_hz, max_hz);
+        (0..bands)
+            .map(|band| band_level(&magnitudes, self.size, sample_rate, edge(band), edge(band + 1)))
+            .collect()
+    }
+}
+
+/// Range kept inside [1Hz, Nyquist] with min <= max
+pub fn clamp_range((min_hz, max_hz): (f32, f32), sample_rate: u32) -> (f32, f32) {
+    let max_hz = max_hz.min(sample_rate as f32 / 2.0);
+    (min_hz.max(1.0).min(max_hz), max_hz)
+}
+
+/// Loudest bin whose centre lies in [low_hz, high_hz), nearest bin when the band is narrower than one bin
+pub(crate) fn band_level(magnitudes: &[f32], size: usize, sample_rate: u32, low_hz: f32, high_hz: f32) -> f32 {
+    let bin_hz = sample_rate as f32 / size as f32;
+    let last = magnitudes.len() - 1;
+    let first = ((low_hz / bin_hz).ceil() as usize).min(last);
+    let end = ((high_hz / bin_hz).ceil() as usize).min(last + 1);
+    if first >= end {
+        let centre = ((low_hz + high_hz) / 2.0 / bin_hz).round() as usize;
+        return magnitudes[centre.min(last)];
+    }
+    magnitudes[first..end].iter().copied().fold(SPECTRUM_FLOOR_DB, f32::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fft_matches_naive_dft() {
+        let n = 64;
+        let input: Vec<f32> = (0..n).map(|i| ((i * 7 % 13) as f32 - 6.0) / 6.0).collect();
+        let mut re = input.clone();
+        let mut im = vec![0.0; n];
+        fft(&mut re, &mut im);
+        for k in 0..n {
+            let (mut dft_re, mut dft_im) = (0.0f64, 0.0f64);
+            for (t, &x) in input.iter().enumerate() {
+                let angle = -2.0 * std::f64::consts::PI * (k * t) as f64 / n as f64;
+                dft_re += x as f64 * angle.cos();
+                dft_im += x as f64 * angle.sin();
+            }
+            assert!((re[k] as f64 - dft_re).abs() < 1e-4 && (im[k] as f64 - dft_im).abs() < 1e-4, "bin {}", k);
+        }
+    }
+
+    #[test]
+    fn test_sine_peaks_in_its_band_at_full_scale() {
+        // 1.5kHz stereo sine (exactly bin 32 of 1024 at 48kHz), older audio ahead of the window is ignored
+        let mut samples = vec![0.9; 2 * 4096];
+        for i in 0..1024 {
+            let value = (2.0 * PI * 1500.0 * i as f32 / 48000.0).sin();
+            samples.extend_from_slice(&[value, value]);
+        }
+        let mut analyzer = SpectrumAnalyzer::new(1000);
+        assert_eq!(analyzer.size(), 1024);
+
+        let magnitudes = analyzer.magnitudes_db(&samples, 2);
+        assert_eq!(magnitudes.len(), 513);
+        assert!(magnitudes[32].abs() < 0.1, "{}", magnitudes[32]);
+        assert!(magnitudes[100] < -60.0);
+
+        let bands = analyzer.log_bands(&samples, 2, 48000, 16, 50.0, 24000.0);
+        let loudest = (0..16).max_by(|&a, &b| bands[a].total_cmp(&bands[b])).unwrap();
+        let low = 50.0 * (480.0f32).powf(loudest as f32 / 16.0);
+        let high = 50.0 * (480.0f32).powf((loudest + 1) as f32 / 16.0);
+        assert!(low <= 1500.0 && 1500.0 < high);
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Lines kept for the log pane
+pub const DEFAULT_LOG_LINES: usize = 500;
+
+/// True when stdin and stdout are both terminals, i.e. the dashboard can run
+pub fn is_tty() -> bool {
+    unsafe { libc::isatty(libc::STDIN_FILENO) == 1 && libc::isatty(libc::STDOUT_FILENO) == 1 }
+}
+
+/// (columns, rows) of the terminal on stdout, None if it isn't one
+pub fn stdout_size() -> Option<(u16, u16)> {
+    window_size(libc::STDOUT_FILENO)
+}
+
+fn window_size(fd: RawFd) -> Option<(u16, u16)> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let ok = unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) } == 0;
+    (ok && size.ws_col > 0 && size.ws_row > 0).then_some((size.ws_col, size.ws_row))
+}
+
+/// Recent output lines, shared between the capture thread and the dashboard
+#[derive(Debug, Clone)]
+pub struct LogBuffer {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    capacity: usize,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Add a line, control characters (ANSI colors, \r redraws) are stripped, blank lines skipped
+    pub fn push(&self, line: &str) {
+        let line = sanitize(line);
+        if line.trim().is_empty() {
+            return;
+        }
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    /// Newest `count` lines, oldest first
+    pub fn tail(&self, count: usize) -> Vec<String> {
+        let lines = self.lines.lock().unwrap();
+        lines.iter().skip(lines.len().saturating_sub(count)).cloned().collect()
+    }
+}
+
+/// Drop escape sequences and other control characters, keep what follows the last \r
+pub(crate) fn sanitize(line: &str) -> String {
+    let line = line.rsplit('\r').find(|part| !part.is_empty()).unwrap_or("");
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // CSI: ESC [ params... final byte in @..~
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+        } else if c == '\t' {
+            out.push_str("    ");
+        } else if !c.is_control() {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Point `fds` (ex: stdout / stderr) at a new pipe, a thread hands every line written to them to `on_line`
+/// The thread ends by itself once the fds are pointed elsewhere again (last write end closed)
+pub(crate) fn capture_lines(fds: &[RawFd], mut on_line: impl FnMut(&str) + Send + 'static) -> io::Result<()> {
+    let mut pipe = [0; 2];
+    check(unsafe { libc::pipe(pipe.as_mut_ptr()) })?;
+    for &fd in fds {
+        check(unsafe { libc::dup2(pipe[1], fd) })?;
+    }
+    unsafe { libc::close(pipe[1]) };
+    let reader = unsafe { File::from_raw_fd(pipe[0]) };
+    thread::spawn(move || {
+        for line in BufReader::new(reader).split(b'\n') {
+            let Ok(line) = line else { break };
+            on_line(&String::from_utf8_lossy(&line));
+        }
+    });
+    Ok(())
+}
+
+/// Terminal
+///
+/// Just enough terminal handling for the dashboard, plain ANSI + termios (no curses)
+/// -Raw input: no echo or line buffering, Ctrl-C arrives as a key instead of killing the process
+/// -Alternate screen, the shell's scrollback is left as it was
+/// -stdout/stderr are redirected into a LogBuffer while active, so stray prints land in the log pane
+/// -Everything is restored on drop
+pub struct Terminal {
+    tty: File,
+    saved_termios: libc::termios,
+    saved_stdout: OwnedFd,
+    saved_stderr: OwnedFd,
+    log: LogBuffer,
+}
+
+impl Terminal {
+    /// Take over the terminal, output printed from now on goes to `log`
+    pub fn enter(log: LogBuffer) -> io::Result<Self> {
+        io::stdout().flush()?;
+        let mut saved_termios: libc::termios = unsafe { std::mem::zeroed() };
+        check(unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut saved_termios) })?;
+        let mut raw = saved_termios;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+        raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+        raw.c_cc[libc::VMIN] = 0; // reads return straight away
+        raw.c_cc[libc::VTIME] = 0;
+        check(unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) })?;
+
+        // Until the Terminal exists its Drop can't put the mode back, undo it here on failure
+        let restore_termios = |error: io::Error| {
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &saved_termios) };
+            error
+        };
+        let saved_stdout = dup_fd(libc::STDOUT_FILENO).map_err(restore_termios)?;
+        let saved_stderr = dup_fd(libc::STDERR_FILENO).map_err(restore_termios)?;
+        let tty = File::from(saved_stdout.try_clone().map_err(restore_termios)?);
+        let capture = log.clone();
+        let mut terminal = Self {
+            tty,
+            saved_termios,
+            saved_stdout,
+            saved_stderr,
+            log,
+        };
+
+        // From here on errors drop `terminal`, which restores the fds and termios
+        capture_lines(&[libc::STDOUT_FILENO, libc::STDERR_FILENO], move |line| capture.push(line))?;
+        terminal.tty.write_all(b"\x1b[?1049h\x1b[?25l\x1b[2J")?; // alternate screen, hide cursor
+        Ok(terminal)
+    }
+
+    /// (columns, rows), 80x24 if the size can't be read
+    pub fn size(&self) -> (u16, u16) {
+        window_size(self.tty.as_raw_fd()).unwrap_or((80, 24))
+    }
+
+    /// Redraw the whole screen, `frame` lines should already fit the width
+    pub fn draw(&mut self, frame: &str) -> io::Result<()> {
+        let mut out = String::with_capacity(frame.len() + 256);
+        out.push_str("\x1b[H");
+        for (i, line) in frame.lines().enumerate() {
+            if i > 0 {
+                out.push_str("\r\n");
+            }
+            out.push_str(line);
+            out.push_str("\x1b[0m\x1b[K"); // reset color, clear the rest of the row
+        }
+        out.push_str("\x1b[J");
+        self.tty.write_all(out.as_bytes())?;
+        self.tty.flush()
+    }
+
+    /// Bytes typed since the last call, never blocks
+    pub fn read_input(&mut self) -> Vec<u8> {
+        let mut buffer = [0u8; 64];
+        match io::stdin().lock().read(&mut buffer) {
+            Ok(n) => buffer[..n].to_vec(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = self.tty.write_all(b"\x1b[?25h\x1b[?1049l");
+        let _ = self.tty.flush();
+        let _ = io::stdout().flush();
+        unsafe {
+            libc::dup2(self.saved_stdout.as_raw_fd(), libc::STDOUT_FILENO);
+            libc::dup2(self.saved_stderr.as_raw_fd(), libc::STDERR_FILENO);
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.saved_termios);
+        }
+        // A panic message was captured along with everything else, show the tail so it isn't lost
+        if thread::panicking() {
+            for line in self.log.tail(20) {
+                eprintln!("{}", line);
+            }
+        }
+    }
+}
+
+fn dup_fd(fd: RawFd) -> io::Result<OwnedFd> {
+    Ok(unsafe { OwnedFd::from_raw_fd(check(libc::dup(fd))?) })
+}
+
+fn check(result: libc::c_int) -> io::Result<libc::c_int> {
+    if result < 0 { Err(io::Error::last_os_error()) } else { Ok(result) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_buffer_strips_control_and_keeps_tail() {
+        let log = LogBuffer::new(3);
+        log.push("\x1b[2K\rAudio: [\x1b[32m███\x1b[0m] -20dB");
+        log.push("   ");
+        log.push("one\ttwo");
+        log.push("three");
+        log.push("four");
+        assert_eq!(log.tail(10), vec!["one    two", "three", "four"]);
+        assert_eq!(log.tail(1), vec!["four"]);
+
+        let log = LogBuffer::new(3);
+        log.push("\x1b[2K\rAudio: [\x1b[32m███\x1b[0m] -20dB");
+        assert_eq!(log.tail(1), vec!["Audio: [███] -20dB"]);
+    }
+}
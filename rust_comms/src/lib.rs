@@ -1,19 +1,266 @@
 pub mod audio;
 pub mod display;
 pub mod ar;
+pub mod monitoring;
 
-pub use audio::{AudioMetrics, AudioProcessor, WavFileWriter};
+pub use audio::{AudioMetrics, AudioPipeline, AudioProcessor, RecordingCatalog, RecordingInfo, RecordingMetadata, RecordingQuery, ReplayBuffer, WavFileWriter};
 pub use audio::filters::{NoiseGate, Normalizer};
 pub use display::AudioMeter;
 pub use ar::{ARBridgeServer, ARFrame};
 
 // python binding via PyO3
+use pyo3::buffer::PyBuffer;
+use ar::protocol::{BoundingBox, DetectedObject, GestureType, HandPose, HandTrackingData, TrackingSource, Vector3};
+use ar::{BridgeStats, FrameFeed, StreamConfig, DEFAULT_FRAME_SOCKET_PATH};
+use audio::{AudioWriter, FilterConfig, LoudnessMeter, RecordingContext, RollingLevels, RotationPolicy, WavFormat};
+use audio::metrics::ChannelLevels;
+use pyo3::exceptions::{PyConnectionError, PyTypeError, PyValueError};
 use pyo3::prelude::*;
-use pyo3::types::PyBytes;
+use pyo3::types::{PyBytes, PyMemoryView, PyString};
+use std::borrow::Cow;
+use std::io::Write;
+use std::os::unix::net::UnixStream;
+use std::sync::Arc;
 
-#[pyclass]
+/// f32 samples borrowed from a Python buffer, no copy
+///
+/// -float32 buffers: numpy arrays, array('f'), memoryview.cast('f')
+/// -raw byte buffers (bytes, bytearray) holding native f32, the old bytes API
+/// -C-contiguous, 1-D or 2-D (frames, channels) interleaved
+/// -byte buffers off a 4-byte boundary are read through a copy and can't be filtered in place
+enum SampleBuffer {
+    Floats(PyBuffer<f32>),
+    Bytes(PyBuffer<u8>),
+    /// Zero-length buffer, array('f') hands out an unaligned pointer for these
+    Empty,
+}
+
+impl SampleBuffer {
+    fn get(obj: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let buffer = if let Ok(floats) = PyBuffer::<f32>::get(obj) {
+            SampleBuffer::Floats(floats)
+        } else if let Ok(bytes) = PyBuffer::<u8>::get(obj) {
+            if !bytes.len_bytes().is_multiple_of(4) {
+                return Err(PyValueError::new_err(format!(
+                    "{} bytes is not a whole number of float32 samples",
+                    bytes.len_bytes()
+                )));
+            }
+            SampleBuffer::Bytes(bytes)
+        } else {
+            // Not float32 or bytes: name the dtype if it's a buffer at all
+            let view = PyMemoryView::from(obj);
+            if view.as_ref().is_ok_and(|view| view.len().is_ok_and(|len| len == 0)) {
+                return Ok(SampleBuffer::Empty);
+            }
+            let format = view
+                .and_then(|view| view.getattr("format"))
+                .and_then(|format| format.extract::<String>());
+            return Err(match format {
+                Ok(format) if format == "f" => PyValueError::new_err("float32 buffer is not 4-byte aligned"),
+                Ok(format) => PyValueError::new_err(format!("expected float32 samples, got buffer format '{}'", format)),
+                Err(_) => PyTypeError::new_err(format!(
+                    "expected a float32 array or bytes-like object, got {}",
+                    obj.get_type().name()?
+                )),
+            });
+        };
+        if !buffer.is_c_contiguous() {
+            return Err(PyValueError::new_err("samples must be C-contiguous, use numpy.ascontiguousarray"));
+        }
+        if buffer.dimensions() > 2 {
+            return Err(PyValueError::new_err(format!(
+                "expected 1-D samples or 2-D (frames, channels), got {}-D",
+                buffer.dimensions()
+            )));
+        }
+        Ok(buffer)
+    }
+
+    fn ptr(&self) -> *mut f32 {
+        match self {
+            SampleBuffer::Floats(buffer) => buffer.buf_ptr() as *mut f32,
+            SampleBuffer::Bytes(buffer) => buffer.buf_ptr() as *mut f32,
+            SampleBuffer::Empty => std::ptr::NonNull::dangling().as_ptr(),
+        }
+    }
+
+    /// Sample count, not bytes
+    fn len(&self) -> usize {
+        match self {
+            SampleBuffer::Floats(buffer) => buffer.item_count(),
+            SampleBuffer::Bytes(buffer) => buffer.len_bytes() / 4,
+            SampleBuffer::Empty => 0,
+        }
+    }
+
+    fn readonly(&self) -> bool {
+        match self {
+            SampleBuffer::Floats(buffer) => buffer.readonly(),
+            SampleBuffer::Bytes(buffer) => buffer.readonly(),
+            SampleBuffer::Empty => false,
+        }
+    }
+
+    fn is_c_contiguous(&self) -> bool {
+        match self {
+            SampleBuffer::Floats(buffer) => buffer.is_c_contiguous(),
+            SampleBuffer::Bytes(buffer) => buffer.is_c_contiguous(),
+            SampleBuffer::Empty => true,
+        }
+    }
+
+    fn dimensions(&self) -> usize {
+        match self {
+            SampleBuffer::Floats(buffer) => buffer.dimensions(),
+            SampleBuffer::Bytes(buffer) => buffer.dimensions(),
+            SampleBuffer::Empty => 1,
+        }
+    }
+
+    /// Samples per row of a 2-D (frames, channels) buffer, None for 1-D
+    fn row_len(&self) -> Option<usize> {
+        match self {
+            SampleBuffer::Floats(buffer) if buffer.dimensions() == 2 => Some(buffer.shape()[1]),
+            SampleBuffer::Bytes(buffer) if buffer.dimensions() == 2 => Some(buffer.shape()[1] / 4),
+            _ => None,
+        }
+    }
+
+    /// False for a byte buffer that doesn't start on a 4-byte boundary
+    fn is_aligned(&self) -> bool {
+        self.len() == 0 || (self.ptr() as usize).is_multiple_of(std::mem::align_of::<f32>())
+    }
+
+    /// Borrowed when aligned, copied out of the bytes otherwise
+    fn samples(&self) -> Cow<'_, [f32]> {
+        if self.len() == 0 {
+            return Cow::Borrowed(&[]);
+        }
+        if self.is_aligned() {
+            // Safety: contiguous, aligned and len() f32s long (checked in get), buffer held by self
+            return Cow::Borrowed(unsafe { std::slice::from_raw_parts(self.ptr(), self.len()) });
+        }
+        // Safety: contiguous and len() * 4 bytes long, read as bytes so alignment doesn't matter
+        let bytes = unsafe { std::slice::from_raw_parts(self.ptr() as *const u8, self.len() * 4) };
+        Cow::Owned(bytes.chunks_exact(4).map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]])).collect())
+    }
+
+    /// Pointer for filtering in place, caller checked the buffer is writable
+    fn sample_ptr(&self, what: &str) -> PyResult<SamplePtr> {
+        if !self.is_aligned() {
+            return Err(PyValueError::new_err(format!("{} is not 4-byte aligned for float32", what)));
+        }
+        Ok(SamplePtr(self.ptr(), self.len()))
+    }
+}
+
+/// Raw sample pointer that can cross into py.detach
+///
+/// -only dereferenced while the owning PyBuffer is alive in the caller
+/// -the export keeps numpy arrays / bytearrays from being resized meanwhile, but other Python threads
+///  can still write to the same memory, callers mustn't touch an array while it is being processed
+struct SamplePtr(*mut f32, usize);
+
+unsafe impl Send for SamplePtr {}
+
+impl SamplePtr {
+    /// Safety: pointer valid and aligned for len f32 writes, not aliased by Rust for the lifetime
+    unsafe fn as_mut_slice<'a>(&self) -> &'a mut [f32] {
+        if self.1 == 0 {
+            return &mut [];
+        }
+        unsafe { std::slice::from_raw_parts_mut(self.0, self.1) }
+    }
+
+    /// Safety: both valid for self.1 * 4 bytes, may overlap, copied bytewise so `self` may be unaligned
+    unsafe fn copy_to(&self, to: &SamplePtr) {
+        if self.1 > 0 {
+            unsafe { std::ptr::copy(self.0 as *const u8, to.0 as *mut u8, self.1 * 4) };
+        }
+    }
+}
+
+fn f32_bytes(samples: &[f32]) -> Vec<u8> {
+    samples.iter().flat_map(|&f| f.to_ne_bytes()).collect()
+}
+
+/// Run a filter over Python samples with the GIL released
+///
+/// -out given: samples copied into out (same length, writable) and processed there, returns out
+/// -bytes and no out: processed copy returned as new bytes (old API)
+/// -other writable buffer and no out: processed in place, returns samples
+/// -in place and out= work on the Python memory directly, don't use the array from another
+///  thread until this returns
+///
+/// Args:
+///     samples: float32 array / buffer, or bytes of native f32
+///     out: optional writable float32 buffer for the result
+///     filter: the Rust filter's process, runs without the GIL
+fn process_buffer<'py>(
+    py: Python<'py>,
+    samples: &Bound<'py, PyAny>,
+    out: Option<&Bound<'py, PyAny>>,
+    filter: impl FnOnce(&mut [f32]) + Send,
+) -> PyResult<Py<PyAny>> {
+    let input = SampleBuffer::get(samples)?;
+    if let Some(out) = out {
+        let output = SampleBuffer::get(out)?;
+        if output.readonly() {
+            return Err(PyValueError::new_err("out is read-only"));
+        }
+        let to = output.sample_ptr("out")?;
+        if output.len() != input.len() {
+            return Err(PyValueError::new_err(format!(
+                "out holds {} samples, input has {}",
+                output.len(),
+                input.len()
+            )));
+        }
+        let from = SamplePtr(input.ptr(), input.len());
+        py.detach(move || {
+            // ptr::copy since out may be the input itself
+            unsafe { from.copy_to(&to) };
+            filter(unsafe { to.as_mut_slice() });
+        });
+        return Ok(out.clone().unbind());
+    }
+    if samples.is_instance_of::<PyBytes>() {
+        let mut copy = input.samples().into_owned();
+        let copy = py.detach(move || {
+            filter(&mut copy);
+            f32_bytes(&copy)
+        });
+        return Ok(PyBytes::new(py, &copy).into_any().unbind());
+    }
+    if input.readonly() {
+        return Err(PyValueError::new_err("samples are read-only, pass a writable array or out="));
+    }
+    let samples_ptr = input.sample_ptr("samples")?;
+    py.detach(move || filter(unsafe { samples_ptr.as_mut_slice() }));
+    Ok(samples.clone().unbind())
+}
+
+/// Filter state -> JSON bytes for __getstate__
+fn state_to_py<T: serde::Serialize>(py: Python<'_>, state: &T) -> PyResult<Py<PyAny>> {
+    let json = serde_json::to_vec(state).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(PyBytes::new(py, &json).into_any().unbind())
+}
+
+/// __setstate__ input (bytes or str of JSON) -> filter state
+fn state_from_py<T: serde::de::DeserializeOwned>(state: &Bound<'_, PyAny>) -> PyResult<T> {
+    let parsed = match state.cast::<PyBytes>() {
+        Ok(bytes) => serde_json::from_slice(bytes.as_bytes()),
+        Err(_) => serde_json::from_str(&state.extract::<String>()?),
+    };
+    parsed.map_err(|e| PyValueError::new_err(format!("Bad filter state: {}", e)))
+}
+
+#[pyclass(module = "merlin_audio")]
 pub struct PyNoiseGate {
     inner: NoiseGate,
+    /// Constructor args, pickled alongside the state
+    args: (f32, f32, f32, f32),
 }
 
 #[pymethods]
@@ -22,25 +269,32 @@ impl PyNoiseGate {
     fn new(threshold_db: f32, attack_ms: f32, release_ms: f32, sample_rate: f32) -> Self {
         Self {
             inner: NoiseGate::new(threshold_db, attack_ms, release_ms, sample_rate),
+            args: (threshold_db, attack_ms, release_ms, sample_rate),
         }
     }
 
-    fn process(&mut self, py: Python, samples: &PyBytes) -> PyResult<PyObject> {
-        let bytes = samples.as_bytes();
-        //convert bytes to f32 vector
-        let mut float_samples: Vec<f32> = bytes
-            .chunks_exact(4)
-            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-            .collect();
+    fn __getnewargs__(&self) -> (f32, f32, f32, f32) {
+        self.args
+    }
 
-        // apply rust noise gate filter
-        self.inner.process(&mut float_samples);
-        //convert back to bytes
-        let result_bytes: Vec<u8> = float_samples
-            .iter()
-            .flat_map(|&f| f.to_le_bytes())
-            .collect();
-        Ok(PyBytes::new(py, &result_bytes).into())
+    /// Envelope and open/closed state as JSON bytes, for pickle or a state file
+    fn __getstate__(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        state_to_py(py, &self.inner.snapshot())
+    }
+
+    /// Carry on from a saved state, works on a gate with other parameters too
+    fn __setstate__(&mut self, state: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.inner.restore(&state_from_py(state)?).map_err(PyValueError::new_err)
+    }
+
+    /// Filter float32 samples, in place or into `out`
+    ///
+    /// -numpy float32 / array('f') / writable buffer: processed in place, returned
+    /// -bytes: returns new bytes (old API)
+    /// -out: result written there instead, input untouched
+    #[pyo3(signature = (samples, out=None))]
+    fn process(&mut self, py: Python<'_>, samples: &Bound<'_, PyAny>, out: Option<&Bound<'_, PyAny>>) -> PyResult<Py<PyAny>> {
+        process_buffer(py, samples, out, |samples| self.inner.process(samples))
     }
 
     fn reset(&mut self) {
@@ -49,9 +303,10 @@ impl PyNoiseGate {
 }
 
 // Python wrapper for normalizer filter
-#[pyclass]
+#[pyclass(module = "merlin_audio")]
 pub struct PyNormalizer {
     inner: Normalizer,
+    args: (f32, f32, f32),
 }
 
 #[pymethods]
@@ -60,36 +315,1378 @@ impl PyNormalizer {
     fn new(target_level_db: f32, window_ms: f32, sample_rate: f32) -> Self {
         Self {
             inner: Normalizer::new(target_level_db, window_ms, sample_rate),
+            args: (target_level_db, window_ms, sample_rate),
         }
     }
 
-    fn process(&mut self, py: Python, samples: &PyBytes) -> PyResult<PyObject> {
-        let bytes = samples.as_bytes();
+    fn __getnewargs__(&self) -> (f32, f32, f32) {
+        self.args
+    }
+
+    /// RMS window and smoothed gain as JSON bytes
+    fn __getstate__(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        state_to_py(py, &self.inner.snapshot())
+    }
 
-        let mut float_samples: Vec<f32> = bytes
-            .chunks_exact(4)
-            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-            .collect();
+    fn __setstate__(&mut self, state: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.inner.restore(&state_from_py(state)?).map_err(PyValueError::new_err)
+    }
 
-        self.inner.process(&mut float_samples);
+    /// Filter float32 samples, in place or into `out`
+    ///
+    /// -numpy float32 / array('f') / writable buffer: processed in place, returned
+    /// -bytes: returns new bytes (old API)
+    /// -out: result written there instead, input untouched
+    #[pyo3(signature = (samples, out=None))]
+    fn process(&mut self, py: Python<'_>, samples: &Bound<'_, PyAny>, out: Option<&Bound<'_, PyAny>>) -> PyResult<Py<PyAny>> {
+        process_buffer(py, samples, out, |samples| self.inner.process(samples))
+    }
 
-        let result_bytes: Vec<u8> = float_samples
-            .iter()
-            .flat_map(|&f| f.to_le_bytes())
-            .collect();
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+}
+
+/// New float32 object holding `samples`, the same kind as `like`
+///
+/// -bytes in: bytes out
+/// -numpy array in: numpy float32 array out, (frames, channels) when multichannel
+/// -anything else: array('f')
+fn samples_to_py<'py>(py: Python<'py>, like: &Bound<'py, PyAny>, samples: &[f32], channels: u16) -> PyResult<Bound<'py, PyAny>> {
+    let bytes = PyBytes::new(py, &f32_bytes(samples));
+    if like.is_instance_of::<PyBytes>() {
+        return Ok(bytes.into_any());
+    }
+    if like.hasattr("__array_interface__")?
+        && let Ok(numpy) = py.import("numpy")
+    {
+        let array = numpy.call_method1("frombuffer", (bytes, "float32"))?.call_method0("copy")?;
+        return if channels > 1 { array.call_method1("reshape", (-1, channels)) } else { Ok(array) };
+    }
+    py.import("array")?.call_method1("array", ("f", bytes))
+}
+
+/// Gate / normalizer / resample / metrics chain in one call
+///
+/// Config is a dict or JSON string (see audio::pipeline::PipelineConfig):
+/// {"sample_rate": 16000, "stages": [{"type": "gate", "threshold_db": -45}, {"type": "normalizer"}, {"type": "metrics"}]}
+#[pyclass(module = "merlin_audio")]
+pub struct PyAudioPipeline {
+    inner: AudioPipeline,
+}
 
-        Ok(PyBytes::new(py, &result_bytes).into())
+#[pymethods]
+impl PyAudioPipeline {
+    #[new]
+    fn new(py: Python<'_>, config: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let json: String = match config.extract::<String>() {
+            Ok(json) => json,
+            Err(_) => py.import("json")?.call_method1("dumps", (config,))?.extract()?,
+        };
+        Ok(Self {
+            inner: AudioPipeline::from_json(&json).map_err(PyValueError::new_err)?,
+        })
     }
 
+    /// Run one chunk through every stage with the GIL released
+    ///
+    /// Returns (audio, metrics): audio is new (the input is untouched) and the same kind as
+    /// the input, metrics is {"sample_rate", "channels", "stages": {name: {...}}}
+    fn process<'py>(&mut self, py: Python<'py>, samples: &Bound<'py, PyAny>) -> PyResult<(Bound<'py, PyAny>, Py<PyAny>)> {
+        let input = SampleBuffer::get(samples)?.samples().into_owned();
+        let pipeline = &mut self.inner;
+        let output = py.detach(move || pipeline.process(&input)).map_err(PyValueError::new_err)?;
+        let audio = samples_to_py(py, samples, &output.samples, output.channels)?;
+        Ok((audio, json_to_py(py, &output)?))
+    }
+
+    /// Clear filter envelopes, gain, resampler history and loudness
     fn reset(&mut self) {
         self.inner.reset();
     }
+
+    /// Config as JSON, pickle rebuilds the stages from it before __setstate__
+    fn __getnewargs__(&self) -> PyResult<(String,)> {
+        let config = serde_json::to_string(self.inner.config()).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok((config,))
+    }
+
+    /// Gate and normalizer state by stage name as JSON bytes
+    fn __getstate__(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        state_to_py(py, &self.inner.snapshot())
+    }
+
+    /// Restore by stage name, all or nothing (ValueError on unknown names / mismatched types)
+    fn __setstate__(&mut self, state: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.inner.restore(&state_from_py(state)?).map_err(PyValueError::new_err)
+    }
+
+    /// Config with every default filled in, as a dict
+    fn config(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        json_to_py(py, self.inner.config())
+    }
+
+    #[getter]
+    fn output_rate(&self) -> u32 {
+        self.inner.output_rate()
+    }
+
+    #[getter]
+    fn output_channels(&self) -> u16 {
+        self.inner.output_channels()
+    }
+}
+
+/// Finished recording, what WavFileWriter.finish_writing returned
+#[pyclass(frozen)]
+pub struct PyRecordingInfo {
+    inner: RecordingInfo,
+}
+
+#[pymethods]
+impl PyRecordingInfo {
+    #[getter]
+    fn file_path(&self) -> String {
+        self.inner.file_path.to_string_lossy().into_owned()
+    }
+
+    /// JSON sidecar written next to the audio
+    #[getter]
+    fn sidecar_path(&self) -> String {
+        audio::sidecar::sidecar_path(&self.inner.file_path).to_string_lossy().into_owned()
+    }
+
+    #[getter]
+    fn duration_seconds(&self) -> f64 {
+        self.inner.duration_seconds
+    }
+
+    #[getter]
+    fn file_size_bytes(&self) -> u64 {
+        self.inner.file_size_bytes
+    }
+
+    #[getter]
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate
+    }
+
+    #[getter]
+    fn channels(&self) -> u16 {
+        self.inner.channels
+    }
+
+    fn to_dict(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        json_to_py(py, &self.inner)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "PyRecordingInfo({:?}, {:.2}s, {} bytes)",
+            self.inner.file_path, self.inner.duration_seconds, self.inner.file_size_bytes
+        )
+    }
+}
+
+/// Levels of the audio written so far, same fields as rust_comms' AudioMetrics
+/// Read-only copy, take a new one with PyWavFileWriter.metrics()
+#[pyclass(frozen)]
+pub struct PyMetricsSnapshot {
+    inner: AudioMetrics,
+}
+
+/// LUFS values are -inf until enough audio has been seen, None reads better in Python
+fn finite(value: f32) -> Option<f32> {
+    value.is_finite().then_some(value)
+}
+
+#[pymethods]
+impl PyMetricsSnapshot {
+    /// Last chunk RMS / peak (linear) and RMS in dBFS
+    #[getter]
+    fn rms(&self) -> f32 {
+        self.inner.rms
+    }
+
+    #[getter]
+    fn peak(&self) -> f32 {
+        self.inner.peak
+    }
+
+    #[getter]
+    fn db(&self) -> f32 {
+        self.inner.db
+    }
+
+    #[getter]
+    fn peak_hold_db(&self) -> f32 {
+        self.inner.levels.peak_hold_db
+    }
+
+    #[getter]
+    fn clipped_total(&self) -> u64 {
+        self.inner.levels.clipped_total
+    }
+
+    #[getter]
+    fn momentary_lufs(&self) -> Option<f32> {
+        finite(self.inner.loudness.momentary_lufs)
+    }
+
+    #[getter]
+    fn short_term_lufs(&self) -> Option<f32> {
+        finite(self.inner.loudness.short_term_lufs)
+    }
+
+    #[getter]
+    fn integrated_lufs(&self) -> Option<f32> {
+        finite(self.inner.loudness.integrated_lufs)
+    }
+
+    /// (rms_db, peak_db, clipped) per channel of the last chunk
+    #[getter]
+    fn channels(&self) -> Vec<(f32, f32, bool)> {
+        self.inner
+            .channels
+            .as_slice()
+            .iter()
+            .map(|level| (level.rms_db, level.peak_db, level.clipped))
+            .collect()
+    }
+
+    /// Everything, including the 1s/10s/60s windows, in the JSON telemetry shape
+    fn to_dict(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        json_to_py(py, &self.inner)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("PyMetricsSnapshot(db={:.1}, peak={:.3})", self.inner.db, self.inner.peak)
+    }
+}
+
+/// AudioMetrics kept up to date from the chunks written, like AudioProcessor does for the mic
+struct WriterMeter {
+    levels: RollingLevels,
+    loudness: LoudnessMeter,
+    metrics: AudioMetrics,
+}
+
+impl WriterMeter {
+    fn new(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            levels: RollingLevels::new(sample_rate, channels, FilterConfig::default().gate_threshold_db),
+            loudness: LoudnessMeter::new(sample_rate, channels),
+            metrics: AudioMetrics::new(),
+        }
+    }
+
+    fn update(&mut self, samples: &[f32], channels: u16) {
+        if samples.is_empty() {
+            return;
+        }
+        let sum_squares: f64 = samples.iter().map(|&x| (x * x) as f64).sum();
+        let rms = (sum_squares / samples.len() as f64).sqrt() as f32;
+        let peak = samples.iter().map(|&x| x.abs()).fold(0.0f32, f32::max);
+        self.metrics.update(rms, peak, 20.0 * rms.max(1e-10).log10());
+        self.metrics.set_levels(self.levels.update(samples));
+        self.metrics.set_loudness(self.loudness.process(samples));
+        self.metrics.set_channels(ChannelLevels::measure(samples, channels));
+    }
+}
+
+/// Rust recorder for Python services: same timestamped names, rotation, fsynced headers
+/// and JSON sidecars as rust_comms recordings
+///
+/// with PyWavFileWriter("recordings", sample_rate=16000) as writer:
+///     writer.write(chunk)
+/// info = writer.last_recording
+#[pyclass]
+pub struct PyWavFileWriter {
+    inner: WavFileWriter,
+    sample_rate: Option<u32>,
+    channels: u16,
+    meter: Option<WriterMeter>,
+    recordings: Vec<RecordingInfo>, // closed since start(), rotated files included
+}
+
+fn writer_error(e: Box<dyn std::error::Error>) -> PyErr {
+    pyo3::exceptions::PyIOError::new_err(e.to_string())
+}
+
+#[pymethods]
+impl PyWavFileWriter {
+    /// Args:
+    ///     output_dir: recordings directory, created on start
+    ///     sample_rate / channels: used by start() and the context manager when not given there
+    ///     format: pcm16 (default), pcm16-dither, pcm24 or float32
+    ///     max_seconds / max_mb: rotate to a new file at these limits (daily rotation always on)
+    ///     device / tags: copied into every sidecar
+    #[new]
+    #[pyo3(signature = (output_dir, sample_rate=None, channels=1, format="pcm16", max_seconds=None, max_mb=None, device=None, tags=Vec::new()))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        output_dir: &str,
+        sample_rate: Option<u32>,
+        channels: u16,
+        format: &str,
+        max_seconds: Option<f64>,
+        max_mb: Option<f64>,
+        device: Option<String>,
+        tags: Vec<String>,
+    ) -> PyResult<Self> {
+        let format: WavFormat = format.parse().map_err(PyValueError::new_err)?;
+        let rotation = RotationPolicy {
+            max_duration_seconds: max_seconds,
+            max_file_bytes: max_mb.map(|mb| (mb * 1024.0 * 1024.0) as u64),
+            ..RotationPolicy::default()
+        };
+        let context = RecordingContext {
+            device,
+            filters: None,
+            tags,
+        };
+        Ok(Self {
+            inner: WavFileWriter::new(output_dir)
+                .with_format(format)
+                .with_rotation(rotation)
+                .with_context(context),
+            sample_rate,
+            channels,
+            meter: None,
+            recordings: Vec::new(),
+        })
+    }
+
+    /// Open a new timestamped file
+    #[pyo3(signature = (sample_rate=None, channels=None))]
+    fn start(&mut self, sample_rate: Option<u32>, channels: Option<u16>) -> PyResult<()> {
+        let sample_rate = sample_rate
+            .or(self.sample_rate)
+            .ok_or_else(|| PyValueError::new_err("sample_rate not given here or to the constructor"))?;
+        let channels = channels.unwrap_or(self.channels);
+        if sample_rate == 0 {
+            return Err(PyValueError::new_err("sample_rate must be above 0"));
+        }
+        self.inner.start_writing(sample_rate, channels).map_err(writer_error)?;
+        (self.sample_rate, self.channels) = (Some(sample_rate), channels);
+        self.recordings.clear();
+        self.meter = Some(WriterMeter::new(sample_rate, channels));
+        Ok(())
+    }
+
+    /// Append float32 samples (numpy 1-D interleaved or 2-D (frames, channels), any float32 buffer, f32 bytes)
+    /// Encoding and disk writes run with the GIL released
+    fn write(&mut self, py: Python<'_>, samples: &Bound<'_, PyAny>) -> PyResult<()> {
+        if !self.inner.is_writing() {
+            return Err(PyValueError::new_err("not recording, call start() first"));
+        }
+        let buffer = SampleBuffer::get(samples)?;
+        let channels = self.channels;
+        if let Some(row) = buffer.row_len()
+            && row != channels as usize
+        {
+            return Err(PyValueError::new_err(format!("rows hold {} channels, recording has {}", row, channels)));
+        }
+        if !buffer.len().is_multiple_of(channels as usize) {
+            return Err(PyValueError::new_err(format!(
+                "{} samples is not a whole number of {}-channel frames",
+                buffer.len(),
+                channels
+            )));
+        }
+        let samples = buffer.samples().into_owned();
+        let (writer, meter) = (&mut self.inner, &mut self.meter);
+        let written = py
+            .detach(move || {
+                if let Some(meter) = meter {
+                    meter.update(&samples, channels);
+                }
+                writer.write_samples(&samples).map_err(|e| e.to_string())
+            })
+            .map_err(pyo3::exceptions::PyIOError::new_err);
+        // Files rotated out are closed even if a later part of the write failed
+        self.recordings.extend(self.inner.take_rotated());
+        written
+    }
+
+    /// Close the file and write its sidecar, None if nothing was open
+    fn finish(&mut self, py: Python<'_>) -> PyResult<Option<PyRecordingInfo>> {
+        let writer = &mut self.inner;
+        let info = py
+            .detach(move || writer.finish_writing().map_err(|e| e.to_string()))
+            .map_err(pyo3::exceptions::PyIOError::new_err)?;
+        self.recordings.extend(self.inner.take_rotated());
+        if let Some(info) = &info {
+            self.recordings.push(info.clone());
+        }
+        Ok(info.map(|inner| PyRecordingInfo { inner }))
+    }
+
+    /// Levels of everything written since start()
+    fn metrics(&self) -> PyMetricsSnapshot {
+        PyMetricsSnapshot {
+            inner: self.meter.as_ref().map_or_else(AudioMetrics::new, |meter| meter.metrics),
+        }
+    }
+
+    #[getter]
+    fn recording(&self) -> bool {
+        self.inner.is_writing()
+    }
+
+    /// Length of the file being written, 0 when idle
+    #[getter]
+    fn recorded_seconds(&self) -> f64 {
+        self.inner.recorded_seconds()
+    }
+
+    #[getter]
+    fn current_file(&self) -> Option<String> {
+        self.inner.current_file().map(|path| path.to_string_lossy().into_owned())
+    }
+
+    /// Info for the last file closed (the with block's recording, or its last part if it rotated)
+    #[getter]
+    fn last_recording(&self) -> Option<PyRecordingInfo> {
+        self.recordings.last().cloned().map(|inner| PyRecordingInfo { inner })
+    }
+
+    /// Every file closed since start(), oldest first: more than one when the recording rotated
+    #[getter]
+    fn recordings(&self) -> Vec<PyRecordingInfo> {
+        self.recordings.iter().cloned().map(|inner| PyRecordingInfo { inner }).collect()
+    }
+
+    /// Starts recording unless start() was already called
+    fn __enter__(slf: Bound<'_, Self>) -> PyResult<Bound<'_, Self>> {
+        {
+            let mut writer = slf.borrow_mut();
+            if !writer.inner.is_writing() {
+                writer.start(None, None)?;
+            }
+        }
+        Ok(slf)
+    }
+
+    /// Always finishes, so an exception inside the block still leaves a valid file
+    fn __exit__(
+        &mut self,
+        py: Python<'_>,
+        _exc_type: Option<&Bound<'_, PyAny>>,
+        _exc_value: Option<&Bound<'_, PyAny>>,
+        _traceback: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<bool> {
+        self.finish(py)?;
+        Ok(false)
+    }
+}
+
+// Python wrapper for replay buffer
+// Python feeds its own stream in, then dumps "what did I just say?" on demand
+#[pyclass]
+pub struct PyReplayBuffer {
+    inner: ReplayBuffer,
+}
+
+#[pymethods]
+impl PyReplayBuffer {
+    #[new]
+    #[pyo3(signature = (seconds, sample_rate, channels=1))]
+    fn new(seconds: f32, sample_rate: u32, channels: u16) -> Self {
+        Self {
+            inner: ReplayBuffer::new(seconds, sample_rate, channels),
+        }
+    }
+
+    /// Append float32 samples (array, buffer or f32 bytes)
+    fn push(&mut self, samples: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.inner.push(&SampleBuffer::get(samples)?.samples());
+        Ok(())
+    }
+
+    /// Last `seconds` of audio (all if None) as little-endian f32 bytes
+    #[pyo3(signature = (seconds=None))]
+    fn snapshot(&self, py: Python<'_>, seconds: Option<f32>) -> Py<PyAny> {
+        let snapshot = self.inner.snapshot(seconds);
+        PyBytes::new(py, &f32_bytes(&snapshot.samples)).into_any().unbind()
+    }
+
+    /// Write the last `seconds` to a timestamped WAV in output_dir, returns the file path
+    #[pyo3(signature = (output_dir, seconds=None))]
+    fn dump(&self, output_dir: &str, seconds: Option<f32>) -> PyResult<Option<String>> {
+        let mut writer = WavFileWriter::new(output_dir);
+        let info = self
+            .inner
+            .snapshot(seconds)
+            .write_to(&mut writer)
+            .map_err(|e| pyo3::exceptions::PyIOError::new_err(e.to_string()))?;
+        Ok(info.map(|info| info.file_path.to_string_lossy().into_owned()))
+    }
+
+    fn buffered_seconds(&self) -> f32 {
+        self.inner.buffered_seconds()
+    }
+
+    fn clear(&mut self) {
+        self.inner.clear();
+    }
+}
+
+/// Recordings dir index for Python: list / filter / annotate / export
+/// Recordings come back as plain dicts (same shape as the JSON sidecars)
+#[pyclass]
+pub struct PyRecordingCatalog {
+    inner: RecordingCatalog,
+}
+
+fn catalog_error(e: std::io::Error) -> PyErr {
+    pyo3::exceptions::PyIOError::new_err(e.to_string())
+}
+
+/// Metadata -> dict via json.loads, keeps the Python shape identical to the sidecar files
+fn metadata_to_py(py: Python<'_>, metadata: &RecordingMetadata) -> PyResult<Py<PyAny>> {
+    json_to_py(py, metadata)
+}
+
+/// Any Serialize -> plain Python objects via json.loads
+fn json_to_py<T: serde::Serialize>(py: Python<'_>, value: &T) -> PyResult<Py<PyAny>> {
+    let json = serde_json::to_string(value).map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
+    Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_query(
+    start: Option<&str>,
+    end: Option<&str>,
+    min_duration: Option<f64>,
+    max_duration: Option<f64>,
+    min_lufs: Option<f32>,
+    max_lufs: Option<f32>,
+    tag: Option<String>,
+) -> PyResult<RecordingQuery> {
+    let time = |text: Option<&str>| -> PyResult<_> {
+        text.map(audio::catalog::parse_time)
+            .transpose()
+            .map_err(pyo3::exceptions::PyValueError::new_err)
+    };
+    Ok(RecordingQuery {
+        from: time(start)?,
+        to: time(end)?,
+        min_duration_seconds: min_duration,
+        max_duration_seconds: max_duration,
+        min_lufs,
+        max_lufs,
+        tag,
+    })
+}
+
+#[pymethods]
+impl PyRecordingCatalog {
+    #[new]
+    fn new(recordings_dir: &str) -> PyResult<Self> {
+        Ok(Self {
+            inner: RecordingCatalog::open(recordings_dir).map_err(catalog_error)?,
+        })
+    }
+
+    /// Re-scan the directory for new recordings
+    fn refresh(&mut self) -> PyResult<()> {
+        self.inner.refresh().map_err(catalog_error)
+    }
+
+    /// Index one new recording (ex: PyRecordingInfo.file_path) without re-scanning, returns its metadata
+    fn add(&mut self, py: Python<'_>, file: &str) -> PyResult<Py<PyAny>> {
+        let entry = self.inner.add(file).map_err(catalog_error)?;
+        metadata_to_py(py, &entry.metadata)
+    }
+
+    /// Every recording, oldest first
+    fn list(&self, py: Python<'_>) -> PyResult<Vec<Py<PyAny>>> {
+        self.inner
+            .entries()
+            .iter()
+            .map(|entry| metadata_to_py(py, &entry.metadata))
+            .collect()
+    }
+
+    /// Times are "YYYY-MM-DD[ HH:MM:SS]" local or RFC 3339
+    #[pyo3(signature = (start=None, end=None, min_duration=None, max_duration=None, min_lufs=None, max_lufs=None, tag=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn query(
+        &self,
+        py: Python<'_>,
+        start: Option<&str>,
+        end: Option<&str>,
+        min_duration: Option<f64>,
+        max_duration: Option<f64>,
+        min_lufs: Option<f32>,
+        max_lufs: Option<f32>,
+        tag: Option<String>,
+    ) -> PyResult<Vec<Py<PyAny>>> {
+        let query = build_query(start, end, min_duration, max_duration, min_lufs, max_lufs, tag)?;
+        self.inner
+            .query(&query)
+            .iter()
+            .map(|entry| metadata_to_py(py, &entry.metadata))
+            .collect()
+    }
+
+    /// Set the transcript and/or add tags, returns the updated metadata
+    #[pyo3(signature = (file, transcript=None, tags=None))]
+    fn annotate(
+        &mut self,
+        py: Python<'_>,
+        file: &str,
+        transcript: Option<String>,
+        tags: Option<Vec<String>>,
+    ) -> PyResult<Py<PyAny>> {
+        let metadata = self
+            .inner
+            .annotate(file, transcript, &tags.unwrap_or_default())
+            .map_err(catalog_error)?;
+        metadata_to_py(py, &metadata)
+    }
+
+    /// Copy matching recordings + sidecars into dest_dir, returns the copied paths
+    #[pyo3(signature = (dest_dir, start=None, end=None, min_duration=None, max_duration=None, min_lufs=None, max_lufs=None, tag=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn export(
+        &self,
+        dest_dir: &str,
+        start: Option<&str>,
+        end: Option<&str>,
+        min_duration: Option<f64>,
+        max_duration: Option<f64>,
+        min_lufs: Option<f32>,
+        max_lufs: Option<f32>,
+        tag: Option<String>,
+    ) -> PyResult<Vec<String>> {
+        let query = build_query(start, end, min_duration, max_duration, min_lufs, max_lufs, tag)?;
+        let copied = RecordingCatalog::export(&self.inner.query(&query), dest_dir).map_err(catalog_error)?;
+        Ok(copied.iter().map(|path| path.to_string_lossy().into_owned()).collect())
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.entries().len()
+    }
+}
+
+/// Numbers from a float32 buffer, a number, or any nesting of iterables (lists, tuples, numpy arrays)
+fn flatten_floats(obj: &Bound<'_, PyAny>, out: &mut Vec<f32>) -> PyResult<()> {
+    if let Ok(value) = obj.extract::<f32>() {
+        out.push(value);
+        return Ok(());
+    }
+    if obj.is_instance_of::<PyString>() {
+        return Err(PyValueError::new_err("expected numbers, got a string"));
+    }
+    if let Ok(buffer) = SampleBuffer::get(obj) {
+        out.extend_from_slice(&buffer.samples());
+        return Ok(());
+    }
+    for item in obj.try_iter()? {
+        flatten_floats(&item?, out)?;
+    }
+    Ok(())
+}
+
+/// Exactly `count` numbers for `field`, in whatever shape Python had them
+fn floats(field: &str, obj: &Bound<'_, PyAny>, count: usize) -> PyResult<Vec<f32>> {
+    let mut values = Vec::with_capacity(count);
+    flatten_floats(obj, &mut values).map_err(|e| PyValueError::new_err(format!("{}: {}", field, e)))?;
+    if values.len() != count {
+        return Err(PyValueError::new_err(format!("{}: expected {} numbers, got {}", field, count, values.len())));
+    }
+    Ok(values)
+}
+
+fn vector3(field: &str, obj: &Bound<'_, PyAny>) -> PyResult<Vector3> {
+    let xyz = floats(field, obj, 3)?;
+    Ok(Vector3::new(xyz[0], xyz[1], xyz[2]))
+}
+
+/// Protocol enum from its wire name ("OpenPalm", "JetsonMediaPipe")
+fn protocol_enum<T: serde::de::DeserializeOwned>(field: &str, name: &str) -> PyResult<T> {
+    serde_json::from_value(serde_json::Value::String(name.to_string()))
+        .map_err(|e| PyValueError::new_err(format!("{}: {}", field, e)))
+}
+
+fn protocol_name<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn protocol_json<T: serde::Serialize>(value: &T) -> PyResult<String> {
+    serde_json::to_string(value).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+fn timestamp_us() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_micros() as u64)
+}
+
+/// One detection for the headset, validated on construction
+/// bbox is normalized (x, y, width, height), confidence in [0.0, 1.0]
+#[pyclass]
+#[derive(Clone)]
+pub struct PyDetectedObject {
+    inner: DetectedObject,
+}
+
+#[pymethods]
+impl PyDetectedObject {
+    #[new]
+    #[pyo3(signature = (class_name, confidence, bbox, position_3d=None, tracking_id=None))]
+    fn new(
+        class_name: String,
+        confidence: f32,
+        bbox: &Bound<'_, PyAny>,
+        position_3d: Option<&Bound<'_, PyAny>>,
+        tracking_id: Option<u32>,
+    ) -> PyResult<Self> {
+        let bbox = floats("bbox", bbox, 4)?;
+        let inner = DetectedObject {
+            class: class_name,
+            confidence,
+            bbox: BoundingBox {
+                x: bbox[0],
+                y: bbox[1],
+                width: bbox[2],
+                height: bbox[3],
+            },
+            position_3d: position_3d.map(|position| vector3("position_3d", position)).transpose()?,
+            tracking_id,
+        };
+        inner.validate().map_err(PyValueError::new_err)?;
+        Ok(Self { inner })
+    }
+
+    #[getter]
+    fn class_name(&self) -> String {
+        self.inner.class.clone()
+    }
+
+    #[getter]
+    fn confidence(&self) -> f32 {
+        self.inner.confidence
+    }
+
+    #[getter]
+    fn bbox(&self) -> (f32, f32, f32, f32) {
+        let bbox = &self.inner.bbox;
+        (bbox.x, bbox.y, bbox.width, bbox.height)
+    }
+
+    #[getter]
+    fn position_3d(&self) -> Option<(f32, f32, f32)> {
+        self.inner.position_3d.map(|p| (p.x, p.y, p.z))
+    }
+
+    #[getter]
+    fn tracking_id(&self) -> Option<u32> {
+        self.inner.tracking_id
+    }
+
+    fn to_json(&self) -> PyResult<String> {
+        protocol_json(&self.inner)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("PyDetectedObject({:?}, confidence={})", self.inner.class, self.inner.confidence)
+    }
+}
+
+/// 21 landmarks (MediaPipe order) as any (21, 3) shape, confidences default to 1.0
+/// gesture is a protocol name: OpenPalm, ClosedFist, Pointing, ThumbsUp, ThumbsDown, Peace, Pinch
+#[pyclass]
+#[derive(Clone)]
+pub struct PyHandPose {
+    inner: HandPose,
+}
+
+#[pymethods]
+impl PyHandPose {
+    #[new]
+    #[pyo3(signature = (landmarks, confidences=None, gesture=None))]
+    fn new(landmarks: &Bound<'_, PyAny>, confidences: Option<&Bound<'_, PyAny>>, gesture: Option<&str>) -> PyResult<Self> {
+        let points = floats("landmarks", landmarks, 21 * 3)?;
+        let mut inner = HandPose {
+            landmarks: [Vector3::zero(); 21],
+            confidences: [1.0; 21],
+            gesture: gesture.map(|name| protocol_enum::<GestureType>("gesture", name)).transpose()?,
+        };
+        for (landmark, xyz) in inner.landmarks.iter_mut().zip(points.chunks_exact(3)) {
+            *landmark = Vector3::new(xyz[0], xyz[1], xyz[2]);
+        }
+        if let Some(confidences) = confidences {
+            inner.confidences.copy_from_slice(&floats("confidences", confidences, 21)?);
+        }
+        inner.validate().map_err(PyValueError::new_err)?;
+        Ok(Self { inner })
+    }
+
+    #[getter]
+    fn landmarks(&self) -> Vec<(f32, f32, f32)> {
+        self.inner.landmarks.iter().map(|p| (p.x, p.y, p.z)).collect()
+    }
+
+    #[getter]
+    fn confidences(&self) -> Vec<f32> {
+        self.inner.confidences.to_vec()
+    }
+
+    #[getter]
+    fn gesture(&self) -> Option<String> {
+        self.inner.gesture.as_ref().map(protocol_name)
+    }
+
+    fn to_json(&self) -> PyResult<String> {
+        protocol_json(&self.inner)
+    }
+}
+
+/// Both hands from one tracker, source is JetsonMediaPipe, Quest3Native or Fused
+#[pyclass]
+#[derive(Clone)]
+pub struct PyHandTrackingData {
+    inner: HandTrackingData,
+}
+
+#[pymethods]
+impl PyHandTrackingData {
+    #[new]
+    #[pyo3(signature = (left=None, right=None, confidence=1.0, source="JetsonMediaPipe"))]
+    fn new(left: Option<PyHandPose>, right: Option<PyHandPose>, confidence: f32, source: &str) -> PyResult<Self> {
+        let inner = HandTrackingData {
+            left_hand: left.map(|hand| hand.inner),
+            right_hand: right.map(|hand| hand.inner),
+            confidence,
+            source: protocol_enum::<TrackingSource>("source", source)?,
+        };
+        inner.validate().map_err(PyValueError::new_err)?;
+        Ok(Self { inner })
+    }
+
+    #[getter]
+    fn left(&self) -> Option<PyHandPose> {
+        self.inner.left_hand.clone().map(|inner| PyHandPose { inner })
+    }
+
+    #[getter]
+    fn right(&self) -> Option<PyHandPose> {
+        self.inner.right_hand.clone().map(|inner| PyHandPose { inner })
+    }
+
+    #[getter]
+    fn confidence(&self) -> f32 {
+        self.inner.confidence
+    }
+
+    #[getter]
+    fn source(&self) -> String {
+        protocol_name(&self.inner.source)
+    }
+
+    fn to_json(&self) -> PyResult<String> {
+        protocol_json(&self.inner)
+    }
+}
+
+/// One frame of CV output, timestamp (µs since epoch) defaults to now
+/// frame_id is assigned by PyARPublisher so the headset can spot dropped frames
+#[pyclass]
+#[derive(Clone)]
+pub struct PyARFrame {
+    inner: ARFrame,
+}
+
+#[pymethods]
+impl PyARFrame {
+    #[new]
+    #[pyo3(signature = (objects=Vec::new(), hands=None, timestamp=None))]
+    fn new(objects: Vec<PyDetectedObject>, hands: Option<PyHandTrackingData>, timestamp: Option<u64>) -> PyResult<Self> {
+        let mut inner = ARFrame::new_dummy(timestamp.unwrap_or_else(timestamp_us));
+        inner.objects = objects.into_iter().map(|object| object.inner).collect();
+        inner.hands = hands.map(|hands| hands.inner);
+        inner.validate().map_err(PyValueError::new_err)?;
+        Ok(Self { inner })
+    }
+
+    /// Parse and validate an ARFrame in its wire JSON
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        let inner: ARFrame = serde_json::from_str(json).map_err(|e| PyValueError::new_err(format!("Bad ARFrame: {}", e)))?;
+        inner.validate().map_err(PyValueError::new_err)?;
+        Ok(Self { inner })
+    }
+
+    #[getter]
+    fn timestamp(&self) -> u64 {
+        self.inner.timestamp
+    }
+
+    #[getter]
+    fn frame_id(&self) -> u32 {
+        self.inner.frame_id
+    }
+
+    #[getter]
+    fn objects(&self) -> Vec<PyDetectedObject> {
+        self.inner.objects.iter().cloned().map(|inner| PyDetectedObject { inner }).collect()
+    }
+
+    #[getter]
+    fn hands(&self) -> Option<PyHandTrackingData> {
+        self.inner.hands.clone().map(|inner| PyHandTrackingData { inner })
+    }
+
+    fn to_json(&self) -> PyResult<String> {
+        protocol_json(&self.inner)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "PyARFrame(frame_id={}, timestamp={}, objects={}, hands={})",
+            self.inner.frame_id,
+            self.inner.timestamp,
+            self.inner.objects.len(),
+            self.inner.hands.is_some()
+        )
+    }
+}
+
+enum PublisherTarget {
+    /// Bridge running in this process, frames go straight into its feed
+    InProcess { feed: FrameFeed, stats: Arc<BridgeStats> },
+    /// ar_server / rust_comms bridge listening on a frame socket, frames go as JSON lines
+    Socket { path: String, stream: Option<UnixStream> },
+}
+
+/// Sends ARFrames from Python detectors to the Quest
+///
+/// -PyARPublisher.serve("0.0.0.0:8765"): starts an ARBridgeServer in this process
+/// -PyARPublisher.connect(): feeds a running bridge over its frame socket
+///  (MERLIN_AR_FRAME_SOCKET, /tmp/merlin_ar_frames.sock by default), reconnecting once if it restarted
+#[pyclass]
+pub struct PyARPublisher {
+    target: PublisherTarget,
+    next_frame_id: u32,
+    frames_published: u64,
+}
+
+impl PyARPublisher {
+    fn with_target(target: PublisherTarget) -> Self {
+        Self {
+            target,
+            next_frame_id: 0,
+            frames_published: 0,
+        }
+    }
+}
+
+fn connect_frame_socket(path: &str) -> PyResult<UnixStream> {
+    UnixStream::connect(path).map_err(|e| PyConnectionError::new_err(format!("AR frame socket {}: {}", path, e)))
+}
+
+#[pymethods]
+impl PyARPublisher {
+    /// Run a bridge on bind_addr in a background thread and publish into it
+    /// compression deflates binary frames for clients that negotiate it
+    #[staticmethod]
+    #[pyo3(signature = (bind_addr, compression=false))]
+    fn serve(bind_addr: &str, compression: bool) -> PyResult<Self> {
+        // Surface a taken port here, run() would only print it from its thread
+        std::net::TcpListener::bind(bind_addr)
+            .map_err(|e| pyo3::exceptions::PyIOError::new_err(format!("AR bridge {}: {}", bind_addr, e)))?;
+        let config = StreamConfig { enable_compression: compression, ..StreamConfig::default() };
+        let server = ARBridgeServer::new(bind_addr).with_config(config);
+        let (feed, stats) = (server.feed(), server.stats());
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Runtime::new().expect("Failed to start AR bridge runtime");
+            if let Err(e) = runtime.block_on(server.run()) {
+                eprintln!("AR bridge stopped: {}", e);
+            }
+        });
+        Ok(Self::with_target(PublisherTarget::InProcess { feed, stats }))
+    }
+
+    #[staticmethod]
+    #[pyo3(signature = (socket_path=None))]
+    fn connect(socket_path: Option<String>) -> PyResult<Self> {
+        let path = socket_path
+            .or_else(|| std::env::var("MERLIN_AR_FRAME_SOCKET").ok())
+            .unwrap_or_else(|| DEFAULT_FRAME_SOCKET_PATH.to_string());
+        let stream = connect_frame_socket(&path)?;
+        Ok(Self::with_target(PublisherTarget::Socket { path, stream: Some(stream) }))
+    }
+
+    /// Stamp the next frame_id and send, returns the frame_id used
+    fn publish(&mut self, py: Python<'_>, frame: &PyARFrame) -> PyResult<u32> {
+        let frame_id = self.next_frame_id;
+        let mut frame = frame.inner.clone();
+        frame.frame_id = frame_id;
+        match &mut self.target {
+            PublisherTarget::InProcess { feed, .. } => {
+                feed.publish(frame);
+            }
+            PublisherTarget::Socket { path, stream } => {
+                let mut line = protocol_json(&frame)?;
+                line.push('\n');
+                let path = path.as_str();
+                py.detach(|| -> PyResult<()> {
+                    if let Some(connected) = stream.as_mut()
+                        && connected.write_all(line.as_bytes()).is_ok()
+                    {
+                        return Ok(());
+                    }
+                    // Bridge restarted (or close() was called): one fresh connection, then give up
+                    *stream = None;
+                    let mut reconnected = connect_frame_socket(path)?;
+                    reconnected
+                        .write_all(line.as_bytes())
+                        .map_err(|e| PyConnectionError::new_err(format!("AR frame socket {}: {}", path, e)))?;
+                    *stream = Some(reconnected);
+                    Ok(())
+                })?;
+            }
+        }
+        self.frames_published += 1;
+        self.next_frame_id = frame_id.wrapping_add(1);
+        Ok(frame_id)
+    }
+
+    /// Connected headsets, None when publishing over the socket
+    #[getter]
+    fn clients(&self) -> Option<usize> {
+        match &self.target {
+            PublisherTarget::InProcess { stats, .. } => Some(stats.client_count()),
+            PublisherTarget::Socket { .. } => None,
+        }
+    }
+
+    #[getter]
+    fn frames_published(&self) -> u64 {
+        self.frames_published
+    }
+
+    /// Drop the socket connection (the next publish reconnects); no-op in-process
+    fn close(&mut self) {
+        if let PublisherTarget::Socket { stream, .. } = &mut self.target {
+            *stream = None;
+        }
+    }
 }
 
 //Python module definiton: 
 #[pymodule]
-fn merlin_audio(_py: Python, m:&PyModule) -> PyResult<()> {
+fn merlin_audio(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<PyNoiseGate>()?;
     m.add_class::<PyNormalizer>()?;
+    m.add_class::<PyAudioPipeline>()?;
+    m.add_class::<PyReplayBuffer>()?;
+    m.add_class::<PyRecordingCatalog>()?;
+    m.add_class::<PyWavFileWriter>()?;
+    m.add_class::<PyRecordingInfo>()?;
+    m.add_class::<PyMetricsSnapshot>()?;
+    m.add_class::<PyDetectedObject>()?;
+    m.add_class::<PyHandPose>()?;
+    m.add_class::<PyHandTrackingData>()?;
+    m.add_class::<PyARFrame>()?;
+    m.add_class::<PyARPublisher>()?;
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::PyDict;
+
+    /// Run indented Python test code with the module bound as `merlin_audio`, a failed assert fails the test
+    fn run_python(code: &str) {
+        Python::initialize();
+        Python::attach(|py| {
+            let module = pyo3::wrap_pymodule!(merlin_audio)(py);
+            py.import("sys").unwrap().getattr("modules").unwrap().set_item("merlin_audio", &module).unwrap();
+            let globals = PyDict::new(py);
+            globals.set_item("merlin_audio", module).unwrap();
+            globals.set_item("code", code).unwrap();
+            if let Err(e) = py.run(c"import textwrap\nexec(textwrap.dedent(code), globals())", Some(&globals), None) {
+                e.display(py);
+                panic!("Python test failed: {}", e);
+            }
+        });
+    }
+
+    #[test]
+    fn test_filters_process_python_buffers() {
+        run_python(
+            r#"
+            import array, struct
+            from merlin_audio import PyNoiseGate
+
+            def gate():
+                return PyNoiseGate(-10.0, 1.0, 10.0, 16000.0)
+
+            quiet = [0.001] * 1600
+
+            # float32 in place: same object back, samples gated
+            samples = array.array('f', quiet)
+            assert gate().process(samples) is samples
+            assert samples[-1] == 0.0
+
+            # out=: input untouched, result in out
+            samples = array.array('f', quiet)
+            out = array.array('f', [1.0] * 1600)
+            assert gate().process(samples, out=out) is out
+            assert samples == array.array('f', quiet) and out[-1] == 0.0
+
+            # old bytes API: new bytes, same result as in place
+            packed = struct.pack('1600f', *quiet)
+            result = gate().process(packed)
+            assert isinstance(result, bytes) and len(result) == len(packed)
+            assert struct.unpack('1600f', result)[-1] == 0.0
+
+            # 2-D (frames, channels) float32 buffer, processed in place
+            frames = bytearray(struct.pack('1600f', *quiet))
+            view = memoryview(frames).cast('f', shape=[800, 2])
+            assert gate().process(view) is view
+            assert view[799, 1] == 0.0
+
+            # bytes off a 4-byte boundary: readable through a copy, not filterable in place
+            unaligned = memoryview(bytearray(1 + len(packed)))[1:]
+            unaligned[:] = packed
+            assert struct.unpack('1600f', gate().process(unaligned.tobytes()))[-1] == 0.0
+            out = array.array('f', [1.0] * 1600)
+            gate().process(unaligned, out=out)
+            assert out[-1] == 0.0
+            try:
+                gate().process(unaligned)
+                raise AssertionError("unaligned in place accepted")
+            except ValueError as e:
+                assert "aligned" in str(e)
+
+            def rejects(samples, message, **kwargs):
+                try:
+                    gate().process(samples, **kwargs)
+                except ValueError as e:
+                    assert message in str(e), str(e)
+                else:
+                    raise AssertionError("accepted " + repr(samples))
+
+            rejects(memoryview(struct.pack('4f', 0, 0, 0, 0)).cast('f'), "read-only")
+            rejects(array.array('f', quiet), "read-only", out=memoryview(bytes(6400)).cast('f'))
+            rejects(array.array('d', [0.0] * 4), "format 'd'")
+            rejects(b'\x00' * 6, "not a whole number of float32 samples")
+            rejects(array.array('f', quiet), "out holds 3 samples", out=array.array('f', [0.0] * 3))
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_filter_state_survives_pickle() {
+        run_python(
+            r#"
+            import array, math, pickle
+            from merlin_audio import PyNoiseGate, PyNormalizer
+
+            def tone(n, amplitude, start=0):
+                return array.array('f', (amplitude * math.sin(0.05 * (start + i)) for i in range(n)))
+
+            for original in (PyNoiseGate(-30.0, 5.0, 200.0, 16000.0), PyNormalizer(-20.0, 100.0, 16000.0)):
+                # Mid-envelope: loud audio, then the start of a fade
+                original.process(tone(3200, 0.5))
+                original.process(tone(400, 0.01, 3200))
+                restored = pickle.loads(pickle.dumps(original))
+                assert type(restored) is type(original)
+
+                after, expected = tone(1600, 0.01, 3600), tone(1600, 0.01, 3600)
+                restored.process(after)
+                original.process(expected)
+                assert after == expected, type(original).__name__
+
+                # Fresh filter differs, so the state really carried over
+                fresh = tone(1600, 0.01, 3600)
+                type(original)(*original.__getnewargs__()).process(fresh)
+                assert fresh != expected, type(original).__name__
+
+            gate, normalizer = PyNoiseGate(-30.0, 5.0, 200.0, 16000.0), PyNormalizer(-20.0, 100.0, 16000.0)
+            for bad in (b'{not json', '[]', normalizer.__getstate__()):
+                try:
+                    gate.__setstate__(bad)
+                except ValueError:
+                    pass
+                else:
+                    raise AssertionError("accepted state " + repr(bad))
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_pipeline_binding_config_and_errors() {
+        run_python(
+            r#"
+            import array, json, math, struct, threading
+            from merlin_audio import PyAudioPipeline
+
+            config = {"sample_rate": 48000, "channels": 2, "stages": [
+                {"type": "gate", "threshold_db": -60},
+                {"type": "resample", "rate": 16000},
+                {"type": "metrics", "name": "out"},
+            ]}
+            stereo = array.array('f', (0.1 * math.sin(0.02 * (i // 2)) for i in range(9600)))
+            before = array.array('f', stereo)
+
+            pipeline = PyAudioPipeline(config)
+            assert (pipeline.output_rate, pipeline.output_channels) == (16000, 1)
+            assert pipeline.config()["stages"][0]["attack_ms"] == 10.0
+            audio, metrics = pipeline.process(stereo)
+            assert stereo == before, "input must be left alone"
+            assert isinstance(audio, array.array) and audio.typecode == 'f'
+            # 3:1 mono, less the resampler's filter delay on the first chunk
+            assert 1590 <= len(audio) <= 1600
+            assert (metrics["sample_rate"], metrics["channels"]) == (16000, 1)
+            assert set(metrics["stages"]) == {"gate", "resample", "out"}
+            assert metrics["stages"]["resample"]["frames"] == len(audio)
+            assert metrics["stages"]["out"]["rms_db"] < 0
+
+            # JSON string config and bytes in give the same audio back as bytes
+            same, _ = PyAudioPipeline(json.dumps(config)).process(stereo.tobytes())
+            assert isinstance(same, bytes) and same == audio.tobytes()
+
+            # Chunks processed on other threads (GIL released inside) match the serial result
+            results = {}
+            def run(key):
+                results[key] = PyAudioPipeline(config).process(stereo)[0]
+            threads = [threading.Thread(target=run, args=(key,)) for key in range(4)]
+            for thread in threads:
+                thread.start()
+            for thread in threads:
+                thread.join()
+            assert all(result == audio for result in results.values())
+
+            def rejects(call, message):
+                try:
+                    call()
+                except ValueError as e:
+                    assert message in str(e), str(e)
+                else:
+                    raise AssertionError("accepted " + message)
+
+            rejects(lambda: PyAudioPipeline({"sample_rate": 16000, "stages": [{"type": "echo"}]}), "Bad pipeline config")
+            rejects(lambda: PyAudioPipeline("{not json"), "Bad pipeline config")
+            rejects(lambda: pipeline.process(array.array('f', [0.0] * 3)), "not a whole number of 2-channel frames")
+            rejects(lambda: pipeline.process(array.array('d', [0.0] * 4)), "format 'd'")
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_python_frames_reach_the_feed_socket() {
+        let path = std::env::temp_dir().join(format!("merlin_py_frames_{}.sock", uuid::Uuid::new_v4()));
+        let feed = FrameFeed::new();
+        feed.listen(&path).unwrap();
+
+        let code = r#"
+            from merlin_audio import PyARFrame, PyARPublisher, PyDetectedObject, PyHandPose, PyHandTrackingData
+
+            def rejects(call, message):
+                try:
+                    call()
+                except ValueError as e:
+                    assert message in str(e), str(e)
+                else:
+                    raise AssertionError("accepted " + message)
+
+            cup = PyDetectedObject("cup", 0.9, (0.1, 0.2, 0.3, 0.4), position_3d=(0.0, 0.1, 1.2), tracking_id=7)
+            landmarks = [(0.5, 0.4 + 0.01 * i, -0.3) for i in range(21)]
+            hands = PyHandTrackingData(right=PyHandPose(landmarks, gesture="OpenPalm"), confidence=0.8)
+            publisher = PyARPublisher.connect("SOCKET_PATH")
+            assert publisher.publish(PyARFrame([cup], hands, timestamp=41)) == 0
+            assert publisher.publish(PyARFrame([cup], hands, timestamp=42)) == 1
+            assert publisher.frames_published == 2 and publisher.clients is None
+
+            # protocol.rs validate() errors come back as ValueError
+            rejects(lambda: PyDetectedObject("", 0.9, (0.1, 0.2, 0.3, 0.4)), "class is empty")
+            rejects(lambda: PyDetectedObject("cup", 0.9, (0.1, 0.2, 2.0, 0.4)), "bbox")
+            rejects(lambda: PyDetectedObject("cup", 0.9, (0.1, 0.2, 0.3)), "bbox")
+            rejects(lambda: PyHandPose(landmarks[:20]), "landmarks")
+            rejects(lambda: PyHandPose(landmarks, gesture="Wave"), "gesture")
+            rejects(lambda: PyARFrame.from_json('{"timestamp": 1}'), "missing field")
+            "#;
+        run_python(&code.replace("SOCKET_PATH", path.to_str().unwrap()));
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+        let frame = loop {
+            if let Some(published) = feed.newer_than(0).filter(|published| published.frame.timestamp == 42) {
+                break published.frame;
+            }
+            assert!(std::time::Instant::now() < deadline, "frame never reached the feed");
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        };
+        assert_eq!(frame.frame_id, 1);
+        assert_eq!(frame.objects[0].class, "cup");
+        assert_eq!(frame.objects[0].tracking_id, Some(7));
+        let right = frame.hands.unwrap().right_hand.unwrap();
+        assert_eq!(right.gesture, Some(GestureType::OpenPalm));
+        assert!((right.landmarks[20].y - 0.6).abs() < 1e-6);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_writer_context_manager_and_recording_info() {
+        let dir = std::env::temp_dir().join(format!("merlin_py_writer_{}", uuid::Uuid::new_v4()));
+        let code = r#"
+            import array, json, math, os
+            from merlin_audio import PyWavFileWriter
+
+            tone = array.array('f', [0.5 * math.sin(2 * math.pi * 440 * n / 16000) for n in range(16000)])
+            with PyWavFileWriter("OUTPUT_DIR", sample_rate=16000, tags=["test"]) as writer:
+                assert writer.recording and writer.current_file.endswith(".wav")
+                writer.write(tone)
+                assert abs(writer.recorded_seconds - 1.0) < 1e-6
+            assert not writer.recording and writer.current_file is None and writer.recorded_seconds == 0
+
+            info = writer.last_recording
+            assert info.sample_rate == 16000 and info.channels == 1
+            assert abs(info.duration_seconds - 1.0) < 1e-6
+            assert info.file_size_bytes == os.path.getsize(info.file_path)
+            with open(info.sidecar_path) as f:
+                assert json.load(f)["tags"] == ["test"]
+            assert info.to_dict()["sample_rate"] == 16000
+            assert [r.file_path for r in writer.recordings] == [info.file_path]
+
+            # Closing again is a no-op and leaves the last recording alone
+            assert writer.finish() is None
+            assert writer.last_recording.file_path == info.file_path
+
+            # Levels of the tone, dB relative to full scale
+            metrics = writer.metrics()
+            assert abs(metrics.peak - 0.5) < 1e-3
+            assert abs(metrics.db - 20 * math.log10(0.5 / math.sqrt(2))) < 0.1
+            assert metrics.clipped_total == 0 and len(metrics.channels) == 1
+            assert metrics.to_dict()["peak"] == metrics.peak
+
+            # An exception inside the block still closes a valid file
+            try:
+                with writer:
+                    writer.write(tone[:1600])
+                    raise RuntimeError("boom")
+            except RuntimeError:
+                pass
+            assert not writer.recording
+            assert abs(writer.last_recording.duration_seconds - 0.1) < 1e-6
+            assert writer.last_recording.file_path != info.file_path
+
+            try:
+                writer.write(tone)
+            except ValueError as e:
+                assert "call start() first" in str(e)
+            else:
+                raise AssertionError("write after close was accepted")
+            "#;
+        run_python(&code.replace("OUTPUT_DIR", dir.to_str().unwrap()));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -1,48 +1,482 @@
-use p1::audio::{AudioMetrics, AudioProcessor, WavFileWriter};
-use p1::display::AudioMeter;
+use merlin_audio::audio::{AudioMetrics, AudioOutput, AudioProcessor, AudioPublisher, AudioWriter, DeviceRequest, LiveRecording, NetworkSourceConfig, ReplayBuffer, Recorder, RecordingFormat, RecordingKey, RetentionManager, RetentionPolicy, RotationPolicy};
+use merlin_audio::audio::output::DEFAULT_PLAYBACK_SOCKET_PATH;
+use merlin_audio::audio::repair::{repair_directory, RepairOutcome, REPAIR_MIN_AGE};
+use merlin_audio::audio::publisher::DEFAULT_SOCKET_PATH;
+use merlin_audio::ar::{ARBridgeServer, BridgeStats, StreamConfig, DEFAULT_FRAME_SOCKET_PATH};
+use merlin_audio::display::terminal;
+use merlin_audio::display::{
+    AudioMeter, DashboardSink, DisplayAction, DisplayMode, DisplaySink, DisplaySnapshot, JsonLinesSink, LineMeterSink,
+    MeterConfig, RecordingStatus, RecordingWatcher, SpectrogramSink,
+};
+use merlin_audio::audio::RecordingContext;
+use merlin_audio::monitoring::MetricsExporter;
 
-use std::io::{self, Write};
+mod cli;
+
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
 use std::sync::{Arc, Mutex}; //Thread-safe shraed state
 use std::time::Duration;
 use std::thread;
 
 
+const RECORDINGS_DIR: &str = "./recordings";
+const RETENTION_INTERVAL: Duration = Duration::from_secs(60);
+const MB: f64 = 1024.0 * 1024.0;
+
 fn main() -> Result<(), Box<dyn std::error::Error>> { //Error handling with Result<T, E>
+    //`rust_comms repair [dir]` fixes recordings cut off by a crash, then exits
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("repair") {
+        let dir = args.get(2).map(String::as_str).unwrap_or(RECORDINGS_DIR);
+        exit_with(run_repair(Path::new(dir), true));
+    }
+    //`rust_comms recordings [list|export <dest>] [filters]` queries the sidecar catalog
+    if args.get(1).map(String::as_str) == Some("recordings") {
+        exit_with(cli::run_recordings(&args[2..], RECORDINGS_DIR));
+    }
+    //`rust_comms decrypt <file|dir>` / `rust_comms keygen <path>` for encrypted recordings
+    if args.get(1).map(String::as_str) == Some("decrypt") {
+        exit_with(cli::run_decrypt(&args[2..]));
+    }
+    if args.get(1).map(String::as_str) == Some("keygen") {
+        exit_with(cli::run_keygen(&args[2..]));
+    }
+    //`rust_comms process <file|dir>` runs WAVs through the filter chain offline for tuning
+    if args.get(1).map(String::as_str) == Some("process") {
+        exit_with(cli::run_process(&args[2..]));
+    }
+    //`rust_comms spectrogram <file.wav>` prints a recording's spectrogram
+    if args.get(1).map(String::as_str) == Some("spectrogram") {
+        exit_with(cli::run_spectrogram(&args[2..]));
+    }
+
+    //`rust_comms [--output dashboard|line|json|spectrogram] [--rate <hz>]`, full-screen dashboard on a terminal, line meter otherwise
+    let run = match cli::run_args(&args[1..]) {
+        Ok(run) => run,
+        Err(e) => exit_with(Err(e.into())),
+    };
+    let mode = match run.output {
+        Some(mode @ (DisplayMode::Dashboard | DisplayMode::Spectrogram)) if !terminal::is_tty() => {
+            eprintln!("No terminal for the {}, using the line meter", mode);
+            DisplayMode::Line
+        }
+        Some(mode) => mode,
+        None if terminal::is_tty() => DisplayMode::Dashboard,
+        None => DisplayMode::Line,
+    };
+    //JSON takes over stdout before anything else prints, so the stream is JSON only
+    let json = match mode {
+        DisplayMode::Json => Some(JsonLinesSink::stdout(run.rate_hz)?),
+        _ => None,
+    };
+
     println!("Starting MERLIN Audio System...");
 
+    //Anything left open by a power cut gets fixed before we start adding files
+    if Path::new(RECORDINGS_DIR).exists()
+        && let Err(e) = run_repair(Path::new(RECORDINGS_DIR), false)
+    {
+        eprintln!("Recording repair failed: {}", e);
+    }
+
     //Allow shared auido metrics (thread-safe using Arc<Mutex<T>>)
     // Atomic ref counting (ARC) for shared ownership across threads
     // Mutual exclusion (Ensures only one thread modifies at a time)
     let metrics = Arc::new(Mutex::new(AudioMetrics::new()));
     let metrics_clone = Arc::clone(&metrics);
 
-    let _wav_writer = WavFileWriter::new("./recordings");
-    println!("WAV recorder initialized: ./recordings");
+    //MERLIN_RECORD_FORMAT=pcm16|pcm16-dither|pcm24|float32|flac|flac24, float32 WAV by default
+    //(MERLIN_WAV_FORMAT is still read when it isn't set)
+    let record_format = match std::env::var("MERLIN_RECORD_FORMAT").or_else(|_| std::env::var("MERLIN_WAV_FORMAT")) {
+        Ok(format) => format.parse().unwrap_or_else(|e| {
+            eprintln!("{}, using float32 WAV", e);
+            RecordingFormat::default()
+        }),
+        Err(_) => RecordingFormat::default(),
+    };
+    //MERLIN_ROTATE_SECONDS / MERLIN_ROTATE_MB start a new file once either is reached
+    let rotation = RotationPolicy {
+        max_duration_seconds: env_f64("MERLIN_ROTATE_SECONDS"),
+        max_file_bytes: env_f64("MERLIN_ROTATE_MB").map(|mb| (mb * MB) as u64),
+        ..Default::default()
+    };
+    let recorder = Recorder::new(RECORDINGS_DIR, record_format).with_rotation(rotation);
+    //Encrypted at rest in the record format above: MERLIN_RECORD_KEY_FILE=<path> or MERLIN_RECORD_PASSPHRASE
+    let recorder = match encryption_key()? {
+        Some(key) => {
+            println!("Recorder initialized: {} ({}, encrypted)", RECORDINGS_DIR, record_format);
+            recorder.with_encryption(key)
+        }
+        None => {
+            println!("Recorder initialized: {} ({})", RECORDINGS_DIR, record_format);
+            recorder
+        }
+    };
+    let recorder = Arc::new(Mutex::new(recorder));
+
+    //Input source: local mic by default, MERLIN_AUDIO_INPUT=udp:<addr> to take the Quest mic over the network
+    let mut processor: AudioProcessor = match std::env::var("MERLIN_AUDIO_INPUT") {
+        Ok(input) if input.starts_with("udp:") => {
+            let config = NetworkSourceConfig {
+                bind_addr: input["udp:".len()..].to_string(),
+                ..Default::default()
+            };
+            AudioProcessor::new_network(metrics_clone, config)
+        }
+        _ => AudioProcessor::new(metrics_clone),
+    }
+    .expect("Failed to create audio processor");
+    let replay = processor.replay_buffer();
+    //Continuous recording of processed audio, armed with the dashboard's r key or the "record" command
+    let live = LiveRecording::start(Arc::clone(&recorder), processor.sample_rate(), processor.channels());
+    processor.set_recording_tap(live.tap());
+    recorder.lock().unwrap().set_context(RecordingContext {
+        device: Some(processor.device_name()),
+        filters: Some(processor.filter_config()),
+        tags: Vec::new(),
+    });
+
+    //Publish processed 16kHz audio so the voice brain doesn't open its own mic
+    let socket_path = std::env::var("MERLIN_AUDIO_SOCKET").unwrap_or_else(|_| DEFAULT_SOCKET_PATH.to_string());
+    match AudioPublisher::bind(&socket_path, processor.sample_rate(), processor.channels()) {
+        Ok(publisher) => processor.set_publisher(publisher),
+        Err(e) => eprintln!("Audio publisher disabled ({}): {}", socket_path, e),
+    }
+
+    //TTS playback goes through us so the mic is ducked while MERLIN talks
+    let output = AudioOutput::new().unwrap_or_else(|e| {
+        eprintln!("No audio output ({}), using null sink", e);
+        AudioOutput::null(16000, 1)
+    });
+    processor.set_playback(output.playback_state(), -30.0);
+    output.start().expect("Failed to start audio output");
+    let playback_path = std::env::var("MERLIN_PLAYBACK_SOCKET").unwrap_or_else(|_| DEFAULT_PLAYBACK_SOCKET_PATH.to_string());
+    if let Err(e) = output.listen(&playback_path) {
+        eprintln!("Playback socket disabled ({}): {}", playback_path, e);
+    }
 
-    let mut processor: AudioProcessor = AudioProcessor::new(metrics_clone).expect("Failed to create audio processor");
+    //Optional OpenMetrics endpoint, MERLIN_METRICS_ADDR=0.0.0.0:9464 to enable
+    if let Ok(metrics_addr) = std::env::var("MERLIN_METRICS_ADDR") {
+        let exporter = MetricsExporter::new()
+            .with_audio(Arc::clone(&metrics))
+            .with_recorder(Arc::clone(&recorder))
+            .with_live_recording(live.clone());
+        if let Err(e) = exporter.serve(&metrics_addr) {
+            eprintln!("Metrics endpoint disabled ({}): {}", metrics_addr, e);
+        }
+    }
 
-    //Start audio processing in background thread
+    //Retention: MERLIN_RETENTION_MAX_MB, MERLIN_RETENTION_MAX_AGE_HOURS, MERLIN_RETENTION_MIN_FREE_MB
+    let retention = RetentionPolicy {
+        max_total_bytes: env_f64("MERLIN_RETENTION_MAX_MB").map(|mb| (mb * MB) as u64),
+        max_age: env_f64("MERLIN_RETENTION_MAX_AGE_HOURS").map(|h| Duration::from_secs_f64(h * 3600.0)),
+        min_free_bytes: env_f64("MERLIN_RETENTION_MIN_FREE_MB").map(|mb| (mb * MB) as u64),
+    };
+    if retention != RetentionPolicy::default() {
+        println!("Recording retention: {:?}", retention);
+        let recorder = Arc::clone(&recorder);
+        let mut manager = RetentionManager::new(RECORDINGS_DIR, retention);
+        let _retention_handle = thread::spawn(move || loop {
+            let open_file = recorder.lock().unwrap().current_file().cloned();
+            if let Err(e) = manager.enforce(open_file.as_deref()) {
+                eprintln!("\nRetention failed: {}", e);
+            }
+            thread::sleep(RETENTION_INTERVAL);
+        });
+    }
+
+    //Optional in-process AR bridge, MERLIN_AR_BRIDGE_ADDR=0.0.0.0:8765, so the dashboard can show its clients
+    let bridge = std::env::var("MERLIN_AR_BRIDGE_ADDR").ok().map(|addr| {
+        let server = ARBridgeServer::new(addr).with_config(StreamConfig::from_env());
+        let stats = server.stats();
+        let frame_socket = std::env::var("MERLIN_AR_FRAME_SOCKET").unwrap_or_else(|_| DEFAULT_FRAME_SOCKET_PATH.to_string());
+        if let Err(e) = server.feed().listen(&frame_socket) {
+            eprintln!("AR frame socket disabled ({}): {}", frame_socket, e);
+        }
+        thread::spawn(move || {
+            let runtime = tokio::runtime::Runtime::new().expect("Failed to start AR bridge runtime");
+            if let Err(e) = runtime.block_on(server.run()) {
+                eprintln!("AR bridge stopped: {}", e);
+            }
+        });
+        stats
+    });
+
+    let bypass = processor.filter_bypass();
+    let device = processor.device_name_handle();
+    let (device_tx, device_rx) = mpsc::channel();
+    let controls = Arc::new(Controls {
+        replay,
+        recorder,
+        live,
+        bypass,
+        devices: device_tx,
+    });
+
+    //Start audio processing in background thread, it stays there to handle device switches
     let _processor_handle = thread::spawn(move || {
-        processor.start().expect("Failed to start audio processing");
+        // Only opening the first device can fail, switches keep the old device on errors
+        processor.run(device_rx).expect("Failed to start audio processing");
     });
 
     thread::sleep(Duration::from_millis(100)); //Give audio thread time to initialize
 
-    //Display real-time audio emter in main thread
-    let mut meter = AudioMeter::new();
-    println!("Audio monitoring is LIVE");
+    let mut sink: Box<dyn DisplaySink> = match (mode, json) {
+        (_, Some(json)) => Box::new(json),
+        (DisplayMode::Dashboard, _) => Box::new(DashboardSink::enter()?),
+        (DisplayMode::Spectrogram, _) => Box::new(SpectrogramSink::enter(run.spectrogram)?),
+        _ => Box::new(LineMeterSink::new(AudioMeter::new().with_config(meter_config()))),
+    };
+    //The full-screen views read keys themselves
+    if !matches!(mode, DisplayMode::Dashboard | DisplayMode::Spectrogram) {
+        //Admin commands on stdin, e.g. "replay 10" dumps the last 10s to a WAV
+        let admin_controls = Arc::clone(&controls);
+        let _admin_handle = thread::spawn(move || {
+            run_admin_commands(&admin_controls);
+        });
+        println!("Audio monitoring is LIVE (type \"replay [seconds]\" + Enter to save recent audio)");
+    }
+
+    run_display(sink.as_mut(), &metrics, &controls, &device, bridge.as_deref())?;
+    //Restore the terminal first so the recording summary lands on the console
+    drop(sink);
+    controls.shutdown();
+    Ok(())
+}
+
+/// Runtime switches, shared by the dashboard keys and the stdin admin commands
+struct Controls {
+    replay: Arc<Mutex<ReplayBuffer>>,
+    recorder: Arc<Mutex<Recorder>>,
+    live: LiveRecording,
+    bypass: Arc<AtomicBool>,
+    devices: Sender<DeviceRequest>,
+}
+
+impl Controls {
+    /// Dump the replay buffer (all of it if no seconds given) as a new recording
+    fn save_replay(&self, seconds: Option<f32>) {
+        // Copy out under the lock, write to disk after releasing it
+        let snapshot = self.replay.lock().unwrap().snapshot(seconds);
+        if snapshot.samples.is_empty() {
+            println!("\nReplay buffer is empty");
+            return;
+        }
+        let mut recorder = self.recorder.lock().unwrap();
+        if recorder.is_writing() {
+            println!("\nRecording in progress, stop it before saving a replay");
+            return;
+        }
+        match snapshot.write_to(&mut *recorder) {
+            Ok(Some(info)) => info.print_summary(),
+            Ok(None) => {}
+            Err(e) => eprintln!("\nReplay dump failed: {}", e),
+        }
+    }
 
+    fn toggle_recording(&self) {
+        if self.live.toggle() {
+            println!("\nRecording armed");
+        } else {
+            println!("\nRecording stopped");
+        }
+    }
+
+    fn toggle_bypass(&self) {
+        let bypassed = !self.bypass.fetch_xor(true, Ordering::Relaxed);
+        println!("\nFilters {}", if bypassed { "bypassed" } else { "enabled" });
+    }
+
+    fn switch_device(&self, request: DeviceRequest) {
+        if self.devices.send(request).is_err() {
+            eprintln!("\nAudio thread stopped, can't switch devices");
+        }
+    }
+
+    /// Fix truncated WAVs in the recordings dir (the open recording is skipped)
+    fn repair(&self) {
+        // Hold the writer so the file being recorded isn't patched underneath it
+        let writer = self.recorder.lock().unwrap();
+        match repair_directory(RECORDINGS_DIR, writer.current_file().map(|p| p.as_path()), Some(REPAIR_MIN_AGE)) {
+            Ok(results) => {
+                println!();
+                for (path, outcome) in results {
+                    print_repair(&path, &outcome, true);
+                }
+            }
+            Err(e) => eprintln!("\nRepair failed: {}", e),
+        }
+    }
+
+    fn recording_status(&self) -> RecordingStatus {
+        let recorder = self.recorder.lock().unwrap();
+        RecordingStatus {
+            armed: self.live.is_armed(),
+            file: recorder.current_file().cloned(),
+            seconds: recorder.recorded_seconds(),
+            format: match recorder.is_encrypted() {
+                true => format!("{}+encrypted", recorder.format()),
+                false => recorder.format().to_string(),
+            },
+            last: self.live.last_recording(),
+            dropped_buffers: self.live.dropped_count(),
+        }
+    }
+
+    /// Stop an armed recording and give the writer a moment to finish the file
+    fn shutdown(&self) {
+        self.live.disarm();
+        for _ in 0..20 {
+            if !self.recorder.lock().unwrap().is_writing() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+}
+
+/// Line meter settings, MERLIN_METER_RANGE="-60,0" sets the dBFS scale, NO_COLOR disables colors
+fn meter_config() -> MeterConfig {
+    let mut config = MeterConfig::default();
+    if let Ok(range) = std::env::var("MERLIN_METER_RANGE") {
+        match range.split_once(',').map(|(min, max)| (min.trim().parse::<f32>(), max.trim().parse::<f32>())) {
+            Some((Ok(min), Ok(max))) if min < max => {
+                config.min_db = min;
+                config.max_db = max;
+            }
+            _ => eprintln!("Ignoring MERLIN_METER_RANGE={:?}, expected \"min,max\" in dBFS", range),
+        }
+    }
+    if std::env::var_os("NO_COLOR").is_some() {
+        config.colors = None;
+    }
+    config
+}
+
+/// Read admin commands line by line until stdin closes
+/// -replay [seconds]: dump the replay buffer (all of it if no seconds given)
+/// -repair: fix truncated WAVs in the recordings dir (the open recording is skipped)
+/// -record: arm / stop continuous recording
+/// -bypass: toggle the gate + normalizer
+/// -device [name]: switch input device (next one if no name given)
+fn run_admin_commands(controls: &Controls) {
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else { break };
+        let mut parts = line.split_whitespace();
+        match parts.next() {
+            Some("replay") | Some("r") => {
+                controls.save_replay(parts.next().and_then(|s| s.parse::<f32>().ok()));
+            }
+            Some("repair") => controls.repair(),
+            Some("record") => controls.toggle_recording(),
+            Some("bypass") => controls.toggle_bypass(),
+            Some("device") => {
+                let name = parts.collect::<Vec<_>>().join(" ");
+                if name.is_empty() {
+                    controls.switch_device(DeviceRequest::Next);
+                } else {
+                    controls.switch_device(DeviceRequest::Named(name));
+                }
+            }
+            Some(other) => println!("\nUnknown command: {}", other),
+            None => {}
+        }
+    }
+}
+
+/// Feed `sink` snapshots and recording events until it asks to quit (only the dashboard does)
+fn run_display(
+    sink: &mut dyn DisplaySink,
+    metrics: &Arc<Mutex<AudioMetrics>>,
+    controls: &Controls,
+    device: &Arc<Mutex<String>>,
+    bridge: Option<&BridgeStats>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut watcher = RecordingWatcher::new();
     loop {
-        //Update display with current audio levels
-        let current_metrics = metrics.lock().unwrap().clone();
-        meter.display(&current_metrics);
+        for action in sink.actions() {
+            match action {
+                DisplayAction::ToggleRecording => controls.toggle_recording(),
+                DisplayAction::ToggleBypass => controls.toggle_bypass(),
+                DisplayAction::NextDevice => controls.switch_device(DeviceRequest::Next),
+                DisplayAction::SaveReplay => controls.save_replay(None),
+                DisplayAction::Quit => return Ok(()),
+            }
+        }
+
+        let snapshot = DisplaySnapshot {
+            metrics: *metrics.lock().unwrap(),
+            device: device.lock().unwrap().clone(),
+            audio: sink.audio_seconds().map(|seconds| controls.replay.lock().unwrap().snapshot(Some(seconds))),
+            recording: controls.recording_status(),
+            clients: bridge.map(|stats| stats.clients()),
+        };
+        for event in watcher.changes(&snapshot.recording) {
+            sink.event(&event)?;
+        }
+        sink.update(&snapshot)?;
+        thread::sleep(sink.interval());
+    }
+}
+
+/// Master key for encrypted recordings, if MERLIN_RECORD_KEY_FILE or the passphrase env var is set
+fn encryption_key() -> Result<Option<RecordingKey>, Box<dyn std::error::Error>> {
+    if let Ok(path) = std::env::var("MERLIN_RECORD_KEY_FILE") {
+        return Ok(Some(RecordingKey::from_key_file(&path).map_err(|e| format!("Key file {}: {}", path, e))?));
+    }
+    if let Ok(passphrase) = std::env::var(cli::PASSPHRASE_ENV) {
+        let passphrase = zeroize::Zeroizing::new(passphrase);
+        return Ok(Some(RecordingKey::from_passphrase(&passphrase)?));
+    }
+    Ok(None)
+}
+
+/// End a subcommand: exit 0, or report the error and exit with its status (2 for a bad command line)
+fn exit_with(result: Result<(), Box<dyn std::error::Error>>) -> ! {
+    let code = match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            cli::exit_code(e.as_ref())
+        }
+    };
+    std::process::exit(code)
+}
 
-        //Refresh display every 50ms (20fps)
-        //Target: < 50ms latency for audio -> display pipeline
-        thread::sleep(Duration::from_millis(50));
+/// Repair every WAV in `dir`, `verbose` also lists files that were fine
+/// Recently modified files are skipped, another recorder may still be writing them
+fn run_repair(dir: &Path, verbose: bool) -> Result<(), Box<dyn std::error::Error>> {
+    for (path, outcome) in repair_directory(dir, None, Some(REPAIR_MIN_AGE))? {
+        print_repair(&path, &outcome, verbose);
+    }
+    Ok(())
+}
 
-        //Flush stdout to ensure display updates apear immediately
-        io::stdout().flush().unwrap();
+fn print_repair(path: &Path, outcome: &RepairOutcome, verbose: bool) {
+    match outcome {
+        RepairOutcome::Intact if verbose => println!("OK        {:?}", path),
+        RepairOutcome::Intact => {}
+        RepairOutcome::Repaired { data_bytes, recovered_bytes, trimmed_bytes } => println!(
+            "REPAIRED  {:?}: {} data bytes ({} recovered, {} trimmed)",
+            path, data_bytes, recovered_bytes, trimmed_bytes
+        ),
+        RepairOutcome::Unrecoverable(reason) => println!("BROKEN    {:?}: {}", path, reason),
+        RepairOutcome::Skipped(reason) => println!("SKIPPED   {:?}: {}", path, reason),
     }
+}
 
+/// Numeric env var, None when unset, unparsable, negative or not finite (all of these are sizes / durations)
+fn env_f64(name: &str) -> Option<f64> {
+    let value = std::env::var(name).ok()?;
+    match value.parse::<f64>() {
+        Ok(number) if number.is_finite() && number >= 0.0 => Some(number),
+        _ => {
+            eprintln!("Ignoring {}={:?}: not a number of 0 or more", name, value);
+            None
+        }
+    }
 }
@@ -0,0 +1,304 @@
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::ar::BridgeStats;
+use crate::audio::{AudioMetrics, AudioWriter, LiveRecording, Recorder};
+
+/// Metrics Exporter
+///
+/// OpenMetrics text endpoint so the Jetson can be watched remotely
+/// -GET /metrics, everything else is 404
+/// -Each source is optional: the audio binary has no bridge and vice versa
+/// -Values are read at scrape time, nothing is buffered
+#[derive(Clone, Default)]
+pub struct MetricsExporter {
+    audio: Option<Arc<Mutex<AudioMetrics>>>,
+    recorder: Option<Arc<Mutex<Recorder>>>,
+    live: Option<LiveRecording>,
+    bridge: Option<Arc<BridgeStats>>,
+}
+
+/// Common Prometheus exporter port range, not taken by anything else on the Jetson
+pub const DEFAULT_METRICS_ADDR: &str = "0.0.0.0:9464";
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+/// Slow or idle scrapers don't get to block the next one
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+impl MetricsExporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_audio(mut self, metrics: Arc<Mutex<AudioMetrics>>) -> Self {
+        self.audio = Some(metrics);
+        self
+    }
+
+    pub fn with_recorder(mut self, recorder: Arc<Mutex<Recorder>>) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Armed state and dropped buffers of the live recording, which writes through the recorder
+    pub fn with_live_recording(mut self, live: LiveRecording) -> Self {
+        self.live = Some(live);
+        self
+    }
+
+    pub fn with_bridge(mut self, stats: Arc<BridgeStats>) -> Self {
+        self.bridge = Some(stats);
+        self
+    }
+
+    /// Listen on `bind_addr` in a background thread
+    /// Returns the bound address (useful with port 0)
+    pub fn serve(&self, bind_addr: &str) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(bind_addr)?;
+        let addr = listener.local_addr()?;
+        let exporter = self.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { continue };
+                if let Err(e) = exporter.handle(stream) {
+                    eprintln!("Metrics request failed: {}", e);
+                }
+            }
+        });
+        println!("Metrics endpoint: http://{}/metrics", addr);
+        Ok(addr)
+    }
+
+    fn handle(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+        stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+        let mut reader = BufReader::new(stream);
+
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        // Headers are irrelevant, just consume them
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 2 {
+            header.clear();
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or("");
+        let path = parts.next().unwrap_or("");
+        let path = path.split('?').next().unwrap_or("");
+
+        let (status, content_type, body) = match (method, path) {
+            ("GET", "/metrics") => ("200 OK", CONTENT_TYPE, self.render()),
+            _ => ("404 Not Found", "text/plain; charset=utf-8", "not found\n".to_string()),
+        };
+        let mut stream = reader.into_inner();
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            body
+        )?;
+        stream.flush()
+    }
+
+    /// Current values in OpenMetrics text format
+    pub fn render(&self) -> String {
+        let mut out = OpenMetrics::default();
+
+        if let Some(ref audio) = self.audio
+            && let Ok(metrics) = audio.lock()
+        {
+            let m = *metrics;
+            let t = &m.telemetry;
+            out.gauge("merlin_audio_rms", "Buffer RMS of the raw input", m.rms as f64);
+            out.gauge("merlin_audio_peak", "Buffer peak of the raw input", m.peak as f64);
+            out.gauge("merlin_audio_level_dbfs", "Buffer RMS in dBFS", m.db as f64);
+            out.gauge("merlin_audio_mean_level_10s_dbfs", "Energy mean over the last 10s", m.levels.last_10s.mean_db as f64);
+            out.gauge("merlin_audio_peak_hold_dbfs", "Decaying peak hold", m.levels.peak_hold_db as f64);
+            out.counter("merlin_audio_clipped_samples", "Samples at or beyond full scale", m.levels.clipped_total);
+            out.gauge("merlin_audio_momentary_lufs", "EBU R128 momentary loudness", m.loudness.momentary_lufs as f64);
+            out.gauge("merlin_audio_integrated_lufs", "EBU R128 integrated loudness", m.loudness.integrated_lufs as f64);
+            out.gauge("merlin_audio_gate_open", "1 while the noise gate is open", m.filters.gate_open as u8 as f64);
+            out.gauge("merlin_audio_gate_gain", "Noise gate envelope", m.filters.gate_gain as f64);
+            out.gauge("merlin_audio_normalizer_gain_db", "Normalizer gain", m.filters.normalizer_gain_db as f64);
+            out.counter("merlin_audio_buffers", "Audio buffers processed", t.buffers);
+            out.counter("merlin_audio_backend_errors", "Stream errors reported by the audio backend", t.backend_errors);
+            out.counter("merlin_audio_disconnects", "Input device disconnects", t.disconnects);
+            out.counter("merlin_audio_overruns", "Buffers processed slower than real time", t.overruns);
+            out.counter("merlin_audio_late_callbacks", "Callbacks more than 1.5 periods apart", t.late_callbacks);
+            out.counter("merlin_audio_dropped_buffers", "Buffers dropped by full downstream queues or a busy replay buffer", t.dropped_buffers);
+            out.gauge("merlin_audio_load_ratio", "Processing time over buffer duration", t.load as f64);
+            out.gauge("merlin_audio_latency_seconds", "Capture to metrics latency", t.latency_ms as f64 / 1000.0);
+        }
+
+        let armed = self.live.as_ref().is_some_and(|live| live.is_armed());
+        if let Some(ref recorder) = self.recorder
+            && let Ok(recorder) = recorder.lock()
+        {
+            let active = recorder.is_writing() || armed;
+            out.gauge("merlin_recording_active", "1 while recording, live or a replay dump", active as u8 as f64);
+            out.gauge("merlin_recording_duration_seconds", "Length of the open recording", recorder.recorded_seconds());
+        }
+        if let Some(ref live) = self.live {
+            out.gauge("merlin_recording_armed", "1 while the live recording is armed", armed as u8 as f64);
+            out.counter("merlin_recording_dropped_buffers", "Buffers the live recording couldn't keep up with", live.dropped_count());
+        }
+
+        if let Some(ref bridge) = self.bridge {
+            let clients = bridge.clients();
+            out.gauge("merlin_bridge_clients", "Connected AR clients", clients.len() as f64);
+            out.counter("merlin_bridge_connections", "Connections accepted", bridge.connections());
+            out.counter("merlin_bridge_frames_sent", "Frames sent to all clients", bridge.frames_sent());
+            out.counter("merlin_bridge_send_errors", "Failed frame sends", bridge.send_errors());
+
+            out.family("merlin_bridge_client_fps", "gauge", "Frames delivered per second");
+            for client in &clients {
+                out.sample("merlin_bridge_client_fps", &[("client", client.name())], client.fps as f64);
+            }
+            out.family("merlin_bridge_client_frames_sent", "counter", "Frames sent per client");
+            for client in &clients {
+                out.sample("merlin_bridge_client_frames_sent_total", &[("client", client.name())], client.frames_sent as f64);
+            }
+            out.family("merlin_bridge_client_send_errors", "counter", "Failed frame sends per client");
+            for client in &clients {
+                out.sample("merlin_bridge_client_send_errors_total", &[("client", client.name())], client.send_errors as f64);
+            }
+        }
+
+        out.finish()
+    }
+}
+
+/// Minimal OpenMetrics text builder
+#[derive(Default)]
+struct OpenMetrics {
+    text: String,
+}
+
+impl OpenMetrics {
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.text, "# TYPE {} {}", name, kind);
+        let _ = writeln!(self.text, "# HELP {} {}", name, help);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.text.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(k, v)| format!("{}=\"{}\"", k, escape_label(v)))
+                .collect();
+            let _ = write!(self.text, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.text, " {}", format_value(value));
+    }
+
+    fn gauge(&mut self, name: &str, help: &str, value: f64) {
+        self.family(name, "gauge", help);
+        self.sample(name, &[], value);
+    }
+
+    /// Counter samples carry the `_total` suffix, the family name doesn't
+    fn counter(&mut self, name: &str, help: &str, value: u64) {
+        self.family(name, "counter", help);
+        self.sample(&format!("{}_total", name), &[], value as f64);
+    }
+
+    fn finish(mut self) -> String {
+        self.text.push_str("# EOF\n");
+        self.text
+    }
+}
+
+/// OpenMetrics spells infinities +Inf / -Inf (ex: loudness before any audio)
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value == f64::INFINITY {
+        "+Inf".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn scrape(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_scrape_metrics_endpoint() {
+        let metrics = Arc::new(Mutex::new(AudioMetrics::new()));
+        {
+            let mut m = metrics.lock().unwrap();
+            m.update(0.1, 0.5, -20.0);
+            m.telemetry.backend_errors = 3;
+            m.telemetry.disconnects = 1;
+            m.filters.gate_open = true;
+        }
+        let bridge = Arc::new(BridgeStats::new());
+        bridge.client_connected("session-1");
+        bridge.set_client_id("session-1", "quest\"3");
+        for _ in 0..5 {
+            bridge.frame_sent("session-1");
+        }
+        bridge.send_error("session-1");
+
+        let recorder = Arc::new(Mutex::new(Recorder::new("/tmp/unused", Default::default())));
+        let live = LiveRecording::start(Arc::clone(&recorder), 16000, 1);
+        let exporter = MetricsExporter::new()
+            .with_audio(metrics)
+            .with_recorder(recorder)
+            .with_live_recording(live.clone())
+            .with_bridge(bridge);
+        let addr = exporter.serve("127.0.0.1:0").unwrap();
+
+        let response = scrape(addr, "/metrics");
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+        assert!(head.contains("application/openmetrics-text"));
+        assert!(body.contains("merlin_audio_level_dbfs -20\n"));
+        assert!(body.contains("# TYPE merlin_audio_backend_errors counter\n"));
+        assert!(body.contains("merlin_audio_backend_errors_total 3\n"));
+        assert!(body.contains("merlin_audio_disconnects_total 1\n"));
+        assert!(body.contains("merlin_audio_gate_open 1\n"));
+        assert!(body.contains("merlin_audio_integrated_lufs -Inf\n"));
+        assert!(body.contains("merlin_recording_active 0\n"));
+        assert!(body.contains("merlin_recording_armed 0\n"));
+        assert!(body.contains("merlin_recording_dropped_buffers_total 0\n"));
+        assert!(body.contains("merlin_bridge_clients 1\n"));
+        assert!(body.contains("merlin_bridge_frames_sent_total 5\n"));
+        assert!(body.contains("merlin_bridge_send_errors_total 1\n"));
+        assert!(body.contains("merlin_bridge_client_frames_sent_total{client=\"quest\\\"3\"} 5\n"));
+        assert!(body.contains("merlin_bridge_client_fps{client="));
+        assert!(body.ends_with("# EOF\n"));
+
+        // Armed live recording counts as recording before its first buffer opens the file
+        live.arm();
+        let body = scrape(addr, "/metrics");
+        assert!(body.contains("merlin_recording_active 1\n"));
+        assert!(body.contains("merlin_recording_armed 1\n"));
+        live.disarm();
+
+        assert!(scrape(addr, "/").starts_with("HTTP/1.1 404"));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod exporter;
+
+pub use exporter::{MetricsExporter, DEFAULT_METRICS_ADDR};